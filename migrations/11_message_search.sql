@@ -0,0 +1,34 @@
+-- Full-text index over decrypted direct messages and channel messages.
+-- rowid is the event_id of the indexed message.
+CREATE VIRTUAL TABLE IF NOT EXISTS message_search USING fts5(
+    content,
+    -- chat pubkey for direct messages, channel id for channel messages
+    chat_id UNINDEXED,
+    author UNINDEXED,
+    is_channel UNINDEXED,
+    -- UNIX timestamp as integer milliseconds
+    created_at UNINDEXED,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+-- Channel messages are stored in plaintext so the index is kept by triggers.
+-- Direct messages are indexed by the backend after decryption.
+CREATE TRIGGER IF NOT EXISTS channel_message_search_insert AFTER INSERT ON channel_message
+BEGIN
+    INSERT INTO message_search (rowid, content, chat_id, author, is_channel, created_at)
+    VALUES (new.event_id, new.content, new.channel_id, new.author, 1, new.created_at);
+END;
+
+CREATE TRIGGER IF NOT EXISTS channel_message_search_delete AFTER DELETE ON channel_message
+BEGIN
+    DELETE FROM message_search WHERE rowid = old.event_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS message_search_delete AFTER DELETE ON message
+BEGIN
+    DELETE FROM message_search WHERE rowid = old.event_id;
+END;
+
+INSERT INTO message_search (rowid, content, chat_id, author, is_channel, created_at)
+SELECT event_id, content, channel_id, author, 1, created_at FROM channel_message
+WHERE event_id NOT IN (SELECT rowid FROM message_search);
@@ -0,0 +1,2 @@
+-- Images in messages from this contact are downloaded without asking
+ALTER TABLE contact ADD COLUMN auto_load_media INTEGER NOT NULL DEFAULT 0;
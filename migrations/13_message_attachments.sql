@@ -0,0 +1,3 @@
+-- Files shared in the message, from its NIP-92 imeta tags, as a JSON array
+ALTER TABLE message ADD COLUMN attachments TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE channel_message ADD COLUMN attachments TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,15 @@
+-- NIP-57 zap receipts (kind 9735) of the user's profile and of the messages in the database.
+CREATE TABLE IF NOT EXISTS zap_receipt (
+    -- hex hash of the receipt event
+    receipt_hash TEXT PRIMARY KEY,
+    -- hex hash of the zapped event, NULL for profile zaps
+    zapped_event_hash TEXT,
+    recipient TEXT NOT NULL,
+    -- author of the zap request, not of the receipt
+    sender TEXT NOT NULL,
+    amount_msats INTEGER NOT NULL,
+    -- UNIX timestamp as integer milliseconds
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_zap_receipt_zapped_event ON zap_receipt(zapped_event_hash);
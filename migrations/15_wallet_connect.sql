@@ -0,0 +1,25 @@
+-- NIP-47 wallet connection of the account, kept in the encrypted database since the URI holds a secret.
+CREATE TABLE IF NOT EXISTS wallet_connect (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    -- nostr+walletconnect:// URI
+    uri TEXT NOT NULL,
+    -- UNIX timestamp as integer milliseconds
+    created_at INTEGER NOT NULL
+);
+
+-- Invoices paid through the connected wallet.
+CREATE TABLE IF NOT EXISTS wallet_payment (
+    -- hex hash of the kind 23194 request event
+    request_hash TEXT PRIMARY KEY,
+    invoice TEXT NOT NULL,
+    amount_msats INTEGER NOT NULL,
+    -- 0: pending, 1: paid, 2: failed
+    status INTEGER NOT NULL,
+    preimage TEXT,
+    error TEXT,
+    -- UNIX timestamps as integer milliseconds
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_wallet_payment_created_at ON wallet_payment(created_at);
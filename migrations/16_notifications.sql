@@ -0,0 +1,17 @@
+-- Notification rules of a direct message or channel chat, chats without a row use the defaults.
+CREATE TABLE IF NOT EXISTS chat_notification (
+    -- 0: direct message, 1: channel
+    chat_type INTEGER NOT NULL,
+    -- contact's public key or channel id, in hex
+    chat_id TEXT NOT NULL,
+    -- UNIX timestamp as integer milliseconds, NULL when not muted
+    muted_until INTEGER,
+    -- channels only notify when the user is mentioned
+    mentions_only INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (chat_type, chat_id)
+);
+
+-- Do-not-disturb schedule, minutes since local midnight
+ALTER TABLE user_config ADD COLUMN dnd_enabled INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE user_config ADD COLUMN dnd_start INTEGER NOT NULL DEFAULT 1320;
+ALTER TABLE user_config ADD COLUMN dnd_end INTEGER NOT NULL DEFAULT 420;
@@ -0,0 +1,15 @@
+-- Users that have the user in their contact list (kind 3).
+CREATE TABLE IF NOT EXISTS follower (
+    -- follower's public key in hex
+    pubkey TEXT PRIMARY KEY,
+    -- creation of the last contact list seen from the follower,
+    -- older lists are ignored
+    list_created_at INTEGER NOT NULL,
+    -- first list that followed the user, since the last unfollow
+    followed_at INTEGER NOT NULL,
+    -- list that dropped the user, NULL while following
+    unfollowed_at INTEGER
+    -- all timestamps are UNIX timestamps as integer milliseconds
+);
+
+CREATE INDEX IF NOT EXISTS idx_follower_followed_at ON follower(followed_at);
@@ -0,0 +1,9 @@
+-- Pubkeys in the user's NIP-51 mute list (kind 10000), their events are dropped.
+CREATE TABLE IF NOT EXISTS muted_pubkey (
+    -- muted public key in hex
+    pubkey TEXT PRIMARY KEY,
+    -- 1 when kept in the encrypted content of the list, hidden from others
+    private INTEGER NOT NULL DEFAULT 0,
+    -- UNIX timestamp as integer milliseconds
+    created_at INTEGER NOT NULL
+);
@@ -0,0 +1,28 @@
+-- Contacts on the user's published contact list. Unknown senders stay out of it,
+-- and accepted ones only join it when the user chooses to.
+ALTER TABLE contact ADD COLUMN listed INTEGER NOT NULL DEFAULT 0;
+
+-- Pubkeys in the p tags of the user's last stored contact list (kind 3).
+-- The user is never one of their own contacts, lists of contacts aren't the user's.
+CREATE TEMP TABLE published_contact AS
+SELECT json_extract(tag.value, '$[1]') AS pubkey
+FROM (
+    SELECT tags FROM event
+    WHERE kind = 3 AND pubkey NOT IN (SELECT pubkey FROM contact)
+    ORDER BY event_id DESC LIMIT 1
+) AS list, json_each(list.tags) AS tag
+WHERE json_extract(tag.value, '$[0]') = 'p';
+
+-- Contacts on the published list stay known and listed, whether or not they were answered.
+UPDATE contact SET status = 1, listed = 1
+WHERE pubkey IN (SELECT pubkey FROM published_contact);
+
+-- Without a stored list every contact was published. Other senders the user never
+-- answered become message requests (status 0), the rest are known.
+UPDATE contact SET status = 1,
+    listed = NOT EXISTS (SELECT 1 FROM event WHERE kind = 3 AND pubkey NOT IN (SELECT pubkey FROM contact))
+WHERE pubkey NOT IN (SELECT pubkey FROM published_contact)
+    AND (pubkey NOT IN (SELECT chat_pubkey FROM message)
+        OR pubkey IN (SELECT chat_pubkey FROM message WHERE is_users = 1));
+
+DROP TABLE published_contact;
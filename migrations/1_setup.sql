@@ -7,6 +7,4 @@ PRAGMA main.synchronous = NORMAL;
 
 PRAGMA foreign_keys = ON;
 
-PRAGMA application_id = 1654008667;
-
-PRAGMA user_version = 1;
\ No newline at end of file
+PRAGMA application_id = 1654008667;
\ No newline at end of file
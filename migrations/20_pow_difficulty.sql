@@ -0,0 +1,4 @@
+-- NIP-13 difficulty mined into the user's events, 0 disables proof of work
+ALTER TABLE user_config ADD COLUMN pow_difficulty INTEGER NOT NULL DEFAULT 0;
+-- Difficulty a relay requires, raised when it rejects an event with a `pow:` message
+ALTER TABLE relay ADD COLUMN pow_difficulty INTEGER NOT NULL DEFAULT 0;
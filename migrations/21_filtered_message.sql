@@ -0,0 +1,18 @@
+-- NIP-13 difficulty asked of direct and channel messages from non-contacts,
+-- 0 lets every message through
+ALTER TABLE user_config ADD COLUMN incoming_pow_threshold INTEGER NOT NULL DEFAULT 0;
+
+-- Messages from non-contacts held back for not meeting the threshold.
+-- The event is kept so the message isn't received again.
+CREATE TABLE IF NOT EXISTS filtered_message (
+    event_id INTEGER PRIMARY KEY,
+    -- sender's public key in hex
+    pubkey TEXT NOT NULL,
+    -- channel id in hex, NULL for direct messages
+    channel_id TEXT,
+    -- leading zero bits of the event id
+    difficulty INTEGER NOT NULL,
+    -- UNIX timestamp as integer milliseconds
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (event_id) REFERENCES event(event_id) ON DELETE CASCADE
+);
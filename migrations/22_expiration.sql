@@ -0,0 +1,15 @@
+-- NIP-40 expiration of the event as UNIX timestamp in integer milliseconds,
+-- NULL for events that never expire
+ALTER TABLE event ADD COLUMN expires_at INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_event_expires_at ON event(expires_at);
+
+-- Expiration timer added to outgoing messages of a chat
+CREATE TABLE IF NOT EXISTS chat_expiration (
+    -- 0 for direct messages, 1 for channels
+    chat_type INTEGER NOT NULL,
+    -- contact pubkey or channel id in hex
+    chat_id TEXT NOT NULL,
+    timer_secs INTEGER NOT NULL,
+    PRIMARY KEY (chat_type, chat_id)
+);
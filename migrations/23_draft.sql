@@ -0,0 +1,11 @@
+-- Unsent text of the message composer of each chat
+CREATE TABLE IF NOT EXISTS draft (
+    -- 0 for direct messages, 1 for channels
+    chat_type INTEGER NOT NULL,
+    -- contact pubkey or channel id in hex
+    chat_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    -- UNIX timestamp as integer milliseconds
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (chat_type, chat_id)
+);
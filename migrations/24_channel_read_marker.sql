@@ -0,0 +1,2 @@
+-- Messages of the channel after this are unread, null until the channel is opened
+ALTER TABLE channel_subscription ADD COLUMN last_read_at INTEGER;
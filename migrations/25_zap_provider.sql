@@ -0,0 +1,10 @@
+-- LNURL server of each zap recipient, from the `nostrPubkey` of its pay endpoint.
+-- Only receipts signed by that key are counted.
+CREATE TABLE IF NOT EXISTS zap_provider (
+    -- recipient's public key in hex
+    recipient TEXT PRIMARY KEY,
+    -- public key the LNURL server signs receipts with, in hex
+    nostr_pubkey TEXT NOT NULL,
+    -- UNIX timestamp as integer milliseconds
+    updated_at INTEGER NOT NULL
+);
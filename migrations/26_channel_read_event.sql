@@ -0,0 +1,3 @@
+-- Latest message of the channel when it was read. Messages are unread when
+-- received after it, their author-set created_at can't push the marker ahead.
+ALTER TABLE channel_subscription ADD COLUMN last_read_event_id INTEGER;
@@ -0,0 +1,16 @@
+-- Total size of the original and resized files, 0 until measured
+ALTER TABLE image_cache ADD COLUMN size_bytes INTEGER NOT NULL DEFAULT 0;
+
+-- UNIX milliseconds
+ALTER TABLE image_cache ADD COLUMN last_accessed_at INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE profile_meta_cache ADD COLUMN last_accessed_at INTEGER NOT NULL DEFAULT 0;
+
+-- Entries cached before access tracking count as accessed now
+UPDATE image_cache SET last_accessed_at = CAST(strftime('%s', 'now') AS INTEGER) * 1000;
+
+UPDATE profile_meta_cache SET last_accessed_at = CAST(strftime('%s', 'now') AS INTEGER) * 1000;
+
+CREATE INDEX IF NOT EXISTS image_last_accessed_index ON image_cache(last_accessed_at);
+
+CREATE INDEX IF NOT EXISTS profile_last_accessed_index ON profile_meta_cache(last_accessed_at);
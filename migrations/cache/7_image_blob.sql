@@ -0,0 +1,28 @@
+-- Downloaded image files, addressed by the sha256 of their content
+CREATE TABLE IF NOT EXISTS image_blob (
+    content_hash TEXT PRIMARY KEY,
+    -- original file, resized copies live in the same directory
+    path TEXT NOT NULL,
+    -- UNIX milliseconds
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS image_blob_path_index ON image_blob(path);
+
+-- Last response for each image URL, used to revalidate it
+CREATE TABLE IF NOT EXISTS image_url (
+    -- sha256 of the URL
+    url_hash TEXT PRIMARY KEY,
+    url TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    etag TEXT,
+    last_modified TEXT,
+    -- UNIX milliseconds
+    fetched_at INTEGER NOT NULL,
+    max_age_secs INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS image_url_content_hash_index ON image_url(content_hash);
+
+-- Null for images downloaded before the blob store
+ALTER TABLE image_cache ADD COLUMN content_hash TEXT;
@@ -0,0 +1,14 @@
+-- OpenGraph metadata of links found in messages
+CREATE TABLE IF NOT EXISTS link_preview (
+    -- sha256 of the URL
+    url_hash TEXT PRIMARY KEY,
+    url TEXT NOT NULL,
+    -- all null when the page had no metadata or couldn't be fetched
+    title TEXT,
+    description TEXT,
+    image_url TEXT,
+    -- UNIX milliseconds
+    fetched_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS link_preview_fetched_at_index ON link_preview(fetched_at);
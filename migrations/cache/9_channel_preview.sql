@@ -0,0 +1,14 @@
+-- Messages of channels found while searching, shown before subscribing
+CREATE TABLE IF NOT EXISTS channel_preview_message (
+    event_hash TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    author TEXT NOT NULL,
+    -- UNIX milliseconds
+    created_at INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    -- UNIX milliseconds
+    fetched_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS channel_preview_message_channel_index ON channel_preview_message(channel_id, created_at);
+CREATE INDEX IF NOT EXISTS channel_preview_message_fetched_at_index ON channel_preview_message(fetched_at);
@@ -35,6 +35,15 @@ pub struct App {
     state: AppState,
     color_theme: Option<style::Theme>,
 }
+impl App {
+    fn send_window_focused(&mut self, focused: bool) {
+        if let AppState::Loaded { conn, .. } = &mut self.state {
+            if let Err(e) = conn.send(ToBackend::WindowFocused(focused)) {
+                tracing::error!("{}", e);
+            }
+        }
+    }
+}
 
 impl Application for App {
     type Theme = crate::style::Theme;
@@ -86,30 +95,35 @@ impl Application for App {
     }
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
         match message {
-            Message::RuntimeEvent(event) => {
-                if let iced::Event::Window(window::Event::CloseRequested) = event {
-                    match &mut self.state {
-                        AppState::Loading => {
+            Message::RuntimeEvent(event) => match event {
+                iced::Event::Window(window::Event::Focused) => {
+                    self.send_window_focused(true);
+                }
+                iced::Event::Window(window::Event::Unfocused) => {
+                    self.send_window_focused(false);
+                }
+                iced::Event::Window(window::Event::CloseRequested) => match &mut self.state {
+                    AppState::Loading => {
+                        return window::close();
+                    }
+                    AppState::Loaded {
+                        conn,
+                        already_sent_shutdown: shutdown_sent,
+                        ..
+                    } => {
+                        tracing::info!("Shutting down backend");
+                        if *shutdown_sent {
                             return window::close();
-                        }
-                        AppState::Loaded {
-                            conn,
-                            already_sent_shutdown: shutdown_sent,
-                            ..
-                        } => {
-                            tracing::info!("Shutting down backend");
-                            if *shutdown_sent {
+                        } else {
+                            *shutdown_sent = true;
+                            if let Err(_e) = conn.send(ToBackend::Shutdown) {
                                 return window::close();
-                            } else {
-                                *shutdown_sent = true;
-                                if let Err(_e) = conn.send(ToBackend::Shutdown) {
-                                    return window::close();
-                                }
                             }
                         }
                     }
-                }
-            }
+                },
+                _ => (),
+            },
             Message::RouterMessage(msg) => {
                 if let AppState::Loaded { router, conn, .. } = &mut self.state {
                     match router.update(msg, conn) {
@@ -5,7 +5,7 @@ use iced::{alignment, Length};
 use unicode_segmentation::UnicodeSegmentation;
 
 use crate::consts::YMD_FORMAT;
-use crate::db::{DbContact, ImageDownloaded};
+use crate::db::{DbContact, ImageDownloaded, NotificationChat};
 use crate::error::BackendClosed;
 use crate::net::{self, BackEndConnection, ImageSize};
 use crate::style;
@@ -80,6 +80,8 @@ pub struct ChatContact {
     pub contact: DbContact,
     profile_img_handle: image::Handle,
     chat_info: ChatInfo,
+    /// Unsent composer text, shown in place of the last message
+    draft: String,
 }
 
 impl ChatContact {
@@ -89,6 +91,9 @@ impl ChatContact {
         conn: &mut BackEndConnection,
     ) -> Result<Self, BackendClosed> {
         conn.send(net::ToBackend::FetchChatInfo(db_contact.clone()))?;
+        conn.send(net::ToBackend::FetchDraft(NotificationChat::Dm(
+            db_contact.pubkey().to_owned(),
+        )))?;
         let size = ImageSize::Small;
         let profile_img_handle = db_contact.profile_image(size, conn)?;
         Ok(Self {
@@ -97,6 +102,7 @@ impl ChatContact {
             contact: db_contact.clone(),
             profile_img_handle,
             chat_info: ChatInfo::default(),
+            draft: "".into(),
         })
     }
     pub fn view(&self, active_id: Option<i32>) -> Element<MessageWrapper> {
@@ -119,17 +125,29 @@ impl ChatContact {
 
                 let card_bottom_row = iced_lazy::responsive(|size| {
                     // --- BOTTOM ROW ---
-                    let content = &self.chat_info.last_message;
+                    let (prefix, content) = if self.draft.is_empty() {
+                        ("", &self.chat_info.last_message)
+                    } else {
+                        (DRAFT_PREFIX, &self.draft)
+                    };
                     let left_pixels = size.width - NOTIFICATION_COUNT_WIDTH - 5.0; //spacing;
                     let pixel_p_char = 8.0; // 8px = 1 char
-                    let taker = (left_pixels / pixel_p_char).floor() as usize;
+                    let taker = ((left_pixels / pixel_p_char).floor() as usize)
+                        .saturating_sub(prefix.len());
                     let content = if taker > content.len() {
                         content.to_owned()
                     } else {
                         let truncated = content.graphemes(true).take(taker).collect::<String>();
                         format!("{}...", &truncated)
                     };
-                    let last_message_cp = container(text(&content).size(18.0)).width(Length::Fill);
+                    let mut last_message_row = row![].spacing(4);
+                    if !prefix.is_empty() {
+                        last_message_row = last_message_row
+                            .push(text(prefix).size(18.0).style(style::Text::Danger));
+                    }
+                    let last_message_cp =
+                        container(last_message_row.push(text(&content).size(18.0)))
+                            .width(Length::Fill);
 
                     container(
                         row![last_message_cp, self.make_notifications()]
@@ -194,6 +212,12 @@ impl ChatContact {
     pub fn reset_unseen(&mut self) {
         self.chat_info.unseen_messages = 0;
     }
+    pub fn draft(&self) -> &str {
+        &self.draft
+    }
+    pub fn set_draft(&mut self, draft: String) {
+        self.draft = draft;
+    }
     pub fn update_chat_info(&mut self, new_info: ChatInfo) {
         self.chat_info.update(new_info);
     }
@@ -245,3 +269,4 @@ impl ChatContact {
 
 pub(crate) const CARD_HEIGHT: f32 = 80.0;
 const NOTIFICATION_COUNT_WIDTH: f32 = 30.0;
+const DRAFT_PREFIX: &str = "Draft:";
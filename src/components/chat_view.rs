@@ -1,15 +1,22 @@
 use crate::components::chat_contact::ChatContact;
 use crate::components::{common_scrollable, Responsive};
 use crate::consts::YMD_FORMAT;
-use crate::icon::{dots_vertical_icon, file_icon_regular, search_icon, send_icon};
+use crate::icon::{
+    dots_vertical_icon, file_icon_regular, hourglass_icon, paperclip_icon, regular_bell_icon,
+    search_icon, send_icon, xmark_icon,
+};
 use crate::style;
 use crate::types::chat_message::{self, ChatMessage};
+use crate::types::{Attachment, MediaPreviews, ZapTarget, ZapTotals};
 use crate::utils::from_naive_utc_to_local;
 use crate::widget::{Button, Container, Element};
 use chrono::{Datelike, NaiveDateTime};
 use iced::widget::{button, column, container, row, scrollable, text, text_input};
-use iced::{Alignment, Length, Point, Size};
+use iced::{Alignment, Length, Point, Size, Subscription};
+use nostr::prelude::ToBech32;
 use nostr::secp256k1::XOnlyPublicKey;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -20,33 +27,161 @@ pub enum Message {
     OpenContactProfile,
     ChatRightClick(ChatMessage, Point),
     ChannelOpenModalPressed,
+    ChatSearchPressed,
     ChannelSearchPressed,
     ChannelMenuPressed,
     ChannelUserNamePressed(XOnlyPublicKey),
+    MediaPressed(PathBuf),
+    LoadMediaPressed(String),
+    LinkPressed(String),
+    DownloadAttachmentPressed(Attachment),
+    AttachPressed,
+    RemoveAttachmentPressed(usize),
+    ZapPressed(ZapTarget, String),
+    NotificationsPressed,
+    ExpirationPressed,
+    MentionPressed(XOnlyPublicKey, String),
+    /// Checks whether the typing paused long enough to save the draft
+    DraftTick,
+}
+
+/// Pause in the typing after which the draft is saved
+const DRAFT_SAVE_DELAY: Duration = Duration::from_millis(1000);
+
+#[derive(Debug, Clone)]
+enum UploadState {
+    Idle,
+    ChoosingFile,
+    Uploading,
+    Failed(String),
 }
 
 pub struct ChatView {
     dm_msg_input: String,
+    /// Uploaded files waiting for the message to be sent
+    attachments: Vec<Attachment>,
+    upload_state: UploadState,
+    /// Names completed in the input and who they refer to
+    mentions: Vec<(String, XOnlyPublicKey)>,
+    /// Last edit of the input not saved as a draft yet
+    draft_edited_at: Option<Instant>,
 }
 impl ChatView {
     pub fn new() -> Self {
         Self {
             dm_msg_input: "".into(),
+            attachments: vec![],
+            upload_state: UploadState::Idle,
+            mentions: vec![],
+            draft_edited_at: None,
         }
     }
+    pub fn dm_msg(&self) -> &str {
+        &self.dm_msg_input
+    }
     pub fn update_dm_msg(&mut self, text: String) {
+        if text.is_empty() {
+            self.mentions.clear();
+        }
         self.dm_msg_input = text;
     }
+    /// Typed text, saved as the draft once the typing pauses
+    pub fn edit_draft(&mut self, text: String) {
+        self.update_dm_msg(text);
+        self.draft_edited_at = Some(Instant::now());
+    }
+    /// Draft to be saved, once the typing paused or right away when `now`
+    pub fn unsaved_draft(&mut self, now: bool) -> Option<String> {
+        let edited_at = self.draft_edited_at?;
+        if !now && edited_at.elapsed() < DRAFT_SAVE_DELAY {
+            return None;
+        }
+        self.draft_edited_at = None;
+        Some(self.dm_msg_input.clone())
+    }
+    /// The draft was saved with the input as it is
+    pub fn draft_saved(&mut self) {
+        self.draft_edited_at = None;
+    }
+    pub fn subscription(&self) -> Subscription<Message> {
+        if self.draft_edited_at.is_some() {
+            iced::time::every(DRAFT_SAVE_DELAY).map(|_| Message::DraftTick)
+        } else {
+            Subscription::none()
+        }
+    }
+    /// Text typed after an `@` at the end of the input
+    pub fn mention_query(&self) -> Option<&str> {
+        mention_query(&self.dm_msg_input)
+    }
+    /// Replaces the `@` being typed with the chosen name
+    pub fn complete_mention(&mut self, pubkey: XOnlyPublicKey, name: String) {
+        let Some(query) = self.mention_query() else {
+            return;
+        };
+        let start = self.dm_msg_input.len() - query.len();
+        self.dm_msg_input.truncate(start);
+        self.dm_msg_input.push_str(&name);
+        self.dm_msg_input.push(' ');
+        self.mentions.push((name, pubkey));
+    }
+    /// Content to be sent, with the completed `@name`s as `nostr:npub` mentions
+    pub fn encode_mentions(&self, content: &str) -> String {
+        encode_mentions(content, &self.mentions)
+    }
+    pub fn choosing_file(&mut self) {
+        self.upload_state = UploadState::ChoosingFile;
+    }
+    /// True when the file was picked to be attached, then its upload starts
+    pub fn file_picked(&mut self) -> bool {
+        if let UploadState::ChoosingFile = self.upload_state {
+            self.upload_state = UploadState::Uploading;
+            return true;
+        }
+        false
+    }
+    pub fn pick_canceled(&mut self) {
+        if let UploadState::ChoosingFile = self.upload_state {
+            self.upload_state = UploadState::Idle;
+        }
+    }
+    pub fn upload_finished(&mut self, result: Result<Attachment, String>) {
+        if let UploadState::Uploading = self.upload_state {
+            self.upload_state = match result {
+                Ok(attachment) => {
+                    self.attachments.push(attachment);
+                    UploadState::Idle
+                }
+                Err(e) => UploadState::Failed(e),
+            };
+        }
+    }
+    pub fn remove_attachment(&mut self, index: usize) {
+        if index < self.attachments.len() {
+            self.attachments.remove(index);
+        }
+    }
+    pub fn has_attachments(&self) -> bool {
+        !self.attachments.is_empty()
+    }
+    pub fn take_attachments(&mut self) -> Vec<Attachment> {
+        std::mem::take(&mut self.attachments)
+    }
+    #[allow(clippy::too_many_arguments)]
     pub fn channel_view<'a>(
         &'a self,
         scrollable_id: &'a scrollable::Id,
         chat_input_id: &'a text_input::Id,
         messages: &'a [ChatMessage],
+        media: &'a MediaPreviews,
+        zaps: &'a ZapTotals,
         name: &str,
         members: i32,
         disable_input: bool,
+        suggestions: &[(XOnlyPublicKey, String)],
+        user_npub: Option<&'a str>,
     ) -> Element<'a, Message> {
-        let chat_messages = create_channel_content(scrollable_id, messages);
+        let chat_messages = create_channel_content(scrollable_id, messages, media, zaps, user_npub);
         let mut message_input =
             text_input("Write a message...", &self.dm_msg_input).id(chat_input_id.clone());
         let mut send_btn =
@@ -67,6 +202,7 @@ impl ChatView {
         container(column![
             channel_navbar(name, members),
             chat_messages,
+            mention_suggestions(suggestions),
             msg_input_row
         ])
         .width(Length::Fill)
@@ -77,6 +213,8 @@ impl ChatView {
         scrollable_id: &'a scrollable::Id,
         chat_input_id: &'a text_input::Id,
         messages: &'a [ChatMessage],
+        media: &'a MediaPreviews,
+        zaps: &'a ZapTotals,
         active_chat: Option<&'a ChatContact>,
     ) -> Element<'a, Message> {
         let Some(active_contact) = active_chat else {
@@ -89,7 +227,7 @@ impl ChatView {
             .into();
         };
 
-        let chat_messages = create_chat_content(scrollable_id, messages);
+        let chat_messages = create_chat_content(scrollable_id, messages, media, zaps);
         let message_input = text_input("Write a message...", &self.dm_msg_input)
             .on_submit(Message::DMSentPress(self.dm_msg_input.clone()))
             .on_input(Message::DMNMessageChange)
@@ -97,7 +235,11 @@ impl ChatView {
         let send_btn = button(send_icon().style(style::Text::Primary))
             .style(style::Button::Invisible)
             .on_press(Message::DMSentPress(self.dm_msg_input.clone()));
-        let msg_input_row = container(row![message_input, send_btn].spacing(5))
+        let mut attach_btn = button(paperclip_icon()).style(style::Button::Invisible);
+        if let UploadState::Idle | UploadState::Failed(_) = self.upload_state {
+            attach_btn = attach_btn.on_press(Message::AttachPressed);
+        }
+        let msg_input_row = container(row![attach_btn, message_input, send_btn].spacing(5))
             .style(style::Container::Default)
             .height(CHAT_INPUT_HEIGHT)
             .padding([10, 5]);
@@ -109,6 +251,7 @@ impl ChatView {
             chat_navbar(active_contact),
             add_or_remove_user,
             chat_messages,
+            staged_attachments(&self.attachments, &self.upload_state),
             msg_input_row
         ])
         .width(Length::Fill)
@@ -116,9 +259,105 @@ impl ChatView {
     }
 }
 
+fn mention_suggestions<'a>(suggestions: &[(XOnlyPublicKey, String)]) -> Element<'a, Message> {
+    if suggestions.is_empty() {
+        return text("").into();
+    }
+    let buttons = suggestions
+        .iter()
+        .fold(row![].spacing(5), |row, (pubkey, name)| {
+            row.push(
+                button(text(format!("@{}", name)).size(14))
+                    .style(style::Button::Bordered)
+                    .on_press(Message::MentionPressed(*pubkey, name.to_owned())),
+            )
+        });
+    container(buttons)
+        .width(Length::Fill)
+        .style(style::Container::Default)
+        .padding([5, 10])
+        .into()
+}
+
+fn mention_query(input: &str) -> Option<&str> {
+    let start = input.rfind('@')?;
+    let query = &input[start + 1..];
+    let at_word_start = input[..start]
+        .chars()
+        .last()
+        .map_or(true, char::is_whitespace);
+    (at_word_start && !query.contains(char::is_whitespace)).then_some(query)
+}
+
+fn encode_mentions(content: &str, mentions: &[(String, XOnlyPublicKey)]) -> String {
+    // longer names first so "@Al" doesn't eat the start of "@Alice"
+    let mut mentions: Vec<_> = mentions.iter().collect();
+    mentions.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+    mentions
+        .into_iter()
+        .fold(content.to_owned(), |content, (name, pubkey)| {
+            match pubkey.to_bech32() {
+                Ok(npub) => content.replace(&format!("@{}", name), &format!("nostr:{}", npub)),
+                Err(_) => content,
+            }
+        })
+}
+
+fn staged_attachments<'a>(
+    attachments: &'a [Attachment],
+    upload_state: &'a UploadState,
+) -> Element<'a, Message> {
+    let status: Element<_> = match upload_state {
+        UploadState::Uploading => text("Uploading file...")
+            .size(14)
+            .style(style::Text::Alpha(0.5))
+            .into(),
+        UploadState::Failed(e) => text(format!("Upload failed: {}", e))
+            .size(14)
+            .style(style::Text::Danger)
+            .into(),
+        UploadState::Idle | UploadState::ChoosingFile => text("").into(),
+    };
+    let is_idle = matches!(upload_state, UploadState::Idle | UploadState::ChoosingFile);
+    if attachments.is_empty() && is_idle {
+        return text("").into();
+    }
+
+    let staged =
+        attachments
+            .iter()
+            .enumerate()
+            .fold(row![].spacing(5), |row, (index, attachment)| {
+                let remove_btn = button(xmark_icon().size(14))
+                    .style(style::Button::Invisible)
+                    .on_press(Message::RemoveAttachmentPressed(index));
+                row.push(
+                    container(
+                        row![text(attachment.file_name()).size(14), remove_btn]
+                            .spacing(5)
+                            .align_items(Alignment::Center),
+                    )
+                    .padding([2, 8])
+                    .style(style::Container::Frame),
+                )
+            });
+
+    container(
+        row![staged, status]
+            .spacing(10)
+            .align_items(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .style(style::Container::Default)
+    .padding([5, 10])
+    .into()
+}
+
 fn create_chat_content<'a>(
     scrollable_id: &'a scrollable::Id,
     messages: &'a [ChatMessage],
+    media: &'a MediaPreviews,
+    zaps: &'a ZapTotals,
 ) -> Element<'a, Message> {
     let lazy = Responsive::new(move |_size| {
         if messages.is_empty() {
@@ -146,7 +385,7 @@ fn create_chat_content<'a>(
                 last_date = Some(*msg_date);
             }
 
-            let msg_view = msg.view(false).map(map_chat_msgs);
+            let msg_view = msg.view(false, false, media, zaps).map(map_chat_msgs);
 
             col = col.push(msg_view);
         }
@@ -169,6 +408,22 @@ fn create_chat_content<'a>(
         .into()
 }
 
+/// Scroll offset that puts the message with `event_id` in view
+pub fn message_scroll_offset(
+    messages: &[ChatMessage],
+    event_id: i64,
+) -> Option<scrollable::RelativeOffset> {
+    let position = messages
+        .iter()
+        .position(|m| m.event_id() == Some(event_id))?;
+    let y = if messages.len() > 1 {
+        position as f32 / (messages.len() - 1) as f32
+    } else {
+        1.0
+    };
+    Some(scrollable::RelativeOffset { x: 0.0, y })
+}
+
 fn chat_day_divider<Message: 'static>(date: NaiveDateTime) -> Element<'static, Message> {
     let local_date = from_naive_utc_to_local(date);
     let text_container = container(text(local_date.format(YMD_FORMAT).to_string()))
@@ -226,9 +481,20 @@ fn header_details(chat: &ChatContact) -> Button<'_, Message> {
 }
 
 fn header_action_buttons<'a>() -> Element<'a, Message> {
-    row![button(file_icon_regular())
-        .style(style::Button::Invisible)
-        .on_press(Message::OpenContactProfile)]
+    row![
+        button(search_icon())
+            .style(style::Button::Invisible)
+            .on_press(Message::ChatSearchPressed),
+        button(regular_bell_icon())
+            .style(style::Button::Invisible)
+            .on_press(Message::NotificationsPressed),
+        button(hourglass_icon())
+            .style(style::Button::Invisible)
+            .on_press(Message::ExpirationPressed),
+        button(file_icon_regular())
+            .style(style::Button::Invisible)
+            .on_press(Message::OpenContactProfile)
+    ]
     .padding(10)
     .align_items(Alignment::End)
     .into()
@@ -237,6 +503,9 @@ fn header_action_buttons<'a>() -> Element<'a, Message> {
 fn create_channel_content<'a>(
     scrollable_id: &'a scrollable::Id,
     messages: &'a [ChatMessage],
+    media: &'a MediaPreviews,
+    zaps: &'a ZapTotals,
+    user_npub: Option<&'a str>,
 ) -> Element<'a, Message> {
     let lazy = Responsive::new(move |_size| {
         if messages.is_empty() {
@@ -267,7 +536,11 @@ fn create_channel_content<'a>(
 
             let show_name = msg.show_name(previous_msg.as_ref());
 
-            let msg_view = msg.view(show_name).map(map_chat_msgs);
+            let mentioned = user_npub.map_or(false, |npub| msg.mentions(npub));
+
+            let msg_view = msg
+                .view(show_name, mentioned, media, zaps)
+                .map(map_chat_msgs);
 
             col = col.push(msg_view);
 
@@ -296,6 +569,13 @@ fn map_chat_msgs(message: chat_message::Message) -> Message {
     match message {
         chat_message::Message::ChatRightClick(msg, point) => Message::ChatRightClick(msg, point),
         chat_message::Message::UserNameClick(author) => Message::ChannelUserNamePressed(author),
+        chat_message::Message::MediaPressed(path) => Message::MediaPressed(path),
+        chat_message::Message::LoadMediaPressed(media_url) => Message::LoadMediaPressed(media_url),
+        chat_message::Message::LinkPressed(link_url) => Message::LinkPressed(link_url),
+        chat_message::Message::DownloadAttachmentPressed(attachment) => {
+            Message::DownloadAttachmentPressed(attachment)
+        }
+        chat_message::Message::ZapPressed(target, name) => Message::ZapPressed(target, name),
     }
 }
 
@@ -329,11 +609,19 @@ fn channel_header_action_buttons<'a>() -> Element<'a, Message> {
         .style(style::Button::Invisible)
         .on_press(Message::ChannelSearchPressed);
 
+    let bell_btn = button(regular_bell_icon())
+        .style(style::Button::Invisible)
+        .on_press(Message::NotificationsPressed);
+
+    let expiration_btn = button(hourglass_icon())
+        .style(style::Button::Invisible)
+        .on_press(Message::ExpirationPressed);
+
     let menu_btn = button(dots_vertical_icon())
         .style(style::Button::Invisible)
         .on_press(Message::ChannelMenuPressed);
 
-    row![src_btn, menu_btn]
+    row![src_btn, bell_btn, expiration_btn, menu_btn]
         .padding(10)
         .align_items(Alignment::End)
         .into()
@@ -11,19 +11,29 @@ pub enum Message {
     AddContactPress,
     SearchContactInputChange(String),
     ContactPress(i32),
+    RequestsPress,
 }
 pub struct ContactList {
     search_input: String,
+    /// Lists the message requests, chats from unknown senders, instead of the chats
+    show_requests: bool,
 }
 impl ContactList {
     pub fn new() -> Self {
         Self {
             search_input: "".into(),
+            show_requests: false,
         }
     }
     pub fn search_input_change(&mut self, text: String) {
         self.search_input = text;
     }
+    pub fn toggle_requests(&mut self) {
+        self.show_requests = !self.show_requests;
+    }
+    pub fn show_requests(&mut self, show_requests: bool) {
+        self.show_requests = show_requests;
+    }
     pub fn view<'a>(
         &'a self,
         scrollable_id: &'a scrollable::Id,
@@ -32,6 +42,7 @@ impl ContactList {
         active_idx: Option<i32>,
     ) -> Element<'a, Message> {
         // --- FIRST SPLIT ---
+        let requests_count = chats.iter().filter(|c| !c.contact.is_known()).count();
         let contact_list: Element<_> = if chats.is_empty() {
             container(
                 button("Add Contact")
@@ -44,6 +55,7 @@ impl ContactList {
         } else {
             let contact_list = chats
                 .iter()
+                .filter(|chat| chat.contact.is_known() != self.show_requests)
                 .filter(|chat| chat_matches_search(chat, &self.search_input))
                 .fold(column![].padding(8).spacing(4), |col, chat| {
                     col.push(chat.view(active_idx).map(|m| match m.message {
@@ -70,7 +82,15 @@ impl ContactList {
         .width(Length::Fill)
         .height(NAVBAR_HEIGHT);
 
-        container(column![search_container, contact_list])
+        let requests_btn: Element<_> = match (show_only_profile, self.show_requests) {
+            (false, true) => requests_button("Back to chats".into()),
+            (false, false) if requests_count > 0 => {
+                requests_button(format!("Message requests ({})", requests_count))
+            }
+            _ => text("").into(),
+        };
+
+        container(column![search_container, requests_btn, contact_list])
             .height(Length::Fill)
             .width(Length::Fill)
             .style(style::Container::Frame)
@@ -79,3 +99,15 @@ impl ContactList {
 }
 
 const NAVBAR_HEIGHT: f32 = 50.0;
+
+fn requests_button<'a>(label: String) -> Element<'a, Message> {
+    container(
+        button(text(label).size(16))
+            .width(Length::Fill)
+            .style(style::Button::Bordered)
+            .on_press(Message::RequestsPress),
+    )
+    .padding([0, 10])
+    .width(Length::Fill)
+    .into()
+}
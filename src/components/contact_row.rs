@@ -3,7 +3,7 @@ use iced::Length;
 use nostr::prelude::ToBech32;
 
 use crate::db::DbContact;
-use crate::icon::{delete_icon, edit_icon, reply_icon};
+use crate::icon::{ban_icon, delete_icon, edit_icon, reply_icon};
 use crate::style;
 use crate::utils::hide_string;
 use crate::widget::Element;
@@ -13,6 +13,7 @@ pub enum Message {
     DeleteContact(DbContact),
     EditContact(DbContact),
     SendMessageTo(DbContact),
+    MuteContact(DbContact),
 }
 pub struct ContactRow {
     contact: DbContact,
@@ -57,6 +58,7 @@ impl ContactRow {
                 .align_x(iced::alignment::Horizontal::Left)
                 .width(Length::Fill),
             container(text("")).width(Length::Fixed(EDIT_BTN_WIDTH)),
+            container(text("")).width(Length::Fixed(MUTE_BTN_WIDTH)),
             container(text("")).width(Length::Fixed(REMOVE_BTN_WIDTH)),
         ]
         .spacing(2)
@@ -101,6 +103,16 @@ impl ContactRow {
                 )
                 .style(style::Container::TooltipBg)
             ),
+            container(
+                tooltip(
+                    button(ban_icon().size(16))
+                        .on_press(Message::MuteContact(self.contact.clone()))
+                        .width(Length::Fixed(MUTE_BTN_WIDTH)),
+                    "Mute or Block",
+                    tooltip::Position::Left
+                )
+                .style(style::Container::TooltipBg)
+            ),
             container(
                 tooltip(
                     button(delete_icon().size(16))
@@ -119,6 +131,7 @@ impl ContactRow {
 }
 
 const EDIT_BTN_WIDTH: f32 = 30.0;
+const MUTE_BTN_WIDTH: f32 = 30.0;
 const REMOVE_BTN_WIDTH: f32 = 30.0;
 const PUBKEY_CELL_WIDTH: f32 = 120.0;
 const NAME_CELL_WIDTH_MIN: f32 = 100.0;
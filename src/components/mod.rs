@@ -7,6 +7,7 @@ pub mod contact_row;
 mod copy_btn;
 mod custom_widgets;
 pub mod relay_row;
+pub mod rich_text;
 mod scrollables;
 pub mod status_bar;
 pub mod text;
@@ -18,5 +19,6 @@ pub use contact_row::ContactRow;
 pub use copy_btn::copy_btn;
 pub use custom_widgets::{floating_element, FloatingElement, MouseArea, Responsive};
 pub use relay_row::RelayRow;
+pub use rich_text::rich_text;
 pub use scrollables::{common_scrollable, invisible_scrollable};
 pub use status_bar::StatusBar;
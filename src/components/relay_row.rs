@@ -7,7 +7,7 @@ use crate::net::{self, BackEndConnection};
 use crate::style;
 use crate::widget::{Element, Text};
 use chrono::{NaiveDateTime, Utc};
-use iced::widget::{button, checkbox, container, row, text, tooltip, Space};
+use iced::widget::{button, checkbox, container, row, text, text_input, tooltip, Space};
 use iced::{alignment, Command, Length};
 use ns_client::RelayStatus;
 
@@ -18,6 +18,8 @@ pub enum Message {
     ToggleWrite,
     OpenRelayDocument(DbRelay),
     ReconnectRelay,
+    PowInputChange(String),
+    SubmitPow,
 }
 #[derive(Debug, Clone)]
 pub struct MessageWrapper {
@@ -33,14 +35,21 @@ impl MessageWrapper {
 pub struct RelayRow {
     pub id: i32,
     pub db_relay: DbRelay,
+    pow_input: String,
 }
 
 impl RelayRow {
     pub fn new(id: i32, db_relay: DbRelay) -> Self {
-        Self { id, db_relay }
+        let pow_input = db_relay.pow_difficulty.to_string();
+        Self {
+            id,
+            db_relay,
+            pow_input,
+        }
     }
 
     pub fn relay_updated(&mut self, db_relay: DbRelay) {
+        self.pow_input = db_relay.pow_difficulty.to_string();
         self.db_relay = db_relay;
     }
 
@@ -63,6 +72,18 @@ impl RelayRow {
             Message::ToggleWrite => {
                 conn.send(net::ToBackend::ToggleRelayWrite(self.db_relay.to_owned()))?;
             }
+            Message::PowInputChange(input) => {
+                if input.chars().all(|c| c.is_ascii_digit()) {
+                    self.pow_input = input;
+                }
+            }
+            Message::SubmitPow => match self.pow_input.parse::<u8>() {
+                Ok(difficulty) => conn.send(net::ToBackend::SetRelayPowDifficulty(
+                    self.db_relay.url.to_owned(),
+                    difficulty,
+                ))?,
+                Err(_) => self.pow_input = self.db_relay.pow_difficulty.to_string(),
+            },
         }
         Ok(Command::none())
     }
@@ -97,6 +118,9 @@ impl RelayRow {
             container(text("Write"))
                 .center_x()
                 .width(Length::Fixed(CHECKBOX_CELL_WIDTH)),
+            container(text("PoW"))
+                .center_x()
+                .width(Length::Fixed(POW_CELL_WIDTH)),
             container(text(""))
                 .center_x()
                 .width(Length::Fixed(ACTION_ICON_WIDTH)),
@@ -182,6 +206,18 @@ impl RelayRow {
                 )))
                 .center_x()
                 .width(Length::Fixed(CHECKBOX_CELL_WIDTH)),
+                tooltip(
+                    text_input("0", &self.pow_input)
+                        .on_input(|input| MessageWrapper::new(
+                            self.id,
+                            Message::PowInputChange(input)
+                        ))
+                        .on_submit(MessageWrapper::new(self.id, Message::SubmitPow))
+                        .width(Length::Fixed(POW_CELL_WIDTH)),
+                    "Proof of work difficulty, Enter to save",
+                    tooltip::Position::Top
+                )
+                .style(style::Container::TooltipBg),
                 document_btn,
                 reconnect_btn,
                 delete_btn,
@@ -260,3 +296,4 @@ const RELAY_STATUS_ICON_WIDTH: f32 = 30.0;
 const ACTION_ICON_WIDTH: f32 = 30.0;
 const CHECKBOX_CELL_WIDTH: f32 = 50.0;
 const ACTIVITY_CELL_WIDTH: f32 = 100.0;
+const POW_CELL_WIDTH: f32 = 50.0;
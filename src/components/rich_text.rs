@@ -0,0 +1,396 @@
+//! Markdown subset of the messages: `**bold**`, `*italics*` or `_italics_`,
+//! `` `inline code` ``, fenced code blocks, links, hashtags and `nostr:` mentions.
+use iced::widget::{button, column, container, text};
+use iced::{Font, Length};
+use iced_aw::Wrap;
+use url::Url;
+
+use crate::style;
+use crate::widget::Element;
+
+/// How a run of text is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanStyle {
+    Plain,
+    Bold,
+    Italic,
+    Code,
+    Link,
+    Hashtag,
+    Mention,
+}
+
+/// Run of text with a single style, without its markers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: SpanStyle,
+}
+impl Span {
+    fn new(text: &str, style: SpanStyle) -> Self {
+        Self {
+            text: text.to_owned(),
+            style,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    /// One line of the message, empty for blank lines
+    Line(Vec<Span>),
+    /// Text between ``` fences, kept as written
+    Code(String),
+}
+
+pub fn parse(content: &str) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut code_lines: Option<Vec<&str>> = None;
+
+    for line in content.lines() {
+        let fence = line.trim();
+        let is_fence = fence.starts_with(FENCE);
+
+        code_lines = match code_lines {
+            Some(lines) if is_fence => {
+                blocks.push(Block::Code(lines.join("\n")));
+                None
+            }
+            Some(mut lines) => {
+                lines.push(line);
+                Some(lines)
+            }
+            // ```code``` on a single line
+            None if is_fence && fence.len() > 2 * FENCE.len() && fence.ends_with(FENCE) => {
+                let code = &fence[FENCE.len()..fence.len() - FENCE.len()];
+                blocks.push(Block::Code(code.to_owned()));
+                None
+            }
+            // the language after the fence is ignored
+            None if is_fence => Some(vec![]),
+            None => {
+                blocks.push(Block::Line(parse_line(line)));
+                None
+            }
+        };
+    }
+
+    // an unclosed fence runs to the end of the message
+    if let Some(lines) = code_lines {
+        blocks.push(Block::Code(lines.join("\n")));
+    }
+
+    blocks
+}
+
+fn parse_line(line: &str) -> Vec<Span> {
+    let mut spans = vec![];
+    let mut plain = String::new();
+    let mut rest = line;
+    let mut prev_char: Option<char> = None;
+
+    while let Some(c) = rest.chars().next() {
+        let word_start = prev_char.map_or(true, |p| p.is_whitespace() || p == '(');
+        match inline_span(rest, word_start) {
+            Some((span, len)) => {
+                if !plain.is_empty() {
+                    spans.push(Span::new(&plain, SpanStyle::Plain));
+                    plain.clear();
+                }
+                spans.push(span);
+                prev_char = rest[..len].chars().last();
+                rest = &rest[len..];
+            }
+            None => {
+                plain.push(c);
+                prev_char = Some(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    if !plain.is_empty() {
+        spans.push(Span::new(&plain, SpanStyle::Plain));
+    }
+
+    spans
+}
+
+/// Span starting at the beginning of `rest` and the bytes it takes,
+/// markers included
+fn inline_span(rest: &str, word_start: bool) -> Option<(Span, usize)> {
+    if let Some(inner) = rest.strip_prefix('`') {
+        let end = inner.find('`').filter(|end| *end > 0)?;
+        return Some((Span::new(&inner[..end], SpanStyle::Code), end + 2));
+    }
+    if let Some(inner) = rest.strip_prefix("**") {
+        let end = closing_marker(inner, "**")?;
+        return Some((Span::new(&inner[..end], SpanStyle::Bold), end + 4));
+    }
+    if let Some(inner) = rest.strip_prefix('*') {
+        let end = closing_marker(inner, "*")?;
+        return Some((Span::new(&inner[..end], SpanStyle::Italic), end + 2));
+    }
+    // snake_case words aren't italics
+    if let Some(inner) = rest.strip_prefix('_').filter(|_| word_start) {
+        let end = closing_marker(inner, "_").filter(|end| {
+            !inner[end + 1..]
+                .chars()
+                .next()
+                .map_or(false, char::is_alphanumeric)
+        })?;
+        return Some((Span::new(&inner[..end], SpanStyle::Italic), end + 2));
+    }
+
+    if !word_start {
+        return None;
+    }
+    if rest.starts_with("https://") || rest.starts_with("http://") {
+        let word = rest.split_whitespace().next()?;
+        let link = word.trim_end_matches(|c| matches!(c, ',' | '.' | ';' | ':' | '!' | '?' | ')'));
+        Url::parse(link).ok()?;
+        return Some((Span::new(link, SpanStyle::Link), link.len()));
+    }
+    if let Some(tag) = rest.strip_prefix('#') {
+        let len = word_len(tag);
+        return (len > 0).then(|| (Span::new(&rest[..len + 1], SpanStyle::Hashtag), len + 1));
+    }
+    if let Some(entity) = rest.strip_prefix(NOSTR_URI) {
+        let len = word_len(entity);
+        let total = NOSTR_URI.len() + len;
+        return (len > 0).then(|| (Span::new(&rest[..total], SpanStyle::Mention), total));
+    }
+
+    None
+}
+
+/// Position of the marker closing an emphasis. Emphasis can't start or end
+/// with a space, so `2 * 3 * 4` stays plain.
+fn closing_marker(inner: &str, marker: &str) -> Option<usize> {
+    if inner.starts_with(char::is_whitespace) || inner.starts_with(marker) {
+        return None;
+    }
+    inner.match_indices(marker).map(|(idx, _)| idx).find(|idx| {
+        *idx > 0
+            && !inner[..*idx]
+                .chars()
+                .last()
+                .map_or(true, char::is_whitespace)
+    })
+}
+
+/// Bytes of the leading letters, digits and underscores
+fn word_len(s: &str) -> usize {
+    s.char_indices()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+        .map_or(s.len(), |(idx, _)| idx)
+}
+
+/// Message content with its formatting, `on_link` is called with the
+/// pressed link
+pub fn rich_text<'a, M: 'a + Clone>(
+    content: &str,
+    size: u16,
+    on_link: impl Fn(String) -> M,
+) -> Element<'a, M> {
+    parse(content)
+        .into_iter()
+        .fold(column![].spacing(2), |col, block| match block {
+            Block::Code(code) => col.push(
+                container(text(code).font(MONOSPACE_FONT).size(size - 2))
+                    .padding(8)
+                    .width(Length::Fill)
+                    .style(style::Container::Frame),
+            ),
+            Block::Line(spans) => col.push(line_view(spans, size, &on_link)),
+        })
+        .into()
+}
+
+fn line_view<'a, M: 'a + Clone>(
+    spans: Vec<Span>,
+    size: u16,
+    on_link: &impl Fn(String) -> M,
+) -> Element<'a, M> {
+    // plain lines keep the text widget's own wrapping
+    if spans.iter().all(|span| span.style == SpanStyle::Plain) {
+        let line: String = spans.into_iter().map(|span| span.text).collect();
+        return text(line).size(size).into();
+    }
+
+    let elements = spans
+        .into_iter()
+        .flat_map(|span| match span.style {
+            // code and links aren't broken between lines
+            SpanStyle::Code => vec![
+                container(text(span.text).font(MONOSPACE_FONT).size(size - 2))
+                    .padding([0, 4])
+                    .style(style::Container::Frame)
+                    .into(),
+            ],
+            SpanStyle::Link => vec![button(
+                text(span.text.clone())
+                    .size(size)
+                    .style(style::Text::Primary),
+            )
+            .padding(0)
+            .style(style::Button::Invisible)
+            .on_press(on_link(span.text))
+            .into()],
+            style => span
+                .text
+                .split_whitespace()
+                .map(|word| styled_word(word, style, size))
+                .collect(),
+        })
+        .collect();
+
+    Wrap::with_elements(elements)
+        .spacing(size as f32 / 3.5)
+        .line_spacing(2.0)
+        .into()
+}
+
+fn styled_word<'a, M: 'a>(word: &str, span_style: SpanStyle, size: u16) -> Element<'a, M> {
+    let word = text(word.to_owned()).size(size);
+    match span_style {
+        SpanStyle::Bold => word.font(BOLD_FONT),
+        SpanStyle::Italic => word.font(ITALIC_FONT),
+        SpanStyle::Hashtag | SpanStyle::Mention => word.style(style::Text::Primary),
+        SpanStyle::Plain | SpanStyle::Code | SpanStyle::Link => word,
+    }
+    .into()
+}
+
+const FENCE: &str = "```";
+const NOSTR_URI: &str = "nostr:";
+
+// Fonts
+const BOLD_FONT: Font = Font::External {
+    name: "DejaVu_Sans_Bold",
+    bytes: include_bytes!("../../fonts/DejaVuSans-Bold.ttf"),
+};
+const ITALIC_FONT: Font = Font::External {
+    name: "DejaVu_Sans_Oblique",
+    bytes: include_bytes!("../../fonts/DejaVuSans-Oblique.ttf"),
+};
+const MONOSPACE_FONT: Font = Font::External {
+    name: "DejaVu_Sans_Mono",
+    bytes: include_bytes!("../../fonts/DejaVuSansMono.ttf"),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, style: SpanStyle) -> Span {
+        Span::new(text, style)
+    }
+
+    fn line(content: &str) -> Vec<Span> {
+        match parse(content).as_slice() {
+            [Block::Line(spans)] => spans.to_owned(),
+            other => panic!("Expected one line, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn plain_text_is_one_span() {
+        assert_eq!(
+            line("just some words"),
+            vec![span("just some words", SpanStyle::Plain)]
+        );
+    }
+
+    #[test]
+    fn bold_italics_and_code() {
+        assert_eq!(
+            line("a **bold** and *slanted* or _leaning_ `x + 1`"),
+            vec![
+                span("a ", SpanStyle::Plain),
+                span("bold", SpanStyle::Bold),
+                span(" and ", SpanStyle::Plain),
+                span("slanted", SpanStyle::Italic),
+                span(" or ", SpanStyle::Plain),
+                span("leaning", SpanStyle::Italic),
+                span(" ", SpanStyle::Plain),
+                span("x + 1", SpanStyle::Code),
+            ]
+        );
+    }
+
+    #[test]
+    fn unmatched_markers_stay_plain() {
+        assert_eq!(
+            line("2 * 3 * 4 and **open and `tick"),
+            vec![span("2 * 3 * 4 and **open and `tick", SpanStyle::Plain)]
+        );
+        assert_eq!(
+            line("snake_case_name"),
+            vec![span("snake_case_name", SpanStyle::Plain)]
+        );
+    }
+
+    #[test]
+    fn markers_inside_code_are_kept() {
+        assert_eq!(
+            line("`**not bold**`"),
+            vec![span("**not bold**", SpanStyle::Code)]
+        );
+    }
+
+    #[test]
+    fn links_drop_trailing_punctuation() {
+        assert_eq!(
+            line("see (https://example.com/a?b=1)."),
+            vec![
+                span("see (", SpanStyle::Plain),
+                span("https://example.com/a?b=1", SpanStyle::Link),
+                span(").", SpanStyle::Plain),
+            ]
+        );
+    }
+
+    #[test]
+    fn hashtags_and_mentions() {
+        assert_eq!(
+            line("#nostr talk with nostr:npub1abc, not a#tag"),
+            vec![
+                span("#nostr", SpanStyle::Hashtag),
+                span(" talk with ", SpanStyle::Plain),
+                span("nostr:npub1abc", SpanStyle::Mention),
+                span(", not a#tag", SpanStyle::Plain),
+            ]
+        );
+    }
+
+    #[test]
+    fn fenced_code_blocks() {
+        let content = "before\n```rust\nlet a = **b**;\n  indented\n```\nafter";
+        assert_eq!(
+            parse(content),
+            vec![
+                Block::Line(vec![span("before", SpanStyle::Plain)]),
+                Block::Code("let a = **b**;\n  indented".into()),
+                Block::Line(vec![span("after", SpanStyle::Plain)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn single_line_and_unclosed_fences() {
+        assert_eq!(parse("```ls -la```"), vec![Block::Code("ls -la".into())]);
+        assert_eq!(parse("```\nno end"), vec![Block::Code("no end".into())]);
+    }
+
+    #[test]
+    fn blank_lines_are_kept() {
+        assert_eq!(
+            parse("one\n\ntwo"),
+            vec![
+                Block::Line(vec![span("one", SpanStyle::Plain)]),
+                Block::Line(vec![]),
+                Block::Line(vec![span("two", SpanStyle::Plain)]),
+            ]
+        );
+    }
+}
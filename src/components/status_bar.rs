@@ -1,11 +1,12 @@
 use iced::widget::{button, container, row, text, Space};
 use iced::Subscription;
 use iced::{alignment, Alignment, Command, Length};
+use nostr::EventId;
 
 use crate::consts::NOSTRTALK_VERSION;
 use crate::error::BackendClosed;
 use crate::icon::signal_icon;
-use crate::net::{self, BackEndConnection, BackendEvent};
+use crate::net::{self, BackEndConnection, BackendEvent, MiningProgress};
 use crate::style;
 use crate::views::{GoToView, RouterCommand};
 use crate::widget::Element;
@@ -14,15 +15,19 @@ use crate::widget::Element;
 pub enum Message {
     GoToAbout,
     GoToNetwork,
+    CancelMining(EventId),
     Tick,
 }
 pub struct StatusBar {
     relays_connected: usize,
+    /// Events being mined, in the order they started
+    mining: Vec<MiningProgress>,
 }
 impl StatusBar {
     pub fn new() -> Self {
         Self {
             relays_connected: 0,
+            mining: Vec::new(),
         }
     }
     pub fn backend_event(
@@ -30,11 +35,27 @@ impl StatusBar {
         event: BackendEvent,
         _conn: &mut BackEndConnection,
     ) -> Command<Message> {
-        if let BackendEvent::GotRelayStatusList(list) = event {
-            self.relays_connected = list
-                .iter()
-                .filter(|(_url, status)| status.is_connected())
-                .count();
+        match event {
+            BackendEvent::GotRelayStatusList(list) => {
+                self.relays_connected = list
+                    .iter()
+                    .filter(|(_url, status)| status.is_connected())
+                    .count();
+            }
+            BackendEvent::MiningProgress(progress) => {
+                match self
+                    .mining
+                    .iter_mut()
+                    .find(|p| p.pending_hash == progress.pending_hash)
+                {
+                    Some(current) => *current = progress,
+                    None => self.mining.push(progress),
+                }
+            }
+            BackendEvent::MiningDone { pending_hash, .. } => {
+                self.mining.retain(|p| p.pending_hash != pending_hash);
+            }
+            _ => (),
         }
         Command::none()
     }
@@ -47,6 +68,9 @@ impl StatusBar {
         match message {
             Message::GoToAbout => command.change_route(GoToView::About),
             Message::GoToNetwork => command.change_route(GoToView::Network),
+            Message::CancelMining(pending_hash) => {
+                conn.send(net::ToBackend::CancelMining(pending_hash))?;
+            }
             Message::Tick => {
                 conn.send(net::ToBackend::GetRelayStatusList)?;
             }
@@ -72,8 +96,36 @@ impl StatusBar {
         .on_press(Message::GoToNetwork)
         .style(style::Button::StatusBarButton);
 
+        let mining: Element<_> = match self.mining.first() {
+            Some(progress) => {
+                let queued = match self.mining.len() {
+                    1 => String::new(),
+                    len => format!(" (+{})", len - 1),
+                };
+                let cancel = button(text("Cancel").size(16))
+                    .padding([0, 2])
+                    .height(Length::Fill)
+                    .on_press(Message::CancelMining(progress.pending_hash))
+                    .style(style::Button::StatusBarButton);
+                row![
+                    text(format!(
+                        "Proof of work {}/{}{}",
+                        progress.best, progress.target, queued
+                    ))
+                    .size(16),
+                    cancel
+                ]
+                .spacing(5)
+                .align_items(Alignment::Center)
+                .into()
+            }
+            None => Space::with_width(Length::Shrink).into(),
+        };
+
         container(
-            row![about, Space::with_width(Length::Fill), signal].align_items(Alignment::Center),
+            row![about, Space::with_width(Length::Fill), mining, signal]
+                .spacing(5)
+                .align_items(Alignment::Center),
         )
         .padding(0)
         .align_x(alignment::Horizontal::Right)
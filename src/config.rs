@@ -21,10 +21,34 @@ pub enum Error {
     Serialize(#[from] toml::ser::Error),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Config {
     /// Theme of the application
     pub theme: Theme,
+    /// Disk space in megabytes for cached images
+    #[serde(default = "default_cache_budget_mb")]
+    pub cache_budget_mb: u64,
+    /// NIP-96 server where the files sent in messages are uploaded
+    #[serde(default = "default_media_server")]
+    pub media_server: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            cache_budget_mb: default_cache_budget_mb(),
+            media_server: default_media_server(),
+        }
+    }
+}
+
+fn default_cache_budget_mb() -> u64 {
+    DEFAULT_CACHE_BUDGET_MB
+}
+
+fn default_media_server() -> String {
+    DEFAULT_MEDIA_SERVER.to_owned()
 }
 
 impl Config {
@@ -106,3 +130,5 @@ fn config_dir() -> Result<PathBuf, Error> {
 }
 
 const CONFIG_FILENAME: &str = "config.toml";
+const DEFAULT_CACHE_BUDGET_MB: u64 = 500;
+const DEFAULT_MEDIA_SERVER: &str = "https://nostr.build";
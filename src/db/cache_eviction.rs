@@ -0,0 +1,242 @@
+use std::collections::HashSet;
+
+use chrono::{Duration, Utc};
+use nostr::secp256k1::XOnlyPublicKey;
+use sqlx::{FromRow, Row, SqlitePool};
+use thiserror::Error;
+
+use crate::utils::public_key_or_err;
+
+use super::image_cache::delete_images;
+use super::{ChannelPreviewMessage, ImageBlob, ImageDownloaded, LinkPreview};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+
+    #[error("{0}")]
+    FromImageCache(#[from] super::image_cache::Error),
+
+    #[error("{0}")]
+    FromImageBlob(#[from] super::image_blob::Error),
+
+    #[error("{0}")]
+    FromLinkPreview(#[from] super::link_preview::Error),
+
+    #[error("{0}")]
+    FromChannelPreview(#[from] super::channel_preview::Error),
+}
+
+/// Limits for the image and profile caches
+#[derive(Debug, Clone)]
+pub struct CacheBudget {
+    /// Disk space used by cached images
+    pub max_image_bytes: u64,
+    /// Profiles not looked at for this long are removed
+    pub profile_max_age: Duration,
+}
+impl CacheBudget {
+    pub fn from_megabytes(max_image_mb: u64) -> Self {
+        Self {
+            max_image_bytes: max_image_mb * 1024 * 1024,
+            profile_max_age: Duration::days(PROFILE_MAX_AGE_DAYS),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SweepStats {
+    pub images_evicted: usize,
+    pub profiles_evicted: usize,
+    pub previews_evicted: u64,
+    pub channel_previews_evicted: u64,
+    pub bytes_freed: u64,
+}
+
+struct CachedImage {
+    id: i64,
+    image: ImageDownloaded,
+    size_bytes: u64,
+    /// Profile using the image, none for channel images or outdated profile images
+    owner: Option<XOnlyPublicKey>,
+}
+
+pub struct CacheEviction;
+impl CacheEviction {
+    /// Evicts the least recently used entries until the cache fits the budget.
+    ///
+    /// Profiles and avatars of the `protected` public keys are never evicted.
+    pub async fn sweep(
+        cache_pool: &SqlitePool,
+        protected: &HashSet<XOnlyPublicKey>,
+        budget: &CacheBudget,
+    ) -> Result<SweepStats, Error> {
+        let mut stats = SweepStats::default();
+
+        measure_unsized_images(cache_pool).await?;
+        evict_old_profiles(cache_pool, protected, budget, &mut stats).await?;
+        let now = Utc::now().naive_utc();
+        stats.previews_evicted = LinkPreview::delete_expired(cache_pool, now).await?;
+        stats.channel_previews_evicted =
+            ChannelPreviewMessage::delete_expired(cache_pool, now).await?;
+
+        let mut total_bytes = stored_bytes(cache_pool).await?;
+        let images = fetch_images_lru(cache_pool).await?;
+
+        for cached in images {
+            if total_bytes <= budget.max_image_bytes {
+                break;
+            }
+            if cached
+                .owner
+                .as_ref()
+                .map_or(false, |owner| protected.contains(owner))
+            {
+                continue;
+            }
+            let size_bytes = cached.size_bytes;
+            if evict_image(cache_pool, cached).await? {
+                total_bytes = total_bytes.saturating_sub(size_bytes);
+                stats.images_evicted += 1;
+                stats.bytes_freed += size_bytes;
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Images cached before sizes were tracked
+async fn measure_unsized_images(cache_pool: &SqlitePool) -> Result<(), Error> {
+    let rows = sqlx::query("SELECT * FROM image_cache WHERE size_bytes = 0")
+        .fetch_all(cache_pool)
+        .await?;
+
+    for row in rows {
+        let id: i64 = row.try_get("id")?;
+        let image = ImageDownloaded::from_row(&row)?;
+        sqlx::query("UPDATE image_cache SET size_bytes = ? WHERE id = ?")
+            .bind(image.files_size() as i64)
+            .bind(id)
+            .execute(cache_pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn evict_old_profiles(
+    cache_pool: &SqlitePool,
+    protected: &HashSet<XOnlyPublicKey>,
+    budget: &CacheBudget,
+    stats: &mut SweepStats,
+) -> Result<(), Error> {
+    let oldest_allowed = Utc::now() - budget.profile_max_age;
+    let rows = sqlx::query(
+        "SELECT public_key, event_hash FROM profile_meta_cache WHERE last_accessed_at < ?",
+    )
+    .bind(oldest_allowed.timestamp_millis())
+    .fetch_all(cache_pool)
+    .await?;
+
+    for row in rows {
+        let public_key: String = row.try_get("public_key")?;
+        if protected.contains(&public_key_or_err(&public_key, "public_key")?) {
+            continue;
+        }
+        let event_hash: String = row.try_get("event_hash")?;
+
+        let images = sqlx::query("SELECT * FROM image_cache WHERE event_hash = ?")
+            .bind(&event_hash)
+            .fetch_all(cache_pool)
+            .await?;
+        for image_row in images {
+            let cached = CachedImage {
+                id: image_row.try_get("id")?,
+                image: ImageDownloaded::from_row(&image_row)?,
+                size_bytes: image_row.try_get::<i64, &str>("size_bytes")? as u64,
+                owner: None,
+            };
+            let size_bytes = cached.size_bytes;
+            if evict_image(cache_pool, cached).await? {
+                stats.images_evicted += 1;
+                stats.bytes_freed += size_bytes;
+            }
+        }
+
+        sqlx::query("DELETE FROM profile_meta_cache WHERE public_key = ?")
+            .bind(&public_key)
+            .execute(cache_pool)
+            .await?;
+        stats.profiles_evicted += 1;
+    }
+
+    Ok(())
+}
+
+/// Disk space used by images, counting files shared by many rows once
+async fn stored_bytes(cache_pool: &SqlitePool) -> Result<u64, Error> {
+    let sql = r#"
+        SELECT COALESCE(SUM(size_bytes), 0) FROM
+            (SELECT MAX(size_bytes) AS size_bytes FROM image_cache GROUP BY path);
+    "#;
+    let total: i64 = sqlx::query_scalar(sql).fetch_one(cache_pool).await?;
+    Ok(total as u64)
+}
+
+async fn fetch_images_lru(cache_pool: &SqlitePool) -> Result<Vec<CachedImage>, Error> {
+    let sql = r#"
+        SELECT image_cache.*, profile_meta_cache.public_key AS owner
+        FROM image_cache
+        LEFT JOIN profile_meta_cache ON profile_meta_cache.event_hash = image_cache.event_hash
+        ORDER BY image_cache.last_accessed_at ASC;
+    "#;
+    let rows = sqlx::query(sql).fetch_all(cache_pool).await?;
+
+    let mut images = Vec::with_capacity(rows.len());
+    for row in rows {
+        let owner = row
+            .try_get::<Option<String>, &str>("owner")?
+            .map(|owner| public_key_or_err(&owner, "owner"))
+            .transpose()?;
+        images.push(CachedImage {
+            id: row.try_get("id")?,
+            image: ImageDownloaded::from_row(&row)?,
+            size_bytes: row.try_get::<i64, &str>("size_bytes")? as u64,
+            owner,
+        });
+    }
+
+    Ok(images)
+}
+
+/// Returns true when the files were deleted, i.e. no other row was using them
+async fn evict_image(cache_pool: &SqlitePool, cached: CachedImage) -> Result<bool, Error> {
+    sqlx::query("DELETE FROM image_cache WHERE id = ?")
+        .bind(cached.id)
+        .execute(cache_pool)
+        .await?;
+
+    // blobs are shared by every event using the same picture
+    let still_used: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM image_cache WHERE path = ?")
+        .bind(cached.image.path.to_string_lossy())
+        .fetch_one(cache_pool)
+        .await?;
+    if still_used > 0 {
+        return Ok(false);
+    }
+
+    ImageBlob::delete_by_path(cache_pool, &cached.image.path).await?;
+    let is_blob = cached.image.content_hash.is_some();
+    let blob_dir = cached.image.path.parent().map(|dir| dir.to_owned());
+    delete_images(cached.image).await?;
+    if let (true, Some(blob_dir)) = (is_blob, blob_dir) {
+        // fails if something else is in there, which is fine
+        _ = tokio::fs::remove_dir(blob_dir).await;
+    }
+
+    Ok(true)
+}
+
+const PROFILE_MAX_AGE_DAYS: i64 = 30;
@@ -4,8 +4,10 @@ use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
 use thiserror::Error;
 use url::Url;
 
+use crate::types::Attachment;
 use crate::utils::{
-    channel_id_from_tags, event_hash_or_err, millis_to_naive_or_err, public_key_or_err, url_or_err,
+    attachments_or_err, channel_id_from_tags, event_hash_or_err, millis_to_naive_or_err,
+    public_key_or_err, url_or_err,
 };
 
 use super::DbEvent;
@@ -20,6 +22,9 @@ pub enum Error {
 
     #[error("Not found channel message: event_hash: {0}")]
     NotFoundMessage(EventId),
+
+    #[error("JSON (de)serialization error: {0}")]
+    FromSerdeJson(#[from] serde_json::Error),
 }
 
 #[derive(Debug, Clone)]
@@ -31,7 +36,16 @@ pub struct DbChannelMessage {
     pub created_at: NaiveDateTime,
     pub relay_url: Url,
     pub content: String,
+    pub attachments: Vec<Attachment>,
+}
+/// How much a member posted in a channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelMemberStats {
+    pub author: XOnlyPublicKey,
+    pub message_count: i64,
+    pub last_active: NaiveDateTime,
 }
+
 impl DbChannelMessage {
     pub fn display_name(&self) -> String {
         self.author.to_bech32().unwrap_or(self.author.to_string())
@@ -61,6 +75,53 @@ impl DbChannelMessage {
         Ok(messages)
     }
 
+    /// Messages before and after `created_at`, used to show a message in context
+    pub async fn fetch_around(
+        pool: &SqlitePool,
+        channel_id: &EventId,
+        created_at: NaiveDateTime,
+    ) -> Result<Vec<Self>, Error> {
+        let sql = r#"
+            SELECT * FROM (
+                SELECT * FROM channel_message
+                WHERE channel_id = ?1 AND created_at <= ?2
+                ORDER BY created_at DESC
+                LIMIT 50
+            )
+            UNION
+            SELECT * FROM (
+                SELECT * FROM channel_message
+                WHERE channel_id = ?1 AND created_at > ?2
+                ORDER BY created_at ASC
+                LIMIT 50
+            )
+            ORDER BY created_at ASC;
+        "#;
+        let messages = sqlx::query_as::<_, Self>(sql)
+            .bind(channel_id.to_string())
+            .bind(created_at.timestamp_millis())
+            .fetch_all(pool)
+            .await?;
+        Ok(messages)
+    }
+
+    pub async fn member_stats(
+        pool: &SqlitePool,
+        channel_id: &EventId,
+    ) -> Result<Vec<ChannelMemberStats>, Error> {
+        let sql = r#"
+            SELECT author, COUNT(*) AS message_count, MAX(created_at) AS last_active
+            FROM channel_message
+            WHERE channel_id = ?
+            GROUP BY author;
+        "#;
+        let stats = sqlx::query_as::<_, ChannelMemberStats>(sql)
+            .bind(channel_id.to_string())
+            .fetch_all(pool)
+            .await?;
+        Ok(stats)
+    }
+
     pub async fn insert_confirmed(
         pool: &SqlitePool,
         db_event: &DbEvent,
@@ -82,10 +143,12 @@ impl DbChannelMessage {
 
                 let sql = r#"
                     INSERT INTO channel_message (
-                        event_id, channel_id, author, is_users, created_at, relay_url, content
+                        event_id, channel_id, author, is_users, created_at, relay_url, content,
+                        attachments
                     )
-                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);
                 "#;
+                let attachments = Attachment::from_tags(&db_event.tags);
 
                 let output = sqlx::query(sql)
                     .bind(db_event.event_id)
@@ -95,6 +158,7 @@ impl DbChannelMessage {
                     .bind(db_event.created_at.timestamp_millis())
                     .bind(db_event.relay_url.as_ref())
                     .bind(&db_event.content)
+                    .bind(serde_json::to_string(&attachments)?)
                     .execute(pool)
                     .await?;
 
@@ -131,6 +195,9 @@ impl sqlx::FromRow<'_, SqliteRow> for DbChannelMessage {
         let content: String = row.try_get("content")?;
         let is_users: bool = row.try_get("is_users")?;
 
+        let attachments: String = row.try_get("attachments")?;
+        let attachments = attachments_or_err(&attachments, "attachments")?;
+
         Ok(DbChannelMessage {
             event_id,
             channel_id,
@@ -139,6 +206,23 @@ impl sqlx::FromRow<'_, SqliteRow> for DbChannelMessage {
             created_at,
             relay_url,
             content,
+            attachments,
+        })
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for ChannelMemberStats {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        let author = &row.try_get::<String, &str>("author")?;
+        let author = public_key_or_err(author, "author")?;
+
+        let last_active = row.try_get::<i64, &str>("last_active")?;
+        let last_active = millis_to_naive_or_err(last_active, "last_active")?;
+
+        Ok(ChannelMemberStats {
+            author,
+            message_count: row.try_get("message_count")?,
+            last_active,
         })
     }
 }
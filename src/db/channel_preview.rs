@@ -0,0 +1,121 @@
+use chrono::{Duration, NaiveDateTime};
+use nostr::{secp256k1::XOnlyPublicKey, EventId};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use thiserror::Error;
+
+use crate::utils::{
+    event_hash_or_err, millis_to_naive_or_err, ns_event_to_naive, public_key_or_err,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(nostr::Timestamp),
+}
+
+/// Message of a channel the user isn't subscribed to, kept in the cache
+/// so the channel can be looked at before joining
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelPreviewMessage {
+    pub event_hash: EventId,
+    pub channel_id: EventId,
+    pub author: XOnlyPublicKey,
+    /// From the author's cached profile, filled when fetched by the backend
+    pub author_name: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub content: String,
+}
+impl ChannelPreviewMessage {
+    pub async fn insert(
+        cache_pool: &SqlitePool,
+        channel_id: &EventId,
+        ns_event: &nostr::Event,
+        now: NaiveDateTime,
+    ) -> Result<(), Error> {
+        let created_at = ns_event_to_naive(ns_event.created_at)
+            .map_err(|_| Error::InvalidTimestamp(ns_event.created_at))?;
+        let sql = r#"
+            INSERT OR IGNORE INTO channel_preview_message
+                (event_hash, channel_id, author, created_at, content, fetched_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+        "#;
+        sqlx::query(sql)
+            .bind(ns_event.id.to_string())
+            .bind(channel_id.to_string())
+            .bind(ns_event.pubkey.to_string())
+            .bind(created_at.timestamp_millis())
+            .bind(&ns_event.content)
+            .bind(now.timestamp_millis())
+            .execute(cache_pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Latest `limit` messages of the channel, oldest first
+    pub async fn fetch_latest(
+        cache_pool: &SqlitePool,
+        channel_id: &EventId,
+        limit: u32,
+    ) -> Result<Vec<Self>, Error> {
+        let sql = r#"
+            SELECT * FROM (
+                SELECT * FROM channel_preview_message
+                WHERE channel_id = ?
+                ORDER BY created_at DESC
+                LIMIT ?
+            )
+            ORDER BY created_at ASC;
+        "#;
+        let messages = sqlx::query_as::<_, Self>(sql)
+            .bind(channel_id.to_string())
+            .bind(limit)
+            .fetch_all(cache_pool)
+            .await?;
+        Ok(messages)
+    }
+
+    /// Removes the previews of past searches, returns how many
+    pub async fn delete_expired(cache_pool: &SqlitePool, now: NaiveDateTime) -> Result<u64, Error> {
+        let oldest_fresh = now - Duration::seconds(CHANNEL_PREVIEW_TTL_SECS);
+        let deleted = sqlx::query("DELETE FROM channel_preview_message WHERE fetched_at < ?")
+            .bind(oldest_fresh.timestamp_millis())
+            .execute(cache_pool)
+            .await?
+            .rows_affected();
+        Ok(deleted)
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for ChannelPreviewMessage {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        let created_at = row.try_get::<i64, &str>("created_at")?;
+        let created_at = millis_to_naive_or_err(created_at, "created_at")?;
+
+        let event_hash: String = row.try_get("event_hash")?;
+        let event_hash = event_hash_or_err(&event_hash, "event_hash")?;
+
+        let channel_id: String = row.try_get("channel_id")?;
+        let channel_id = event_hash_or_err(&channel_id, "channel_id")?;
+
+        let author = &row.try_get::<String, &str>("author")?;
+        let author = public_key_or_err(author, "author")?;
+
+        Ok(Self {
+            event_hash,
+            channel_id,
+            author,
+            author_name: None,
+            created_at,
+            content: row.try_get("content")?,
+        })
+    }
+}
+
+/// Messages shown in a channel preview
+pub const CHANNEL_PREVIEW_LIMIT: u32 = 20;
+
+/// Previews are only useful while searching, kept for a day
+const CHANNEL_PREVIEW_TTL_SECS: i64 = 24 * 60 * 60;
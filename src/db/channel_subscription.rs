@@ -21,6 +21,19 @@ pub struct ChannelSubscription {
     pub id: i64,
     pub channel_id: EventId,
     pub subscribed_at: NaiveDateTime,
+    /// When the channel was last opened, `None` until then
+    pub last_read_at: Option<NaiveDateTime>,
+    /// Latest message when the channel was last opened, the ones received after are unread
+    pub last_read_event_id: Option<i64>,
+}
+
+/// Unread count and latest message of a subscribed channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelActivity {
+    pub channel_id: EventId,
+    pub unread_count: i64,
+    pub last_message: Option<String>,
+    pub last_message_at: Option<NaiveDateTime>,
 }
 
 impl ChannelSubscription {
@@ -54,6 +67,82 @@ impl ChannelSubscription {
         Ok(channel)
     }
 
+    /// Marks the messages received so far as read.
+    ///
+    /// The marker is the latest event_id, which follows the local receive
+    /// order, since `created_at` is set by the authors and can be far ahead.
+    pub async fn mark_read(pool: &SqlitePool, channel_id: &EventId) -> Result<(), Error> {
+        let utc_now = UserConfig::get_corrected_time(pool)
+            .await
+            .unwrap_or(Utc::now().naive_utc());
+
+        let sql = r#"
+            UPDATE channel_subscription
+            SET last_read_at = ?1,
+                last_read_event_id = COALESCE(
+                    (SELECT MAX(event_id) FROM channel_message WHERE channel_id = ?2), 0
+                )
+            WHERE channel_id = ?2;
+        "#;
+        sqlx::query(sql)
+            .bind(utc_now.timestamp_millis())
+            .bind(channel_id.to_string())
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Messages from others received since the channel was last read,
+    /// leaving out the history from before subscribing
+    pub async fn fetch_activity(
+        pool: &SqlitePool,
+        channel_id: &EventId,
+    ) -> Result<ChannelActivity, Error> {
+        // channels read before `last_read_event_id` existed only have `last_read_at`
+        let sql = r#"
+            SELECT COUNT(*) FROM channel_message m
+            JOIN channel_subscription s ON s.channel_id = m.channel_id
+            WHERE m.channel_id = ?
+                AND m.is_users = 0
+                AND m.created_at > s.subscribed_at
+                AND CASE
+                    WHEN s.last_read_event_id IS NOT NULL THEN m.event_id > s.last_read_event_id
+                    WHEN s.last_read_at IS NOT NULL THEN m.created_at > s.last_read_at
+                    ELSE 1
+                END;
+        "#;
+        let unread_count: i64 = sqlx::query_scalar(sql)
+            .bind(channel_id.to_string())
+            .fetch_one(pool)
+            .await?;
+
+        let sql = r#"
+            SELECT content, created_at FROM channel_message
+            WHERE channel_id = ?
+            ORDER BY created_at DESC
+            LIMIT 1;
+        "#;
+        let last: Option<(String, i64)> = sqlx::query_as(sql)
+            .bind(channel_id.to_string())
+            .fetch_optional(pool)
+            .await?;
+        let (last_message, last_message_at) = match last {
+            Some((content, created_at)) => (
+                Some(content),
+                Some(millis_to_naive_or_err(created_at, "created_at")?),
+            ),
+            None => (None, None),
+        };
+
+        Ok(ChannelActivity {
+            channel_id: channel_id.to_owned(),
+            unread_count,
+            last_message,
+            last_message_at,
+        })
+    }
+
     pub async fn delete(pool: &SqlitePool, channel_id: &EventId) -> Result<(), Error> {
         let sql = "DELETE FROM channel_subscription WHERE channel_id = ?;";
 
@@ -71,6 +160,11 @@ impl sqlx::FromRow<'_, SqliteRow> for ChannelSubscription {
         let subscribed_at = row.try_get::<i64, &str>("subscribed_at")?;
         let subscribed_at = millis_to_naive_or_err(subscribed_at, "subscribed_at")?;
 
+        let last_read_at = row
+            .try_get::<Option<i64>, &str>("last_read_at")?
+            .map(|millis| millis_to_naive_or_err(millis, "last_read_at"))
+            .transpose()?;
+
         let channel_id: String = row.try_get("channel_id")?;
         let channel_id = event_hash_or_err(&channel_id, "channel_id")?;
 
@@ -78,6 +172,8 @@ impl sqlx::FromRow<'_, SqliteRow> for ChannelSubscription {
             id: row.try_get("id")?,
             channel_id,
             subscribed_at,
+            last_read_at,
+            last_read_event_id: row.try_get("last_read_event_id")?,
         })
     }
 }
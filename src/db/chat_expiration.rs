@@ -0,0 +1,98 @@
+use std::fmt;
+use std::time::Duration;
+
+use sqlx::SqlitePool;
+use thiserror::Error;
+
+use super::NotificationChat;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// How long outgoing messages of a chat live before expiring (NIP-40)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExpirationTimer {
+    #[default]
+    Off,
+    OneHour,
+    OneDay,
+    SevenDays,
+}
+impl ExpirationTimer {
+    pub const ALL: [ExpirationTimer; 4] = [
+        ExpirationTimer::Off,
+        ExpirationTimer::OneHour,
+        ExpirationTimer::OneDay,
+        ExpirationTimer::SevenDays,
+    ];
+
+    /// None when the timer is off
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            ExpirationTimer::Off => None,
+            ExpirationTimer::OneHour => Some(Duration::from_secs(60 * 60)),
+            ExpirationTimer::OneDay => Some(Duration::from_secs(24 * 60 * 60)),
+            ExpirationTimer::SevenDays => Some(Duration::from_secs(7 * 24 * 60 * 60)),
+        }
+    }
+
+    fn from_secs(secs: i64) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|timer| timer.duration().map(|d| d.as_secs() as i64) == Some(secs))
+            .unwrap_or_default()
+    }
+
+    pub async fn fetch(pool: &SqlitePool, chat: &NotificationChat) -> Result<Self, Error> {
+        let sql = "SELECT timer_secs FROM chat_expiration WHERE chat_type = ? AND chat_id = ?";
+        let secs: Option<i64> = sqlx::query_scalar(sql)
+            .bind(chat.chat_type())
+            .bind(chat.chat_id())
+            .fetch_optional(pool)
+            .await?;
+        Ok(secs.map(Self::from_secs).unwrap_or_default())
+    }
+
+    pub async fn save(
+        pool: &SqlitePool,
+        chat: &NotificationChat,
+        timer: ExpirationTimer,
+    ) -> Result<(), Error> {
+        match timer.duration() {
+            None => {
+                let sql = "DELETE FROM chat_expiration WHERE chat_type = ? AND chat_id = ?";
+                sqlx::query(sql)
+                    .bind(chat.chat_type())
+                    .bind(chat.chat_id())
+                    .execute(pool)
+                    .await?;
+            }
+            Some(duration) => {
+                let sql = r#"
+                    INSERT OR REPLACE INTO chat_expiration (chat_type, chat_id, timer_secs)
+                    VALUES (?, ?, ?)
+                "#;
+                sqlx::query(sql)
+                    .bind(chat.chat_type())
+                    .bind(chat.chat_id())
+                    .bind(duration.as_secs() as i64)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+impl fmt::Display for ExpirationTimer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpirationTimer::Off => write!(f, "Off"),
+            ExpirationTimer::OneHour => write!(f, "1 hour"),
+            ExpirationTimer::OneDay => write!(f, "1 day"),
+            ExpirationTimer::SevenDays => write!(f, "7 days"),
+        }
+    }
+}
@@ -42,7 +42,9 @@ pub enum Error {
     FromUrlParse(#[from] url::ParseError),
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Unknown contacts are senders that messaged the user first. Their chats
+/// are message requests until the user accepts them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ContactStatus {
     Unknown = 0,
     Known = 1,
@@ -65,7 +67,11 @@ pub struct DbContact {
     created_at: NaiveDateTime,
     updated_at: NaiveDateTime,
     status: ContactStatus,
+    /// Published in the user's contact list
+    listed: bool,
     profile_cache: Option<ProfileCache>,
+    /// Images in messages from this contact are downloaded without asking
+    auto_load_media: bool,
 }
 
 impl From<&DbContact> for nostr::Contact {
@@ -117,9 +123,11 @@ impl DbContact {
             relay_url: None,
             petname: None,
             status: ContactStatus::Unknown,
+            listed: false,
             created_at: chrono::Utc::now().naive_utc(),
             updated_at: chrono::Utc::now().naive_utc(),
             profile_cache: None,
+            auto_load_media: false,
         }
     }
 
@@ -188,6 +196,12 @@ impl DbContact {
 
         Ok(db_contact)
     }
+    pub fn is_known(&self) -> bool {
+        self.status == ContactStatus::Known
+    }
+    pub fn is_listed(&self) -> bool {
+        self.listed
+    }
     pub fn get_petname(&self) -> Option<String> {
         self.petname.clone()
     }
@@ -218,6 +232,13 @@ impl DbContact {
     pub fn get_relay_url(&self) -> Option<Url> {
         self.relay_url.clone()
     }
+    pub fn auto_load_media(&self) -> bool {
+        self.auto_load_media
+    }
+    pub fn with_auto_load_media(mut self, auto_load_media: bool) -> Self {
+        self.auto_load_media = auto_load_media;
+        self
+    }
     pub fn with_profile_cache(mut self, cache: &ProfileCache) -> Self {
         self.profile_cache = Some(cache.clone());
         self
@@ -275,7 +296,6 @@ impl DbContact {
                 conn.send(net::ToBackend::DownloadImage {
                     image_url: image_url.to_owned(),
                     kind,
-                    identifier: self.pubkey.to_string(),
                     event_hash: cache.event_hash.to_owned(),
                 })?;
             } else {
@@ -295,6 +315,13 @@ impl DbContact {
         Ok(db_contacts)
     }
 
+    /// Contacts to publish in the user's contact list
+    pub async fn fetch_listed(pool: &SqlitePool) -> Result<Vec<DbContact>, Error> {
+        let sql = format!("{} WHERE listed = 1", Self::FETCH_QUERY);
+        let db_contacts = sqlx::query_as::<_, DbContact>(&sql).fetch_all(pool).await?;
+        Ok(db_contacts)
+    }
+
     pub async fn fetch(
         pool: &SqlitePool,
         cache_pool: &SqlitePool,
@@ -314,22 +341,67 @@ impl DbContact {
         Ok(db_contacts)
     }
 
+    /// Inserts a contact added by the user, known and listed
     pub async fn insert(pool: &SqlitePool, pubkey: &XOnlyPublicKey) -> Result<i64, Error> {
+        Self::insert_with_status(pool, pubkey, ContactStatus::Known).await
+    }
+
+    async fn insert_with_status(
+        pool: &SqlitePool,
+        pubkey: &XOnlyPublicKey,
+        status: ContactStatus,
+    ) -> Result<i64, Error> {
         let utc_now = UserConfig::get_corrected_time(pool)
             .await
             .unwrap_or(Utc::now().naive_utc());
 
-        let output =
-            sqlx::query("INSERT INTO contact (pubkey, created_at, updated_at) VALUES (?, ?, ?);")
-                .bind(&pubkey.to_string())
-                .bind(utc_now.timestamp_millis())
-                .bind(utc_now.timestamp_millis())
-                .execute(pool)
-                .await?;
+        let sql = r#"
+            INSERT INTO contact (pubkey, status, listed, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?);
+        "#;
+        let output = sqlx::query(sql)
+            .bind(&pubkey.to_string())
+            .bind(status as u8)
+            .bind(status == ContactStatus::Known)
+            .bind(utc_now.timestamp_millis())
+            .bind(utc_now.timestamp_millis())
+            .execute(pool)
+            .await?;
 
         Ok(output.last_insert_rowid())
     }
 
+    /// Promotes a message request to a known contact, adding it to the
+    /// published contact list when `listed`
+    pub async fn accept(
+        pool: &SqlitePool,
+        pubkey: &XOnlyPublicKey,
+        listed: bool,
+    ) -> Result<(), Error> {
+        let sql = "UPDATE contact SET status = ?, listed = MAX(listed, ?) WHERE pubkey = ?";
+        sqlx::query(sql)
+            .bind(ContactStatus::Known as u8)
+            .bind(listed)
+            .bind(pubkey.to_string())
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Whether `pubkey` is a contact the user added or accepted
+    pub async fn is_known_pubkey(
+        pool: &SqlitePool,
+        pubkey: &XOnlyPublicKey,
+    ) -> Result<bool, Error> {
+        let sql = "SELECT EXISTS(SELECT 1 FROM contact WHERE pubkey = ? AND status = ?)";
+        let known: bool = sqlx::query_scalar(sql)
+            .bind(pubkey.to_string())
+            .bind(ContactStatus::Known as u8)
+            .fetch_one(pool)
+            .await?;
+        Ok(known)
+    }
+
     pub async fn fetch_insert(
         pool: &SqlitePool,
         cache_pool: &SqlitePool,
@@ -343,7 +415,9 @@ impl DbContact {
             .await?;
 
         let mut db_contact = if result.is_none() {
-            let last_insert_rowid = Self::insert(pool, pubkey).await?;
+            // someone the user doesn't know yet, kept as a message request
+            let last_insert_rowid =
+                Self::insert_with_status(pool, pubkey, ContactStatus::Unknown).await?;
             let sql = format!("{} WHERE id = ?", Self::FETCH_QUERY);
             sqlx::query_as::<_, DbContact>(&sql)
                 .bind(last_insert_rowid)
@@ -387,6 +461,7 @@ impl DbContact {
         }
     }
 
+    /// Upserts an entry of the user's contact list, which is known and listed
     pub async fn upsert_contact(pool: &SqlitePool, contact: &DbContact) -> Result<(), Error> {
         tracing::debug!("Upserting Contact {}", contact.pubkey().to_string());
         tracing::debug!("{:?}", contact);
@@ -398,13 +473,13 @@ impl DbContact {
         // SQL queries as static strings
         const UPDATE_SQL: &str = r#"
             UPDATE contact 
-            SET relay_url=?, petname=?, updated_at=?
+            SET relay_url=?, petname=?, status=1, listed=1, updated_at=?
             WHERE pubkey=?
         "#;
         const INSERT_SQL: &str = r#"
             INSERT INTO contact 
-                (pubkey, relay_url, petname, status, created_at, updated_at) 
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                (pubkey, relay_url, petname, status, listed, created_at, updated_at) 
+            VALUES (?1, ?2, ?3, ?4, 1, ?5, ?6)
         "#;
 
         let mut tx = pool.begin().await?;
@@ -425,7 +500,7 @@ impl DbContact {
                 .bind(&contact.pubkey.to_string())
                 .bind(&contact.relay_url.as_ref().map(|url| url.to_string()))
                 .bind(&contact.petname)
-                .bind(contact.status as u8)
+                .bind(ContactStatus::Known as u8)
                 .bind(contact.created_at.timestamp_millis())
                 .bind(contact.updated_at.timestamp_millis())
                 .execute(&mut tx)
@@ -450,14 +525,14 @@ impl DbContact {
 
         let sql = r#"
             UPDATE contact 
-            SET relay_url=?, petname=?, status=?, updated_at=?
+            SET relay_url=?, petname=?, auto_load_media=?, updated_at=?
             WHERE pubkey=?
         "#;
 
         sqlx::query(sql)
             .bind(&contact.relay_url.as_ref().map(|url| url.to_string()))
             .bind(&contact.petname)
-            .bind(contact.status as u8)
+            .bind(contact.auto_load_media)
             .bind(utc_now.timestamp_millis())
             .bind(&contact.pubkey.to_string())
             .execute(pool)
@@ -483,6 +558,15 @@ impl DbContact {
 
         Ok(())
     }
+    /// Deletes the contacts of the published list, keeping message requests
+    /// and accepted senders that aren't listed
+    pub async fn delete_listed(pool: &SqlitePool) -> Result<(), Error> {
+        let sql = "DELETE FROM contact WHERE listed = 1;";
+
+        sqlx::query(sql).execute(pool).await?;
+
+        Ok(())
+    }
     pub async fn has_contact(pool: &SqlitePool, pubkey: &XOnlyPublicKey) -> Result<bool, Error> {
         let sql = "SELECT EXISTS(SELECT 1 FROM contact WHERE pubkey=?)";
 
@@ -523,6 +607,8 @@ impl sqlx::FromRow<'_, SqliteRow> for DbContact {
             petname,
             relay_url,
             status: row.get::<u8, &str>("status").into(),
+            listed: row.try_get("listed")?,
+            auto_load_media: row.try_get("auto_load_media")?,
         })
     }
 }
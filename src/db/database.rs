@@ -2,8 +2,12 @@ use crate::{consts::APP_PROJECT_DIRS, db::UserConfig};
 use thiserror::Error;
 
 use directories::ProjectDirs;
-use sqlx::SqlitePool;
+use nostr::hashes::{sha256, Hash};
+use nostr::Keys;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, SqliteConnection, SqlitePool};
 use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -20,6 +24,38 @@ pub enum Error {
         "Database version is newer than supported by this executable (v{current} > v{db_ver})"
     )]
     NewerDbVersion { current: usize, db_ver: usize },
+
+    #[error("No migration for the {schema} database from v{version}")]
+    MissingMigration { schema: String, version: usize },
+
+    #[error("{0}")]
+    FromKeys(#[from] nostr::key::Error),
+}
+
+/// Key used by SQLCipher to encrypt the account database
+#[derive(Clone)]
+pub struct DbKey(String);
+impl DbKey {
+    /// Derives the key from the account secret key, so that no extra secret
+    /// needs to be stored next to the database.
+    pub fn from_keys(keys: &Keys) -> Result<Self, Error> {
+        let secret_key = keys.secret_key()?;
+        let mut bytes = DB_KEY_CONTEXT.to_vec();
+        bytes.extend_from_slice(&secret_key.secret_bytes());
+        let hash = sha256::Hash::hash(&bytes);
+        Ok(Self(hash.to_string()))
+    }
+
+    /// Raw key in the format expected by `PRAGMA key`, skipping SQLCipher's
+    /// passphrase derivation since the key already has full entropy.
+    fn pragma_value(&self) -> String {
+        format!("\"x'{}'\"", self.0)
+    }
+}
+impl std::fmt::Debug for DbKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DbKey(..)")
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -29,41 +65,127 @@ pub struct Database {
 }
 
 impl Database {
-    pub async fn new(pubkey: &str) -> Result<Self, Error> {
-        let pool = db_pool(pubkey).await?;
+    pub async fn new(keys: &Keys) -> Result<Self, Error> {
+        let db_key = DbKey::from_keys(keys)?;
+        let pool = db_pool(&keys.public_key().to_string(), &db_key).await?;
         let cache_pool = get_cache_pool().await?;
         let s = Self { pool, cache_pool };
         Ok(s)
     }
 }
 
-async fn db_pool(pubkey: &str) -> Result<SqlitePool, Error> {
+async fn db_pool(pubkey: &str, db_key: &DbKey) -> Result<SqlitePool, Error> {
     let dirs = ProjectDirs::from(APP_PROJECT_DIRS.0, APP_PROJECT_DIRS.1, APP_PROJECT_DIRS.2)
         .ok_or(Error::NotFoundProjectDirectory)?;
     let data_dir = dirs.data_dir();
     std::fs::create_dir_all(data_dir)?;
 
-    let db_url = if IN_MEMORY {
-        "sqlite::memory:".to_owned()
-    } else {
-        let mut path_ext = String::new();
-        for dir in data_dir.iter() {
-            if let Some(p) = dir.to_str() {
-                if p.contains('\\') || p.contains('/') {
-                    continue;
-                }
-                path_ext.push_str(&format!("/{}", p));
-            }
-        }
-        format!("sqlite://{}/{}.db3?mode=rwc", &path_ext, pubkey)
-    };
+    if IN_MEMORY {
+        let pool = SqlitePool::connect("sqlite::memory:").await?;
+        upgrade_db(&pool).await?;
+        return Ok(pool);
+    }
 
     tracing::info!("Connecting database");
-    let pool = SqlitePool::connect(&db_url).await?;
+    let db_path = data_dir.join(format!("{}.db3", pubkey));
+    let pool = connect_encrypted(&db_path, db_key).await?;
     upgrade_db(&pool).await?;
     Ok(pool)
 }
 
+/// Opens the encrypted database at `db_path`, creating it if needed.
+///
+/// Databases created before encryption was added are encrypted in place.
+pub async fn connect_encrypted(db_path: &Path, db_key: &DbKey) -> Result<SqlitePool, Error> {
+    if is_plaintext_db(db_path)? {
+        encrypt_plaintext_db(db_path, db_key).await?;
+    }
+
+    let options = SqliteConnectOptions::new()
+        .filename(db_path)
+        .create_if_missing(true)
+        .pragma("key", db_key.pragma_value());
+    let pool = SqlitePool::connect_with(options).await?;
+
+    Ok(pool)
+}
+
+/// Unencrypted SQLite files always start with this header
+fn is_plaintext_db(db_path: &Path) -> Result<bool, Error> {
+    use std::io::Read;
+
+    let mut file = match std::fs::File::open(db_path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    let mut header = [0u8; 16];
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(&header == SQLITE_HEADER),
+        // empty or truncated files have no data to keep
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Exports a plaintext database into an encrypted copy and replaces the original with it.
+async fn encrypt_plaintext_db(db_path: &Path, db_key: &DbKey) -> Result<(), Error> {
+    tracing::info!("Encrypting existing database");
+
+    let encrypted_path = with_extension_suffix(db_path, "encrypting");
+    if encrypted_path.exists() {
+        // leftover of an interrupted migration, the plaintext file is still the source of truth
+        std::fs::remove_file(&encrypted_path)?;
+    }
+
+    let mut conn = SqliteConnectOptions::new()
+        .filename(db_path)
+        .connect()
+        .await?;
+
+    // sqlcipher_export copies the schema and data, but not the schema version
+    let user_version: i64 = sqlx::query_scalar("PRAGMA user_version;")
+        .fetch_one(&mut conn)
+        .await?;
+
+    let attach = format!(
+        "ATTACH DATABASE '{}' AS encrypted KEY {};",
+        encrypted_path.to_string_lossy().replace('\'', "''"),
+        db_key.pragma_value()
+    );
+    sqlx::query(&attach).execute(&mut conn).await?;
+    sqlx::query("SELECT sqlcipher_export('encrypted');")
+        .execute(&mut conn)
+        .await?;
+    sqlx::query(&format!(
+        "PRAGMA encrypted.user_version = {};",
+        user_version
+    ))
+    .execute(&mut conn)
+    .await?;
+    sqlx::query("DETACH DATABASE encrypted;")
+        .execute(&mut conn)
+        .await?;
+    conn.close().await?;
+
+    std::fs::rename(&encrypted_path, db_path)?;
+    for suffix in ["wal", "shm"] {
+        let path = with_extension_suffix(db_path, suffix);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    tracing::info!("Database encrypted");
+    Ok(())
+}
+
+fn with_extension_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!("-{}", suffix));
+    PathBuf::from(path)
+}
+
 async fn get_cache_pool() -> Result<SqlitePool, Error> {
     let dirs = ProjectDirs::from(APP_PROJECT_DIRS.0, APP_PROJECT_DIRS.1, APP_PROJECT_DIRS.2)
         .ok_or(Error::NotFoundProjectDirectory)?;
@@ -96,58 +218,150 @@ async fn get_cache_pool() -> Result<SqlitePool, Error> {
 }
 
 pub async fn upgrade_cache_db(cache_pool: &SqlitePool) -> Result<(), Error> {
-    for sql in CACHE_SETUP {
-        sqlx::query(sql).execute(cache_pool).await?;
-    }
-    Ok(())
+    upgrade_cache_db_to(cache_pool, CACHE_DB_VERSION).await
+}
+
+/// Upgrade the cache DB up to `version`, used to test a migration on the schema before it
+pub async fn upgrade_cache_db_to(cache_pool: &SqlitePool, version: usize) -> Result<(), Error> {
+    sqlx::query(CACHE_SETTINGS).execute(cache_pool).await?;
+    migrate(cache_pool, Schema::Cache, version).await
 }
 
 /// Upgrade DB to latest version, and execute pragma settings
 pub async fn upgrade_db(pool: &SqlitePool) -> Result<(), Error> {
-    // check the version.
+    upgrade_db_to(pool, DB_VERSION).await
+}
+
+/// Upgrade DB up to `version`, used to test a migration on the schema before it
+pub async fn upgrade_db_to(pool: &SqlitePool, version: usize) -> Result<(), Error> {
+    sqlx::query(DB_SETTINGS).execute(pool).await?;
+    migrate(pool, Schema::Account, version).await
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Schema {
+    Account,
+    Cache,
+}
+impl Schema {
+    fn latest_version(&self) -> usize {
+        match self {
+            Schema::Account => DB_VERSION,
+            Schema::Cache => CACHE_DB_VERSION,
+        }
+    }
+
+    /// Runs the migration that takes the schema from `version` to `version + 1`
+    async fn migrate_from(&self, conn: &mut SqliteConnection, version: usize) -> Result<(), Error> {
+        match (self, version) {
+            (Schema::Account, 0) => initial_setup(conn).await,
+            (Schema::Account, 1) => mig_1_to_2(conn).await,
+            (Schema::Account, 2) => mig_2_to_3(conn).await,
+            (Schema::Account, 3) => mig_3_to_4(conn).await,
+            (Schema::Account, 4) => mig_4_to_5(conn).await,
+            (Schema::Account, 5) => mig_5_to_6(conn).await,
+            (Schema::Account, 6) => mig_6_to_7(conn).await,
+            (Schema::Account, 7) => mig_7_to_8(conn).await,
+            (Schema::Account, 8) => mig_8_to_9(conn).await,
+            (Schema::Account, 9) => mig_9_to_10(conn).await,
+            (Schema::Account, 10) => mig_10_to_11(conn).await,
+            (Schema::Account, 11) => mig_11_to_12(conn).await,
+            (Schema::Account, 12) => mig_12_to_13(conn).await,
+            (Schema::Account, 13) => mig_13_to_14(conn).await,
+            (Schema::Account, 14) => mig_14_to_15(conn).await,
+            (Schema::Account, 15) => mig_15_to_16(conn).await,
+            (Schema::Account, 16) => mig_16_to_17(conn).await,
+            (Schema::Cache, 0) => cache_initial_setup(conn).await,
+            (Schema::Cache, 1) => cache_mig_1_to_2(conn).await,
+            (Schema::Cache, 2) => cache_mig_2_to_3(conn).await,
+            (Schema::Cache, 3) => cache_mig_3_to_4(conn).await,
+            (Schema::Cache, 4) => cache_mig_4_to_5(conn).await,
+            _ => Err(Error::MissingMigration {
+                schema: format!("{:?}", self),
+                version,
+            }),
+        }
+    }
+}
+
+/// Upgrades the schema one version at a time, up to `target`.
+///
+/// Each step runs in its own transaction together with the `user_version` bump,
+/// so a failed step leaves the database at the previous version.
+async fn migrate(pool: &SqlitePool, schema: Schema, target: usize) -> Result<(), Error> {
+    let latest = schema.latest_version();
     let mut curr_version = curr_db_version(pool).await?;
-    tracing::info!("DB version = {:?}", curr_version);
+    tracing::info!("{:?} DB version = {:?}", schema, curr_version);
+
+    // Database is newer than what this code understands, abort
+    if curr_version > latest {
+        return Err(Error::NewerDbVersion {
+            current: curr_version,
+            db_ver: latest,
+        });
+    }
 
-    match curr_version.cmp(&DB_VERSION) {
+    match curr_version.cmp(&target) {
         // Database is new or not current
         Ordering::Less => {
-            // initialize from scratch
-            if curr_version == 0 {
-                curr_version = initial_setup(pool).await?;
+            if curr_version > 0 {
+                backup_db(pool, curr_version).await?;
             }
 
-            // for initialized but out-of-date schemas, proceed to
-            // upgrade sequentially until we are current.
-            /* if curr_version == 1 {
-                curr_version = mig_1_to_2(pool).await?;
-            } */
-            /* if curr_version == 2 {
-                curr_version = mig_2_to_3(pool).await?;
-            } */
-
-            if curr_version == DB_VERSION {
-                tracing::info!("All migration scripts completed successfully (v{DB_VERSION})");
+            while curr_version < target {
+                let mut tx = pool.begin().await?;
+                schema.migrate_from(&mut tx, curr_version).await?;
+                curr_version += 1;
+                // PRAGMA statements don't accept bound parameters
+                sqlx::query(&format!("PRAGMA user_version = {};", curr_version))
+                    .execute(&mut tx)
+                    .await?;
+                tx.commit().await?;
+                tracing::info!("{:?} database schema upgraded to v{}", schema, curr_version);
             }
+
+            tracing::info!("All migration scripts completed successfully (v{target})");
         }
         // Database is current, all is good
-        Ordering::Equal => {
-            tracing::debug!("Database version was already current (v{DB_VERSION})");
-        }
-        // Database is newer than what this code understands, abort
-        Ordering::Greater => {
-            return Err(Error::NewerDbVersion {
-                current: curr_version,
-                db_ver: DB_VERSION,
-            });
+        Ordering::Equal | Ordering::Greater => {
+            tracing::debug!(
+                "{:?} database version was already current (v{curr_version})",
+                schema
+            );
         }
     }
 
-    // Setup PRAGMA
-    // sqlx::query(STARTUP_SQL).execute(pool).await?;
-    // tracing::debug!("SQLite PRAGMA startup completed");
     Ok(())
 }
 
+/// Copies the database file to `<file>.v<version>.bak` before upgrading it.
+///
+/// In-memory databases have no file and are not backed up.
+async fn backup_db(pool: &SqlitePool, version: usize) -> Result<(), Error> {
+    let file: Option<String> =
+        sqlx::query_scalar("SELECT file FROM pragma_database_list WHERE name = 'main';")
+            .fetch_optional(pool)
+            .await?;
+    let Some(file) = file.filter(|f| !f.is_empty()) else {
+        return Ok(());
+    };
+
+    // move everything from the WAL into the main file before copying it
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE);")
+        .execute(pool)
+        .await?;
+
+    let backup_path = backup_path(Path::new(&file), version);
+    std::fs::copy(&file, &backup_path)?;
+    tracing::info!("Database backup created: {}", backup_path.display());
+
+    Ok(())
+}
+
+pub fn backup_path(db_path: &Path, version: usize) -> PathBuf {
+    with_extension_suffix(db_path, &format!("v{}.bak", version))
+}
+
 /// Determine the current application database schema version.
 pub async fn curr_db_version(pool: &SqlitePool) -> Result<usize, Error> {
     let query = "PRAGMA user_version;";
@@ -155,35 +369,182 @@ pub async fn curr_db_version(pool: &SqlitePool) -> Result<usize, Error> {
     Ok(curr_version as usize)
 }
 
-async fn initial_setup(pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+async fn initial_setup(conn: &mut SqliteConnection) -> Result<(), Error> {
     tracing::info!("Database initial setup");
 
     for sql in INITIAL_SETUP {
-        sqlx::query(sql).execute(pool).await?;
+        sqlx::query(sql).execute(&mut *conn).await?;
     }
 
-    UserConfig::setup_user_config(pool).await?;
+    UserConfig::setup_user_config(conn).await?;
 
-    tracing::info!("Database schema initialized to v1");
+    Ok(())
+}
 
-    Ok(1)
+async fn mig_1_to_2(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/11_message_search.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
 }
 
-const _UPGRADE_SQL: [&str; 0] = [
-// include_str!("../../migrations/migration.sql")
-];
+async fn mig_2_to_3(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!(
+        "../../migrations/12_contact_auto_load_media.sql"
+    ))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn mig_3_to_4(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/13_message_attachments.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn mig_4_to_5(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/14_zap_receipt.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn mig_5_to_6(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/15_wallet_connect.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn mig_6_to_7(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/16_notifications.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn mig_7_to_8(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/17_follower.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn mig_8_to_9(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/18_muted_pubkey.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn mig_9_to_10(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/19_contact_request.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
 
-/* async fn mig_1_to_2(pool: &SqlitePool) -> Result<usize, Error> {
-    sqlx::query(include_str!("../migrations/002.sql")).execute(pool).await?;
-    tracing::info!("database schema upgraded v1 -> v2");
-    Ok(2)
-} */
+async fn mig_10_to_11(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/20_pow_difficulty.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn mig_11_to_12(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/21_filtered_message.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn mig_12_to_13(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/22_expiration.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn mig_13_to_14(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/23_draft.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn mig_14_to_15(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/24_channel_read_marker.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn mig_15_to_16(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/25_zap_provider.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn mig_16_to_17(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/26_channel_read_event.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn cache_initial_setup(conn: &mut SqliteConnection) -> Result<(), Error> {
+    tracing::info!("Cache database initial setup");
+
+    for sql in CACHE_SETUP {
+        sqlx::query(sql).execute(&mut *conn).await?;
+    }
+
+    Ok(())
+}
+
+async fn cache_mig_1_to_2(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/cache/6_cache_eviction.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn cache_mig_2_to_3(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/cache/7_image_blob.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn cache_mig_3_to_4(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/cache/8_link_preview.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+async fn cache_mig_4_to_5(conn: &mut SqliteConnection) -> Result<(), Error> {
+    sqlx::query(include_str!("../../migrations/cache/9_channel_preview.sql"))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
 
 /// Latest database version
-pub const DB_VERSION: usize = 1;
+pub const DB_VERSION: usize = 17;
+
+/// Latest cache database version
+pub const CACHE_DB_VERSION: usize = 5;
 
-const INITIAL_SETUP: [&str; 9] = [
-    include_str!("../../migrations/1_setup.sql"),
+/// Connection settings, executed on every start outside of the migrations
+/// since some of them can't run inside a transaction
+const DB_SETTINGS: &str = include_str!("../../migrations/1_setup.sql");
+const CACHE_SETTINGS: &str = include_str!("../../migrations/cache/1_setup.sql");
+
+const INITIAL_SETUP: [&str; 8] = [
     include_str!("../../migrations/2_event.sql"),
     include_str!("../../migrations/3_relay.sql"),
     include_str!("../../migrations/5_contact.sql"),
@@ -194,8 +555,7 @@ const INITIAL_SETUP: [&str; 9] = [
     include_str!("../../migrations/10_subscribed_channel.sql"),
 ];
 
-const CACHE_SETUP: [&str; 5] = [
-    include_str!("../../migrations/cache/1_setup.sql"),
+const CACHE_SETUP: [&str; 4] = [
     include_str!("../../migrations/cache/2_profile_meta_cache.sql"),
     include_str!("../../migrations/cache/3_channel_cache.sql"),
     include_str!("../../migrations/cache/4_image_cache.sql"),
@@ -203,3 +563,6 @@ const CACHE_SETUP: [&str; 5] = [
 ];
 
 const IN_MEMORY: bool = false;
+
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+const DB_KEY_CONTEXT: &[u8] = b"nostrtalk-db-key";
@@ -0,0 +1,56 @@
+use chrono::Utc;
+use sqlx::SqlitePool;
+use thiserror::Error;
+
+use super::NotificationChat;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// Unsent composer text of a chat
+pub struct DbDraft;
+impl DbDraft {
+    /// Empty when the chat has no draft
+    pub async fn fetch(pool: &SqlitePool, chat: &NotificationChat) -> Result<String, Error> {
+        let sql = "SELECT content FROM draft WHERE chat_type = ? AND chat_id = ?";
+        let content: Option<String> = sqlx::query_scalar(sql)
+            .bind(chat.chat_type())
+            .bind(chat.chat_id())
+            .fetch_optional(pool)
+            .await?;
+        Ok(content.unwrap_or_default())
+    }
+
+    /// An empty `content` deletes the draft
+    pub async fn save(
+        pool: &SqlitePool,
+        chat: &NotificationChat,
+        content: &str,
+    ) -> Result<(), Error> {
+        if content.is_empty() {
+            let sql = "DELETE FROM draft WHERE chat_type = ? AND chat_id = ?";
+            sqlx::query(sql)
+                .bind(chat.chat_type())
+                .bind(chat.chat_id())
+                .execute(pool)
+                .await?;
+            return Ok(());
+        }
+
+        let sql = r#"
+            INSERT OR REPLACE INTO draft (chat_type, chat_id, content, updated_at)
+            VALUES (?, ?, ?, ?)
+        "#;
+        sqlx::query(sql)
+            .bind(chat.chat_type())
+            .bind(chat.chat_id())
+            .bind(content)
+            .bind(Utc::now().timestamp_millis())
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
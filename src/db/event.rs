@@ -1,12 +1,15 @@
 use chrono::NaiveDateTime;
-use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use sqlx::{sqlite::SqliteRow, Row, SqliteConnection, SqlitePool};
 use std::str::FromStr;
 use thiserror::Error;
 use url::Url;
 
 use crate::{
     db::DbRelayResponse,
-    utils::{handle_decode_error, millis_to_naive_or_err, ns_event_to_millis, url_or_err},
+    utils::{
+        event_expiration, handle_decode_error, millis_to_naive_or_err, ns_event_to_millis,
+        url_or_err,
+    },
 };
 use nostr::{
     secp256k1::{schnorr::Signature, XOnlyPublicKey},
@@ -169,13 +172,33 @@ impl DbEvent {
             return Ok(None);
         }
 
+        let mut conn = pool.acquire().await?;
+        let event_id = Self::insert_with(&mut conn, relay_url, ns_event).await?;
+        drop(conn);
+
+        let db_event = Self::fetch_id(pool, event_id)
+            .await?
+            .ok_or(Error::EventNotInDatabase(ns_event.id.to_owned()))?;
+
+        DbRelayResponse::insert_ok(pool, relay_url, &db_event).await?;
+
+        Ok(Some(db_event))
+    }
+
+    /// Inserts on the connection, to be part of a transaction with other
+    /// changes. Returns the new event_id, the relay response is left to the caller.
+    pub async fn insert_with(
+        conn: &mut SqliteConnection,
+        relay_url: &Url,
+        ns_event: &nostr::Event,
+    ) -> Result<i64, Error> {
         tracing::debug!("inserting event id: {}", ns_event.id);
         tracing::trace!("inserting event {:?}", ns_event);
         let sql = r#"
             INSERT INTO event
                 (event_hash, pubkey, kind, content, sig, 
-                    tags, relay_url, created_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                    tags, relay_url, created_at, expires_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
         "#;
 
         let inserted = sqlx::query(sql)
@@ -187,26 +210,64 @@ impl DbEvent {
             .bind(&serde_json::to_string(&ns_event.tags)?)
             .bind(&relay_url.to_string())
             .bind(ns_event_to_millis(ns_event.created_at))
-            .execute(pool)
+            .bind(event_expiration(&ns_event.tags).map(ns_event_to_millis))
+            .execute(conn)
             .await?;
 
-        let db_event = Self::fetch_id(pool, inserted.last_insert_rowid())
-            .await?
-            .ok_or(Error::EventNotInDatabase(ns_event.id.to_owned()))?;
-
-        DbRelayResponse::insert_ok(pool, relay_url, &db_event).await?;
-
-        Ok(Some(db_event))
+        Ok(inserted.last_insert_rowid())
     }
 
     pub async fn delete(pool: &SqlitePool, event_id: i64) -> Result<(), Error> {
+        let mut conn = pool.acquire().await?;
+        Self::delete_with(&mut conn, event_id).await
+    }
+
+    /// Deletes on the connection, to be part of a transaction with other changes
+    pub async fn delete_with(conn: &mut SqliteConnection, event_id: i64) -> Result<(), Error> {
         tracing::info!("Deleting event with id {}", event_id);
         let sql = "DELETE FROM event WHERE event_id = ?";
 
-        sqlx::query(sql).bind(event_id).execute(pool).await?;
+        sqlx::query(sql).bind(event_id).execute(conn).await?;
 
         Ok(())
     }
+
+    /// Deletes events past their NIP-40 expiration together with their
+    /// direct and channel messages.
+    ///
+    /// Returns the ids of the deleted events.
+    pub async fn delete_expired(pool: &SqlitePool, now: Timestamp) -> Result<Vec<i64>, Error> {
+        let mut tx = pool.begin().await?;
+
+        let sql = "SELECT event_id FROM event WHERE expires_at IS NOT NULL AND expires_at <= ?";
+        let expired: Vec<i64> = sqlx::query_scalar(sql)
+            .bind(ns_event_to_millis(now))
+            .fetch_all(&mut tx)
+            .await?;
+
+        for event_id in &expired {
+            sqlx::query("DELETE FROM message WHERE event_id = ?")
+                .bind(event_id)
+                .execute(&mut tx)
+                .await?;
+            sqlx::query("DELETE FROM channel_message WHERE event_id = ?")
+                .bind(event_id)
+                .execute(&mut tx)
+                .await?;
+            sqlx::query("DELETE FROM event WHERE event_id = ?")
+                .bind(event_id)
+                .execute(&mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        if !expired.is_empty() {
+            tracing::info!("Deleted {} expired events", expired.len());
+        }
+
+        Ok(expired)
+    }
 }
 
 impl sqlx::FromRow<'_, SqliteRow> for DbEvent {
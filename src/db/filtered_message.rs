@@ -0,0 +1,114 @@
+use chrono::NaiveDateTime;
+use nostr::secp256k1::XOnlyPublicKey;
+use nostr::EventId;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use thiserror::Error;
+
+use crate::db::DbEvent;
+use crate::utils::{event_hash_or_err, millis_to_naive_or_err, public_key_or_err};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// Message from a non-contact held back for not having enough proof of work
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbFilteredMessage {
+    pub event_id: i64,
+    pub pubkey: XOnlyPublicKey,
+    /// None for direct messages
+    pub channel_id: Option<EventId>,
+    pub difficulty: u8,
+    pub created_at: NaiveDateTime,
+    /// Content of the event, encrypted for direct messages
+    pub content: String,
+}
+impl DbFilteredMessage {
+    const FETCH_QUERY: &'static str = r#"
+        SELECT filtered_message.*, event.content
+        FROM filtered_message
+        INNER JOIN event ON event.event_id = filtered_message.event_id
+    "#;
+
+    pub async fn insert(
+        pool: &SqlitePool,
+        db_event: &DbEvent,
+        channel_id: Option<&EventId>,
+        difficulty: u8,
+    ) -> Result<DbFilteredMessage, Error> {
+        let sql = r#"
+            INSERT OR IGNORE INTO filtered_message
+                (event_id, pubkey, channel_id, difficulty, created_at)
+            VALUES (?, ?, ?, ?, ?)
+        "#;
+        sqlx::query(sql)
+            .bind(db_event.event_id)
+            .bind(db_event.pubkey.to_string())
+            .bind(channel_id.map(|id| id.to_hex()))
+            .bind(difficulty)
+            .bind(db_event.created_at.timestamp_millis())
+            .execute(pool)
+            .await?;
+        Ok(Self {
+            event_id: db_event.event_id,
+            pubkey: db_event.pubkey,
+            channel_id: channel_id.copied(),
+            difficulty,
+            created_at: db_event.created_at,
+            content: db_event.content.to_owned(),
+        })
+    }
+
+    /// The most recent first
+    pub async fn fetch(pool: &SqlitePool) -> Result<Vec<DbFilteredMessage>, Error> {
+        let sql = format!(
+            "{} ORDER BY filtered_message.created_at DESC",
+            Self::FETCH_QUERY
+        );
+        Ok(sqlx::query_as::<_, DbFilteredMessage>(&sql)
+            .fetch_all(pool)
+            .await?)
+    }
+
+    pub async fn count(pool: &SqlitePool) -> Result<i64, Error> {
+        let sql = "SELECT COUNT(*) FROM filtered_message";
+        let count: i64 = sqlx::query_scalar(sql).fetch_one(pool).await?;
+        Ok(count)
+    }
+
+    /// The event stays, so the message isn't received again
+    pub async fn delete(pool: &SqlitePool, event_id: i64) -> Result<(), Error> {
+        let sql = "DELETE FROM filtered_message WHERE event_id = ?";
+        sqlx::query(sql).bind(event_id).execute(pool).await?;
+        Ok(())
+    }
+
+    pub async fn delete_all(pool: &SqlitePool) -> Result<(), Error> {
+        sqlx::query("DELETE FROM filtered_message")
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for DbFilteredMessage {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        let channel_id = row
+            .try_get::<Option<String>, &str>("channel_id")?
+            .map(|channel_id| event_hash_or_err(&channel_id, "channel_id"))
+            .transpose()?;
+        Ok(Self {
+            event_id: row.try_get::<i64, &str>("event_id")?,
+            pubkey: public_key_or_err(&row.try_get::<String, &str>("pubkey")?, "pubkey")?,
+            channel_id,
+            difficulty: row.try_get::<u8, &str>("difficulty")?,
+            created_at: millis_to_naive_or_err(
+                row.try_get::<i64, &str>("created_at")?,
+                "created_at",
+            )?,
+            content: row.try_get::<String, &str>("content")?,
+        })
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::NaiveDateTime;
+use nostr::secp256k1::XOnlyPublicKey;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use thiserror::Error;
+
+use crate::utils::{millis_to_naive_or_err, public_key_or_err};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// User that has the user in their contact list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbFollower {
+    pub pubkey: XOnlyPublicKey,
+    /// Creation of the last contact list seen from the follower
+    pub list_created_at: NaiveDateTime,
+    pub followed_at: NaiveDateTime,
+    /// Some when a newer list dropped the user
+    pub unfollowed_at: Option<NaiveDateTime>,
+}
+impl DbFollower {
+    pub fn is_following(&self) -> bool {
+        self.unfollowed_at.is_none()
+    }
+
+    /// Records a contact list of `pubkey`, created at `list_created_at`,
+    /// that `follows` the user or not. Lists older than the last one seen are
+    /// ignored. Returns the follower only when it followed or unfollowed.
+    pub async fn update_from_list(
+        pool: &SqlitePool,
+        pubkey: &XOnlyPublicKey,
+        list_created_at: NaiveDateTime,
+        follows: bool,
+    ) -> Result<Option<DbFollower>, Error> {
+        let mut tx = pool.begin().await?;
+        let sql = "SELECT * FROM follower WHERE pubkey = ?";
+        let current = sqlx::query_as::<_, DbFollower>(sql)
+            .bind(pubkey.to_string())
+            .fetch_optional(&mut tx)
+            .await?;
+
+        let (row, changed) = match current {
+            Some(current) if current.list_created_at >= list_created_at => return Ok(None),
+            Some(current) => {
+                let changed = current.is_following() != follows;
+                let row = match (changed, follows) {
+                    // unchanged followers still move the list time forward
+                    (false, _) => DbFollower {
+                        list_created_at,
+                        ..current
+                    },
+                    (true, true) => DbFollower {
+                        list_created_at,
+                        followed_at: list_created_at,
+                        unfollowed_at: None,
+                        ..current
+                    },
+                    (true, false) => DbFollower {
+                        list_created_at,
+                        unfollowed_at: Some(list_created_at),
+                        ..current
+                    },
+                };
+                (row, changed)
+            }
+            // lists of strangers that don't follow the user aren't stored
+            None if !follows => return Ok(None),
+            None => {
+                let row = DbFollower {
+                    pubkey: pubkey.to_owned(),
+                    list_created_at,
+                    followed_at: list_created_at,
+                    unfollowed_at: None,
+                };
+                (row, true)
+            }
+        };
+
+        let sql = r#"
+            INSERT OR REPLACE INTO follower
+                (pubkey, list_created_at, followed_at, unfollowed_at)
+            VALUES (?, ?, ?, ?)
+        "#;
+        sqlx::query(sql)
+            .bind(row.pubkey.to_string())
+            .bind(row.list_created_at.timestamp_millis())
+            .bind(row.followed_at.timestamp_millis())
+            .bind(row.unfollowed_at.map(|time| time.timestamp_millis()))
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(changed.then_some(row))
+    }
+
+    /// Current followers, the most recent first
+    pub async fn fetch(pool: &SqlitePool) -> Result<Vec<DbFollower>, Error> {
+        let sql = r#"
+            SELECT * FROM follower
+            WHERE unfollowed_at IS NULL
+            ORDER BY followed_at DESC
+        "#;
+        Ok(sqlx::query_as::<_, DbFollower>(sql).fetch_all(pool).await?)
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for DbFollower {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        let unfollowed_at = row
+            .try_get::<Option<i64>, &str>("unfollowed_at")?
+            .map(|millis| millis_to_naive_or_err(millis, "unfollowed_at"))
+            .transpose()?;
+        Ok(Self {
+            pubkey: public_key_or_err(&row.try_get::<String, &str>("pubkey")?, "pubkey")?,
+            list_created_at: millis_to_naive_or_err(
+                row.try_get::<i64, &str>("list_created_at")?,
+                "list_created_at",
+            )?,
+            followed_at: millis_to_naive_or_err(
+                row.try_get::<i64, &str>("followed_at")?,
+                "followed_at",
+            )?,
+            unfollowed_at,
+        })
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use nostr::hashes::{sha256, Hash};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use thiserror::Error;
+
+use crate::utils::millis_to_naive_or_err;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// Image file stored once for every URL and event that uses it
+#[derive(Debug, Clone)]
+pub struct ImageBlob {
+    pub content_hash: String,
+    pub path: PathBuf,
+}
+impl ImageBlob {
+    pub fn content_hash(bytes: &[u8]) -> String {
+        sha256::Hash::hash(bytes).to_string()
+    }
+
+    pub async fn fetch(
+        cache_pool: &SqlitePool,
+        content_hash: &str,
+    ) -> Result<Option<ImageBlob>, Error> {
+        let blob =
+            sqlx::query_as::<_, ImageBlob>("SELECT * FROM image_blob WHERE content_hash = ?")
+                .bind(content_hash)
+                .fetch_optional(cache_pool)
+                .await?;
+        Ok(blob)
+    }
+
+    pub async fn insert(cache_pool: &SqlitePool, blob: &ImageBlob) -> Result<(), Error> {
+        let sql = r#"
+            INSERT OR REPLACE INTO image_blob (content_hash, path, created_at)
+            VALUES (?, ?, ?)
+        "#;
+        sqlx::query(sql)
+            .bind(&blob.content_hash)
+            .bind(blob.path.to_string_lossy())
+            .bind(Utc::now().timestamp_millis())
+            .execute(cache_pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Removes the blob and the URLs pointing to it, after its files were deleted
+    pub async fn delete_by_path(cache_pool: &SqlitePool, path: &Path) -> Result<(), Error> {
+        let mut tx = cache_pool.begin().await?;
+        let path = path.to_string_lossy();
+
+        sqlx::query(
+            r#"
+            DELETE FROM image_url WHERE content_hash IN
+                (SELECT content_hash FROM image_blob WHERE path = ?)
+        "#,
+        )
+        .bind(&path)
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query("DELETE FROM image_blob WHERE path = ?")
+            .bind(&path)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for ImageBlob {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        let path: String = row.try_get("path")?;
+        Ok(Self {
+            content_hash: row.try_get("content_hash")?,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+/// Last response for an image URL, with the validators to revalidate it
+#[derive(Debug, Clone)]
+pub struct ImageUrl {
+    pub url: String,
+    pub content_hash: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched_at: NaiveDateTime,
+    pub max_age_secs: i64,
+}
+impl ImageUrl {
+    /// Fresh entries can be used without asking the server
+    pub fn is_fresh(&self, now: NaiveDateTime) -> bool {
+        now < self.fetched_at + Duration::seconds(self.max_age_secs)
+    }
+
+    pub fn has_validators(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+
+    pub async fn fetch(cache_pool: &SqlitePool, url: &str) -> Result<Option<ImageUrl>, Error> {
+        let entry = sqlx::query_as::<_, ImageUrl>("SELECT * FROM image_url WHERE url_hash = ?")
+            .bind(url_hash(url))
+            .fetch_optional(cache_pool)
+            .await?;
+        Ok(entry)
+    }
+
+    pub async fn upsert(cache_pool: &SqlitePool, entry: &ImageUrl) -> Result<(), Error> {
+        let sql = r#"
+            INSERT OR REPLACE INTO image_url
+                (url_hash, url, content_hash, etag, last_modified, fetched_at, max_age_secs)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#;
+        sqlx::query(sql)
+            .bind(url_hash(&entry.url))
+            .bind(&entry.url)
+            .bind(&entry.content_hash)
+            .bind(&entry.etag)
+            .bind(&entry.last_modified)
+            .bind(entry.fetched_at.timestamp_millis())
+            .bind(entry.max_age_secs)
+            .execute(cache_pool)
+            .await?;
+        Ok(())
+    }
+
+    /// The server confirmed the cached content is still valid
+    pub async fn revalidated(
+        cache_pool: &SqlitePool,
+        url: &str,
+        fetched_at: NaiveDateTime,
+        max_age_secs: i64,
+    ) -> Result<(), Error> {
+        sqlx::query("UPDATE image_url SET fetched_at = ?, max_age_secs = ? WHERE url_hash = ?")
+            .bind(fetched_at.timestamp_millis())
+            .bind(max_age_secs)
+            .bind(url_hash(url))
+            .execute(cache_pool)
+            .await?;
+        Ok(())
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for ImageUrl {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        let fetched_at = row.try_get::<i64, &str>("fetched_at")?;
+        let fetched_at = millis_to_naive_or_err(fetched_at, "fetched_at")?;
+        Ok(Self {
+            url: row.try_get("url")?,
+            content_hash: row.try_get("content_hash")?,
+            etag: row.try_get("etag")?,
+            last_modified: row.try_get("last_modified")?,
+            fetched_at,
+            max_age_secs: row.try_get("max_age_secs")?,
+        })
+    }
+}
+
+fn url_hash(url: &str) -> String {
+    sha256::Hash::hash(url.as_bytes()).to_string()
+}
@@ -1,9 +1,10 @@
 use std::path::PathBuf;
 
 use crate::{
-    net::{image_filename, ImageKind, ImageSize},
+    net::{blob_filename, image_filename, ImageKind, ImageSize},
     utils::{event_hash_or_err, image_kind_or_err},
 };
+use chrono::Utc;
 use nostr::EventId;
 use serde::{Deserialize, Serialize};
 use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
@@ -35,10 +36,15 @@ pub struct ImageDownloaded {
     pub path: PathBuf,
     pub kind: ImageKind,
     pub event_hash: EventId,
+    /// Blob holding the image files, none for images downloaded before the blob store
+    pub content_hash: Option<String>,
 }
 impl ImageDownloaded {
     pub fn sized_image(&self, size: ImageSize) -> PathBuf {
-        let sized_file_name = image_filename(self.kind, size, "png");
+        let sized_file_name = match self.content_hash {
+            Some(_) => blob_filename(size, "png"),
+            None => image_filename(self.kind, size, "png"),
+        };
         // replace filename with new
         self.path.with_file_name(sized_file_name)
     }
@@ -64,14 +70,18 @@ impl ImageDownloaded {
         }
 
         let insert_query = r#"
-                INSERT INTO image_cache (path, kind, event_hash) 
-                VALUES (?, ?, ?)
+                INSERT INTO image_cache
+                    (path, kind, event_hash, size_bytes, last_accessed_at, content_hash) 
+                VALUES (?, ?, ?, ?, ?, ?)
             "#;
 
         sqlx::query(insert_query)
             .bind(&image.path.to_string_lossy())
             .bind(image.kind.as_i32())
             .bind(&image.event_hash.to_string())
+            .bind(image.files_size() as i64)
+            .bind(Utc::now().timestamp_millis())
+            .bind(&image.content_hash)
             .execute(cache_pool)
             .await?;
 
@@ -85,6 +95,41 @@ impl ImageDownloaded {
         Ok(cache)
     }
 
+    /// Marks the image as recently used, so it is the last to be evicted
+    pub async fn touch(
+        cache_pool: &SqlitePool,
+        event_hash: &EventId,
+        kind: ImageKind,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            "UPDATE image_cache SET last_accessed_at = ? WHERE event_hash = ? AND kind = ?",
+        )
+        .bind(Utc::now().timestamp_millis())
+        .bind(event_hash.to_string())
+        .bind(kind.as_i32())
+        .execute(cache_pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Original image and its resized copies
+    pub fn files(&self) -> Vec<PathBuf> {
+        vec![
+            self.path.to_owned(),
+            self.sized_image(ImageSize::Medium),
+            self.sized_image(ImageSize::Small),
+        ]
+    }
+
+    /// Size on disk of all the image files
+    pub fn files_size(&self) -> u64 {
+        self.files()
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
     pub async fn delete(
         cache_pool: &SqlitePool,
         event_hash: &EventId,
@@ -124,23 +169,19 @@ impl sqlx::FromRow<'_, SqliteRow> for ImageDownloaded {
             path,
             kind,
             event_hash,
+            content_hash: row.try_get("content_hash")?,
         })
     }
 }
 
-async fn delete_images(cache: ImageDownloaded) -> Result<(), Error> {
-    tokio::fs::remove_file(cache.path).await?;
-
-    match cache.kind {
-        ImageKind::Profile => {
-            let med_path = image_filename(cache.kind, ImageSize::Medium, "png");
-            tokio::fs::remove_file(med_path).await?;
-
-            let sm_path = image_filename(cache.kind, ImageSize::Small, "png");
-            tokio::fs::remove_file(sm_path).await?;
+pub(crate) async fn delete_images(cache: ImageDownloaded) -> Result<(), Error> {
+    for path in cache.files() {
+        match tokio::fs::remove_file(path).await {
+            Ok(()) => (),
+            // files may have been removed by hand, the row is stale anyway
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e.into()),
         }
-        ImageKind::Banner => {}
-        ImageKind::Channel => {}
     }
 
     Ok(())
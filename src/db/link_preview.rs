@@ -0,0 +1,91 @@
+use chrono::{Duration, NaiveDateTime};
+use nostr::hashes::{sha256, Hash};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use thiserror::Error;
+
+use crate::utils::millis_to_naive_or_err;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// OpenGraph metadata of a link found in a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkPreview {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub fetched_at: NaiveDateTime,
+}
+impl LinkPreview {
+    /// Pages without metadata, or that failed to load, are cached empty
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.image_url.is_none()
+    }
+
+    pub fn is_fresh(&self, now: NaiveDateTime) -> bool {
+        now < self.fetched_at + Duration::seconds(LINK_PREVIEW_TTL_SECS)
+    }
+
+    pub async fn fetch(cache_pool: &SqlitePool, url: &str) -> Result<Option<LinkPreview>, Error> {
+        let preview =
+            sqlx::query_as::<_, LinkPreview>("SELECT * FROM link_preview WHERE url_hash = ?")
+                .bind(url_hash(url))
+                .fetch_optional(cache_pool)
+                .await?;
+        Ok(preview)
+    }
+
+    pub async fn upsert(cache_pool: &SqlitePool, preview: &LinkPreview) -> Result<(), Error> {
+        let sql = r#"
+            INSERT OR REPLACE INTO link_preview
+                (url_hash, url, title, description, image_url, fetched_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+        "#;
+        sqlx::query(sql)
+            .bind(url_hash(&preview.url))
+            .bind(&preview.url)
+            .bind(&preview.title)
+            .bind(&preview.description)
+            .bind(&preview.image_url)
+            .bind(preview.fetched_at.timestamp_millis())
+            .execute(cache_pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Removes the previews that would be fetched again anyway, returns how many
+    pub async fn delete_expired(cache_pool: &SqlitePool, now: NaiveDateTime) -> Result<u64, Error> {
+        let oldest_fresh = now - Duration::seconds(LINK_PREVIEW_TTL_SECS);
+        let deleted = sqlx::query("DELETE FROM link_preview WHERE fetched_at < ?")
+            .bind(oldest_fresh.timestamp_millis())
+            .execute(cache_pool)
+            .await?
+            .rows_affected();
+        Ok(deleted)
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for LinkPreview {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        let fetched_at = row.try_get::<i64, &str>("fetched_at")?;
+        let fetched_at = millis_to_naive_or_err(fetched_at, "fetched_at")?;
+        Ok(Self {
+            url: row.try_get("url")?,
+            title: row.try_get("title")?,
+            description: row.try_get("description")?,
+            image_url: row.try_get("image_url")?,
+            fetched_at,
+        })
+    }
+}
+
+fn url_hash(url: &str) -> String {
+    sha256::Hash::hash(url.as_bytes()).to_string()
+}
+
+/// Link previews are fetched again after a day
+const LINK_PREVIEW_TTL_SECS: i64 = 24 * 60 * 60;
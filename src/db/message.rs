@@ -1,5 +1,9 @@
 use super::DbEvent;
-use crate::utils::{message_status_or_err, millis_to_naive_or_err, public_key_or_err, url_or_err};
+use crate::types::Attachment;
+use crate::utils::{
+    attachments_or_err, message_status_or_err, millis_to_naive_or_err, public_key_or_err,
+    url_or_err,
+};
 use chrono::NaiveDateTime;
 use nostr::{nips::nip04, secp256k1::XOnlyPublicKey, EventId, Keys};
 use serde::{Deserialize, Serialize};
@@ -43,6 +47,9 @@ pub enum Error {
 
     #[error("Unknown message status: {0}")]
     UnknownStatus(i32),
+
+    #[error("JSON (de)serialization error: {0}")]
+    FromSerdeJson(#[from] serde_json::Error),
 }
 
 pub struct MessageTagInfo {
@@ -92,6 +99,7 @@ pub struct DbMessage {
     pub created_at: chrono::NaiveDateTime,
     pub status: MessageStatus,
     pub relay_url: nostr::Url,
+    pub attachments: Vec<Attachment>,
 }
 
 impl DbMessage {
@@ -101,23 +109,11 @@ impl DbMessage {
         self.status.is_unseen()
     }
 
+    /// Text of the message, without the encrypted attachments
     pub fn decrypt_message(&self, keys: &Keys, tag_info: &MessageTagInfo) -> Result<String, Error> {
-        let users_secret_key = keys.secret_key()?;
-        if self.is_users {
-            nip04::decrypt(
-                &users_secret_key,
-                &tag_info.to_pubkey,
-                &self.encrypted_content,
-            )
-            .map_err(|e| Error::Decryption(e.to_string()))
-        } else {
-            nip04::decrypt(
-                &users_secret_key,
-                &tag_info.from_pubkey,
-                &self.encrypted_content,
-            )
-            .map_err(|e| Error::Decryption(e.to_string()))
-        }
+        let payload = decrypt_payload(keys, self.is_users, tag_info, &self.encrypted_content)?;
+        let (content, _attachments) = Attachment::split_dm_payload(&payload);
+        Ok(content.to_owned())
     }
 
     pub async fn fetch(pool: &SqlitePool) -> Result<Vec<DbMessage>, Error> {
@@ -194,6 +190,39 @@ impl DbMessage {
         Ok(messages)
     }
 
+    /// Messages before and after `created_at`, used to show a message in context
+    pub async fn fetch_chat_around(
+        pool: &SqlitePool,
+        chat_pubkey: &XOnlyPublicKey,
+        created_at: NaiveDateTime,
+    ) -> Result<Vec<DbMessage>, Error> {
+        let sql = r#"
+            SELECT * FROM (
+                SELECT * FROM (
+                    SELECT * FROM message
+                    WHERE chat_pubkey = ?1 AND created_at <= ?2
+                    ORDER BY created_at DESC
+                    LIMIT 50
+                )
+                UNION
+                SELECT * FROM (
+                    SELECT * FROM message
+                    WHERE chat_pubkey = ?1 AND created_at > ?2
+                    ORDER BY created_at ASC
+                    LIMIT 50
+                )
+            )
+            ORDER BY created_at ASC;
+        "#;
+        let messages = sqlx::query_as::<_, DbMessage>(sql)
+            .bind(&chat_pubkey.to_string())
+            .bind(created_at.timestamp_millis())
+            .fetch_all(pool)
+            .await?;
+
+        Ok(messages)
+    }
+
     pub async fn fetch_chat_last(
         pool: &SqlitePool,
         chat_pubkey: &XOnlyPublicKey,
@@ -214,8 +243,11 @@ impl DbMessage {
         Ok(message)
     }
 
+    /// The attachments in the encrypted payload are stored with the public ones,
+    /// so the message doesn't need to be decrypted to list them
     pub async fn insert_confirmed(
         pool: &SqlitePool,
+        keys: &Keys,
         db_event: &DbEvent,
         chat_pubkey: &XOnlyPublicKey,
         is_users: bool,
@@ -230,9 +262,11 @@ impl DbMessage {
             None => {
                 let sql = r#"
                     INSERT INTO message 
-                    (event_id, content, chat_pubkey, is_users, created_at, status, relay_url)
-                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);
+                    (event_id, content, chat_pubkey, is_users, created_at, status, relay_url, attachments)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);
                 "#;
+                let mut attachments = Attachment::from_tags(&db_event.tags);
+                attachments.extend(encrypted_attachments(keys, db_event, is_users));
 
                 sqlx::query(sql)
                     .bind(db_event.event_id)
@@ -242,6 +276,7 @@ impl DbMessage {
                     .bind(db_event.created_at.timestamp_millis())
                     .bind(MessageStatus::Delivered.to_i32())
                     .bind(&db_event.relay_url.to_string())
+                    .bind(serde_json::to_string(&attachments)?)
                     .execute(pool)
                     .await?;
 
@@ -271,6 +306,17 @@ impl DbMessage {
         Ok(())
     }
 
+    /// Deletes the messages of a chat. Their events are kept, so relays
+    /// sending them again don't bring the chat back.
+    pub async fn delete_chat(pool: &SqlitePool, chat_pubkey: &XOnlyPublicKey) -> Result<(), Error> {
+        let sql = "DELETE FROM message WHERE chat_pubkey = ?";
+        sqlx::query(sql)
+            .bind(chat_pubkey.to_string())
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
     pub(crate) async fn reset_unseen(
         pool: &SqlitePool,
         chat_pubkey: &XOnlyPublicKey,
@@ -304,6 +350,36 @@ impl DbMessage {
     }
 }
 
+fn decrypt_payload(
+    keys: &Keys,
+    is_users: bool,
+    tag_info: &MessageTagInfo,
+    encrypted_content: &str,
+) -> Result<String, Error> {
+    let users_secret_key = keys.secret_key()?;
+    let chat_pubkey = if is_users {
+        &tag_info.to_pubkey
+    } else {
+        &tag_info.from_pubkey
+    };
+    nip04::decrypt(&users_secret_key, chat_pubkey, encrypted_content)
+        .map_err(|e| Error::Decryption(e.to_string()))
+}
+
+/// Messages that fail to decrypt are still stored, just without attachments
+fn encrypted_attachments(keys: &Keys, db_event: &DbEvent, is_users: bool) -> Vec<Attachment> {
+    let payload =
+        MessageTagInfo::from_event_tags(&db_event.event_hash, &db_event.pubkey, &db_event.tags)
+            .and_then(|tag_info| decrypt_payload(keys, is_users, &tag_info, &db_event.content));
+    match payload {
+        Ok(payload) => Attachment::split_dm_payload(&payload).1,
+        Err(e) => {
+            tracing::warn!("Failed to decrypt message attachments: {}", e);
+            vec![]
+        }
+    }
+}
+
 impl sqlx::FromRow<'_, SqliteRow> for DbMessage {
     fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
         let created_at: i64 = row.try_get("created_at")?;
@@ -318,6 +394,9 @@ impl sqlx::FromRow<'_, SqliteRow> for DbMessage {
         let relay_url: String = row.try_get("relay_url")?;
         let relay_url = url_or_err(&relay_url, "relay_url")?;
 
+        let attachments: String = row.try_get("attachments")?;
+        let attachments = attachments_or_err(&attachments, "attachments")?;
+
         Ok(DbMessage {
             event_id: row.try_get::<i64, &str>("event_id")?,
             encrypted_content: row.try_get::<String, &str>("content")?,
@@ -326,6 +405,7 @@ impl sqlx::FromRow<'_, SqliteRow> for DbMessage {
             created_at,
             status,
             relay_url,
+            attachments,
         })
     }
 }
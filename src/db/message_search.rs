@@ -0,0 +1,221 @@
+use chrono::NaiveDateTime;
+use nostr::{secp256k1::XOnlyPublicKey, EventId, Keys};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use thiserror::Error;
+
+use crate::utils::{event_hash_or_err, millis_to_naive_or_err, public_key_or_err};
+
+use super::DbMessage;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// Chat that a search result belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchChat {
+    Contact(XOnlyPublicKey),
+    Channel(EventId),
+}
+impl SearchChat {
+    fn chat_id(&self) -> String {
+        match self {
+            SearchChat::Contact(pubkey) => pubkey.to_string(),
+            SearchChat::Channel(channel_id) => channel_id.to_string(),
+        }
+    }
+}
+
+/// Which chats a search looks into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchScope {
+    Chat(SearchChat),
+    DirectMessages,
+    Channels,
+}
+impl SearchScope {
+    fn chat_id(&self) -> Option<String> {
+        match self {
+            SearchScope::Chat(chat) => Some(chat.chat_id()),
+            _ => None,
+        }
+    }
+    fn is_channel(&self) -> Option<bool> {
+        match self {
+            SearchScope::Chat(_) => None,
+            SearchScope::DirectMessages => Some(false),
+            SearchScope::Channels => Some(true),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MessageSearchQuery {
+    pub text: String,
+    pub scope: SearchScope,
+    pub author: Option<XOnlyPublicKey>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MessageSearchResult {
+    pub event_id: i64,
+    pub chat: SearchChat,
+    pub author: XOnlyPublicKey,
+    pub created_at: NaiveDateTime,
+    pub snippet: String,
+}
+
+pub struct MessageSearch;
+impl MessageSearch {
+    /// Index the decrypted content of a direct message.
+    ///
+    /// Channel messages are indexed by database triggers.
+    pub async fn insert_dm(
+        pool: &SqlitePool,
+        keys: &Keys,
+        db_message: &DbMessage,
+        decrypted_content: &str,
+    ) -> Result<(), Error> {
+        let author = if db_message.is_users {
+            keys.public_key()
+        } else {
+            db_message.chat_pubkey
+        };
+
+        let sql = r#"
+            INSERT OR REPLACE INTO message_search
+                (rowid, content, chat_id, author, is_channel, created_at)
+            VALUES (?1, ?2, ?3, ?4, 0, ?5);
+        "#;
+
+        sqlx::query(sql)
+            .bind(db_message.event_id)
+            .bind(decrypted_content)
+            .bind(db_message.chat_pubkey.to_string())
+            .bind(author.to_string())
+            .bind(db_message.created_at.timestamp_millis())
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Direct messages stored before the search index existed
+    pub async fn fetch_unindexed_dms(pool: &SqlitePool) -> Result<Vec<DbMessage>, Error> {
+        let sql = r#"
+            SELECT * FROM message
+            WHERE event_id NOT IN (SELECT rowid FROM message_search);
+        "#;
+        let messages = sqlx::query_as::<_, DbMessage>(sql).fetch_all(pool).await?;
+        Ok(messages)
+    }
+
+    pub async fn search(
+        pool: &SqlitePool,
+        query: &MessageSearchQuery,
+    ) -> Result<Vec<MessageSearchResult>, Error> {
+        let Some(match_query) = fts_query(&query.text) else {
+            return Ok(vec![]);
+        };
+
+        let sql = r#"
+            SELECT rowid AS event_id, chat_id, author, is_channel, created_at,
+                snippet(message_search, 0, '', '', '...', 16) AS snippet
+            FROM message_search
+            WHERE message_search MATCH ?1
+                AND (?2 IS NULL OR chat_id = ?2)
+                AND (?3 IS NULL OR is_channel = ?3)
+                AND (?4 IS NULL OR author = ?4)
+                AND (?5 IS NULL OR created_at >= ?5)
+                AND (?6 IS NULL OR created_at <= ?6)
+            ORDER BY rank, created_at DESC
+            LIMIT ?7;
+        "#;
+
+        let results = sqlx::query_as::<_, MessageSearchResult>(sql)
+            .bind(match_query)
+            .bind(query.scope.chat_id())
+            .bind(query.scope.is_channel())
+            .bind(query.author.map(|a| a.to_string()))
+            .bind(query.since.map(|d| d.timestamp_millis()))
+            .bind(query.until.map(|d| d.timestamp_millis()))
+            .bind(SEARCH_LIMIT)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(results)
+    }
+}
+
+/// Turns user input into an FTS5 query where every word is a quoted prefix term,
+/// so characters like `"`, `*` or `-` don't break the query syntax.
+pub fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<_> = text
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for MessageSearchResult {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        let created_at = row.try_get::<i64, &str>("created_at")?;
+        let created_at = millis_to_naive_or_err(created_at, "created_at")?;
+
+        let author = &row.try_get::<String, &str>("author")?;
+        let author = public_key_or_err(author, "author")?;
+
+        let chat_id: String = row.try_get("chat_id")?;
+        let is_channel: bool = row.try_get("is_channel")?;
+        let chat = if is_channel {
+            SearchChat::Channel(event_hash_or_err(&chat_id, "chat_id")?)
+        } else {
+            SearchChat::Contact(public_key_or_err(&chat_id, "chat_id")?)
+        };
+
+        Ok(MessageSearchResult {
+            event_id: row.try_get("event_id")?,
+            chat,
+            author,
+            created_at,
+            snippet: row.try_get("snippet")?,
+        })
+    }
+}
+
+const SEARCH_LIMIT: i64 = 50;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fts_query_empty() {
+        assert_eq!(fts_query("   "), None);
+    }
+
+    #[test]
+    fn test_fts_query_prefix_terms() {
+        assert_eq!(
+            fts_query("hello  world"),
+            Some("\"hello\"* \"world\"*".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fts_query_escapes_quotes() {
+        assert_eq!(
+            fts_query("say \"hi\""),
+            Some("\"say\"* \"\"\"hi\"\"\"*".to_string())
+        );
+    }
+}
@@ -1,25 +1,56 @@
+pub(crate) mod cache_eviction;
 pub(crate) mod channel_cache;
 pub(crate) mod channel_message;
+pub(crate) mod channel_preview;
 pub(crate) mod channel_subscription;
+pub(crate) mod chat_expiration;
 pub(crate) mod contact;
 pub(crate) mod database;
+pub(crate) mod draft;
 pub(crate) mod event;
+pub(crate) mod filtered_message;
+pub(crate) mod follower;
+pub(crate) mod image_blob;
 pub(crate) mod image_cache;
+pub(crate) mod link_preview;
 pub(crate) mod message;
+pub(crate) mod message_search;
+pub(crate) mod muted_pubkey;
+pub(crate) mod notification;
 pub(crate) mod profile_cache;
 pub(crate) mod relay;
 pub(crate) mod relay_response;
 pub(crate) mod user_config;
+pub(crate) mod wallet;
+pub(crate) mod zap_receipt;
 
+pub use cache_eviction::{CacheBudget, CacheEviction, SweepStats};
 pub use channel_cache::ChannelCache;
-pub use channel_message::DbChannelMessage;
-pub use channel_subscription::ChannelSubscription;
+pub use channel_message::{ChannelMemberStats, DbChannelMessage};
+pub use channel_preview::{ChannelPreviewMessage, CHANNEL_PREVIEW_LIMIT};
+pub use channel_subscription::{ChannelActivity, ChannelSubscription};
+pub use chat_expiration::ExpirationTimer;
 pub use contact::DbContact;
-pub use database::{upgrade_cache_db, upgrade_db, Database};
+pub use database::{
+    backup_path, connect_encrypted, curr_db_version, upgrade_cache_db, upgrade_cache_db_to,
+    upgrade_db, upgrade_db_to, Database, DbKey, CACHE_DB_VERSION, DB_VERSION,
+};
+pub use draft::DbDraft;
 pub use event::DbEvent;
+pub use filtered_message::DbFilteredMessage;
+pub use follower::DbFollower;
+pub use image_blob::{ImageBlob, ImageUrl};
 pub use image_cache::ImageDownloaded;
+pub use link_preview::LinkPreview;
 pub use message::{DbMessage, MessageStatus, MessageTagInfo};
+pub use message_search::{
+    MessageSearch, MessageSearchQuery, MessageSearchResult, SearchChat, SearchScope,
+};
+pub use muted_pubkey::DbMutedPubkey;
+pub use notification::{ChatNotification, DoNotDisturb, Mute, NotificationChat};
 pub use profile_cache::ProfileCache;
 pub use relay::DbRelay;
 pub use relay_response::DbRelayResponse;
 pub use user_config::UserConfig;
+pub use wallet::{DbWalletPayment, PaymentStatus, WalletConnect};
+pub use zap_receipt::{DbZapReceipt, ZapProvider};
@@ -0,0 +1,104 @@
+use chrono::NaiveDateTime;
+use nostr::secp256k1::XOnlyPublicKey;
+use sqlx::{sqlite::SqliteRow, Row, SqliteConnection, SqlitePool};
+use thiserror::Error;
+
+use crate::utils::{millis_to_naive_or_err, public_key_or_err};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// Entry of the user's mute list. Private entries are blocks that only the
+/// user can see, public ones are mutes anyone can read from the list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbMutedPubkey {
+    pub pubkey: XOnlyPublicKey,
+    pub private: bool,
+    pub created_at: NaiveDateTime,
+}
+impl DbMutedPubkey {
+    const INSERT_QUERY: &'static str = r#"
+        INSERT OR REPLACE INTO muted_pubkey (pubkey, private, created_at)
+        VALUES (?, ?, ?)
+    "#;
+
+    pub fn new(pubkey: &XOnlyPublicKey, private: bool, created_at: NaiveDateTime) -> Self {
+        Self {
+            pubkey: pubkey.to_owned(),
+            private,
+            created_at,
+        }
+    }
+
+    /// Inserts or changes between public and private
+    pub async fn insert(pool: &SqlitePool, muted: &DbMutedPubkey) -> Result<(), Error> {
+        sqlx::query(Self::INSERT_QUERY)
+            .bind(muted.pubkey.to_string())
+            .bind(muted.private)
+            .bind(muted.created_at.timestamp_millis())
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete(pool: &SqlitePool, pubkey: &XOnlyPublicKey) -> Result<(), Error> {
+        let sql = "DELETE FROM muted_pubkey WHERE pubkey = ?";
+        sqlx::query(sql)
+            .bind(pubkey.to_string())
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Replaces all entries with the ones of a newer mute list,
+    /// within the caller's transaction
+    pub async fn replace_all_with(
+        conn: &mut SqliteConnection,
+        muted: &[DbMutedPubkey],
+    ) -> Result<(), Error> {
+        sqlx::query("DELETE FROM muted_pubkey")
+            .execute(&mut *conn)
+            .await?;
+        for entry in muted {
+            sqlx::query(Self::INSERT_QUERY)
+                .bind(entry.pubkey.to_string())
+                .bind(entry.private)
+                .bind(entry.created_at.timestamp_millis())
+                .execute(&mut *conn)
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn fetch(pool: &SqlitePool) -> Result<Vec<DbMutedPubkey>, Error> {
+        let sql = "SELECT * FROM muted_pubkey ORDER BY created_at DESC";
+        Ok(sqlx::query_as::<_, DbMutedPubkey>(sql)
+            .fetch_all(pool)
+            .await?)
+    }
+
+    pub async fn is_muted(pool: &SqlitePool, pubkey: &XOnlyPublicKey) -> Result<bool, Error> {
+        let sql = "SELECT EXISTS(SELECT 1 FROM muted_pubkey WHERE pubkey = ?)";
+        let muted: bool = sqlx::query_scalar(sql)
+            .bind(pubkey.to_string())
+            .fetch_one(pool)
+            .await?;
+        Ok(muted)
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for DbMutedPubkey {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            pubkey: public_key_or_err(&row.try_get::<String, &str>("pubkey")?, "pubkey")?,
+            private: row.try_get::<bool, &str>("private")?,
+            created_at: millis_to_naive_or_err(
+                row.try_get::<i64, &str>("created_at")?,
+                "created_at",
+            )?,
+        })
+    }
+}
@@ -0,0 +1,194 @@
+use chrono::{NaiveDateTime, NaiveTime, Timelike};
+use nostr::{secp256k1::XOnlyPublicKey, EventId};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use thiserror::Error;
+
+use crate::utils::{event_hash_or_err, millis_to_naive_or_err, public_key_or_err};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+
+    #[error("Unknown chat type: {0}")]
+    UnknownChatType(i32),
+}
+
+/// Chat that notification rules apply to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NotificationChat {
+    Dm(XOnlyPublicKey),
+    Channel(EventId),
+}
+impl NotificationChat {
+    pub(crate) fn chat_type(&self) -> i32 {
+        match self {
+            NotificationChat::Dm(_) => 0,
+            NotificationChat::Channel(_) => 1,
+        }
+    }
+    pub(crate) fn chat_id(&self) -> String {
+        match self {
+            NotificationChat::Dm(pubkey) => pubkey.to_string(),
+            NotificationChat::Channel(channel_id) => channel_id.to_hex(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mute {
+    Off,
+    Until(NaiveDateTime),
+    Forever,
+}
+
+/// Per-chat notification rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatNotification {
+    pub chat: NotificationChat,
+    pub mute: Mute,
+    /// Only for channels
+    pub mentions_only: bool,
+}
+impl ChatNotification {
+    pub fn new(chat: NotificationChat) -> Self {
+        Self {
+            chat,
+            mute: Mute::Off,
+            mentions_only: false,
+        }
+    }
+
+    /// An expired mute is the same as no mute
+    pub fn is_muted(&self, now: NaiveDateTime) -> bool {
+        match self.mute {
+            Mute::Off => false,
+            Mute::Until(until) => now < until,
+            Mute::Forever => true,
+        }
+    }
+
+    pub fn allows(&self, now: NaiveDateTime, mentioned: bool) -> bool {
+        if self.is_muted(now) {
+            return false;
+        }
+        match self.chat {
+            NotificationChat::Dm(_) => true,
+            NotificationChat::Channel(_) => !self.mentions_only || mentioned,
+        }
+    }
+
+    /// The defaults when the chat has no rules
+    pub async fn fetch(
+        pool: &SqlitePool,
+        chat: &NotificationChat,
+    ) -> Result<ChatNotification, Error> {
+        let sql = "SELECT * FROM chat_notification WHERE chat_type = ? AND chat_id = ?";
+        let rules = sqlx::query_as::<_, ChatNotification>(sql)
+            .bind(chat.chat_type())
+            .bind(chat.chat_id())
+            .fetch_optional(pool)
+            .await?;
+        Ok(rules.unwrap_or_else(|| ChatNotification::new(chat.to_owned())))
+    }
+
+    pub async fn save(pool: &SqlitePool, rules: &ChatNotification) -> Result<(), Error> {
+        let muted_until = match rules.mute {
+            Mute::Off => None,
+            Mute::Until(until) => Some(until.timestamp_millis()),
+            Mute::Forever => Some(MUTED_FOREVER),
+        };
+        let sql = r#"
+            INSERT OR REPLACE INTO chat_notification
+                (chat_type, chat_id, muted_until, mentions_only)
+            VALUES (?, ?, ?, ?)
+        "#;
+        sqlx::query(sql)
+            .bind(rules.chat.chat_type())
+            .bind(rules.chat.chat_id())
+            .bind(muted_until)
+            .bind(rules.mentions_only)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for ChatNotification {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        let chat_id = row.try_get::<String, &str>("chat_id")?;
+        let chat = match row.try_get::<i32, &str>("chat_type")? {
+            0 => NotificationChat::Dm(public_key_or_err(&chat_id, "chat_id")?),
+            1 => NotificationChat::Channel(event_hash_or_err(&chat_id, "chat_id")?),
+            other => {
+                return Err(sqlx::Error::ColumnDecode {
+                    index: "chat_type".into(),
+                    source: Box::new(Error::UnknownChatType(other)),
+                })
+            }
+        };
+        let mute = match row.try_get::<Option<i64>, &str>("muted_until")? {
+            None => Mute::Off,
+            Some(MUTED_FOREVER) => Mute::Forever,
+            Some(millis) => Mute::Until(millis_to_naive_or_err(millis, "muted_until")?),
+        };
+        Ok(Self {
+            chat,
+            mute,
+            mentions_only: row.try_get::<bool, &str>("mentions_only")?,
+        })
+    }
+}
+
+/// Daily window, in local time, without notifications
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DoNotDisturb {
+    pub enabled: bool,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+impl DoNotDisturb {
+    /// The window may go past midnight, like 22:00 to 07:00
+    pub fn is_active(&self, local_time: NaiveTime) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.start <= self.end {
+            self.start <= local_time && local_time < self.end
+        } else {
+            local_time >= self.start || local_time < self.end
+        }
+    }
+
+    pub async fn fetch(pool: &SqlitePool) -> Result<DoNotDisturb, Error> {
+        let sql = "SELECT dnd_enabled, dnd_start, dnd_end FROM user_config WHERE id = 1";
+        let (enabled, start, end): (bool, u32, u32) = sqlx::query_as(sql).fetch_one(pool).await?;
+        Ok(Self {
+            enabled,
+            start: minutes_to_time(start),
+            end: minutes_to_time(end),
+        })
+    }
+
+    pub async fn save(pool: &SqlitePool, dnd: &DoNotDisturb) -> Result<(), Error> {
+        let sql = "UPDATE user_config SET dnd_enabled = ?, dnd_start = ?, dnd_end = ? WHERE id = 1";
+        sqlx::query(sql)
+            .bind(dnd.enabled)
+            .bind(time_to_minutes(dnd.start))
+            .bind(time_to_minutes(dnd.end))
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
+
+fn minutes_to_time(minutes: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt((minutes / 60) % 24, minutes % 60, 0).unwrap_or_default()
+}
+
+fn time_to_minutes(time: NaiveTime) -> u32 {
+    time.hour() * 60 + time.minute()
+}
+
+/// `muted_until` of chats muted until unmuted by the user
+const MUTED_FOREVER: i64 = i64::MAX;
@@ -5,7 +5,7 @@ use crate::{
         public_key_or_err, url_or_err,
     },
 };
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use nostr::{secp256k1::XOnlyPublicKey, EventId};
 use serde::{Deserialize, Serialize};
 use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
@@ -57,6 +57,7 @@ impl ProfileCache {
             .await?;
 
         if let Some(profile_cache) = &mut result {
+            Self::touch(cache_pool, public_key).await?;
             profile_cache.profile_pic_cache =
                 ImageDownloaded::fetch(cache_pool, &profile_cache.event_hash, ImageKind::Profile)
                     .await?;
@@ -68,6 +69,16 @@ impl ProfileCache {
         Ok(result)
     }
 
+    /// Marks the profile as recently used, so it is the last to be evicted
+    pub async fn touch(cache_pool: &SqlitePool, public_key: &XOnlyPublicKey) -> Result<(), Error> {
+        sqlx::query("UPDATE profile_meta_cache SET last_accessed_at = ? WHERE public_key = ?")
+            .bind(Utc::now().timestamp_millis())
+            .bind(public_key.to_string())
+            .execute(cache_pool)
+            .await?;
+        Ok(())
+    }
+
     // pub async fn fetch_channel_members(
     //     cache_pool: &SqlitePool,
     //     channel_id: &EventId,
@@ -121,7 +132,7 @@ impl ProfileCache {
 
         let update_query = r#"
             UPDATE profile_meta_cache 
-            SET updated_at=?, event_hash=?, metadata=?, from_relay=?
+            SET updated_at=?, event_hash=?, metadata=?, from_relay=?, last_accessed_at=?
             WHERE public_key = ?
         "#;
         let mut rows_affected = sqlx::query(update_query)
@@ -129,6 +140,7 @@ impl ProfileCache {
             .bind(&event_hash.to_string())
             .bind(&metadata.as_json())
             .bind(&relay_url.to_string())
+            .bind(Utc::now().timestamp_millis())
             .bind(&public_key.to_string())
             .execute(&mut tx)
             .await?
@@ -137,8 +149,8 @@ impl ProfileCache {
         if rows_affected == 0 {
             let insert_query = r#"
                 INSERT INTO profile_meta_cache
-                    (public_key, updated_at, event_hash, metadata, from_relay) 
-                VALUES (?, ?, ?, ?, ?)
+                    (public_key, updated_at, event_hash, metadata, from_relay, last_accessed_at) 
+                VALUES (?, ?, ?, ?, ?, ?)
             "#;
             rows_affected = sqlx::query(insert_query)
                 .bind(&public_key.to_string())
@@ -146,6 +158,7 @@ impl ProfileCache {
                 .bind(&event_hash.to_string())
                 .bind(&metadata.as_json())
                 .bind(&relay_url.to_string())
+                .bind(Utc::now().timestamp_millis())
                 .execute(&mut tx)
                 .await?
                 .rows_affected();
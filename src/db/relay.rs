@@ -27,6 +27,8 @@ pub struct DbRelay {
     pub read: bool,
     pub write: bool,
     pub advertise: bool,
+    /// NIP-13 difficulty the relay requires for the user's events
+    pub pow_difficulty: u8,
     pub information: Option<RelayInformation>,
 }
 
@@ -71,6 +73,27 @@ impl DbRelay {
         Ok(())
     }
 
+    pub async fn set_pow_difficulty(
+        pool: &SqlitePool,
+        url: &Url,
+        difficulty: u8,
+    ) -> Result<(), Error> {
+        let sql = "UPDATE relay SET pow_difficulty=? WHERE url=?";
+        sqlx::query(sql)
+            .bind(difficulty)
+            .bind(&url.to_string())
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Highest difficulty among the relays the user writes to
+    pub async fn fetch_max_pow_difficulty(pool: &SqlitePool) -> Result<u8, Error> {
+        let sql = "SELECT COALESCE(MAX(pow_difficulty), 0) FROM relay WHERE write = 1";
+        let difficulty: u8 = sqlx::query_scalar(sql).fetch_one(pool).await?;
+        Ok(difficulty)
+    }
+
     pub async fn delete(pool: &SqlitePool, url: &Url) -> Result<(), Error> {
         let sql = "DELETE FROM relay WHERE url=?";
         sqlx::query(sql)
@@ -92,6 +115,7 @@ impl sqlx::FromRow<'_, SqliteRow> for DbRelay {
             read: row.try_get::<bool, &str>("read")?,
             write: row.try_get::<bool, &str>("write")?,
             advertise: row.try_get::<bool, &str>("advertise")?,
+            pow_difficulty: row.try_get::<u8, &str>("pow_difficulty")?,
             information: None,
         })
     }
@@ -4,7 +4,7 @@ use crate::{
 };
 
 use chrono::NaiveDateTime;
-use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use sqlx::{sqlite::SqliteRow, Row, SqliteConnection, SqlitePool};
 use thiserror::Error;
 use url::Url;
 
@@ -28,14 +28,14 @@ pub struct UserConfig {
 }
 
 impl UserConfig {
-    pub async fn setup_user_config(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    pub async fn setup_user_config(conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
         tracing::debug!("setup_user_config");
         let query = r#"
             INSERT INTO user_config 
                 (id, has_logged_in, ntp_offset, recommended_relay) 
             VALUES (1, 0, 0, "");
         "#;
-        sqlx::query(query).execute(pool).await?;
+        sqlx::query(query).execute(conn).await?;
         Ok(())
     }
 
@@ -105,6 +105,32 @@ impl UserConfig {
         Ok(())
     }
 
+    /// NIP-13 difficulty mined into the user's events, 0 when disabled
+    pub async fn get_pow_difficulty(pool: &SqlitePool) -> Result<u8, Error> {
+        let query = "SELECT pow_difficulty FROM user_config WHERE id = 1;";
+        let difficulty: u8 = sqlx::query_scalar(query).fetch_one(pool).await?;
+        Ok(difficulty)
+    }
+
+    pub async fn set_pow_difficulty(pool: &SqlitePool, difficulty: u8) -> Result<(), Error> {
+        let query = "UPDATE user_config SET pow_difficulty = ? WHERE id = 1;";
+        sqlx::query(query).bind(difficulty).execute(pool).await?;
+        Ok(())
+    }
+
+    /// NIP-13 difficulty asked of messages from non-contacts, 0 when disabled
+    pub async fn get_incoming_pow_threshold(pool: &SqlitePool) -> Result<u8, Error> {
+        let query = "SELECT incoming_pow_threshold FROM user_config WHERE id = 1;";
+        let threshold: u8 = sqlx::query_scalar(query).fetch_one(pool).await?;
+        Ok(threshold)
+    }
+
+    pub async fn set_incoming_pow_threshold(pool: &SqlitePool, threshold: u8) -> Result<(), Error> {
+        let query = "UPDATE user_config SET incoming_pow_threshold = ? WHERE id = 1;";
+        sqlx::query(query).bind(threshold).execute(pool).await?;
+        Ok(())
+    }
+
     pub(crate) async fn get_relay(pool: &SqlitePool) -> Result<Option<Url>, Error> {
         let query = "SELECT recommended_relay FROM user_config WHERE id = 1;";
         let recommended_relay: String = sqlx::query_scalar(query).fetch_one(pool).await?;
@@ -0,0 +1,211 @@
+use chrono::{NaiveDateTime, Utc};
+use nostr::EventId;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use thiserror::Error;
+
+use crate::net::wallet_connect::WalletConnectUri;
+use crate::utils::{event_hash_or_err, millis_to_naive_or_err};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+
+    #[error("{0}")]
+    FromWalletConnect(#[from] crate::net::wallet_connect::Error),
+
+    #[error("Unknown payment status: {0}")]
+    UnknownStatus(i32),
+}
+
+/// The account's NIP-47 wallet connection
+pub struct WalletConnect;
+impl WalletConnect {
+    pub async fn fetch(pool: &SqlitePool) -> Result<Option<WalletConnectUri>, Error> {
+        let sql = "SELECT uri FROM wallet_connect WHERE id = 1";
+        let uri: Option<String> = sqlx::query_scalar(sql).fetch_optional(pool).await?;
+        Ok(uri.map(|uri| uri.parse()).transpose()?)
+    }
+
+    /// Replaces the previous connection
+    pub async fn save(pool: &SqlitePool, uri: &WalletConnectUri) -> Result<(), Error> {
+        let sql = r#"
+            INSERT OR REPLACE INTO wallet_connect (id, uri, created_at)
+            VALUES (1, ?, ?)
+        "#;
+        sqlx::query(sql)
+            .bind(uri.to_string())
+            .bind(Utc::now().timestamp_millis())
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete(pool: &SqlitePool) -> Result<(), Error> {
+        sqlx::query("DELETE FROM wallet_connect WHERE id = 1")
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentStatus {
+    Pending,
+    Paid { preimage: String },
+    Failed { error: String },
+}
+impl PaymentStatus {
+    fn to_i32(&self) -> i32 {
+        match self {
+            PaymentStatus::Pending => 0,
+            PaymentStatus::Paid { .. } => 1,
+            PaymentStatus::Failed { .. } => 2,
+        }
+    }
+}
+
+/// Invoice paid through the connected wallet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbWalletPayment {
+    pub request_hash: EventId,
+    pub invoice: String,
+    pub amount_msats: u64,
+    pub status: PaymentStatus,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+impl DbWalletPayment {
+    pub fn new(request_hash: EventId, invoice: &str, amount_msats: u64) -> Self {
+        let now = Utc::now().naive_utc();
+        Self {
+            request_hash,
+            invoice: invoice.to_owned(),
+            amount_msats,
+            status: PaymentStatus::Pending,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub async fn insert(pool: &SqlitePool, payment: &DbWalletPayment) -> Result<(), Error> {
+        let (preimage, error) = payment.status_columns();
+        let sql = r#"
+            INSERT INTO wallet_payment
+                (request_hash, invoice, amount_msats, status, preimage, error, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#;
+        sqlx::query(sql)
+            .bind(payment.request_hash.to_hex())
+            .bind(&payment.invoice)
+            .bind(payment.amount_msats as i64)
+            .bind(payment.status.to_i32())
+            .bind(preimage)
+            .bind(error)
+            .bind(payment.created_at.timestamp_millis())
+            .bind(payment.updated_at.timestamp_millis())
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Most recent first
+    pub async fn fetch(pool: &SqlitePool, limit: i64) -> Result<Vec<DbWalletPayment>, Error> {
+        let sql = "SELECT * FROM wallet_payment ORDER BY created_at DESC LIMIT ?";
+        let payments = sqlx::query_as::<_, DbWalletPayment>(sql)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+        Ok(payments)
+    }
+
+    pub async fn fetch_request(
+        pool: &SqlitePool,
+        request_hash: &EventId,
+    ) -> Result<Option<DbWalletPayment>, Error> {
+        let sql = "SELECT * FROM wallet_payment WHERE request_hash = ?";
+        let payment = sqlx::query_as::<_, DbWalletPayment>(sql)
+            .bind(request_hash.to_hex())
+            .fetch_optional(pool)
+            .await?;
+        Ok(payment)
+    }
+
+    /// Only pending payments are updated, so a late answer can't change
+    /// the result. Returns the updated payment.
+    pub async fn resolve(
+        pool: &SqlitePool,
+        request_hash: &EventId,
+        status: PaymentStatus,
+    ) -> Result<Option<DbWalletPayment>, Error> {
+        let Some(mut payment) = Self::fetch_request(pool, request_hash).await? else {
+            return Ok(None);
+        };
+        if payment.status != PaymentStatus::Pending {
+            return Ok(None);
+        }
+        payment.status = status;
+        payment.updated_at = Utc::now().naive_utc();
+
+        let (preimage, error) = payment.status_columns();
+        let sql = r#"
+            UPDATE wallet_payment
+            SET status = ?, preimage = ?, error = ?, updated_at = ?
+            WHERE request_hash = ?
+        "#;
+        sqlx::query(sql)
+            .bind(payment.status.to_i32())
+            .bind(preimage)
+            .bind(error)
+            .bind(payment.updated_at.timestamp_millis())
+            .bind(request_hash.to_hex())
+            .execute(pool)
+            .await?;
+        Ok(Some(payment))
+    }
+
+    fn status_columns(&self) -> (Option<&str>, Option<&str>) {
+        match &self.status {
+            PaymentStatus::Pending => (None, None),
+            PaymentStatus::Paid { preimage } => (Some(preimage), None),
+            PaymentStatus::Failed { error } => (None, Some(error)),
+        }
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for DbWalletPayment {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        let status = match row.try_get::<i32, &str>("status")? {
+            0 => PaymentStatus::Pending,
+            1 => PaymentStatus::Paid {
+                preimage: row
+                    .try_get::<Option<String>, &str>("preimage")?
+                    .unwrap_or_default(),
+            },
+            2 => PaymentStatus::Failed {
+                error: row
+                    .try_get::<Option<String>, &str>("error")?
+                    .unwrap_or_default(),
+            },
+            other => {
+                return Err(sqlx::Error::ColumnDecode {
+                    index: "status".into(),
+                    source: Box::new(Error::UnknownStatus(other)),
+                })
+            }
+        };
+        let created_at = row.try_get::<i64, &str>("created_at")?;
+        let updated_at = row.try_get::<i64, &str>("updated_at")?;
+        Ok(Self {
+            request_hash: event_hash_or_err(
+                &row.try_get::<String, &str>("request_hash")?,
+                "request_hash",
+            )?,
+            invoice: row.try_get("invoice")?,
+            amount_msats: row.try_get::<i64, &str>("amount_msats")? as u64,
+            status,
+            created_at: millis_to_naive_or_err(created_at, "created_at")?,
+            updated_at: millis_to_naive_or_err(updated_at, "updated_at")?,
+        })
+    }
+}
@@ -0,0 +1,253 @@
+use chrono::NaiveDateTime;
+use nostr::{secp256k1::XOnlyPublicKey, EventId, Kind, Tag};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use thiserror::Error;
+
+use crate::net::zap::{ZAP_RECEIPT_KIND, ZAP_REQUEST_KIND};
+use crate::utils::{
+    event_hash_or_err, invoice_amount_msats, millis_to_naive_or_err, ns_event_to_naive,
+    public_key_or_err,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+
+    #[error("Not a zap receipt: {0}")]
+    NotZapReceipt(EventId),
+
+    #[error("Zap receipt without the {1} tag: {0}")]
+    MissingTag(EventId, &'static str),
+
+    #[error("Invalid zap request in receipt: {0}")]
+    InvalidZapRequest(EventId),
+
+    #[error("Zap receipt amount doesn't match the request: {0}")]
+    AmountMismatch(EventId),
+
+    #[error("Zap receipt {1} tag doesn't match the request: {0}")]
+    TagMismatch(EventId, &'static str),
+
+    #[error("Zap receipt not signed by the recipient's LNURL server: {0}")]
+    WrongSigner(EventId),
+
+    #[error("{0}")]
+    FromUtils(#[from] crate::utils::Error),
+}
+
+/// Payment of a zap, published by the recipient's LNURL server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbZapReceipt {
+    pub receipt_hash: EventId,
+    /// None when the profile was zapped
+    pub zapped_event_hash: Option<EventId>,
+    pub recipient: XOnlyPublicKey,
+    pub sender: XOnlyPublicKey,
+    pub amount_msats: u64,
+    pub created_at: NaiveDateTime,
+}
+impl DbZapReceipt {
+    /// Zapped public key, from the `p` tag of a kind 9735 event
+    pub fn recipient_of(ns_event: &nostr::Event) -> Result<XOnlyPublicKey, Error> {
+        if ns_event.kind != Kind::from(ZAP_RECEIPT_KIND) {
+            return Err(Error::NotZapReceipt(ns_event.id));
+        }
+        pubkey_tag(&ns_event.tags).ok_or(Error::MissingTag(ns_event.id, "p"))
+    }
+
+    /// Reads a kind 9735 event, which must be signed by `provider`, the
+    /// recipient's LNURL server. The amount comes from the paid invoice, and
+    /// the sender from the zap request in the `description` tag, which must
+    /// be signed, ask for that same amount and zap the same `p` and `e` tags.
+    pub fn from_event(ns_event: &nostr::Event, provider: &XOnlyPublicKey) -> Result<Self, Error> {
+        let recipient = Self::recipient_of(ns_event)?;
+        if &ns_event.pubkey != provider {
+            return Err(Error::WrongSigner(ns_event.id));
+        }
+        let zapped_event_hash = event_tag(&ns_event.tags);
+        let amount_msats = tag_value(&ns_event.tags, "bolt11")
+            .and_then(|invoice| invoice_amount_msats(&invoice))
+            .ok_or(Error::MissingTag(ns_event.id, "bolt11"))?;
+        let description = tag_value(&ns_event.tags, "description")
+            .ok_or(Error::MissingTag(ns_event.id, "description"))?;
+
+        let zap_request = nostr::Event::from_json(description)
+            .ok()
+            .filter(|zap_request| zap_request.verify().is_ok())
+            .filter(|zap_request| zap_request.kind == Kind::from(ZAP_REQUEST_KIND))
+            .ok_or(Error::InvalidZapRequest(ns_event.id))?;
+        if let Some(requested) = tag_value(&zap_request.tags, "amount") {
+            if requested.parse::<u64>().ok() != Some(amount_msats) {
+                return Err(Error::AmountMismatch(ns_event.id));
+            }
+        }
+        if pubkey_tag(&zap_request.tags) != Some(recipient) {
+            return Err(Error::TagMismatch(ns_event.id, "p"));
+        }
+        if event_tag(&zap_request.tags) != zapped_event_hash {
+            return Err(Error::TagMismatch(ns_event.id, "e"));
+        }
+
+        Ok(Self {
+            receipt_hash: ns_event.id,
+            zapped_event_hash,
+            recipient,
+            sender: zap_request.pubkey,
+            amount_msats,
+            created_at: ns_event_to_naive(ns_event.created_at)?,
+        })
+    }
+
+    /// Returns false when the receipt was already stored
+    pub async fn insert(pool: &SqlitePool, receipt: &DbZapReceipt) -> Result<bool, Error> {
+        let sql = r#"
+            INSERT OR IGNORE INTO zap_receipt
+                (receipt_hash, zapped_event_hash, recipient, sender, amount_msats, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+        "#;
+        let inserted = sqlx::query(sql)
+            .bind(receipt.receipt_hash.to_hex())
+            .bind(receipt.zapped_event_hash.map(|hash| hash.to_hex()))
+            .bind(receipt.recipient.to_string())
+            .bind(receipt.sender.to_string())
+            .bind(receipt.amount_msats as i64)
+            .bind(receipt.created_at.timestamp_millis())
+            .execute(pool)
+            .await?
+            .rows_affected();
+        Ok(inserted > 0)
+    }
+
+    /// Total zapped to each of the events, as `(event_id, amount_msats)`.
+    /// Events without zaps are left out.
+    pub async fn fetch_totals(
+        pool: &SqlitePool,
+        event_ids: &[i64],
+    ) -> Result<Vec<(i64, u64)>, Error> {
+        let sql = r#"
+            SELECT event.event_id, SUM(zap_receipt.amount_msats) AS total
+            FROM zap_receipt
+            JOIN event ON event.event_hash = zap_receipt.zapped_event_hash
+            WHERE event.event_id IN (SELECT value FROM json_each(?))
+            GROUP BY event.event_id
+        "#;
+        let ids = serde_json::to_string(event_ids).unwrap_or_else(|_| "[]".to_owned());
+        let totals: Vec<(i64, i64)> = sqlx::query_as(sql).bind(ids).fetch_all(pool).await?;
+        Ok(totals
+            .into_iter()
+            .map(|(event_id, total)| (event_id, total as u64))
+            .collect())
+    }
+
+    /// Total zapped to the event, with its id in the database
+    pub async fn fetch_event_total(
+        pool: &SqlitePool,
+        event_hash: &EventId,
+    ) -> Result<Option<(i64, u64)>, Error> {
+        let sql = r#"
+            SELECT event.event_id, SUM(zap_receipt.amount_msats) AS total
+            FROM zap_receipt
+            JOIN event ON event.event_hash = zap_receipt.zapped_event_hash
+            WHERE zap_receipt.zapped_event_hash = ?
+            GROUP BY event.event_id
+        "#;
+        let total: Option<(i64, i64)> = sqlx::query_as(sql)
+            .bind(event_hash.to_hex())
+            .fetch_optional(pool)
+            .await?;
+        Ok(total.map(|(event_id, total)| (event_id, total as u64)))
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for DbZapReceipt {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        let zapped_event_hash = row
+            .try_get::<Option<String>, &str>("zapped_event_hash")?
+            .map(|hash| event_hash_or_err(&hash, "zapped_event_hash"))
+            .transpose()?;
+        let created_at = row.try_get::<i64, &str>("created_at")?;
+        Ok(Self {
+            receipt_hash: event_hash_or_err(
+                &row.try_get::<String, &str>("receipt_hash")?,
+                "receipt_hash",
+            )?,
+            zapped_event_hash,
+            recipient: public_key_or_err(&row.try_get::<String, &str>("recipient")?, "recipient")?,
+            sender: public_key_or_err(&row.try_get::<String, &str>("sender")?, "sender")?,
+            amount_msats: row.try_get::<i64, &str>("amount_msats")? as u64,
+            created_at: millis_to_naive_or_err(created_at, "created_at")?,
+        })
+    }
+}
+
+/// LNURL server that publishes the zap receipts of a recipient
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZapProvider {
+    pub recipient: XOnlyPublicKey,
+    /// `nostrPubkey` of the pay endpoint, signer of the receipts
+    pub nostr_pubkey: XOnlyPublicKey,
+    pub updated_at: NaiveDateTime,
+}
+impl ZapProvider {
+    /// Inserts or replaces the recipient's provider, it changes with the lightning address
+    pub async fn upsert(pool: &SqlitePool, provider: &ZapProvider) -> Result<(), Error> {
+        let sql = r#"
+            INSERT OR REPLACE INTO zap_provider (recipient, nostr_pubkey, updated_at)
+            VALUES (?, ?, ?)
+        "#;
+        sqlx::query(sql)
+            .bind(provider.recipient.to_string())
+            .bind(provider.nostr_pubkey.to_string())
+            .bind(provider.updated_at.timestamp_millis())
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn fetch(
+        pool: &SqlitePool,
+        recipient: &XOnlyPublicKey,
+    ) -> Result<Option<ZapProvider>, Error> {
+        let sql = "SELECT * FROM zap_provider WHERE recipient = ?";
+        Ok(sqlx::query_as::<_, ZapProvider>(sql)
+            .bind(recipient.to_string())
+            .fetch_optional(pool)
+            .await?)
+    }
+}
+
+impl sqlx::FromRow<'_, SqliteRow> for ZapProvider {
+    fn from_row(row: &'_ SqliteRow) -> Result<Self, sqlx::Error> {
+        let updated_at = row.try_get::<i64, &str>("updated_at")?;
+        Ok(Self {
+            recipient: public_key_or_err(&row.try_get::<String, &str>("recipient")?, "recipient")?,
+            nostr_pubkey: public_key_or_err(
+                &row.try_get::<String, &str>("nostr_pubkey")?,
+                "nostr_pubkey",
+            )?,
+            updated_at: millis_to_naive_or_err(updated_at, "updated_at")?,
+        })
+    }
+}
+
+fn pubkey_tag(tags: &[Tag]) -> Option<XOnlyPublicKey> {
+    tags.iter().find_map(|tag| match tag {
+        Tag::PubKey(pubkey, _) => Some(pubkey.to_owned()),
+        _ => None,
+    })
+}
+
+fn event_tag(tags: &[Tag]) -> Option<EventId> {
+    tags.iter().find_map(|tag| match tag {
+        Tag::Event(event_hash, _, _) => Some(event_hash.to_owned()),
+        _ => None,
+    })
+}
+
+fn tag_value(tags: &[Tag], name: &str) -> Option<String> {
+    tags.iter().find_map(|tag| match tag.as_vec().as_slice() {
+        [tag_name, value, ..] if tag_name == name => Some(value.to_owned()),
+        _ => None,
+    })
+}
@@ -16,6 +16,9 @@ pub enum Error {
     #[error("SendError: {0}")]
     FromSend(#[from] mpsc::SendError),
 
+    #[error("Sqlx error: {0}")]
+    FromSqlx(#[from] sqlx::Error),
+
     // I/O Error
     #[error("I/O Error: {0}")]
     FromIo(#[from] std::io::Error),
@@ -47,18 +50,45 @@ pub enum Error {
     #[error("{0}")]
     FromChannelCache(#[from] crate::db::channel_cache::Error),
 
+    #[error("{0}")]
+    FromChannelPreview(#[from] crate::db::channel_preview::Error),
+
+    #[error("{0}")]
+    FromCacheEviction(#[from] crate::db::cache_eviction::Error),
+
+    #[error("{0}")]
+    FromChatExpiration(#[from] crate::db::chat_expiration::Error),
+
     #[error("{0}")]
     FromContact(#[from] crate::db::contact::Error),
 
     #[error("{0}")]
     FromDatabase(#[from] crate::db::database::Error),
 
+    #[error("{0}")]
+    FromDraft(#[from] crate::db::draft::Error),
+
     #[error("{0}")]
     FromEvent(#[from] crate::db::event::Error),
 
+    #[error("{0}")]
+    FromFollower(#[from] crate::db::follower::Error),
+
+    #[error("{0}")]
+    FromFilteredMessage(#[from] crate::db::filtered_message::Error),
+
     #[error("{0}")]
     FromMessage(#[from] crate::db::message::Error),
 
+    #[error("{0}")]
+    FromMessageSearch(#[from] crate::db::message_search::Error),
+
+    #[error("{0}")]
+    FromMutedPubkey(#[from] crate::db::muted_pubkey::Error),
+
+    #[error("{0}")]
+    FromDbNotification(#[from] crate::db::notification::Error),
+
     #[error("{0}")]
     FromProfileCache(#[from] crate::db::profile_cache::Error),
 
@@ -71,9 +101,33 @@ pub enum Error {
     #[error("{0}")]
     FromUserConfig(#[from] crate::db::user_config::Error),
 
+    #[error("{0}")]
+    FromWallet(#[from] crate::db::wallet::Error),
+
+    #[error("{0}")]
+    FromZapReceipt(#[from] crate::db::zap_receipt::Error),
+
     #[error("{0}")]
     FromNtp(#[from] crate::net::ntp::NtpError),
 
+    #[error("{0}")]
+    FromAttachment(#[from] crate::net::attachment::Error),
+
+    #[error("{0}")]
+    FromLinkPreview(#[from] crate::net::link_preview::Error),
+
+    #[error("{0}")]
+    FromNotification(#[from] crate::net::notification::Error),
+
+    #[error("{0}")]
+    FromZap(#[from] crate::net::zap::Error),
+
+    #[error("{0}")]
+    FromMuteList(#[from] crate::net::mute_list::Error),
+
+    #[error("{0}")]
+    FromWalletConnect(#[from] crate::net::wallet_connect::Error),
+
     #[error("App didn't ask for kind: {0:?}")]
     NotSubscribedToKind(nostr::Kind),
 
@@ -94,6 +148,9 @@ pub enum Error {
 
     #[error("Unexpected event kind: {0}")]
     UnexpectedEventKind(u32),
+
+    #[error("Event not found in the database: ID: {0}")]
+    EventNotInDatabase(i64),
 }
 
 #[derive(Error, Debug)]
@@ -121,10 +121,18 @@ pub fn to_cloud_icon() -> Text<'static> {
     solid_icon('\u{F0EE}')
 }
 
+pub fn paperclip_icon() -> Text<'static> {
+    solid_icon('\u{F0C6}')
+}
+
 pub fn download_icon() -> Text<'static> {
     solid_icon('\u{F019}')
 }
 
+pub fn bolt_icon() -> Text<'static> {
+    solid_icon('\u{F0E7}')
+}
+
 pub fn import_icon() -> Text<'static> {
     solid_icon('\u{F56F}')
 }
@@ -153,6 +161,18 @@ pub fn reply_icon() -> Text<'static> {
     solid_icon('\u{F3E5}')
 }
 
+pub fn ban_icon() -> Text<'static> {
+    solid_icon('\u{F05E}')
+}
+
+pub fn hourglass_icon() -> Text<'static> {
+    solid_icon('\u{F252}')
+}
+
+pub fn envelope_icon() -> Text<'static> {
+    solid_icon('\u{F0E0}')
+}
+
 // Fonts
 const SOLID_ICONS: Font = Font::External {
     name: "FA_Solid_Icons",
@@ -0,0 +1,392 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine;
+use directories::ProjectDirs;
+use futures_util::StreamExt;
+use nostr::hashes::{sha256, Hash};
+use nostr::{EventBuilder, Keys, Kind, Tag, TagKind};
+use reqwest::header::AUTHORIZATION;
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use thiserror::Error;
+use url::Url;
+
+use crate::consts::APP_PROJECT_DIRS;
+use crate::types::{Attachment, FileEncryption};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+
+    #[error("Request error: {0}")]
+    FromReqwest(#[from] reqwest::Error),
+
+    #[error("I/O Error: {0}")]
+    FromIo(#[from] std::io::Error),
+
+    #[error("JSON (de)serialization error: {0}")]
+    FromSerdeJson(#[from] serde_json::Error),
+
+    #[error("Nostr Sdk Event Builder Error: {0}")]
+    NostrSdkEventBuilder(#[from] nostr::prelude::builder::Error),
+
+    #[error("File is too big: {0} bytes")]
+    FileTooBig(u64),
+
+    #[error("Upload rejected by the media server: {0}")]
+    UploadRejected(String),
+
+    #[error("Media server response without the file URL")]
+    MissingFileUrl,
+
+    #[error("Downloaded file doesn't match its hash: {0}")]
+    HashMismatch(String),
+
+    #[error("Media server changed the encrypted file")]
+    EncryptedFileChanged,
+
+    #[error("Invalid file key: {0}")]
+    InvalidKey(String),
+
+    #[error("Failed to encrypt the file")]
+    Encryption,
+
+    #[error("Failed to decrypt the file: {0}")]
+    Decryption(String),
+
+    #[error("Not found project directory")]
+    NotFoundProjectDirectory,
+}
+
+/// Document served by NIP-96 media servers at `/.well-known/nostr/nip96.json`
+#[derive(Debug, Deserialize)]
+struct ServerInfo {
+    api_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadResponse {
+    status: String,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    nip94_event: Option<Nip94Event>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Nip94Event {
+    tags: Vec<Vec<String>>,
+}
+impl Nip94Event {
+    fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find_map(|tag| match tag.as_slice() {
+            [tag_name, value, ..] if tag_name == name => Some(value.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// Encrypts the file with a new AES-GCM key and uploads it to the NIP-96
+/// media server at `server_url`, authenticated with a NIP-98 event signed by `keys`.
+///
+/// The server only sees random bytes, the returned attachment has the key
+/// to be sent inside an encrypted direct message.
+pub async fn upload_encrypted_file(
+    client: &reqwest::Client,
+    keys: &Keys,
+    server_url: &Url,
+    path: &Path,
+) -> Result<Attachment, Error> {
+    let bytes = read_file(path).await?;
+    let original_hash = sha256::Hash::hash(&bytes).to_string();
+    let (encrypted, encryption) = encrypt_file(&bytes, original_hash)?;
+    let file_size = encrypted.len() as u64;
+    let file_hash = sha256::Hash::hash(&encrypted).to_string();
+
+    // the original name would leak what the file is
+    let nip94_event = post_file(
+        client,
+        keys,
+        server_url,
+        encrypted,
+        &file_hash,
+        ENCRYPTED_MIME_TYPE,
+        &file_hash,
+    )
+    .await?;
+    let url = nip94_event.tag("url").ok_or(Error::MissingFileUrl)?;
+    if nip94_event
+        .tag("x")
+        .map_or(false, |served_hash| served_hash != file_hash)
+    {
+        return Err(Error::EncryptedFileChanged);
+    }
+
+    Ok(Attachment {
+        url: url.to_owned(),
+        mime_type: Some(mime_type_from_path(path).to_owned()),
+        sha256: Some(file_hash),
+        size: Some(file_size),
+        dim: None,
+        alt: Some(file_name_from_path(path)),
+        encryption: Some(encryption),
+    })
+}
+
+async fn read_file(path: &Path) -> Result<Vec<u8>, Error> {
+    let file_size = tokio::fs::metadata(path).await?.len();
+    if file_size > MAX_UPLOAD_BYTES {
+        return Err(Error::FileTooBig(file_size));
+    }
+    Ok(tokio::fs::read(path).await?)
+}
+
+/// Posts the file to the upload endpoint of the server, returning the
+/// NIP-94 event describing it
+async fn post_file(
+    client: &reqwest::Client,
+    keys: &Keys,
+    server_url: &Url,
+    bytes: Vec<u8>,
+    file_name: &str,
+    mime_type: &str,
+    file_hash: &str,
+) -> Result<Nip94Event, Error> {
+    let file_size = bytes.len();
+    let api_url = fetch_api_url(client, server_url).await?;
+    let auth_header = http_auth_header(keys, &api_url, "POST", file_hash)?;
+
+    let file_part = Part::bytes(bytes)
+        .file_name(file_name.to_owned())
+        .mime_str(mime_type)?;
+    let form = Form::new()
+        .part("file", file_part)
+        .text("size", file_size.to_string())
+        .text("content_type", mime_type.to_owned());
+
+    let response = client
+        .post(api_url)
+        .header(AUTHORIZATION, auth_header)
+        .multipart(form)
+        .timeout(UPLOAD_TIMEOUT)
+        .send()
+        .await?;
+    let status = response.status();
+    let upload: UploadResponse = match response.json().await {
+        Ok(upload) => upload,
+        Err(_) if !status.is_success() => return Err(Error::UploadRejected(status.to_string())),
+        Err(e) => return Err(e.into()),
+    };
+    if !status.is_success() || upload.status != "success" {
+        return Err(Error::UploadRejected(
+            upload.message.unwrap_or_else(|| status.to_string()),
+        ));
+    }
+
+    upload.nip94_event.ok_or(Error::MissingFileUrl)
+}
+
+/// Downloads the attachment to `path`, checking its hash when the message has one.
+///
+/// Encrypted attachments are decrypted before being saved.
+pub async fn download_attachment(
+    client: &reqwest::Client,
+    attachment: &Attachment,
+    path: &Path,
+) -> Result<(), Error> {
+    let response = client
+        .get(Url::parse(&attachment.url)?)
+        .timeout(UPLOAD_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+    let mut bytes = read_attachment_body(response, attachment.size).await?;
+
+    if let Some(expected) = &attachment.sha256 {
+        let actual = sha256::Hash::hash(&bytes).to_string();
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(Error::HashMismatch(attachment.url.to_owned()));
+        }
+    }
+
+    if let Some(encryption) = &attachment.encryption {
+        let decrypted = decrypt_file(&bytes, encryption)?;
+        if let Some(expected) = &encryption.original_sha256 {
+            let actual = sha256::Hash::hash(&decrypted).to_string();
+            if !actual.eq_ignore_ascii_case(expected) {
+                return Err(Error::HashMismatch(attachment.url.to_owned()));
+            }
+        }
+        bytes = decrypted;
+    }
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, &bytes).await?;
+    Ok(())
+}
+
+/// Reads the file up to the `size` of its `imeta` tag, or `MAX_UPLOAD_BYTES`
+/// when the message doesn't have one, and gives up as soon as it goes over
+async fn read_attachment_body(
+    response: reqwest::Response,
+    size: Option<u64>,
+) -> Result<Vec<u8>, Error> {
+    let max_bytes = size.unwrap_or(MAX_UPLOAD_BYTES).min(MAX_UPLOAD_BYTES);
+    if let Some(declared) = response.content_length() {
+        if declared > max_bytes {
+            return Err(Error::FileTooBig(declared));
+        }
+    }
+
+    let mut bytes = vec![];
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        let received = (bytes.len() + chunk.len()) as u64;
+        if received > max_bytes {
+            return Err(Error::FileTooBig(received));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// Where an encrypted attachment is saved once decrypted.
+///
+/// The file name comes from the message, so only its last component is
+/// used, prefixed with the file hash to keep different files apart.
+pub fn attachment_path(attachment: &Attachment) -> Result<PathBuf, Error> {
+    let dirs = ProjectDirs::from(APP_PROJECT_DIRS.0, APP_PROJECT_DIRS.1, APP_PROJECT_DIRS.2)
+        .ok_or(Error::NotFoundProjectDirectory)?;
+    let file_name = Path::new(&attachment.file_name())
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "file".to_owned());
+    let hash = attachment
+        .sha256
+        .to_owned()
+        .unwrap_or_else(|| sha256::Hash::hash(attachment.url.as_bytes()).to_string());
+    let prefix: String = hash
+        .chars()
+        .filter(char::is_ascii_hexdigit)
+        .take(HASH_PREFIX_CHARS)
+        .collect();
+    Ok(dirs
+        .data_dir()
+        .join(ATTACHMENTS_FOLDER_NAME)
+        .join(format!("{}-{}", prefix, file_name)))
+}
+
+fn encrypt_file(bytes: &[u8], original_hash: String) -> Result<(Vec<u8>, FileEncryption), Error> {
+    let key = Aes256Gcm::generate_key(&mut OsRng);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let encrypted = Aes256Gcm::new(&key)
+        .encrypt(&nonce, bytes)
+        .map_err(|_| Error::Encryption)?;
+    let encryption = FileEncryption {
+        key: hex::encode(key),
+        nonce: hex::encode(nonce),
+        original_sha256: Some(original_hash),
+    };
+    Ok((encrypted, encryption))
+}
+
+fn decrypt_file(bytes: &[u8], encryption: &FileEncryption) -> Result<Vec<u8>, Error> {
+    let key = hex::decode(&encryption.key).map_err(|e| Error::InvalidKey(e.to_string()))?;
+    let nonce = hex::decode(&encryption.nonce).map_err(|e| Error::InvalidKey(e.to_string()))?;
+    if nonce.len() != NONCE_BYTES {
+        return Err(Error::InvalidKey(format!("nonce of {} bytes", nonce.len())));
+    }
+    let cipher = Aes256Gcm::new_from_slice(&key)
+        .map_err(|_| Error::InvalidKey(format!("key of {} bytes", key.len())))?;
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), bytes)
+        .map_err(|e| Error::Decryption(e.to_string()))
+}
+
+async fn fetch_api_url(client: &reqwest::Client, server_url: &Url) -> Result<Url, Error> {
+    let info: ServerInfo = client
+        .get(server_url.join(NIP96_WELL_KNOWN)?)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    // relative to the server when it isn't a full URL
+    Ok(server_url.join(&info.api_url)?)
+}
+
+/// NIP-98 `Authorization` header value for a request to `url`
+fn http_auth_header(
+    keys: &Keys,
+    url: &Url,
+    method: &str,
+    payload_hash: &str,
+) -> Result<String, Error> {
+    let tags = [
+        Tag::Generic(TagKind::Custom("u".to_owned()), vec![url.to_string()]),
+        Tag::Generic(
+            TagKind::Custom("method".to_owned()),
+            vec![method.to_owned()],
+        ),
+        Tag::Generic(
+            TagKind::Custom("payload".to_owned()),
+            vec![payload_hash.to_owned()],
+        ),
+    ];
+    let ns_event = EventBuilder::new(Kind::Custom(HTTP_AUTH_KIND), "", &tags).to_event(keys)?;
+    let json = serde_json::to_string(&ns_event)?;
+    Ok(format!(
+        "Nostr {}",
+        base64::engine::general_purpose::STANDARD.encode(json)
+    ))
+}
+
+fn file_name_from_path(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "file".to_owned())
+}
+
+/// The server decides the final type, this is only a hint for the upload
+fn mime_type_from_path(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "json" => "application/json",
+        "txt" | "md" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+const NIP96_WELL_KNOWN: &str = "/.well-known/nostr/nip96.json";
+const HTTP_AUTH_KIND: u64 = 27235;
+/// Most media servers refuse bigger files anyway
+pub const MAX_UPLOAD_BYTES: u64 = 100 * 1024 * 1024;
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+/// Encrypted files are uploaded as plain bytes, the real type is in the message
+const ENCRYPTED_MIME_TYPE: &str = "application/octet-stream";
+const NONCE_BYTES: usize = 12;
+const HASH_PREFIX_CHARS: usize = 12;
+const ATTACHMENTS_FOLDER_NAME: &str = "attachments";
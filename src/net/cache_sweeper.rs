@@ -0,0 +1,54 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use nostr::secp256k1::XOnlyPublicKey;
+
+use crate::config::Config;
+use crate::db::{CacheBudget, CacheEviction, Database, DbContact, SweepStats};
+use crate::Error;
+
+use super::TaskOutput;
+
+/// Periodically evicts unused images and profiles from the cache.
+///
+/// Stops when the backend drops the task receiver, e.g. on logout.
+pub fn spawn_cache_sweeper(
+    sender: tokio::sync::mpsc::Sender<Result<TaskOutput, Error>>,
+    database: Database,
+    public_key: XOnlyPublicKey,
+) {
+    tokio::spawn(async move {
+        // let the login requests fill the cache before the first sweep
+        tokio::time::sleep(FIRST_SWEEP_DELAY).await;
+        loop {
+            tracing::debug!("Starting cache sweep");
+            let result = sweep(&database, &public_key)
+                .await
+                .map(TaskOutput::CacheSwept);
+            if sender.send(result).await.is_err() {
+                tracing::debug!("Cache sweeper stopped");
+                return;
+            }
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+        }
+    });
+}
+
+async fn sweep(database: &Database, public_key: &XOnlyPublicKey) -> Result<SweepStats, Error> {
+    let config = Config::load_file_async().await?;
+    let budget = CacheBudget::from_megabytes(config.cache_budget_mb);
+
+    // contacts and the user are always shown, keep their avatars
+    let mut protected: HashSet<XOnlyPublicKey> = DbContact::fetch_basic(&database.pool)
+        .await?
+        .iter()
+        .map(|contact| contact.pubkey().to_owned())
+        .collect();
+    protected.insert(public_key.to_owned());
+
+    let stats = CacheEviction::sweep(&database.cache_pool, &protected, &budget).await?;
+    Ok(stats)
+}
+
+const FIRST_SWEEP_DELAY: Duration = Duration::from_secs(60);
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30 * 60);
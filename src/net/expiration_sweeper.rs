@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+use nostr::Timestamp;
+
+use crate::db::{Database, DbEvent};
+use crate::Error;
+
+use super::TaskOutput;
+
+/// Periodically deletes events past their NIP-40 expiration, along with
+/// their messages.
+///
+/// Stops when the backend drops the task receiver, e.g. on logout.
+pub fn spawn_expiration_sweeper(
+    sender: tokio::sync::mpsc::Sender<Result<TaskOutput, Error>>,
+    database: Database,
+) {
+    tokio::spawn(async move {
+        loop {
+            let result = DbEvent::delete_expired(&database.pool, Timestamp::now())
+                .await
+                .map(TaskOutput::EventsExpired)
+                .map_err(Error::from);
+            if sender.send(result).await.is_err() {
+                tracing::debug!("Expiration sweeper stopped");
+                return;
+            }
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+        }
+    });
+}
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
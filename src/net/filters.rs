@@ -1,6 +1,10 @@
 use nostr::{secp256k1::XOnlyPublicKey, Filter, Kind, Timestamp};
 
 use crate::db::{DbContact, DbEvent};
+use crate::net::mute_list::MUTE_LIST_KIND;
+use crate::net::wallet_connect::{WalletConnectUri, NWC_RESPONSE_KIND};
+use crate::net::zap::ZAP_RECEIPT_KIND;
+use crate::types::ChannelSearchTerm;
 
 fn to_secs(last_event: &Option<DbEvent>) -> u64 {
     last_event
@@ -62,6 +66,13 @@ pub fn contact_list_filter(public_key: XOnlyPublicKey, last_event: &Option<DbEve
         .since(Timestamp::from(to_secs(last_event)))
 }
 
+pub fn mute_list_filter(public_key: XOnlyPublicKey, last_event: &Option<DbEvent>) -> Filter {
+    Filter::new()
+        .author(public_key.to_string())
+        .kind(Kind::from(MUTE_LIST_KIND))
+        .since(Timestamp::from(to_secs(last_event)))
+}
+
 pub fn messages_filter(public_key: XOnlyPublicKey, last_event: &Option<DbEvent>) -> Vec<Filter> {
     let sent_msgs = Filter::new()
         .kind(nostr::Kind::EncryptedDirectMessage)
@@ -75,18 +86,39 @@ pub fn messages_filter(public_key: XOnlyPublicKey, last_event: &Option<DbEvent>)
     vec![sent_msgs, recv_msgs]
 }
 
-pub fn channel_search_filter(channel_id: &str) -> Filter {
-    // .search(search_term)
-    // .hashtag(search_term)
-    let mut channel_filter = Filter::new()
-        .kind(Kind::ChannelCreation)
-        .limit(CHANNEL_SEARCH_LIMIT);
-
-    if !channel_id.is_empty() {
-        channel_filter = channel_filter.id(channel_id);
+/// Filter for relays that support [NIP-50](https://github.com/nostr-protocol/nips/blob/master/50.md) search
+pub fn channel_search_filter(term: &ChannelSearchTerm) -> Filter {
+    let channel_filter = Filter::new().limit(CHANNEL_SEARCH_LIMIT);
+    match term {
+        ChannelSearchTerm::Id(channel_id) => channel_filter
+            .kind(Kind::ChannelCreation)
+            .id(channel_id.to_hex()),
+        ChannelSearchTerm::Hashtag(tag) => channel_filter
+            .kinds(vec![Kind::ChannelCreation, Kind::ChannelMetadata])
+            .hashtag(tag),
+        ChannelSearchTerm::Text(_) => {
+            let channel_filter =
+                channel_filter.kinds(vec![Kind::ChannelCreation, Kind::ChannelMetadata]);
+            match term.search_text() {
+                Some(text) => channel_filter.search(text),
+                None => channel_filter,
+            }
+        }
     }
+}
 
-    channel_filter
+/// Recent channels and their metadata, searched locally on relays without NIP-50
+pub fn recent_channels_filters() -> Vec<Filter> {
+    vec![
+        Filter::new()
+            .kind(Kind::ChannelCreation)
+            .until(Timestamp::now())
+            .limit(RECENT_CHANNELS_LIMIT),
+        Filter::new()
+            .kind(Kind::ChannelMetadata)
+            .until(Timestamp::now())
+            .limit(RECENT_CHANNELS_LIMIT),
+    ]
 }
 
 pub fn search_channel_details_filter(channel_id: &nostr::EventId) -> Vec<Filter> {
@@ -128,5 +160,62 @@ pub fn channel_details_filter(
     ]
 }
 
+/// Receipts of the zaps to the user, and of the zaps to `zapped_events`
+/// whoever was zapped
+pub fn zap_receipts_filter(
+    public_key: XOnlyPublicKey,
+    zapped_events: &[nostr::EventId],
+    last_event: &Option<DbEvent>,
+) -> Vec<Filter> {
+    let receipt_kind = Kind::from(ZAP_RECEIPT_KIND);
+    let mut filters = vec![Filter::new()
+        .kind(receipt_kind)
+        .pubkey(public_key)
+        .since(Timestamp::from(to_secs(last_event)))];
+    if !zapped_events.is_empty() {
+        filters.push(
+            Filter::new()
+                .kind(receipt_kind)
+                .events(zapped_events.to_vec()),
+        );
+    }
+    filters
+}
+
+/// Contact lists that follow the user. Lists of the current followers are
+/// also wanted since a list that drops the user doesn't have the `p` tag.
+pub fn followers_filter(
+    public_key: XOnlyPublicKey,
+    followers: &[XOnlyPublicKey],
+    last_event: &Option<DbEvent>,
+) -> Vec<Filter> {
+    let since = Timestamp::from(to_secs(last_event));
+    let mut filters = vec![Filter::new()
+        .kind(Kind::ContactList)
+        .pubkey(public_key)
+        .since(since)];
+    if !followers.is_empty() {
+        filters.push(
+            Filter::new()
+                .kind(Kind::ContactList)
+                .authors(followers.iter().map(|f| f.to_string()).collect())
+                .since(since),
+        );
+    }
+    filters
+}
+
+/// Answers of the wallet to the requests signed with the connection secret
+pub fn wallet_responses_filter(uri: &WalletConnectUri) -> Filter {
+    // answers to requests sent just before a restart are still wanted
+    let since = chrono::Utc::now() - chrono::Duration::minutes(10);
+    Filter::new()
+        .kind(Kind::from(NWC_RESPONSE_KIND))
+        .author(uri.wallet_pubkey.to_string())
+        .pubkey(uri.keys().public_key())
+        .since(Timestamp::from(since.timestamp() as u64))
+}
+
 const CHANNEL_SEARCH_LIMIT: usize = 10;
+const RECENT_CHANNELS_LIMIT: usize = 500;
 const CHANNEL_DETAILS_LIMIT: usize = 1000;
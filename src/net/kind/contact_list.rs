@@ -1,10 +1,10 @@
 use crate::{
-    db::{DbContact, DbRelayResponse},
+    db::{DbContact, DbFollower, DbRelayResponse},
     error::Error,
-    utils::ns_event_to_millis,
+    utils::{ns_event_to_millis, ns_event_to_naive},
 };
 use futures_util::SinkExt;
-use nostr::{Keys, Kind};
+use nostr::{Keys, Kind, Tag};
 use sqlx::SqlitePool;
 use url::Url;
 
@@ -31,7 +31,7 @@ pub async fn received_contact_list(
                 // delete old and insert new contact list
                 tracing::info!("ContactList is newer than the last one");
                 DbEvent::delete(pool, db_event.event_id).await?;
-                DbContact::delete_all(pool).await?;
+                DbContact::delete_listed(pool).await?;
             }
         }
         None => {
@@ -44,21 +44,6 @@ pub async fn received_contact_list(
 }
 
 pub async fn handle_contact_list(
-    output: &mut futures::channel::mpsc::Sender<BackendEvent>,
-    keys: &Keys,
-    pool: &SqlitePool,
-    url: &Url,
-    db_event: DbEvent,
-) -> Result<(), Error> {
-    if db_event.pubkey == keys.public_key() {
-        handle_user_contact_list(output, keys, pool, url, db_event).await?;
-    } else {
-        handle_other_contact_list(db_event).await?;
-    }
-    Ok(())
-}
-
-async fn handle_user_contact_list(
     output: &mut futures::channel::mpsc::Sender<BackendEvent>,
     keys: &Keys,
     pool: &SqlitePool,
@@ -86,10 +71,29 @@ async fn handle_user_contact_list(
     Ok(())
 }
 
-async fn handle_other_contact_list(_db_event: DbEvent) -> Result<(), Error> {
-    // Others ContactList That Im in
-    // which means that someone else added me to their contact list
-    // so I could build a followers list from this
-    tracing::info!("*** Others ContactList That Im in ***");
+/// Contact list of someone else, that follows the user or dropped them.
+/// Only the follow state is kept, the list itself isn't stored.
+pub async fn handle_other_contact_list(
+    output: &mut futures::channel::mpsc::Sender<BackendEvent>,
+    keys: &Keys,
+    pool: &SqlitePool,
+    ns_event: &nostr::Event,
+) -> Result<(), Error> {
+    let follows = ns_event
+        .tags
+        .iter()
+        .any(|tag| matches!(tag, Tag::PubKey(pubkey, _) if pubkey == &keys.public_key()));
+    let list_created_at = ns_event_to_naive(ns_event.created_at)?;
+
+    if let Some(follower) =
+        DbFollower::update_from_list(pool, &ns_event.pubkey, list_created_at, follows).await?
+    {
+        tracing::info!(
+            "{} {} the user",
+            follower.pubkey,
+            if follows { "followed" } else { "unfollowed" }
+        );
+        let _ = output.send(BackendEvent::FollowerUpdated(follower)).await;
+    }
     Ok(())
 }
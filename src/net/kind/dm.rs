@@ -1,8 +1,12 @@
-use crate::db::{DbContact, DbEvent, DbMessage, MessageTagInfo};
+use crate::db::{
+    DbContact, DbEvent, DbMessage, DbMutedPubkey, MessageSearch, MessageTagInfo, NotificationChat,
+};
 use crate::error::Error;
-use crate::net::BackendEvent;
+use crate::net::{BackendEvent, Notification, NotificationEngine};
 use crate::types::ChatMessage;
 
+use super::{below_pow_threshold, insert_filtered};
+
 use futures_util::SinkExt;
 use nostr::secp256k1::XOnlyPublicKey;
 use nostr::{EventId, Keys};
@@ -14,6 +18,7 @@ pub async fn handle_dm(
     pool: &SqlitePool,
     cache_pool: &SqlitePool,
     keys: &Keys,
+    notifications: &NotificationEngine,
     url: &Url,
     ns_event: nostr::Event,
 ) -> Result<(), Error> {
@@ -21,12 +26,38 @@ pub async fn handle_dm(
         verify_dm(&ns_event.id, &ns_event.pubkey, &ns_event.tags, keys)? else {
         return Ok(());
     };
+    if !is_users && DbMutedPubkey::is_muted(pool, &chat_pubkey).await? {
+        tracing::debug!("Dropping DM from muted {}", chat_pubkey);
+        return Ok(());
+    }
+    if let Some(difficulty) = below_pow_threshold(pool, keys, &ns_event).await? {
+        return insert_filtered(output, pool, keys, url, &ns_event, None, difficulty).await;
+    }
 
     if let Some(db_event) = DbEvent::insert(pool, url, &ns_event).await? {
         let db_message =
-            DbMessage::insert_confirmed(pool, &db_event, &chat_pubkey, is_users).await?;
-        let db_contact = DbContact::fetch_insert(pool, cache_pool, &db_message.chat_pubkey).await?;
+            DbMessage::insert_confirmed(pool, keys, &db_event, &chat_pubkey, is_users).await?;
+        let mut db_contact =
+            DbContact::fetch_insert(pool, cache_pool, &db_message.chat_pubkey).await?;
+        if is_users && !db_contact.is_known() {
+            // chats the user answered, maybe from another client, aren't requests
+            DbContact::accept(pool, &chat_pubkey, false).await?;
+            db_contact = DbContact::fetch_insert(pool, cache_pool, &chat_pubkey).await?;
+        }
         let decrypted_content = db_message.decrypt_message(keys, &tag_info)?;
+        MessageSearch::insert_dm(pool, keys, &db_message, &decrypted_content).await?;
+
+        // message requests stay quiet, strangers can't spam notifications
+        if !is_users && db_contact.is_known() {
+            let notification = Notification {
+                chat: NotificationChat::Dm(chat_pubkey),
+                title: db_contact.select_name(),
+                body: decrypted_content.to_owned(),
+            };
+            notifications
+                .message_received(pool, keys, &ns_event, notification)
+                .await?;
+        }
 
         let chat_message = if is_users {
             ChatMessage::confirmed_users(&db_message, &decrypted_content)
@@ -57,8 +88,10 @@ pub async fn pending_dm_confirmed(
         return Ok(());
     };
 
-    let db_message = DbMessage::insert_confirmed(pool, db_event, &chat_pubkey, is_users).await?;
+    let db_message =
+        DbMessage::insert_confirmed(pool, keys, db_event, &chat_pubkey, is_users).await?;
     let decrypted_content = db_message.decrypt_message(keys, &tag_info)?;
+    MessageSearch::insert_dm(pool, keys, &db_message, &decrypted_content).await?;
 
     let _ = output
         .send(BackendEvent::ConfirmedDM(
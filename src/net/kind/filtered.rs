@@ -0,0 +1,76 @@
+use crate::db::{DbContact, DbEvent, DbFilteredMessage, UserConfig};
+use crate::error::Error;
+use crate::net::{event_difficulty, BackendEvent};
+use crate::types::Attachment;
+
+use futures_util::SinkExt;
+use nostr::nips::nip04;
+use nostr::{EventId, Keys};
+use sqlx::SqlitePool;
+use url::Url;
+
+/// Difficulty of a message from a non-contact that is below the user's
+/// threshold, None when the message goes through
+pub async fn below_pow_threshold(
+    pool: &SqlitePool,
+    keys: &Keys,
+    ns_event: &nostr::Event,
+) -> Result<Option<u8>, Error> {
+    let threshold = UserConfig::get_incoming_pow_threshold(pool).await?;
+    if threshold == 0 || ns_event.pubkey == keys.public_key() {
+        return Ok(None);
+    }
+    let difficulty = event_difficulty(&ns_event.id);
+    if difficulty >= threshold || DbContact::is_known_pubkey(pool, &ns_event.pubkey).await? {
+        return Ok(None);
+    }
+    Ok(Some(difficulty))
+}
+
+/// Keeps the message out of the chats, in the filtered messages
+pub async fn insert_filtered(
+    output: &mut futures::channel::mpsc::Sender<BackendEvent>,
+    pool: &SqlitePool,
+    keys: &Keys,
+    url: &Url,
+    ns_event: &nostr::Event,
+    channel_id: Option<&EventId>,
+    difficulty: u8,
+) -> Result<(), Error> {
+    tracing::debug!(
+        "Filtering message {} of difficulty {}",
+        ns_event.id,
+        difficulty
+    );
+    if let Some(db_event) = DbEvent::insert(pool, url, ns_event).await? {
+        let filtered = DbFilteredMessage::insert(pool, &db_event, channel_id, difficulty).await?;
+        _ = output
+            .send(BackendEvent::MessageFiltered(decrypt_filtered(
+                keys, filtered,
+            )))
+            .await;
+    }
+    Ok(())
+}
+
+/// Direct messages are shown decrypted, without their attachments
+pub fn decrypt_filtered(keys: &Keys, mut message: DbFilteredMessage) -> DbFilteredMessage {
+    if message.channel_id.is_some() {
+        return message;
+    }
+    let payload = keys
+        .secret_key()
+        .map_err(|e| e.to_string())
+        .and_then(|secret_key| {
+            nip04::decrypt(&secret_key, &message.pubkey, &message.content)
+                .map_err(|e| e.to_string())
+        });
+    message.content = match payload {
+        Ok(payload) => Attachment::split_dm_payload(&payload).0.to_owned(),
+        Err(e) => {
+            tracing::debug!("Filtered message not decrypted: {}", e);
+            "Could not decrypt the message".to_owned()
+        }
+    };
+    message
+}
@@ -1,4 +1,12 @@
 mod contact_list;
 mod dm;
+mod filtered;
+mod mute_list;
+mod wallet_connect;
+mod zap;
 pub use contact_list::*;
 pub use dm::*;
+pub use filtered::*;
+pub use mute_list::*;
+pub use wallet_connect::*;
+pub use zap::*;
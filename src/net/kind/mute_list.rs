@@ -0,0 +1,55 @@
+use crate::db::{DbEvent, DbMutedPubkey, DbRelayResponse};
+use crate::error::Error;
+use crate::net::{BackendEvent, MuteList, MUTE_LIST_KIND};
+use crate::utils::ns_event_to_millis;
+
+use futures_util::SinkExt;
+use nostr::{Keys, Kind};
+use sqlx::SqlitePool;
+use url::Url;
+
+/// User's mute list from the relays, replaces the local one when newer
+pub async fn handle_mute_list(
+    output: &mut futures::channel::mpsc::Sender<BackendEvent>,
+    keys: &Keys,
+    pool: &SqlitePool,
+    url: &Url,
+    ns_event: nostr::Event,
+) -> Result<(), Error> {
+    if ns_event.pubkey != keys.public_key() {
+        tracing::info!("Ignoring mute list of {}", ns_event.pubkey);
+        return Ok(());
+    }
+
+    let last_event = DbEvent::fetch_last_kind(pool, Kind::from(MUTE_LIST_KIND)).await?;
+    if let Some(db_event) = &last_event {
+        if db_event.event_hash == ns_event.id {
+            DbRelayResponse::insert_ok(pool, url, db_event).await?;
+            return Ok(());
+        }
+        if db_event.created_at.timestamp_millis() > ns_event_to_millis(ns_event.created_at) {
+            tracing::info!("MuteList is older than the last one");
+            return Ok(());
+        }
+    }
+
+    // a list that can't be read leaves the current one in place
+    let mute_list = MuteList::from_event(keys, &ns_event)?;
+
+    let mut tx = pool.begin().await?;
+    if let Some(db_event) = &last_event {
+        DbEvent::delete_with(&mut tx, db_event.event_id).await?;
+    }
+    let event_id = DbEvent::insert_with(&mut tx, url, &ns_event).await?;
+    DbMutedPubkey::replace_all_with(&mut tx, &mute_list.muted).await?;
+    tx.commit().await?;
+
+    if let Some(db_event) = DbEvent::fetch_id(pool, event_id).await? {
+        DbRelayResponse::insert_ok(pool, url, &db_event).await?;
+    }
+
+    let muted = DbMutedPubkey::fetch(pool).await?;
+    let _ = output.send(BackendEvent::GotMuteList(muted)).await;
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use crate::db::{DbWalletPayment, PaymentStatus, WalletConnect};
+use crate::error::Error;
+use crate::net::wallet_connect::{WalletResponse, WalletResult};
+use crate::net::BackendEvent;
+
+use futures_util::SinkExt;
+use sqlx::SqlitePool;
+
+/// Updates the payment or the balance with the answer of the connected wallet
+pub async fn handle_wallet_response(
+    output: &mut futures::channel::mpsc::Sender<BackendEvent>,
+    pool: &SqlitePool,
+    ns_event: nostr::Event,
+) -> Result<(), Error> {
+    let Some(uri) = WalletConnect::fetch(pool).await? else {
+        tracing::info!("Wallet response without a connected wallet");
+        return Ok(());
+    };
+    let response = match WalletResponse::from_event(&uri, &ns_event) {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::info!("Ignoring wallet response: {}", e);
+            return Ok(());
+        }
+    };
+
+    let status = match response.result {
+        Ok(WalletResult::GetBalance { balance_msats }) => {
+            _ = output
+                .send(BackendEvent::GotWalletBalance(balance_msats))
+                .await;
+            return Ok(());
+        }
+        Ok(WalletResult::PayInvoice { preimage }) => PaymentStatus::Paid { preimage },
+        Err(error) => {
+            if DbWalletPayment::fetch_request(pool, &response.request_hash)
+                .await?
+                .is_none()
+            {
+                _ = output.send(BackendEvent::WalletError(error)).await;
+                return Ok(());
+            }
+            PaymentStatus::Failed { error }
+        }
+    };
+
+    if let Some(payment) = DbWalletPayment::resolve(pool, &response.request_hash, status).await? {
+        _ = output
+            .send(BackendEvent::WalletPaymentUpdated(payment))
+            .await;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use crate::db::{DbZapReceipt, ZapProvider};
+use crate::error::Error;
+use crate::net::BackendEvent;
+
+use futures_util::SinkExt;
+use sqlx::SqlitePool;
+
+/// Stores the receipt and sends the new total of the zapped message
+pub async fn handle_zap_receipt(
+    output: &mut futures::channel::mpsc::Sender<BackendEvent>,
+    pool: &SqlitePool,
+    ns_event: nostr::Event,
+) -> Result<(), Error> {
+    // anyone can publish a receipt, the invalid ones are just ignored
+    let recipient = match DbZapReceipt::recipient_of(&ns_event) {
+        Ok(recipient) => recipient,
+        Err(e) => {
+            tracing::info!("Ignoring zap receipt: {}", e);
+            return Ok(());
+        }
+    };
+    // known once the recipient's LNURL server was resolved, the receipts are
+    // fetched again then
+    let Some(provider) = ZapProvider::fetch(pool, &recipient).await? else {
+        tracing::info!(
+            "Ignoring zap receipt of unknown LNURL server: {}",
+            ns_event.id
+        );
+        return Ok(());
+    };
+    let receipt = match DbZapReceipt::from_event(&ns_event, &provider.nostr_pubkey) {
+        Ok(receipt) => receipt,
+        Err(e) => {
+            tracing::info!("Ignoring zap receipt: {}", e);
+            return Ok(());
+        }
+    };
+
+    if !DbZapReceipt::insert(pool, &receipt).await? {
+        return Ok(());
+    }
+
+    if let Some(event_hash) = &receipt.zapped_event_hash {
+        if let Some((event_id, total_msats)) =
+            DbZapReceipt::fetch_event_total(pool, event_hash).await?
+        {
+            let _ = output
+                .send(BackendEvent::ZapReceived {
+                    event_id,
+                    total_msats,
+                })
+                .await;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use sqlx::SqlitePool;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use url::Url;
+
+use crate::db::LinkPreview;
+use crate::utils::add_ellipsis_trunc;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid URL: \"{0}\"")]
+    InvalidUrl(#[from] url::ParseError),
+
+    #[error("Request error: {0}")]
+    FromReqwest(#[from] reqwest::Error),
+
+    #[error("Not an HTML page: {0}")]
+    NotHtml(String),
+
+    #[error("{0}")]
+    FromLinkPreview(#[from] crate::db::link_preview::Error),
+}
+
+/// Spaces out the requests made to the sites linked in messages
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+}
+impl RateLimiter {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits until the next request is allowed
+    pub async fn wait(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(LINK_PREVIEW_INTERVAL)
+    }
+}
+
+/// Returns the preview of `link_url`, only requesting the page when the
+/// cached preview expired.
+///
+/// Pages that fail to load are cached as empty previews, so they aren't
+/// requested again before expiring.
+pub async fn fetch_link_preview(
+    client: &reqwest::Client,
+    cache_pool: &SqlitePool,
+    limiter: &RateLimiter,
+    link_url: &str,
+) -> Result<LinkPreview, Error> {
+    if let Some(preview) = LinkPreview::fetch(cache_pool, link_url).await? {
+        if preview.is_fresh(Utc::now().naive_utc()) {
+            return Ok(preview);
+        }
+    }
+
+    limiter.wait().await;
+    let metadata = match fetch_page(client, link_url).await {
+        Ok(html) => parse_metadata(&html, link_url),
+        Err(e) => {
+            tracing::info!("Failed to fetch link preview {}: {}", link_url, e);
+            PageMetadata::default()
+        }
+    };
+
+    let preview = LinkPreview {
+        url: link_url.to_owned(),
+        title: metadata.title,
+        description: metadata.description,
+        image_url: metadata.image_url,
+        fetched_at: Utc::now().naive_utc(),
+    };
+    LinkPreview::upsert(cache_pool, &preview).await?;
+
+    Ok(preview)
+}
+
+/// Reads up to `MAX_PAGE_BYTES` of the page, the metadata is in the head anyway
+async fn fetch_page(client: &reqwest::Client, link_url: &str) -> Result<String, Error> {
+    let mut response = client
+        .get(Url::parse(link_url)?)
+        .header(ACCEPT, "text/html")
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase();
+    if !(content_type.starts_with("text/html") || content_type.starts_with("application/xhtml")) {
+        return Err(Error::NotHtml(content_type));
+    }
+
+    let mut body = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        let remaining = MAX_PAGE_BYTES - body.len();
+        body.extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+        if body.len() >= MAX_PAGE_BYTES {
+            break;
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct PageMetadata {
+    title: Option<String>,
+    description: Option<String>,
+    image_url: Option<String>,
+}
+
+/// Reads the OpenGraph and Twitter card tags, falling back to the page title
+fn parse_metadata(html: &str, page_url: &str) -> PageMetadata {
+    let mut meta: HashMap<String, String> = HashMap::new();
+    for tag in META_TAG.find_iter(html) {
+        let mut key = None;
+        let mut content = None;
+        for attribute in ATTRIBUTE.captures_iter(tag.as_str()) {
+            let value = attribute
+                .get(2)
+                .or_else(|| attribute.get(3))
+                .map_or("", |value| value.as_str());
+            match attribute[1].to_lowercase().as_str() {
+                "property" | "name" => key = Some(value.to_lowercase()),
+                "content" => content = Some(decode_entities(value)),
+                _ => (),
+            }
+        }
+        if let (Some(key), Some(content)) = (key, content) {
+            meta.entry(key).or_insert(content);
+        }
+    }
+
+    let first_of = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| meta.get(*key))
+            .map(|value| value.trim().to_owned())
+            .filter(|value| !value.is_empty())
+    };
+
+    let title = first_of(&["og:title", "twitter:title"])
+        .or_else(|| {
+            TITLE_TAG
+                .captures(html)
+                .map(|captures| decode_entities(captures[1].trim()))
+        })
+        .filter(|title| !title.is_empty());
+    let description = first_of(&["og:description", "twitter:description", "description"]);
+    let image_url = first_of(&["og:image", "og:image:url", "twitter:image"])
+        .and_then(|image| Url::parse(page_url).ok()?.join(&image).ok())
+        .filter(|url| url.scheme() == "https" || url.scheme() == "http")
+        .map(|url| url.to_string());
+
+    PageMetadata {
+        title: title.map(|title| add_ellipsis_trunc(&title, MAX_TITLE_CHARS)),
+        description: description
+            .map(|description| add_ellipsis_trunc(&description, MAX_DESCRIPTION_CHARS)),
+        image_url,
+    }
+}
+
+fn decode_entities(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+static META_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<meta\b[^>]*>").unwrap());
+static ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?is)([a-z:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap());
+static TITLE_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+
+/// Pages are cut after this size
+pub const MAX_PAGE_BYTES: usize = 256 * 1024;
+const MAX_TITLE_CHARS: usize = 120;
+const MAX_DESCRIPTION_CHARS: usize = 240;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// At most two pages per second
+const LINK_PREVIEW_INTERVAL: Duration = Duration::from_millis(500);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_open_graph() {
+        let html = r#"<html><head>
+            <title>Fallback</title>
+            <meta property="og:title" content="Nostr &amp; friends" />
+            <meta name='twitter:description' content='A protocol'>
+            <meta property="og:image" content="/cover.png">
+        </head></html>"#;
+
+        let metadata = parse_metadata(html, "https://example.com/post/1");
+
+        assert_eq!(metadata.title.as_deref(), Some("Nostr & friends"));
+        assert_eq!(metadata.description.as_deref(), Some("A protocol"));
+        assert_eq!(
+            metadata.image_url.as_deref(),
+            Some("https://example.com/cover.png")
+        );
+    }
+
+    #[test]
+    fn test_parse_title_fallback() {
+        let html = "<html><head><title> Plain page </title></head></html>";
+
+        let metadata = parse_metadata(html, "https://example.com");
+
+        assert_eq!(metadata.title.as_deref(), Some("Plain page"));
+        assert_eq!(metadata.description, None);
+        assert_eq!(metadata.image_url, None);
+    }
+
+    #[test]
+    fn test_parse_no_metadata() {
+        let metadata = parse_metadata("just text", "https://example.com");
+        assert_eq!(metadata, PageMetadata::default());
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use futures_util::SinkExt;
 use iced::subscription;
 use nostr::Metadata;
@@ -6,6 +6,7 @@ use ns_client::Subscription;
 use rfd::AsyncFileDialog;
 use serde::Serialize;
 use sqlx::SqlitePool;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::time::Duration;
 use tokio::signal;
@@ -19,6 +20,7 @@ use nostr::Keys;
 use nostr::Kind;
 use nostr::RelayMessage;
 use nostr::SubscriptionId;
+use nostr::Timestamp;
 
 use ns_client::NotificationEvent;
 use ns_client::RelayEvent;
@@ -28,54 +30,127 @@ use crate::components::async_file_importer::FileFilter;
 use crate::components::chat_contact::ChatInfo;
 use crate::config::Config;
 use crate::consts::NIPS_LIST_MARKDOWN;
+use crate::db::channel_cache;
+use crate::db::ChannelActivity;
 use crate::db::ChannelCache;
+use crate::db::ChannelMemberStats;
+use crate::db::ChannelPreviewMessage;
 use crate::db::ChannelSubscription;
+use crate::db::ChatNotification;
 use crate::db::Database;
 use crate::db::DbChannelMessage;
 use crate::db::DbContact;
+use crate::db::DbDraft;
 use crate::db::DbEvent;
+use crate::db::DbFilteredMessage;
+use crate::db::DbFollower;
 use crate::db::DbMessage;
+use crate::db::DbMutedPubkey;
 use crate::db::DbRelay;
 use crate::db::DbRelayResponse;
+use crate::db::DbWalletPayment;
+use crate::db::DoNotDisturb;
+use crate::db::ExpirationTimer;
 use crate::db::ImageDownloaded;
+use crate::db::LinkPreview;
+use crate::db::MessageSearch;
+use crate::db::MessageSearchQuery;
+use crate::db::MessageSearchResult;
 use crate::db::MessageTagInfo;
+use crate::db::NotificationChat;
+use crate::db::PaymentStatus;
 use crate::db::ProfileCache;
+use crate::db::SweepStats;
 use crate::db::UserConfig;
+use crate::db::WalletConnect;
+use crate::db::CHANNEL_PREVIEW_LIMIT;
+use crate::db::{DbZapReceipt, ZapProvider};
 use crate::error::BackendClosed;
+use crate::net::cache_sweeper::spawn_cache_sweeper;
+use crate::net::expiration_sweeper::spawn_expiration_sweeper;
 use crate::net::filters::channel_details_filter;
 use crate::net::filters::channel_members_metadata_filter;
 use crate::net::filters::channel_search_filter;
 use crate::net::filters::contact_list_filter;
+use crate::net::filters::followers_filter;
 use crate::net::filters::members_metadata_filter;
 use crate::net::filters::messages_filter;
+use crate::net::filters::mute_list_filter;
+use crate::net::filters::recent_channels_filters;
 use crate::net::filters::user_metadata_filter;
+use crate::net::filters::wallet_responses_filter;
+use crate::net::filters::zap_receipts_filter;
+use crate::net::kind::below_pow_threshold;
+use crate::net::kind::decrypt_filtered;
 use crate::net::kind::handle_contact_list;
 use crate::net::kind::handle_dm;
+use crate::net::kind::handle_mute_list;
+use crate::net::kind::handle_other_contact_list;
+use crate::net::kind::handle_wallet_response;
+use crate::net::kind::handle_zap_receipt;
+use crate::net::kind::insert_filtered;
 use crate::net::kind::received_contact_list;
 use crate::net::ntp::spawn_ntp_request;
 use crate::net::reqwest_client::fetch_latest_version;
 use crate::style;
+use crate::types::Attachment;
 use crate::types::BackendState;
+use crate::types::ChannelSearchTerm;
 use crate::types::ChatMessage;
 use crate::types::PendingEvent;
 use crate::types::PrefixedId;
 use crate::types::SubName;
+use crate::types::ZapTarget;
 use crate::utils::channel_id_from_tags;
+use crate::utils::event_expiration;
+use crate::utils::event_hashtags;
+use crate::utils::invoice_amount_msats;
 use crate::utils::parse_nips_markdown;
 use crate::utils::NipData;
 use crate::views::login::BasicProfile;
 use crate::Error;
 
+pub(crate) mod attachment;
+pub(crate) mod cache_sweeper;
+pub(crate) mod expiration_sweeper;
 mod filters;
 pub mod kind;
+pub(crate) mod link_preview;
+pub(crate) mod mute_list;
+pub(crate) mod nip05;
+pub(crate) mod notification;
 pub(crate) mod ntp;
+pub(crate) mod pow;
 pub(crate) mod reqwest_client;
+pub(crate) mod wallet_connect;
+pub(crate) mod zap;
 
+use self::attachment::attachment_path;
 use self::filters::contact_list_metadata_filter;
 use self::filters::search_channel_details_filter;
 use self::kind::pending_dm_confirmed;
-use self::reqwest_client::download_image;
-pub(crate) use reqwest_client::{image_filename, ImageKind, ImageSize};
+use self::reqwest_client::blobs_dir;
+pub use attachment::{download_attachment, upload_encrypted_file, MAX_UPLOAD_BYTES};
+pub use link_preview::{fetch_link_preview, RateLimiter, MAX_PAGE_BYTES};
+pub use mute_list::{MuteList, MUTE_LIST_KIND};
+pub use nip05::verify_nip05;
+pub use notification::{DbusSink, MemorySink, Notification, NotificationEngine, NotificationSink};
+pub use pow::{
+    event_difficulty, leading_zero_bits, mine, raised_difficulty, MiningProgress,
+    MAX_POW_DIFFICULTY,
+};
+pub use reqwest_client::{
+    blob_filename, download_image, image_filename, media_event_hash, ImageKind, ImageSize,
+    MAX_IMAGE_BYTES,
+};
+pub use wallet_connect::{
+    WalletConnectUri, WalletRequest, WalletResponse, WalletResult, NWC_REQUEST_KIND,
+    NWC_RESPONSE_KIND,
+};
+pub use zap::{
+    fetch_zap_invoice, fetch_zap_signer, lnurl_pay_url, ZapInvoice, ZapRequest, ZAP_RECEIPT_KIND,
+    ZAP_REQUEST_KIND,
+};
 
 #[derive(Debug, Clone)]
 pub struct BackEndConnection {
@@ -339,23 +414,49 @@ pub async fn handle_event(
 ) -> Result<(), Error> {
     tracing::trace!("Event {} - {} - {:?}", &url, &subscription_id, &ns_event);
 
+    if let Some(expires_at) = event_expiration(&ns_event.tags) {
+        if expires_at <= Timestamp::now() {
+            tracing::debug!("Skipping expired event {}", ns_event.id);
+            return Ok(());
+        }
+    }
+
     if let Some(sub_type) = SubName::from_id(&subscription_id) {
         match sub_type {
             SubName::SearchChannels => {
-                if let Kind::ChannelCreation = ns_event.kind {
-                    let cache = ChannelCache::fetch_insert(backend.cache_pool(), &ns_event).await?;
-                    // let result = ChannelResult::from_ns_event(url.to_owned(), ns_event, cache);
+                let cache_pool = backend.cache_pool();
+                let cache = match ns_event.kind {
+                    Kind::ChannelCreation => {
+                        ChannelCache::fetch_insert(cache_pool, &ns_event).await?
+                    }
+                    Kind::ChannelMetadata => {
+                        match ChannelCache::update(cache_pool, &ns_event).await {
+                            Ok(cache) => cache,
+                            // the relay didn't send the channel creation, at least not yet
+                            Err(channel_cache::Error::NotFoundChannelToUpdate(_)) => return Ok(()),
+                            Err(e) => return Err(e.into()),
+                        }
+                    }
+                    other => return Err(Error::UnexpectedEventKind(other.as_u32())),
+                };
+                // relays without NIP-50 send recent channels, matched here
+                let hashtags = event_hashtags(&ns_event.tags);
+                let matches = backend
+                    .channel_search
+                    .as_ref()
+                    .map_or(true, |term| term.relevance(&cache, &hashtags).is_some());
+                if matches {
                     _ = output
-                        .send(BackendEvent::ChannelSearchCacheCreation(url, cache))
+                        .send(BackendEvent::ChannelSearchCacheCreation(
+                            url, cache, hashtags,
+                        ))
                         .await;
-                    return Ok(());
-                } else {
-                    return Err(Error::UnexpectedEventKind(ns_event.kind.as_u32()));
                 }
+                return Ok(());
             }
             SubName::SearchChannelsDetails(_prefixed_id) => {
                 let cache_pool = backend.cache_pool();
-                let Some(_channel_id) = channel_id_from_tags(&ns_event.tags) else {
+                let Some(channel_id) = channel_id_from_tags(&ns_event.tags) else {
                     return Err(Error::ChannelIdNotFound(ns_event.id.to_owned()));
                 };
                 match ns_event.kind {
@@ -365,14 +466,24 @@ pub async fn handle_event(
                         return Ok(());
                     }
                     Kind::ChannelMessage => {
-                        // Channel messages will be handled like a dm.
-                        // Inserted on db_event table then processed.
-                        // _ = output
-                        //     .send(BackendEvent::SearchChannelMessage(
-                        //         channel_id,
-                        //         ns_event.pubkey,
-                        //     ))
-                        //     .await;
+                        if DbMutedPubkey::is_muted(backend.pool(), &ns_event.pubkey).await? {
+                            tracing::debug!(
+                                "Dropping preview message from muted {}",
+                                ns_event.pubkey
+                            );
+                            return Ok(());
+                        }
+                        // kept in the cache until the user subscribes to the channel
+                        ChannelCache::insert_member(cache_pool, &channel_id, &ns_event.pubkey)
+                            .await?;
+                        ChannelPreviewMessage::insert(
+                            cache_pool,
+                            &channel_id,
+                            &ns_event,
+                            Utc::now().naive_utc(),
+                        )
+                        .await?;
+                        return Ok(());
                     }
                     other => {
                         return Err(Error::UnexpectedEventKind(other.as_u32()));
@@ -400,7 +511,20 @@ pub async fn handle_event(
             Kind::ChannelMessage => {
                 let pool = backend.pool();
                 let cache_pool = backend.cache_pool();
-                handle_channel_message(output, keys, pool, cache_pool, &url, ns_event).await?;
+                let notifications = &backend.notifications;
+                handle_channel_message(
+                    output,
+                    keys,
+                    pool,
+                    cache_pool,
+                    notifications,
+                    &url,
+                    ns_event,
+                )
+                .await?;
+            }
+            Kind::ContactList if ns_event.pubkey != keys.public_key() => {
+                handle_other_contact_list(output, keys, backend.pool(), &ns_event).await?;
             }
             Kind::ContactList => {
                 let pool = backend.pool();
@@ -411,12 +535,31 @@ pub async fn handle_event(
             Kind::EncryptedDirectMessage => {
                 let pool = backend.pool();
                 let cache_pool = backend.cache_pool();
-                handle_dm(output, pool, cache_pool, keys, &url, ns_event).await?;
+                let notifications = &backend.notifications;
+                handle_dm(
+                    output,
+                    pool,
+                    cache_pool,
+                    keys,
+                    notifications,
+                    &url,
+                    ns_event,
+                )
+                .await?;
             }
             Kind::Metadata => {
                 let cache_pool = backend.cache_pool();
                 insert_metadata_event(output, cache_pool, &url, ns_event).await?;
             }
+            kind if kind == Kind::from(ZAP_RECEIPT_KIND) => {
+                handle_zap_receipt(output, backend.pool(), ns_event).await?;
+            }
+            kind if kind == Kind::from(NWC_RESPONSE_KIND) => {
+                handle_wallet_response(output, backend.pool(), ns_event).await?;
+            }
+            kind if kind == Kind::from(MUTE_LIST_KIND) => {
+                handle_mute_list(output, keys, backend.pool(), &url, ns_event).await?;
+            }
             _other_kind => {
                 tracing::info!("Other kind event: {:?}", _other_kind);
                 // _ = output
@@ -453,6 +596,7 @@ async fn handle_relay_message(
             );
 
             if !status {
+                raise_relay_pow(output, backend, &url, &error_msg).await?;
                 _ = output.send(BackendEvent::RelayError(url, error_msg)).await;
                 return Ok(());
             }
@@ -494,6 +638,27 @@ async fn handle_relay_message(
     Ok(())
 }
 
+/// Raises the proof of work difficulty of a relay that rejected an event
+/// for not having enough work
+async fn raise_relay_pow(
+    output: &mut futures::channel::mpsc::Sender<BackendEvent>,
+    backend: &mut BackendState,
+    url: &Url,
+    error_msg: &str,
+) -> Result<(), Error> {
+    let Some(mut db_relay) = DbRelay::fetch_by_url(backend.pool(), url).await? else {
+        return Ok(());
+    };
+    let Some(difficulty) = raised_difficulty(error_msg, db_relay.pow_difficulty) else {
+        return Ok(());
+    };
+    tracing::info!("{} asks for proof of work, difficulty {}", url, difficulty);
+    DbRelay::set_pow_difficulty(backend.pool(), url, difficulty).await?;
+    db_relay.pow_difficulty = difficulty;
+    _ = output.send(BackendEvent::RelayUpdated(db_relay)).await;
+    Ok(())
+}
+
 async fn confirm_pending(
     output: &mut futures::channel::mpsc::Sender<BackendEvent>,
     keys: &Keys,
@@ -516,6 +681,8 @@ async fn confirm_pending(
             Kind::EncryptedDirectMessage => {
                 pending_dm_confirmed(output, pool, keys, &db_event).await?;
             }
+            // the entries were stored when muting
+            kind if kind == Kind::from(MUTE_LIST_KIND) => (),
             _ => {
                 return Err(Error::NotSubscribedToKind(db_event.kind));
             }
@@ -528,12 +695,14 @@ async fn get_clients(
     keys: &Keys,
     create_account: Option<BasicProfile>,
 ) -> Result<ClientState, Error> {
-    let db_client = Database::new(&keys.public_key().to_string()).await?;
+    let db_client = Database::new(keys).await?;
     let (tasks_tx, tasks_rx) = tokio::sync::mpsc::channel(100);
     let req_client = reqwest::Client::new();
     let nostr = RelayPool::new();
     let notifications = nostr.notifications();
     let nips_data = parse_nips_markdown(NIPS_LIST_MARKDOWN)?;
+    spawn_cache_sweeper(tasks_tx.clone(), db_client.clone(), keys.public_key());
+    spawn_expiration_sweeper(tasks_tx.clone(), db_client.clone());
     let backend = BackendState::new(db_client, req_client, nostr, nips_data, create_account);
 
     spawn_ntp_request(tasks_tx.clone());
@@ -581,6 +750,15 @@ async fn handle_relay_info(
     url: Url,
     info: ns_client::RelayInformation,
 ) -> Result<(), Error> {
+    let supports_search = info
+        .document
+        .as_ref()
+        .and_then(|document| document.supported_nips.as_ref())
+        .map_or(false, |nips| nips.contains(&NIP_SEARCH));
+    backend
+        .search_support
+        .insert(url.to_owned(), supports_search);
+
     let db_relay = DbRelay::fetch_by_url(backend.pool(), &url)
         .await?
         .map(|mut db_relay| {
@@ -599,11 +777,47 @@ pub enum TaskOutput {
     Ntp(u64, String),
     LatestVersion(String),
     ImageDownloaded(ImageDownloaded),
+    MediaDownloaded {
+        media_url: String,
+        image: ImageDownloaded,
+    },
+    MediaFailed(String),
+    LinkPreview(LinkPreview),
+    /// Whether the member's NIP-05 identifier points back to them
+    Nip05Verified(XOnlyPublicKey, bool),
+    FileUploaded(Attachment),
+    UploadFailed(String),
+    AttachmentSaved {
+        url: String,
+        path: PathBuf,
+    },
+    AttachmentFailed(String),
+    ZapInvoice {
+        recipient: XOnlyPublicKey,
+        invoice: ZapInvoice,
+    },
+    ZapFailed(String),
+    /// LNURL servers of the zapped users, resolved before subscribing to the receipts
+    ZapProvidersResolved {
+        providers: Vec<ZapProvider>,
+        zapped_events: Vec<EventId>,
+    },
+    /// The wallet didn't answer the payment request in time
+    WalletTimeout(EventId),
+    CacheSwept(SweepStats),
+    /// Ids of the deleted events
+    EventsExpired(Vec<i64>),
+    MiningProgress(MiningProgress),
+    /// None when the mining was cancelled
+    EventMined {
+        pending_hash: EventId,
+        mined: Option<nostr::UnsignedEvent>,
+    },
 }
 
 async fn handle_task_result(
     output: &mut futures::channel::mpsc::Sender<BackendEvent>,
-    _keys: &Keys,
+    keys: &Keys,
     backend: &mut BackendState,
     result: Result<TaskOutput, Error>,
 ) -> Result<(), Error> {
@@ -625,9 +839,106 @@ async fn handle_task_result(
             ImageDownloaded::insert(backend.cache_pool(), &image).await?;
             _ = output.send(BackendEvent::ImageDownloaded(image)).await;
         }
+        TaskOutput::MediaDownloaded { media_url, image } => {
+            ImageDownloaded::insert(backend.cache_pool(), &image).await?;
+            _ = output
+                .send(BackendEvent::MediaDownloaded { media_url, image })
+                .await;
+        }
+        TaskOutput::MediaFailed(media_url) => {
+            _ = output.send(BackendEvent::MediaFailed(media_url)).await;
+        }
+        TaskOutput::LinkPreview(preview) => {
+            _ = output.send(BackendEvent::GotLinkPreview(preview)).await;
+        }
+        TaskOutput::Nip05Verified(pubkey, verified) => {
+            _ = output
+                .send(BackendEvent::GotNip05Status(pubkey, verified))
+                .await;
+        }
+        TaskOutput::FileUploaded(attachment) => {
+            _ = output.send(BackendEvent::FileUploaded(attachment)).await;
+        }
+        TaskOutput::UploadFailed(error) => {
+            _ = output.send(BackendEvent::UploadFailed(error)).await;
+        }
+        TaskOutput::AttachmentSaved { url, path } => {
+            _ = output
+                .send(BackendEvent::AttachmentSaved { url, path })
+                .await;
+        }
+        TaskOutput::AttachmentFailed(url) => {
+            _ = output.send(BackendEvent::AttachmentFailed(url)).await;
+        }
+        TaskOutput::ZapInvoice { recipient, invoice } => {
+            let provider = ZapProvider {
+                recipient,
+                nostr_pubkey: invoice.nostr_pubkey,
+                updated_at: Utc::now().naive_utc(),
+            };
+            ZapProvider::upsert(backend.pool(), &provider).await?;
+            _ = output.send(BackendEvent::GotZapInvoice(invoice)).await;
+        }
+        TaskOutput::ZapProvidersResolved {
+            providers,
+            zapped_events,
+        } => {
+            for provider in &providers {
+                ZapProvider::upsert(backend.pool(), provider).await?;
+            }
+            // receipts of the user received before were ignored, fetch them again
+            let refetch = providers.iter().any(|p| p.recipient == keys.public_key());
+            subscribe_zap_receipts(keys, backend, &zapped_events, refetch).await?;
+        }
+        TaskOutput::ZapFailed(error) => {
+            _ = output.send(BackendEvent::ZapFailed(error)).await;
+        }
+        TaskOutput::WalletTimeout(request_hash) => {
+            let status = PaymentStatus::Failed {
+                error: "No answer from the wallet".into(),
+            };
+            if let Some(payment) =
+                DbWalletPayment::resolve(backend.pool(), &request_hash, status).await?
+            {
+                _ = output
+                    .send(BackendEvent::WalletPaymentUpdated(payment))
+                    .await;
+            }
+        }
         TaskOutput::LatestVersion(version) => {
             _ = output.send(BackendEvent::LatestVersion(version)).await;
         }
+        TaskOutput::CacheSwept(stats) => {
+            tracing::info!(
+                "Cache sweep: {} images, {} profiles, {} link previews and {} channel messages evicted, {} bytes freed",
+                stats.images_evicted,
+                stats.profiles_evicted,
+                stats.previews_evicted,
+                stats.channel_previews_evicted,
+                stats.bytes_freed
+            );
+        }
+        TaskOutput::EventsExpired(event_ids) => {
+            if !event_ids.is_empty() {
+                _ = output.send(BackendEvent::MessagesExpired(event_ids)).await;
+            }
+        }
+        TaskOutput::MiningProgress(progress) => {
+            _ = output.send(BackendEvent::MiningProgress(progress)).await;
+        }
+        TaskOutput::EventMined {
+            pending_hash,
+            mined,
+        } => {
+            if let Some(event_hash) = backend.send_mined(keys, &pending_hash, mined)? {
+                _ = output
+                    .send(BackendEvent::MiningDone {
+                        pending_hash,
+                        event_hash,
+                    })
+                    .await;
+            }
+        }
     }
     Ok(())
 }
@@ -642,6 +953,52 @@ pub enum BackendEvent {
         event_hash: EventId,
     },
     ImageDownloaded(ImageDownloaded),
+    MediaDownloaded {
+        media_url: String,
+        image: ImageDownloaded,
+    },
+    MediaFailed(String),
+    GotLinkPreview(LinkPreview),
+    GotNip05Status(XOnlyPublicKey, bool),
+    FileUploaded(Attachment),
+    UploadFailed(String),
+    AttachmentSaved {
+        url: String,
+        path: PathBuf,
+    },
+    AttachmentFailed(String),
+    AttachmentCanceled(String),
+    GotZapInvoice(ZapInvoice),
+    ZapFailed(String),
+    GotWalletConnect(Option<WalletConnectUri>),
+    /// In msats
+    GotWalletBalance(u64),
+    GotWalletPayments(Vec<DbWalletPayment>),
+    WalletPaymentUpdated(DbWalletPayment),
+    WalletError(String),
+    GotChatNotification(ChatNotification),
+    GotChatExpiration(NotificationChat, ExpirationTimer),
+    /// Empty when the chat has no draft
+    GotDraft(NotificationChat, String),
+    /// Event ids of the messages deleted for expiring
+    MessagesExpired(Vec<i64>),
+    GotDoNotDisturb(DoNotDisturb),
+    /// Followers with their contact, or a new one with the cached profile
+    /// when they aren't in the contact list
+    GotFollowers(Vec<(DbFollower, DbContact)>),
+    FollowerUpdated(DbFollower),
+    GotMuteList(Vec<DbMutedPubkey>),
+    GotPowDifficulty(u8),
+    GotIncomingPowThreshold(u8),
+    /// Filtered messages with direct messages decrypted
+    GotFilteredMessages(Vec<DbFilteredMessage>),
+    MessageFiltered(DbFilteredMessage),
+    MiningProgress(MiningProgress),
+    /// The event pending as `pending_hash` was sent as `event_hash`
+    MiningDone {
+        pending_hash: EventId,
+        event_hash: EventId,
+    },
 
     // ---  ---
     ThemeChanged(style::Theme),
@@ -721,16 +1078,29 @@ pub enum BackendEvent {
 
     LoadingChannelDetails(Url, EventId),
     GotChannelMessages(EventId, Vec<ChatMessage>),
+    GotChannelMemberStats(EventId, Vec<ChannelMemberStats>),
+    GotChannelPreview(EventId, Vec<ChannelPreviewMessage>),
     ReceivedChannelMessage(EventId, ChatMessage),
     ChannelSubscribed(EventId),
     ChannelUnsubscribed(EventId),
     GotSubscribedChannels(Vec<ChannelCache>),
+    GotChannelActivity(ChannelActivity),
     ChannelCacheUpdated(ChannelCache),
 
-    ChannelSearchCacheCreation(Url, ChannelCache),
+    /// Channel found while searching, with the `t` tags of its event to rank it
+    ChannelSearchCacheCreation(Url, ChannelCache, Vec<String>),
     EOSESearchChannels(Url),
     EOSESearchChannelsDetails(PrefixedId),
     GotChannelCache(ChannelCache),
+
+    GotSearchResults(Vec<MessageSearchResult>),
+
+    /// Totals in msats of the zapped messages, by event id
+    GotZapTotals(Vec<(i64, u64)>),
+    ZapReceived {
+        event_id: i64,
+        total_msats: u64,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -770,7 +1140,7 @@ pub enum ToBackend {
     ExportContacts,
     FetchChatInfo(DbContact),
     FetchContactWithMetadata(XOnlyPublicKey),
-    SendDM(DbContact, String),
+    SendDM(DbContact, String, Vec<Attachment>),
     SendChannelMessage(EventId, String),
     CreateChannel,
     FetchMoreMessages(DbContact, NaiveDateTime),
@@ -782,23 +1152,95 @@ pub enum ToBackend {
     DownloadImage {
         image_url: String,
         kind: ImageKind,
-        identifier: String,
         event_hash: EventId,
     },
+    DownloadMedia(String),
+    FetchLinkPreview(String),
+    VerifyNip05(XOnlyPublicKey, String),
+    UploadFile(PathBuf),
+    DownloadAttachment(Attachment),
     SyncWithNTP,
     GetRelayStatusList,
     ReconnectRelay(url::Url),
     MessageSeen(i64),
     FetchChannelMessages(EventId),
+    FetchChannelMemberStats(EventId),
+    /// Latest messages of a channel found while searching
+    FetchChannelPreview(EventId),
     FetchMembersInfo(std::collections::HashSet<XOnlyPublicKey>),
     FetchProfileCache(XOnlyPublicKey),
 
     SubscribeToChannel(nostr::EventId),
     UnsubscribeToChannel(nostr::EventId),
     FetchSubscribedChannels,
+    FetchChannelActivity(EventId),
+    MarkChannelRead(EventId),
     FetchChannelCache(EventId),
     SubscribeToChannelDetails(Url, Vec<EventId>),
     SubscribeChannelMembersMeta(EventId),
+
+    SearchMessages(MessageSearchQuery),
+    FetchMessagesAround(DbContact, NaiveDateTime),
+    FetchChannelMessagesAround(EventId, NaiveDateTime),
+
+    FetchZapTotals(Vec<i64>),
+    RequestZapInvoice {
+        target: ZapTarget,
+        amount_msats: u64,
+        comment: String,
+    },
+
+    FetchWalletConnect,
+    SaveWalletConnect(WalletConnectUri),
+    RemoveWalletConnect,
+    FetchWalletBalance,
+    FetchWalletPayments,
+    PayInvoice(String),
+
+    /// Notifications are only shown while the window is unfocused
+    WindowFocused(bool),
+    FetchChatNotification(NotificationChat),
+    SetChatNotification(ChatNotification),
+    FetchChatExpiration(NotificationChat),
+    SetChatExpiration(NotificationChat, ExpirationTimer),
+    FetchDraft(NotificationChat),
+    /// An empty text deletes the draft
+    SaveDraft(NotificationChat, String),
+    FetchDoNotDisturb,
+    SetDoNotDisturb(DoNotDisturb),
+
+    FetchFollowers,
+
+    FetchMuteList,
+    /// Private entries are encrypted in the published list
+    MutePubkey {
+        pubkey: XOnlyPublicKey,
+        private: bool,
+    },
+    UnmutePubkey(XOnlyPublicKey),
+
+    /// Moves a message request to the chats, adding the sender to the
+    /// published contact list when `add_to_list`
+    AcceptRequest {
+        db_contact: DbContact,
+        add_to_list: bool,
+    },
+    /// Deletes a message request and its messages
+    DeleteRequest(DbContact),
+
+    FetchPowDifficulty,
+    /// Difficulty for all outgoing events, on top of the relay ones
+    SetPowDifficulty(u8),
+    SetRelayPowDifficulty(Url, u8),
+    /// Sends the event without waiting for the proof of work
+    CancelMining(EventId),
+
+    FetchIncomingPowThreshold,
+    /// Difficulty asked of messages from non-contacts
+    SetIncomingPowThreshold(u8),
+    FetchFilteredMessages,
+    DeleteFilteredMessage(i64),
+    ClearFilteredMessages,
 }
 
 pub async fn process_message(
@@ -838,7 +1280,7 @@ pub async fn process_message(
             }
         }
         ToBackend::ExportContacts => {
-            let pending_event = backend.new_contact_list_event(keys).await?;
+            let pending_event = backend.new_contact_list_event(keys, task_tx).await?;
             match save_file(pending_event.ns_event(), "json").await {
                 Ok(event) => {
                     _ = output.send(event).await;
@@ -889,22 +1331,33 @@ pub async fn process_message(
         }
         ToBackend::DownloadImage {
             image_url,
-            identifier,
             kind,
             event_hash,
         } => match ImageDownloaded::fetch(backend.cache_pool(), &event_hash, kind).await? {
             Some(image) => {
+                ImageDownloaded::touch(backend.cache_pool(), &event_hash, kind).await?;
                 _ = output.send(BackendEvent::ImageDownloaded(image)).await;
             }
             None => {
                 let task_tx_1 = task_tx.clone();
-                let identifier_1 = identifier.clone();
+                let req_client_1 = backend.req_client.clone();
+                let cache_pool_1 = backend.cache_pool().clone();
                 let image_url_1 = image_url.to_string();
                 tokio::spawn(async move {
-                    let result = download_image(&image_url_1, &event_hash, &identifier_1, kind)
+                    let result = match blobs_dir() {
+                        Ok(blobs_dir) => download_image(
+                            &req_client_1,
+                            &cache_pool_1,
+                            &blobs_dir,
+                            &image_url_1,
+                            &event_hash,
+                            kind,
+                        )
                         .await
                         .map(TaskOutput::ImageDownloaded)
-                        .map_err(|e| e.into());
+                        .map_err(|e| e.into()),
+                        Err(e) => Err(e.into()),
+                    };
                     if let Err(e) = task_tx_1.send(result).await {
                         tracing::error!("Error sending image downloaded event: {}", e);
                     }
@@ -914,6 +1367,417 @@ pub async fn process_message(
                     .await;
             }
         },
+        ToBackend::DownloadMedia(media_url) => {
+            let event_hash = media_event_hash(&media_url);
+            match ImageDownloaded::fetch(backend.cache_pool(), &event_hash, ImageKind::Media)
+                .await?
+            {
+                Some(image) => {
+                    ImageDownloaded::touch(backend.cache_pool(), &event_hash, ImageKind::Media)
+                        .await?;
+                    _ = output
+                        .send(BackendEvent::MediaDownloaded { media_url, image })
+                        .await;
+                }
+                None => {
+                    let task_tx_1 = task_tx.clone();
+                    let req_client_1 = backend.req_client.clone();
+                    let cache_pool_1 = backend.cache_pool().clone();
+                    tokio::spawn(async move {
+                        let result = match blobs_dir() {
+                            Ok(blobs_dir) => {
+                                download_image(
+                                    &req_client_1,
+                                    &cache_pool_1,
+                                    &blobs_dir,
+                                    &media_url,
+                                    &event_hash,
+                                    ImageKind::Media,
+                                )
+                                .await
+                            }
+                            Err(e) => Err(e),
+                        };
+                        // a broken link in a message is not a backend error
+                        let task_output = match result {
+                            Ok(image) => TaskOutput::MediaDownloaded { media_url, image },
+                            Err(e) => {
+                                tracing::info!("Failed to download media {}: {}", media_url, e);
+                                TaskOutput::MediaFailed(media_url)
+                            }
+                        };
+                        if let Err(e) = task_tx_1.send(Ok(task_output)).await {
+                            tracing::error!("Error sending media downloaded event: {}", e);
+                        }
+                    });
+                }
+            }
+        }
+        ToBackend::FetchLinkPreview(link_url) => {
+            let task_tx_1 = task_tx.clone();
+            let req_client_1 = backend.req_client.clone();
+            let cache_pool_1 = backend.cache_pool().clone();
+            let limiter_1 = backend.link_limiter.clone();
+            tokio::spawn(async move {
+                let result =
+                    fetch_link_preview(&req_client_1, &cache_pool_1, &limiter_1, &link_url)
+                        .await
+                        .map(TaskOutput::LinkPreview)
+                        .map_err(|e| e.into());
+                if let Err(e) = task_tx_1.send(result).await {
+                    tracing::error!("Error sending link preview event: {}", e);
+                }
+            });
+        }
+        ToBackend::VerifyNip05(pubkey, nip05) => {
+            let task_tx_1 = task_tx.clone();
+            let req_client_1 = backend.req_client.clone();
+            let limiter_1 = backend.nip05_limiter.clone();
+            tokio::spawn(async move {
+                // an unreachable domain only means the identifier isn't verified
+                let verified = verify_nip05(&req_client_1, &limiter_1, &pubkey, &nip05)
+                    .await
+                    .unwrap_or_else(|e| {
+                        tracing::info!("Failed to verify NIP-05 {}: {}", nip05, e);
+                        false
+                    });
+                let task_output = TaskOutput::Nip05Verified(pubkey, verified);
+                if let Err(e) = task_tx_1.send(Ok(task_output)).await {
+                    tracing::error!("Error sending NIP-05 status event: {}", e);
+                }
+            });
+        }
+        ToBackend::UploadFile(path) => {
+            let server_url = Url::parse(&Config::load_file_async().await?.media_server)?;
+            let task_tx_1 = task_tx.clone();
+            let req_client_1 = backend.req_client.clone();
+            let keys_1 = keys.clone();
+            tokio::spawn(async move {
+                // only direct messages have attachments, their files are encrypted
+                let task_output =
+                    match upload_encrypted_file(&req_client_1, &keys_1, &server_url, &path).await {
+                        Ok(attachment) => TaskOutput::FileUploaded(attachment),
+                        Err(e) => {
+                            tracing::error!("Failed to upload {}: {}", path.display(), e);
+                            TaskOutput::UploadFailed(e.to_string())
+                        }
+                    };
+                if let Err(e) = task_tx_1.send(Ok(task_output)).await {
+                    tracing::error!("Error sending file uploaded event: {}", e);
+                }
+            });
+        }
+        ToBackend::DownloadAttachment(attachment) => {
+            // decrypted files go to the app folder, the user only picks
+            // where to save the public ones
+            let path = if attachment.encryption.is_some() {
+                attachment_path(&attachment)?
+            } else {
+                let save_handle = AsyncFileDialog::new()
+                    .set_file_name(&attachment.file_name())
+                    .save_file()
+                    .await;
+                let Some(save_handle) = save_handle else {
+                    _ = output
+                        .send(BackendEvent::AttachmentCanceled(attachment.url))
+                        .await;
+                    return Ok(());
+                };
+                save_handle.path().to_owned()
+            };
+            let task_tx_1 = task_tx.clone();
+            let req_client_1 = backend.req_client.clone();
+            tokio::spawn(async move {
+                let url = attachment.url.to_owned();
+                let task_output = match download_attachment(&req_client_1, &attachment, &path).await
+                {
+                    Ok(()) => TaskOutput::AttachmentSaved { url, path },
+                    Err(e) => {
+                        tracing::info!("Failed to download attachment {}: {}", url, e);
+                        TaskOutput::AttachmentFailed(url)
+                    }
+                };
+                if let Err(e) = task_tx_1.send(Ok(task_output)).await {
+                    tracing::error!("Error sending attachment downloaded event: {}", e);
+                }
+            });
+        }
+        ToBackend::RequestZapInvoice {
+            target,
+            amount_msats,
+            comment,
+        } => {
+            let pool = backend.pool();
+            let profile =
+                ProfileCache::fetch_by_public_key(backend.cache_pool(), &target.recipient).await?;
+            let Some(profile) = profile else {
+                let error = zap::Error::NoLightningAddress.to_string();
+                _ = output.send(BackendEvent::ZapFailed(error)).await;
+                return Ok(());
+            };
+            let event_hash = match target.event_id {
+                Some(event_id) => DbEvent::fetch_id(pool, event_id)
+                    .await?
+                    .map(|db_event| db_event.event_hash),
+                None => None,
+            };
+            // the receipt is published where the user reads from
+            let relays = DbRelay::fetch(pool)
+                .await?
+                .into_iter()
+                .filter(|db_relay| db_relay.read)
+                .map(|db_relay| db_relay.url.to_string())
+                .collect();
+            let zap = ZapRequest {
+                recipient: target.recipient,
+                event_hash,
+                amount_msats,
+                comment,
+                relays,
+            };
+
+            let task_tx_1 = task_tx.clone();
+            let req_client_1 = backend.req_client.clone();
+            let keys_1 = keys.clone();
+            tokio::spawn(async move {
+                let task_output = match fetch_zap_invoice(
+                    &req_client_1,
+                    &keys_1,
+                    &profile.metadata,
+                    &zap,
+                )
+                .await
+                {
+                    Ok(invoice) => TaskOutput::ZapInvoice {
+                        recipient: zap.recipient,
+                        invoice,
+                    },
+                    Err(e) => {
+                        tracing::info!("Failed to fetch zap invoice: {}", e);
+                        TaskOutput::ZapFailed(e.to_string())
+                    }
+                };
+                if let Err(e) = task_tx_1.send(Ok(task_output)).await {
+                    tracing::error!("Error sending zap invoice event: {}", e);
+                }
+            });
+        }
+        ToBackend::FetchZapTotals(event_ids) => {
+            let pool = backend.pool();
+            let totals = DbZapReceipt::fetch_totals(pool, &event_ids).await?;
+
+            let mut zapped_events = vec![];
+            let mut recipients = vec![keys.public_key()];
+            for event_id in event_ids {
+                if let Some(db_event) = DbEvent::fetch_id(pool, event_id).await? {
+                    zapped_events.push(db_event.event_hash);
+                    if !recipients.contains(&db_event.pubkey) {
+                        recipients.push(db_event.pubkey);
+                    }
+                }
+            }
+
+            // receipts are only counted when signed by the recipient's LNURL server
+            let mut unresolved = vec![];
+            for recipient in recipients {
+                if ZapProvider::fetch(pool, &recipient).await?.is_some() {
+                    continue;
+                }
+                if let Some(profile) =
+                    ProfileCache::fetch_by_public_key(backend.cache_pool(), &recipient).await?
+                {
+                    unresolved.push((recipient, profile.metadata));
+                }
+            }
+
+            if unresolved.is_empty() {
+                subscribe_zap_receipts(keys, backend, &zapped_events, false).await?;
+            } else {
+                let task_tx_1 = task_tx.clone();
+                let req_client_1 = backend.req_client.clone();
+                tokio::spawn(async move {
+                    let mut providers = vec![];
+                    for (recipient, metadata) in unresolved {
+                        match fetch_zap_signer(&req_client_1, &metadata).await {
+                            Ok(nostr_pubkey) => providers.push(ZapProvider {
+                                recipient,
+                                nostr_pubkey,
+                                updated_at: Utc::now().naive_utc(),
+                            }),
+                            Err(e) => tracing::debug!("No zap provider for {}: {}", recipient, e),
+                        }
+                    }
+                    let task_output = TaskOutput::ZapProvidersResolved {
+                        providers,
+                        zapped_events,
+                    };
+                    if let Err(e) = task_tx_1.send(Ok(task_output)).await {
+                        tracing::error!("Error sending zap providers event: {}", e);
+                    }
+                });
+            }
+
+            _ = output.send(BackendEvent::GotZapTotals(totals)).await;
+        }
+        ToBackend::FetchWalletConnect => {
+            let uri = WalletConnect::fetch(backend.pool()).await?;
+            _ = output.send(BackendEvent::GotWalletConnect(uri)).await;
+        }
+        ToBackend::SaveWalletConnect(uri) => {
+            if let Some(old_uri) = WalletConnect::fetch(backend.pool()).await? {
+                if old_uri.relay_url != uri.relay_url {
+                    remove_wallet_relay(backend, &old_uri).await?;
+                }
+            }
+            WalletConnect::save(backend.pool(), &uri).await?;
+            connect_wallet(backend, &uri)?;
+            send_wallet_request(backend, &task_tx, &uri, WalletRequest::GetBalance).await?;
+            _ = output.send(BackendEvent::GotWalletConnect(Some(uri))).await;
+        }
+        ToBackend::RemoveWalletConnect => {
+            if let Some(uri) = WalletConnect::fetch(backend.pool()).await? {
+                WalletConnect::delete(backend.pool()).await?;
+                remove_wallet_relay(backend, &uri).await?;
+            }
+            _ = output.send(BackendEvent::GotWalletConnect(None)).await;
+        }
+        ToBackend::FetchWalletBalance => {
+            if let Some(uri) = WalletConnect::fetch(backend.pool()).await? {
+                send_wallet_request(backend, &task_tx, &uri, WalletRequest::GetBalance).await?;
+            }
+        }
+        ToBackend::FetchWalletPayments => {
+            let payments = DbWalletPayment::fetch(backend.pool(), WALLET_PAYMENTS_LIMIT).await?;
+            _ = output.send(BackendEvent::GotWalletPayments(payments)).await;
+        }
+        ToBackend::PayInvoice(invoice) => {
+            let Some(uri) = WalletConnect::fetch(backend.pool()).await? else {
+                let error = "No wallet connected".to_owned();
+                _ = output.send(BackendEvent::WalletError(error)).await;
+                return Ok(());
+            };
+            let Some(amount_msats) = invoice_amount_msats(&invoice) else {
+                let error = "Invoices without an amount can't be paid".to_owned();
+                _ = output.send(BackendEvent::WalletError(error)).await;
+                return Ok(());
+            };
+            let request = WalletRequest::PayInvoice(invoice.to_owned());
+            let request_hash = send_wallet_request(backend, &task_tx, &uri, request).await?;
+            let payment = DbWalletPayment::new(request_hash, &invoice, amount_msats);
+            DbWalletPayment::insert(backend.pool(), &payment).await?;
+            _ = output
+                .send(BackendEvent::WalletPaymentUpdated(payment))
+                .await;
+        }
+        ToBackend::WindowFocused(focused) => {
+            backend.notifications.set_window_focused(focused);
+        }
+        ToBackend::FetchChatNotification(chat) => {
+            let rules = ChatNotification::fetch(backend.pool(), &chat).await?;
+            _ = output.send(BackendEvent::GotChatNotification(rules)).await;
+        }
+        ToBackend::SetChatNotification(rules) => {
+            ChatNotification::save(backend.pool(), &rules).await?;
+            _ = output.send(BackendEvent::GotChatNotification(rules)).await;
+        }
+        ToBackend::FetchChatExpiration(chat) => {
+            let timer = ExpirationTimer::fetch(backend.pool(), &chat).await?;
+            _ = output
+                .send(BackendEvent::GotChatExpiration(chat, timer))
+                .await;
+        }
+        ToBackend::FetchDraft(chat) => {
+            let draft = DbDraft::fetch(backend.pool(), &chat).await?;
+            _ = output.send(BackendEvent::GotDraft(chat, draft)).await;
+        }
+        ToBackend::SaveDraft(chat, content) => {
+            DbDraft::save(backend.pool(), &chat, &content).await?;
+        }
+        ToBackend::SetChatExpiration(chat, timer) => {
+            ExpirationTimer::save(backend.pool(), &chat, timer).await?;
+            _ = output
+                .send(BackendEvent::GotChatExpiration(chat, timer))
+                .await;
+        }
+        ToBackend::FetchDoNotDisturb => {
+            let dnd = DoNotDisturb::fetch(backend.pool()).await?;
+            _ = output.send(BackendEvent::GotDoNotDisturb(dnd)).await;
+        }
+        ToBackend::SetDoNotDisturb(dnd) => {
+            DoNotDisturb::save(backend.pool(), &dnd).await?;
+            _ = output.send(BackendEvent::GotDoNotDisturb(dnd)).await;
+        }
+        ToBackend::FetchFollowers => {
+            let (pool, cache_pool) = (backend.pool(), backend.cache_pool());
+            let mut followers = vec![];
+            for follower in DbFollower::fetch(pool).await? {
+                let db_contact =
+                    match DbContact::fetch_one(pool, cache_pool, &follower.pubkey).await? {
+                        Some(db_contact) => db_contact,
+                        None => {
+                            let db_contact = DbContact::new(&follower.pubkey);
+                            match ProfileCache::fetch_by_public_key(cache_pool, &follower.pubkey)
+                                .await?
+                            {
+                                Some(cache) => db_contact.with_profile_cache(&cache),
+                                None => db_contact,
+                            }
+                        }
+                    };
+                followers.push((follower, db_contact));
+            }
+
+            if !followers.is_empty() {
+                let pubkeys: Vec<_> = followers.iter().map(|(f, _)| f.pubkey).collect();
+                let subscription =
+                    Subscription::new(vec![members_metadata_filter(pubkeys.iter())]).eose(None);
+                backend.nostr.subscribe(&subscription)?;
+            }
+
+            _ = output.send(BackendEvent::GotFollowers(followers)).await;
+        }
+        ToBackend::FetchMuteList => {
+            let muted = DbMutedPubkey::fetch(backend.pool()).await?;
+            _ = output.send(BackendEvent::GotMuteList(muted)).await;
+        }
+        ToBackend::MutePubkey { pubkey, private } => {
+            if pubkey == keys.public_key() {
+                tracing::info!("Not muting the user's own pubkey");
+                return Ok(());
+            }
+            let muted_at = UserConfig::get_corrected_time(backend.pool())
+                .await
+                .unwrap_or(Utc::now().naive_utc());
+            let muted = DbMutedPubkey::new(&pubkey, private, muted_at);
+            DbMutedPubkey::insert(backend.pool(), &muted).await?;
+            publish_mute_list(output, keys, backend, task_tx).await?;
+        }
+        ToBackend::UnmutePubkey(pubkey) => {
+            DbMutedPubkey::delete(backend.pool(), &pubkey).await?;
+            publish_mute_list(output, keys, backend, task_tx).await?;
+        }
+        ToBackend::AcceptRequest {
+            db_contact,
+            add_to_list,
+        } => {
+            DbContact::fetch_insert(backend.pool(), backend.cache_pool(), db_contact.pubkey())
+                .await?;
+            DbContact::accept(backend.pool(), db_contact.pubkey(), add_to_list).await?;
+            if add_to_list {
+                backend.new_contact_list_event(keys, task_tx).await?;
+            }
+            if let Some(db_contact) =
+                DbContact::fetch_one(backend.pool(), backend.cache_pool(), db_contact.pubkey())
+                    .await?
+            {
+                _ = output.send(BackendEvent::ContactUpdated(db_contact)).await;
+            }
+        }
+        ToBackend::DeleteRequest(db_contact) => {
+            DbMessage::delete_chat(backend.pool(), db_contact.pubkey()).await?;
+            DbContact::delete(backend.pool(), &db_contact).await?;
+            _ = output.send(BackendEvent::ContactDeleted(db_contact)).await;
+        }
         // -----------
         ToBackend::GetTheme => {
             let config = Config::load_file_async().await?;
@@ -989,6 +1853,53 @@ pub async fn process_message(
             DbRelay::update(backend.pool(), &db_relay).await?;
             _ = output.send(BackendEvent::RelayUpdated(db_relay)).await;
         }
+        ToBackend::SetRelayPowDifficulty(url, difficulty) => {
+            let difficulty = difficulty.min(MAX_POW_DIFFICULTY);
+            DbRelay::set_pow_difficulty(backend.pool(), &url, difficulty).await?;
+            if let Some(db_relay) = DbRelay::fetch_by_url(backend.pool(), &url).await? {
+                _ = output.send(BackendEvent::RelayUpdated(db_relay)).await;
+            }
+        }
+        ToBackend::FetchPowDifficulty => {
+            let difficulty = UserConfig::get_pow_difficulty(backend.pool()).await?;
+            _ = output
+                .send(BackendEvent::GotPowDifficulty(difficulty))
+                .await;
+        }
+        ToBackend::SetPowDifficulty(difficulty) => {
+            let difficulty = difficulty.min(MAX_POW_DIFFICULTY);
+            UserConfig::set_pow_difficulty(backend.pool(), difficulty).await?;
+            _ = output
+                .send(BackendEvent::GotPowDifficulty(difficulty))
+                .await;
+        }
+        ToBackend::CancelMining(pending_hash) => {
+            backend.cancel_mining(&pending_hash);
+        }
+        ToBackend::FetchIncomingPowThreshold => {
+            let threshold = UserConfig::get_incoming_pow_threshold(backend.pool()).await?;
+            _ = output
+                .send(BackendEvent::GotIncomingPowThreshold(threshold))
+                .await;
+        }
+        ToBackend::SetIncomingPowThreshold(threshold) => {
+            let threshold = threshold.min(MAX_POW_DIFFICULTY);
+            UserConfig::set_incoming_pow_threshold(backend.pool(), threshold).await?;
+            _ = output
+                .send(BackendEvent::GotIncomingPowThreshold(threshold))
+                .await;
+        }
+        ToBackend::FetchFilteredMessages => {
+            send_filtered_messages(output, keys, backend.pool()).await?;
+        }
+        ToBackend::DeleteFilteredMessage(event_id) => {
+            DbFilteredMessage::delete(backend.pool(), event_id).await?;
+            send_filtered_messages(output, keys, backend.pool()).await?;
+        }
+        ToBackend::ClearFilteredMessages => {
+            DbFilteredMessage::delete_all(backend.pool()).await?;
+            send_filtered_messages(output, keys, backend.pool()).await?;
+        }
         ToBackend::FetchRelayResponsesUserProfile => {
             let pool = backend.pool();
             if let Some(profile_event) =
@@ -1065,6 +1976,20 @@ pub async fn process_message(
                 _ = output.send(BackendEvent::GotChannelCache(cache)).await;
             }
         }
+        ToBackend::FetchChannelActivity(channel_id) => {
+            let activity = ChannelSubscription::fetch_activity(backend.pool(), &channel_id).await?;
+            _ = output
+                .send(BackendEvent::GotChannelActivity(activity))
+                .await;
+        }
+        ToBackend::MarkChannelRead(channel_id) => {
+            let pool = backend.pool();
+            ChannelSubscription::mark_read(pool, &channel_id).await?;
+            let activity = ChannelSubscription::fetch_activity(pool, &channel_id).await?;
+            _ = output
+                .send(BackendEvent::GotChannelActivity(activity))
+                .await;
+        }
         ToBackend::FetchSubscribedChannels => {
             let pool = backend.pool();
             let cache_pool = backend.cache_pool();
@@ -1110,13 +2035,35 @@ pub async fn process_message(
             _ = output.send(BackendEvent::GotKeys(keys.to_owned())).await;
         }
         ToBackend::FindChannels(search_term) => {
-            let subscription = Subscription::new(vec![channel_search_filter(&search_term)])
+            let term = ChannelSearchTerm::parse(&search_term);
+            let search = Subscription::new(vec![channel_search_filter(&term)])
                 .with_id(SubName::SearchChannels.to_string())
                 .eose(Some(Duration::from_secs(10)));
-            backend.nostr.subscribe(&subscription)?;
+            let recent = Subscription::new(recent_channels_filters())
+                .with_id(SubName::SearchChannels.to_string())
+                .eose(Some(Duration::from_secs(10)));
+
+            let relays = DbRelay::fetch(backend.pool()).await?;
+            for relay in relays.iter().filter(|relay| relay.read) {
+                let subscription = match (&term, backend.search_support.get(&relay.url)) {
+                    (ChannelSearchTerm::Id(_), _) | (_, Some(true)) => &search,
+                    (_, Some(false)) => &recent,
+                    (_, None) => {
+                        // known for the next search
+                        let _ = backend.nostr.relay_info(&relay.url);
+                        &recent
+                    }
+                };
+                if let Err(e) = backend.nostr.relay_subscribe(&relay.url, subscription) {
+                    tracing::info!("Failed to search channels on {}: {}", &relay.url, e);
+                }
+            }
+            backend.channel_search = Some(term);
         }
         ToBackend::UpdateUserProfileMeta(profile_meta) => {
-            backend.new_profile_event(keys, &profile_meta).await?;
+            backend
+                .new_profile_event(keys, task_tx, &profile_meta)
+                .await?;
         }
         ToBackend::SubscribeToChannelDetails(url, channel_ids) => {
             let actions_id = SubscriptionId::generate().to_string();
@@ -1136,14 +2083,14 @@ pub async fn process_message(
         ToBackend::QueryFirstLogin => {
             let pool = backend.pool();
             if UserConfig::query_has_logged_in(pool).await? {
-                prepare_client(keys, backend).await?;
+                prepare_client(keys, backend, task_tx).await?;
                 _ = output.send(BackendEvent::FinishedPreparing).await;
             } else {
                 _ = output.send(BackendEvent::FirstLoginSuccess).await;
             }
         }
         ToBackend::PrepareClient => {
-            prepare_client(keys, backend).await?;
+            prepare_client(keys, backend, task_tx).await?;
             _ = output.send(BackendEvent::FinishedPreparing).await;
         }
         ToBackend::MessageSeen(msg_id) => {
@@ -1173,6 +2120,55 @@ pub async fn process_message(
                 .send(BackendEvent::GotChannelMessages(channel_id, messages))
                 .await;
         }
+        ToBackend::FetchChannelMemberStats(channel_id) => {
+            let stats = DbChannelMessage::member_stats(backend.pool(), &channel_id).await?;
+            _ = output
+                .send(BackendEvent::GotChannelMemberStats(channel_id, stats))
+                .await;
+        }
+        ToBackend::FetchChannelPreview(channel_id) => {
+            let cache_pool = backend.cache_pool();
+            let mut messages =
+                ChannelPreviewMessage::fetch_latest(cache_pool, &channel_id, CHANNEL_PREVIEW_LIMIT)
+                    .await?;
+            let mut names: HashMap<XOnlyPublicKey, Option<String>> = HashMap::new();
+            for msg in &mut messages {
+                if !names.contains_key(&msg.author) {
+                    let name = ProfileCache::fetch_by_public_key(cache_pool, &msg.author)
+                        .await?
+                        .and_then(|profile| {
+                            profile.metadata.display_name.or(profile.metadata.name)
+                        });
+                    names.insert(msg.author, name);
+                }
+                msg.author_name = names[&msg.author].clone();
+            }
+            _ = output
+                .send(BackendEvent::GotChannelPreview(channel_id, messages))
+                .await;
+        }
+        ToBackend::FetchChannelMessagesAround(channel_id, created_at) => {
+            let messages: Vec<_> =
+                DbChannelMessage::fetch_around(backend.pool(), &channel_id, created_at)
+                    .await?
+                    .into_iter()
+                    .map(Into::into)
+                    .collect();
+
+            _ = output
+                .send(BackendEvent::GotChannelMessages(channel_id, messages))
+                .await;
+        }
+        ToBackend::SearchMessages(query) => {
+            let results = MessageSearch::search(backend.pool(), &query).await?;
+            _ = output.send(BackendEvent::GotSearchResults(results)).await;
+        }
+        ToBackend::FetchMessagesAround(db_contact, created_at) => {
+            let db_messages =
+                DbMessage::fetch_chat_around(backend.pool(), db_contact.pubkey(), created_at)
+                    .await?;
+            send_got_chat_messages(output, keys, backend, db_contact, &db_messages).await?;
+        }
         ToBackend::FetchMessages(db_contact) => {
             let pool = backend.pool();
             let db_messages = DbMessage::fetch_chat(pool, db_contact.pubkey()).await?;
@@ -1268,7 +2264,7 @@ pub async fn process_message(
                 }
             }
 
-            backend.new_contact_list_event(keys).await?;
+            backend.new_contact_list_event(keys, task_tx).await?;
 
             _ = output
                 .send(BackendEvent::FileContactsImported(db_contacts))
@@ -1280,10 +2276,15 @@ pub async fn process_message(
                 return Err(Error::SameContactInsert);
             }
 
-            DbContact::insert(backend.pool(), db_contact.pubkey()).await?;
+            if DbContact::has_contact(backend.pool(), db_contact.pubkey()).await? {
+                // adding the sender of a message request accepts it
+                DbContact::accept(backend.pool(), db_contact.pubkey(), true).await?;
+            } else {
+                DbContact::insert(backend.pool(), db_contact.pubkey()).await?;
+            }
             DbContact::update(backend.pool(), &db_contact).await?;
 
-            backend.new_contact_list_event(keys).await?;
+            backend.new_contact_list_event(keys, task_tx).await?;
 
             _ = output.send(BackendEvent::ContactCreated(db_contact)).await;
         }
@@ -1293,13 +2294,13 @@ pub async fn process_message(
             }
             DbContact::update(backend.pool(), &db_contact).await?;
 
-            backend.new_contact_list_event(keys).await?;
+            backend.new_contact_list_event(keys, task_tx).await?;
 
             _ = output.send(BackendEvent::ContactUpdated(db_contact)).await;
         }
         ToBackend::DeleteContact(db_contact) => {
             DbContact::delete(backend.pool(), &db_contact).await?;
-            backend.new_contact_list_event(keys).await?;
+            backend.new_contact_list_event(keys, task_tx).await?;
             _ = output.send(BackendEvent::ContactDeleted(db_contact)).await;
         }
         ToBackend::FetchContacts => {
@@ -1319,7 +2320,13 @@ pub async fn process_message(
             // create a pending event and await confirmation of relays
             let recommended_relay = UserConfig::get_relay(backend.pool()).await?;
             let pending_event = backend
-                .new_channel_msg(keys, &channel_id, recommended_relay.as_ref(), &raw_content)
+                .new_channel_msg(
+                    keys,
+                    task_tx,
+                    &channel_id,
+                    recommended_relay.as_ref(),
+                    &raw_content,
+                )
                 .await?;
 
             let chat_message = ChatMessage::pending(pending_event, &raw_content);
@@ -1328,11 +2335,27 @@ pub async fn process_message(
                 .send(BackendEvent::PendingChannelMsg(channel_id, chat_message))
                 .await;
         }
-        ToBackend::SendDM(db_contact, raw_content) => {
+        ToBackend::SendDM(db_contact, raw_content, attachments) => {
             // create a pending event and await confirmation of relays
-            let pending_event = backend.new_dm(keys, &db_contact, &raw_content).await?;
+            let pending_event = backend
+                .new_dm(keys, task_tx, &db_contact, &raw_content, &attachments)
+                .await?;
 
-            let chat_message = ChatMessage::pending(pending_event, &raw_content);
+            if !db_contact.is_known() {
+                // replying accepts the request, without listing the sender.
+                // Chats started from a follower have no contact yet.
+                DbContact::fetch_insert(backend.pool(), backend.cache_pool(), db_contact.pubkey())
+                    .await?;
+                DbContact::accept(backend.pool(), db_contact.pubkey(), false).await?;
+                if let Some(db_contact) =
+                    DbContact::fetch_one(backend.pool(), backend.cache_pool(), db_contact.pubkey())
+                        .await?
+                {
+                    _ = output.send(BackendEvent::ContactUpdated(db_contact)).await;
+                }
+            }
+
+            let chat_message = ChatMessage::pending_dm(pending_event, &raw_content, attachments);
 
             _ = output
                 .send(BackendEvent::PendingDM(db_contact, chat_message))
@@ -1343,6 +2366,28 @@ pub async fn process_message(
     Ok(())
 }
 
+/// Publishes the muted pubkeys, keeping the other entries of the last list
+async fn publish_mute_list(
+    output: &mut futures::channel::mpsc::Sender<BackendEvent>,
+    keys: &Keys,
+    backend: &mut BackendState,
+    task_tx: &tokio::sync::mpsc::Sender<Result<TaskOutput, Error>>,
+) -> Result<(), Error> {
+    let pool = backend.pool();
+    let last_list = match DbEvent::fetch_last_kind(pool, Kind::from(MUTE_LIST_KIND)).await? {
+        Some(db_event) => MuteList::from_event(keys, &db_event.to_ns_event()?)?,
+        None => MuteList::default(),
+    };
+    let muted = DbMutedPubkey::fetch(pool).await?;
+    let mute_list = last_list.with_muted(muted.clone());
+    backend
+        .new_mute_list_event(keys, task_tx, &mute_list)
+        .await?;
+
+    _ = output.send(BackendEvent::GotMuteList(muted)).await;
+    Ok(())
+}
+
 async fn update_channels_subscription(backend: &mut BackendState) -> Result<(), Error> {
     let pool = backend.pool();
 
@@ -1358,6 +2403,42 @@ async fn update_channels_subscription(backend: &mut BackendState) -> Result<(),
     Ok(())
 }
 
+/// Adds to the search index direct messages received before it existed
+async fn index_unsearched_dms(keys: &Keys, pool: &SqlitePool) -> Result<(), Error> {
+    let db_messages = MessageSearch::fetch_unindexed_dms(pool).await?;
+    if db_messages.is_empty() {
+        return Ok(());
+    }
+
+    tracing::info!("Indexing {} messages for search", db_messages.len());
+    for db_message in &db_messages {
+        let content = match decrypt_for_search(keys, pool, db_message).await {
+            Ok(content) => content,
+            Err(e) => {
+                // indexed empty, so it isn't tried again on every login
+                tracing::error!("Failed to decrypt message for search: {}", e);
+                String::new()
+            }
+        };
+        MessageSearch::insert_dm(pool, keys, db_message, &content).await?;
+    }
+
+    Ok(())
+}
+
+async fn decrypt_for_search(
+    keys: &Keys,
+    pool: &SqlitePool,
+    db_message: &DbMessage,
+) -> Result<String, Error> {
+    let db_event = DbEvent::fetch_id(pool, db_message.event_id)
+        .await?
+        .ok_or(Error::EventNotInDatabase(db_message.event_id))?;
+    let tag_info =
+        MessageTagInfo::from_event_tags(&db_event.event_hash, &db_event.pubkey, &db_event.tags)?;
+    Ok(db_message.decrypt_message(keys, &tag_info)?)
+}
+
 async fn send_got_chat_messages(
     output: &mut futures::channel::mpsc::Sender<BackendEvent>,
     keys: &Keys,
@@ -1432,17 +2513,45 @@ async fn insert_metadata_event(
     Ok(())
 }
 
+async fn send_filtered_messages(
+    output: &mut futures::channel::mpsc::Sender<BackendEvent>,
+    keys: &Keys,
+    pool: &SqlitePool,
+) -> Result<(), Error> {
+    let messages = DbFilteredMessage::fetch(pool)
+        .await?
+        .into_iter()
+        .map(|message| decrypt_filtered(keys, message))
+        .collect();
+    _ = output
+        .send(BackendEvent::GotFilteredMessages(messages))
+        .await;
+    Ok(())
+}
+
 async fn handle_channel_message(
     output: &mut futures::channel::mpsc::Sender<BackendEvent>,
     keys: &Keys,
     pool: &SqlitePool,
     cache_pool: &SqlitePool,
+    notifications: &NotificationEngine,
     relay_url: &Url,
     ns_event: nostr::Event,
 ) -> Result<(), Error> {
     let Some(channel_id) = channel_id_from_tags(&ns_event.tags) else {
         return Err(Error::ChannelIdNotFound(ns_event.id));
     };
+    if DbMutedPubkey::is_muted(pool, &ns_event.pubkey).await? {
+        tracing::debug!("Dropping channel message from muted {}", ns_event.pubkey);
+        return Ok(());
+    }
+    if let Some(difficulty) = below_pow_threshold(pool, keys, &ns_event).await? {
+        let channel_id = Some(&channel_id);
+        return insert_filtered(
+            output, pool, keys, relay_url, &ns_event, channel_id, difficulty,
+        )
+        .await;
+    }
 
     if let Some(db_event) = DbEvent::insert(pool, relay_url, &ns_event).await? {
         let is_users = db_event.pubkey == keys.public_key();
@@ -1462,6 +2571,22 @@ async fn handle_channel_message(
             }
         }
 
+        // the channel name is only looked up when it can be shown
+        if !is_users && !notifications.window_focused() {
+            let channel_name = ChannelCache::fetch_by_channel_id(cache_pool, &channel_id)
+                .await?
+                .and_then(|cache| cache.metadata.name)
+                .unwrap_or_else(|| "Channel".to_owned());
+            let notification = Notification {
+                chat: NotificationChat::Channel(channel_id),
+                title: channel_name,
+                body: ns_event.content.to_owned(),
+            };
+            notifications
+                .message_received(pool, keys, &ns_event, notification)
+                .await?;
+        }
+
         let _ = output
             .send(BackendEvent::ReceivedChannelMessage(
                 channel_id,
@@ -1495,7 +2620,106 @@ pub async fn _create_channel(_client: &RelayPool) -> Result<BackendEvent, Error>
     todo!()
 }
 
-async fn prepare_client(keys: &Keys, backend: &mut BackendState) -> Result<(), Error> {
+/// Adds the wallet's relay to the pool and listens for its answers
+fn connect_wallet(backend: &mut BackendState, uri: &WalletConnectUri) -> Result<(), Error> {
+    add_wallet_relay(backend, uri);
+    let subscription = Subscription::new(vec![wallet_responses_filter(uri)])
+        .with_id(SubName::WalletResponses.to_string());
+    backend.nostr.subscribe(&subscription)?;
+    Ok(())
+}
+
+fn add_wallet_relay(backend: &mut BackendState, uri: &WalletConnectUri) {
+    let opts = ns_client::RelayOptions::new(true, true);
+    if let Err(e) = backend
+        .nostr
+        .add_relay_with_opts(uri.relay_url.as_str(), opts)
+    {
+        // already in the pool when the user reads from it
+        tracing::debug!("Wallet relay not added: {}", e);
+    }
+}
+
+async fn remove_wallet_relay(
+    backend: &mut BackendState,
+    uri: &WalletConnectUri,
+) -> Result<(), Error> {
+    // the user may also read from the wallet's relay
+    if DbRelay::fetch_by_url(backend.pool(), &uri.relay_url)
+        .await?
+        .is_none()
+    {
+        backend.nostr.remove_relay(uri.relay_url.as_str())?;
+    }
+    Ok(())
+}
+
+/// Subscribes to the zap receipts of the user and of the zapped messages
+async fn subscribe_zap_receipts(
+    keys: &Keys,
+    backend: &mut BackendState,
+    zapped_events: &[EventId],
+    refetch: bool,
+) -> Result<(), Error> {
+    let last_event = if refetch {
+        None
+    } else {
+        DbEvent::fetch_last(backend.pool()).await?
+    };
+    let filters = zap_receipts_filter(keys.public_key(), zapped_events, &last_event);
+    let subscription = Subscription::new(filters).with_id(SubName::ZapReceipts.to_string());
+    backend.nostr.subscribe(&subscription)?;
+    Ok(())
+}
+
+/// Sends the request to the wallet, payments not answered in time are failed.
+/// Returns the hash of the request event.
+async fn send_wallet_request(
+    backend: &mut BackendState,
+    task_tx: &tokio::sync::mpsc::Sender<Result<TaskOutput, Error>>,
+    uri: &WalletConnectUri,
+    request: WalletRequest,
+) -> Result<EventId, Error> {
+    let ns_event = request.to_event(uri)?;
+    let request_hash = ns_event.id;
+
+    // the wallet's relay isn't one of the user's, it may be missing from the pool.
+    // Fail then, instead of sending the request nowhere.
+    add_wallet_relay(backend, uri);
+    let in_pool = backend
+        .nostr
+        .relay_status_list()
+        .await?
+        .iter()
+        .any(|(url, _status)| url.as_str() == uri.relay_url.as_str());
+    if !in_pool {
+        return Err(wallet_connect::Error::RelayNotInPool(uri.relay_url.to_owned()).into());
+    }
+
+    // only the wallet's relay, the user's relays have no use for the request
+    backend.nostr.relay_send_event(&uri.relay_url, ns_event)?;
+
+    if let WalletRequest::PayInvoice(_) = request {
+        let task_tx_1 = task_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(WALLET_TIMEOUT).await;
+            if let Err(e) = task_tx_1
+                .send(Ok(TaskOutput::WalletTimeout(request_hash)))
+                .await
+            {
+                tracing::error!("Error sending wallet timeout event: {}", e);
+            }
+        });
+    }
+
+    Ok(request_hash)
+}
+
+async fn prepare_client(
+    keys: &Keys,
+    backend: &mut BackendState,
+    task_tx: &tokio::sync::mpsc::Sender<Result<TaskOutput, Error>>,
+) -> Result<(), Error> {
     let pool = backend.pool();
 
     let relays = DbRelay::fetch(pool).await?;
@@ -1507,6 +2731,8 @@ async fn prepare_client(keys: &Keys, backend: &mut BackendState) -> Result<(), E
 
     UserConfig::store_first_login(pool).await?;
 
+    index_unsearched_dms(keys, pool).await?;
+
     tracing::info!("Adding relays to client: {}", relays.len());
 
     // Only adds to the HashMap
@@ -1543,9 +2769,34 @@ async fn prepare_client(keys: &Keys, backend: &mut BackendState) -> Result<(), E
     let channels_sub = Subscription::new(filters).with_id(SubName::Channels.to_string());
     backend.nostr.subscribe(&channels_sub)?;
 
+    let zap_receipts_sub =
+        Subscription::new(zap_receipts_filter(keys.public_key(), &[], &last_event))
+            .with_id(SubName::ZapReceipts.to_string());
+    backend.nostr.subscribe(&zap_receipts_sub)?;
+
+    let followers: Vec<_> = DbFollower::fetch(pool)
+        .await?
+        .into_iter()
+        .map(|f| f.pubkey)
+        .collect();
+    let followers_sub =
+        Subscription::new(followers_filter(keys.public_key(), &followers, &last_event))
+            .with_id(SubName::Followers.to_string());
+    backend.nostr.subscribe(&followers_sub)?;
+
+    let mute_list_sub = Subscription::new(vec![mute_list_filter(keys.public_key(), &last_event)])
+        .with_id(SubName::MuteList.to_string());
+    backend.nostr.subscribe(&mute_list_sub)?;
+
+    if let Some(uri) = WalletConnect::fetch(pool).await? {
+        connect_wallet(backend, &uri)?;
+    }
+
     if let Some(profile) = backend.create_account.take() {
         let profile_meta: Metadata = profile.into();
-        backend.new_profile_event(keys, &profile_meta).await?;
+        backend
+            .new_profile_event(keys, task_tx, &profile_meta)
+            .await?;
     }
 
     Ok(())
@@ -1579,3 +2830,7 @@ async fn save_file<T: Serialize>(data: &T, extension: &str) -> Result<BackendEve
 }
 
 const BACKEND_CHANNEL_SIZE: usize = 1024;
+/// NIP-50, search capability
+const NIP_SEARCH: u16 = 50;
+const WALLET_PAYMENTS_LIMIT: i64 = 100;
+const WALLET_TIMEOUT: Duration = Duration::from_secs(60);
@@ -0,0 +1,111 @@
+use std::str::FromStr;
+
+use nostr::nips::nip04;
+use nostr::secp256k1::XOnlyPublicKey;
+use nostr::{EventBuilder, Keys, Kind, Tag};
+use thiserror::Error;
+
+use crate::db::DbMutedPubkey;
+use crate::utils::ns_event_to_naive;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Encryption error: {0}")]
+    Nip04(#[from] nip04::Error),
+
+    #[error("{0}")]
+    FromKeys(#[from] nostr::key::Error),
+
+    #[error("JSON (de)serialization error: {0}")]
+    FromSerdeJson(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    FromUtils(#[from] crate::utils::Error),
+
+    #[error("Not the user's mute list")]
+    NotUsersList,
+}
+
+/// NIP-51 mute list, replaceable
+pub const MUTE_LIST_KIND: u64 = 10000;
+
+/// User's mute list. Entries other than pubkeys, like hashtags or words
+/// added by other clients, are kept as they are when publishing.
+#[derive(Debug, Clone, Default)]
+pub struct MuteList {
+    pub muted: Vec<DbMutedPubkey>,
+    other_public: Vec<Tag>,
+    other_private: Vec<Vec<String>>,
+}
+impl MuteList {
+    /// Reads the public tags and the NIP-04 encrypted content, which holds
+    /// the private entries as a JSON array of tags
+    pub fn from_event(keys: &Keys, ns_event: &nostr::Event) -> Result<Self, Error> {
+        if ns_event.pubkey != keys.public_key() {
+            return Err(Error::NotUsersList);
+        }
+        let created_at = ns_event_to_naive(ns_event.created_at)?;
+        let mut list = Self::default();
+
+        for tag in &ns_event.tags {
+            match tag {
+                Tag::PubKey(pubkey, _) => list
+                    .muted
+                    .push(DbMutedPubkey::new(pubkey, false, created_at)),
+                other => list.other_public.push(other.to_owned()),
+            }
+        }
+
+        if !ns_event.content.is_empty() {
+            let content =
+                nip04::decrypt(&keys.secret_key()?, &keys.public_key(), &ns_event.content)?;
+            let private: Vec<Vec<String>> = serde_json::from_str(&content)?;
+            for entry in private {
+                match private_pubkey(&entry) {
+                    Some(pubkey) => list
+                        .muted
+                        .push(DbMutedPubkey::new(&pubkey, true, created_at)),
+                    None => list.other_private.push(entry),
+                }
+            }
+        }
+
+        Ok(list)
+    }
+
+    /// Same list with the current muted pubkeys
+    pub fn with_muted(self, muted: Vec<DbMutedPubkey>) -> Self {
+        Self { muted, ..self }
+    }
+
+    pub fn to_builder(&self, keys: &Keys) -> Result<EventBuilder, Error> {
+        let mut tags = self.other_public.clone();
+        let mut private = self.other_private.clone();
+        for entry in &self.muted {
+            if entry.private {
+                private.push(vec!["p".to_owned(), entry.pubkey.to_string()]);
+            } else {
+                tags.push(Tag::PubKey(entry.pubkey, None));
+            }
+        }
+
+        let content = if private.is_empty() {
+            String::new()
+        } else {
+            let json = serde_json::to_string(&private)?;
+            nip04::encrypt(&keys.secret_key()?, &keys.public_key(), json)?
+        };
+        Ok(EventBuilder::new(
+            Kind::from(MUTE_LIST_KIND),
+            content,
+            &tags,
+        ))
+    }
+}
+
+fn private_pubkey(entry: &[String]) -> Option<XOnlyPublicKey> {
+    match entry {
+        [tag_name, pubkey, ..] if tag_name == "p" => XOnlyPublicKey::from_str(pubkey).ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use nostr::secp256k1::XOnlyPublicKey;
+use reqwest::header::ACCEPT;
+use serde::Deserialize;
+use thiserror::Error;
+use url::Url;
+
+use super::RateLimiter;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid NIP-05 identifier: \"{0}\"")]
+    InvalidIdentifier(String),
+
+    #[error("Invalid URL: \"{0}\"")]
+    InvalidUrl(#[from] url::ParseError),
+
+    #[error("Request error: {0}")]
+    FromReqwest(#[from] reqwest::Error),
+}
+
+#[derive(Debug, Deserialize)]
+struct WellKnown {
+    #[serde(default)]
+    names: HashMap<String, String>,
+}
+
+/// Checks that the domain of a [NIP-05](https://github.com/nostr-protocol/nips/blob/master/05.md)
+/// identifier maps its name to `pubkey`
+pub async fn verify_nip05(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    pubkey: &XOnlyPublicKey,
+    nip05: &str,
+) -> Result<bool, Error> {
+    let (name, url) = well_known_url(nip05)?;
+
+    limiter.wait().await;
+    let well_known: WellKnown = client
+        .get(url)
+        .header(ACCEPT, "application/json")
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(well_known
+        .names
+        .get(&name)
+        .map_or(false, |hex| hex.eq_ignore_ascii_case(&pubkey.to_string())))
+}
+
+/// Name to look up and the domain's `nostr.json` URL. A bare domain is the `_` name.
+fn well_known_url(nip05: &str) -> Result<(String, Url), Error> {
+    let invalid = || Error::InvalidIdentifier(nip05.to_owned());
+    let nip05 = nip05.trim().to_lowercase();
+    let (name, domain) = nip05.split_once('@').unwrap_or(("_", nip05.as_str()));
+    if name.is_empty() || domain.is_empty() || domain.contains('/') {
+        return Err(invalid());
+    }
+    let mut url = Url::parse(&format!("https://{}/.well-known/nostr.json", domain))?;
+    url.query_pairs_mut().append_pair("name", name);
+    Ok((name.to_owned(), url))
+}
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// At most two domains per second
+pub const NIP05_INTERVAL: Duration = Duration::from_millis(500);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_well_known_url() {
+        let (name, url) = well_known_url("Bob@Example.com").unwrap();
+        assert_eq!(name, "bob");
+        assert_eq!(
+            url.as_str(),
+            "https://example.com/.well-known/nostr.json?name=bob"
+        );
+
+        let (name, url) = well_known_url("example.com").unwrap();
+        assert_eq!(name, "_");
+        assert_eq!(url.host_str(), Some("example.com"));
+    }
+
+    #[test]
+    fn test_well_known_url_invalid() {
+        assert!(well_known_url("bob@").is_err());
+        assert!(well_known_url("@example.com").is_err());
+        assert!(well_known_url("bob@example.com/path").is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::{Local, Utc};
+use nostr::prelude::ToBech32;
+use nostr::{Keys, Tag, Timestamp};
+use sqlx::SqlitePool;
+use thiserror::Error;
+
+use crate::db::{ChatNotification, DoNotDisturb, NotificationChat};
+use crate::utils::add_ellipsis_trunc;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    FromDbNotification(#[from] crate::db::notification::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub chat: NotificationChat,
+    pub title: String,
+    pub body: String,
+}
+
+/// Where the notifications are shown
+pub trait NotificationSink: Send + Sync {
+    fn notify(&self, notification: &Notification) -> Result<(), Error>;
+}
+
+/// Desktop notifications, through D-Bus on freedesktop systems
+pub struct DbusSink;
+impl NotificationSink for DbusSink {
+    fn notify(&self, notification: &Notification) -> Result<(), Error> {
+        let mut desktop = notify_rust::Notification::new();
+        desktop
+            .appname(APP_NAME)
+            .summary(&notification.title)
+            .body(&notification.body);
+        // show() blocks on D-Bus, keep it off the backend task
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = desktop.show() {
+                tracing::error!("Desktop notification error: {}", e);
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Keeps the notifications instead of showing them
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    notifications: Mutex<Vec<Notification>>,
+}
+impl MemorySink {
+    pub fn notifications(&self) -> Vec<Notification> {
+        self.notifications
+            .lock()
+            .map(|notifications| notifications.clone())
+            .unwrap_or_default()
+    }
+}
+impl NotificationSink for MemorySink {
+    fn notify(&self, notification: &Notification) -> Result<(), Error> {
+        if let Ok(mut notifications) = self.notifications.lock() {
+            notifications.push(notification.to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Decides which received messages become notifications
+pub struct NotificationEngine {
+    sink: Arc<dyn NotificationSink>,
+    window_focused: bool,
+}
+impl NotificationEngine {
+    pub fn new(sink: Arc<dyn NotificationSink>) -> Self {
+        Self {
+            sink,
+            window_focused: true,
+        }
+    }
+
+    pub fn set_sink(&mut self, sink: Arc<dyn NotificationSink>) {
+        self.sink = sink;
+    }
+
+    pub fn set_window_focused(&mut self, focused: bool) {
+        self.window_focused = focused;
+    }
+
+    pub fn window_focused(&self) -> bool {
+        self.window_focused
+    }
+
+    /// Nothing is shown while the window is focused, for the user's own
+    /// messages and for old messages arriving with the relays' history.
+    /// Otherwise the chat rules and the do-not-disturb schedule decide.
+    pub async fn message_received(
+        &self,
+        pool: &SqlitePool,
+        keys: &Keys,
+        ns_event: &nostr::Event,
+        notification: Notification,
+    ) -> Result<(), Error> {
+        if self.window_focused || ns_event.pubkey == keys.public_key() {
+            return Ok(());
+        }
+        if Timestamp::now().as_i64() - ns_event.created_at.as_i64() > MAX_MESSAGE_AGE_SECS {
+            return Ok(());
+        }
+
+        let rules = ChatNotification::fetch(pool, &notification.chat).await?;
+        if !rules.allows(Utc::now().naive_utc(), is_mentioned(keys, ns_event)) {
+            return Ok(());
+        }
+        let dnd = DoNotDisturb::fetch(pool).await?;
+        if dnd.is_active(Local::now().time()) {
+            return Ok(());
+        }
+
+        let notification = Notification {
+            body: add_ellipsis_trunc(&notification.body, MAX_BODY_LENGTH),
+            ..notification
+        };
+        if let Err(e) = self.sink.notify(&notification) {
+            tracing::error!("Failed to show notification: {}", e);
+        }
+        Ok(())
+    }
+}
+
+/// Tagged with the user's public key or with the npub in the content
+fn is_mentioned(keys: &Keys, ns_event: &nostr::Event) -> bool {
+    let pubkey = keys.public_key();
+    let tagged = ns_event.tags.iter().any(|tag| match tag {
+        Tag::PubKey(tag_pubkey, _) => tag_pubkey == &pubkey,
+        _ => false,
+    });
+    tagged
+        || pubkey
+            .to_bech32()
+            .map(|npub| ns_event.content.contains(&npub))
+            .unwrap_or(false)
+}
+
+const APP_NAME: &str = "NostrTalk";
+const MAX_MESSAGE_AGE_SECS: i64 = 10 * 60;
+const MAX_BODY_LENGTH: usize = 200;
@@ -0,0 +1,108 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use nostr::{EventId, Tag, UnsignedEvent};
+
+/// Highest NIP-13 difficulty set by the user or asked by a relay
+pub const MAX_POW_DIFFICULTY: u8 = 40;
+/// Raise used when a relay asks for more work without saying how much
+const POW_RAISE_STEP: u8 = 4;
+/// Nonces tried between progress reports and cancel checks
+const PROGRESS_INTERVAL: u128 = 50_000;
+
+#[derive(Debug, Clone)]
+pub struct MiningProgress {
+    /// Id of the event before mining, which the pending event keeps meanwhile
+    pub pending_hash: EventId,
+    pub target: u8,
+    /// Most leading zero bits found so far
+    pub best: u8,
+    pub attempts: u64,
+}
+
+pub fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        if *byte == 0 {
+            bits += 8;
+        } else {
+            bits += byte.leading_zeros();
+            break;
+        }
+    }
+    bits
+}
+
+/// NIP-13 difficulty of an event, the leading zero bits of its id.
+/// Ids from relays can be anything, an all-zero id counts as 255.
+pub fn event_difficulty(event_hash: &EventId) -> u8 {
+    u8::try_from(leading_zero_bits(event_hash.as_bytes())).unwrap_or(u8::MAX)
+}
+
+/// Adds a `nonce` tag until the event id has `target` leading zero bits.
+/// Returns None when `cancel` is set before that.
+pub fn mine(
+    mut unsigned: UnsignedEvent,
+    target: u8,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(MiningProgress),
+) -> Option<UnsignedEvent> {
+    let pending_hash = unsigned.id;
+    let base_tags: Vec<Tag> = unsigned
+        .tags
+        .iter()
+        .filter(|tag| !matches!(tag, Tag::POW { .. }))
+        .cloned()
+        .collect();
+    let mut best = 0;
+    let mut nonce: u128 = 0;
+
+    loop {
+        let mut tags = base_tags.clone();
+        tags.push(Tag::POW {
+            nonce,
+            difficulty: target,
+        });
+        let event_hash = EventId::new(
+            &unsigned.pubkey,
+            unsigned.created_at,
+            &unsigned.kind,
+            &tags,
+            &unsigned.content,
+        );
+        let difficulty = event_difficulty(&event_hash);
+        if difficulty >= target {
+            unsigned.tags = tags;
+            unsigned.id = event_hash;
+            return Some(unsigned);
+        }
+        best = best.max(difficulty);
+
+        nonce += 1;
+        if nonce % PROGRESS_INTERVAL == 0 {
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            on_progress(MiningProgress {
+                pending_hash,
+                target,
+                best,
+                attempts: nonce as u64,
+            });
+        }
+    }
+}
+
+/// New difficulty for a relay that rejected an event with a `pow:` message,
+/// like "pow: difficulty 26 is less than 30". None for other rejections.
+pub fn raised_difficulty(message: &str, current: u8) -> Option<u8> {
+    let reason = message.strip_prefix("pow:")?;
+    let asked = reason
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|number| number.parse::<u8>().ok())
+        .max();
+    let raised = match asked {
+        Some(asked) if asked > current => asked,
+        _ => current.saturating_add(POW_RAISE_STEP),
+    };
+    Some(raised.min(MAX_POW_DIFFICULTY))
+}
@@ -1,25 +1,24 @@
 use base64::engine::general_purpose;
-use base64::{alphabet, engine, Engine};
+use base64::Engine;
 use chrono::{DateTime, Utc};
 use directories::ProjectDirs;
-use futures::TryStreamExt;
 use futures_util::StreamExt;
-use image::io::Reader;
 use image::{DynamicImage, ImageFormat};
+use nostr::hashes::{sha256, Hash};
 use nostr::{EventId, Url};
+use reqwest::header::{HeaderMap, HeaderName};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
 use std::env;
-use std::io::Cursor;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
-use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
 
 use crate::consts::{
     APP_PROJECT_DIRS, MEDIUM_PROFILE_IMG_HEIGHT, MEDIUM_PROFILE_IMG_WIDTH,
     SMALL_PROFILE_IMG_HEIGHT, SMALL_PROFILE_IMG_WIDTH,
 };
-use crate::db::ImageDownloaded;
+use crate::db::{ImageBlob, ImageDownloaded, ImageUrl};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -62,6 +61,9 @@ pub enum Error {
     #[error("Invalid image kind")]
     InvalidImageKind,
 
+    #[error("Image is bigger than {0} bytes")]
+    ImageTooBig(u64),
+
     #[error("Invalid base64 encoded image")]
     InvalidBase64,
 
@@ -70,6 +72,9 @@ pub enum Error {
 
     #[error("Invalid image type: {0}")]
     InvalidImageType(String),
+
+    #[error("{0}")]
+    FromImageBlob(#[from] crate::db::image_blob::Error),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -105,6 +110,8 @@ pub enum ImageKind {
     Profile,
     Banner,
     Channel,
+    /// Image linked in a chat message
+    Media,
 }
 impl ImageKind {
     pub fn as_str(&self) -> &str {
@@ -112,6 +119,7 @@ impl ImageKind {
             ImageKind::Profile => "profile_1",
             ImageKind::Banner => "banner_1",
             ImageKind::Channel => "channel_1",
+            ImageKind::Media => "media_1",
         }
     }
     pub fn as_i32(&self) -> i32 {
@@ -119,6 +127,7 @@ impl ImageKind {
             ImageKind::Profile => 1,
             ImageKind::Banner => 2,
             ImageKind::Channel => 3,
+            ImageKind::Media => 4,
         }
     }
     pub fn from_i32(i: i32) -> Result<ImageKind, Error> {
@@ -126,6 +135,7 @@ impl ImageKind {
             1 => Ok(ImageKind::Profile),
             2 => Ok(ImageKind::Banner),
             3 => Ok(ImageKind::Channel),
+            4 => Ok(ImageKind::Media),
             _ => Err(Error::InvalidImageKind),
         }
     }
@@ -135,67 +145,104 @@ pub fn image_filename(kind: ImageKind, size: ImageSize, image_type: &str) -> Str
     format!("{}_{}.{}", kind.as_str(), size.as_str(), image_type)
 }
 
-pub async fn download_image(
-    image_url: &str,
-    event_hash: &EventId,
-    identifier: &str,
-    kind: ImageKind,
-) -> Result<ImageDownloaded, Error> {
-    if image_url.starts_with("data:image/") {
-        parse_base64(image_url, event_hash, identifier, kind).await
-    } else {
-        download_image_url(image_url, event_hash, identifier, kind).await
+/// File name of the original image and its resized copies inside a blob directory
+pub fn blob_filename(size: ImageSize, image_type: &str) -> String {
+    match size {
+        ImageSize::Original => format!("original.{}", image_type),
+        _ => format!("{}.{}", size.as_str(), image_type),
     }
 }
 
-async fn get_dirs(identifier: &str) -> Result<PathBuf, Error> {
+/// Media has no event of its own, so its cache entry is keyed by the URL hash
+pub fn media_event_hash(media_url: &str) -> EventId {
+    EventId::from_hash(sha256::Hash::hash(media_url.as_bytes()))
+}
+
+/// Directory where image blobs are stored
+pub fn blobs_dir() -> Result<PathBuf, Error> {
     let dirs = ProjectDirs::from(APP_PROJECT_DIRS.0, APP_PROJECT_DIRS.1, APP_PROJECT_DIRS.2)
         .ok_or(Error::NotFoundProjectDirectory)?;
-    let images_dir = dirs.cache_dir().join(IMAGES_FOLDER_NAME).join(identifier);
-    tokio::fs::create_dir_all(&images_dir).await?;
-    Ok(images_dir)
+    Ok(dirs
+        .cache_dir()
+        .join(IMAGES_FOLDER_NAME)
+        .join(BLOBS_FOLDER_NAME))
 }
 
-async fn parse_base64(
+/// Returns the cached image for `image_url`, only using the network when
+/// the cached copy is stale or missing.
+pub async fn download_image(
+    client: &reqwest::Client,
+    cache_pool: &SqlitePool,
+    blobs_dir: &Path,
     image_url: &str,
     event_hash: &EventId,
-    identifier: &str,
     kind: ImageKind,
 ) -> Result<ImageDownloaded, Error> {
-    let image_type = image_type_from_base64(image_url).ok_or(Error::InvalidBase64)?;
-    let decoded = engine::GeneralPurpose::new(&alphabet::URL_SAFE, general_purpose::NO_PAD)
-        .decode(image_url)?;
-
-    let images_dir = get_dirs(identifier).await?;
-    let original_path = images_dir.join(image_filename(kind, ImageSize::Original, image_type));
-    let image_format = ImageFormat::from_extension(image_type)
-        .ok_or(Error::InvalidImageType(image_type.to_owned()))?;
-
-    let cursor = Cursor::new(decoded);
-    let mut reader = Reader::new(cursor);
-    reader.set_format(image_format);
-
-    let image = reader.decode()?;
-
-    image.save_with_format(&original_path, image_format)?;
-    save_dynamic_image(&images_dir, &image, kind, ImageSize::Medium)?;
-    save_dynamic_image(&images_dir, &image, kind, ImageSize::Small)?;
+    let blob = if image_url.starts_with("data:image/") {
+        parse_base64(cache_pool, blobs_dir, image_url).await?
+    } else {
+        fetch_image_url(client, cache_pool, blobs_dir, image_url).await?
+    };
 
     Ok(ImageDownloaded {
         kind,
-        path: original_path,
+        path: blob.path,
         event_hash: event_hash.to_owned(),
+        content_hash: Some(blob.content_hash),
     })
 }
 
-async fn download_image_url(
+async fn parse_base64(
+    cache_pool: &SqlitePool,
+    blobs_dir: &Path,
     image_url: &str,
-    event_hash: &EventId,
-    identifier: &str,
-    kind: ImageKind,
-) -> Result<ImageDownloaded, Error> {
-    let image_url = Url::parse(image_url)?;
-    let response = reqwest::get(image_url.clone()).await?;
+) -> Result<ImageBlob, Error> {
+    let image_type = image_type_from_base64(image_url).ok_or(Error::InvalidBase64)?;
+    let (_, encoded) = image_url.split_once(',').ok_or(Error::InvalidBase64)?;
+    let decoded = general_purpose::STANDARD.decode(encoded)?;
+
+    store_blob(cache_pool, blobs_dir, &decoded, image_type).await
+}
+
+async fn fetch_image_url(
+    client: &reqwest::Client,
+    cache_pool: &SqlitePool,
+    blobs_dir: &Path,
+    image_url: &str,
+) -> Result<ImageBlob, Error> {
+    let now = Utc::now().naive_utc();
+
+    let mut cached = None;
+    if let Some(entry) = ImageUrl::fetch(cache_pool, image_url).await? {
+        if let Some(blob) = ImageBlob::fetch(cache_pool, &entry.content_hash).await? {
+            if blob.path.exists() {
+                if entry.is_fresh(now) {
+                    return Ok(blob);
+                }
+                cached = Some((entry, blob));
+            }
+        }
+    }
+
+    let mut request = client.get(Url::parse(image_url)?);
+    if let Some((entry, _)) = &cached {
+        if let Some(etag) = &entry.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request.send().await?;
+    let max_age_secs = max_age_secs(response.headers());
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some((_, blob)) = cached {
+            ImageUrl::revalidated(cache_pool, image_url, now, max_age_secs).await?;
+            return Ok(blob);
+        }
+    }
+    let response = response.error_for_status()?;
 
     let content_type = response
         .headers()
@@ -203,50 +250,97 @@ async fn download_image_url(
         .ok_or(Error::RequestMissingContentType)
         .cloned()?;
 
-    //TODO: builder error for url when BASE64
-
     if !content_type.to_str()?.starts_with("image/") {
         return Err(Error::ImageInvalidContentType(
             content_type.to_str()?.to_owned(),
         ));
     }
 
-    let images_dir = get_dirs(identifier).await?;
-
     // Extract the image type from the content type.
     let content_type_str = content_type.to_str()?;
     let image_type = content_type_str
         .split('/')
         .nth(1)
         .ok_or(Error::ImageInvalidContentType(content_type_str.to_owned()))?;
-    let original_path = images_dir.join(image_filename(kind, ImageSize::Original, image_type));
-    let mut dest = File::create(&original_path).await?;
-    let mut stream = response.bytes_stream().map_err(Error::ReqwestStream);
-    while let Some(item) = stream.next().await {
-        let chunk = item?;
-        dest.write_all(&chunk).await?;
+
+    let etag = header_string(response.headers(), reqwest::header::ETAG);
+    let last_modified = header_string(response.headers(), reqwest::header::LAST_MODIFIED);
+    let bytes = read_image_body(response).await?;
+
+    let blob = store_blob(cache_pool, blobs_dir, &bytes, image_type).await?;
+
+    ImageUrl::upsert(
+        cache_pool,
+        &ImageUrl {
+            url: image_url.to_owned(),
+            content_hash: blob.content_hash.clone(),
+            etag,
+            last_modified,
+            fetched_at: now,
+            max_age_secs,
+        },
+    )
+    .await?;
+
+    Ok(blob)
+}
+
+/// Reads the image, giving up as soon as it goes over `MAX_IMAGE_BYTES`
+/// so a server can't make the whole body sit in memory
+async fn read_image_body(response: reqwest::Response) -> Result<Vec<u8>, Error> {
+    let declared = response.content_length().unwrap_or_default();
+    if declared > MAX_IMAGE_BYTES {
+        return Err(Error::ImageTooBig(MAX_IMAGE_BYTES));
+    }
+
+    let mut bytes = Vec::with_capacity(declared as usize);
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(Error::ReqwestStream)?;
+        if (bytes.len() + chunk.len()) as u64 > MAX_IMAGE_BYTES {
+            return Err(Error::ImageTooBig(MAX_IMAGE_BYTES));
+        }
+        bytes.extend_from_slice(&chunk);
     }
+    Ok(bytes)
+}
 
-    resize_and_save_image(&original_path, &images_dir, kind, ImageSize::Medium)?;
-    resize_and_save_image(&original_path, &images_dir, kind, ImageSize::Small)?;
+/// Saves the image and its resized copies, unless the same content is already stored
+async fn store_blob(
+    cache_pool: &SqlitePool,
+    blobs_dir: &Path,
+    bytes: &[u8],
+    image_type: &str,
+) -> Result<ImageBlob, Error> {
+    let content_hash = ImageBlob::content_hash(bytes);
+    if let Some(blob) = ImageBlob::fetch(cache_pool, &content_hash).await? {
+        if blob.path.exists() {
+            tracing::debug!("Image already stored: {}", &content_hash);
+            return Ok(blob);
+        }
+    }
 
-    Ok(ImageDownloaded {
-        kind,
+    let blob_dir = blobs_dir.join(&content_hash);
+    tokio::fs::create_dir_all(&blob_dir).await?;
+
+    let image = image::load_from_memory(bytes)?;
+    let original_path = blob_dir.join(blob_filename(ImageSize::Original, image_type));
+    tokio::fs::write(&original_path, bytes).await?;
+    save_resized_image(&blob_dir, &image, ImageSize::Medium)?;
+    save_resized_image(&blob_dir, &image, ImageSize::Small)?;
+
+    let blob = ImageBlob {
+        content_hash,
         path: original_path,
-        event_hash: event_hash.to_owned(),
-    })
+    };
+    ImageBlob::insert(cache_pool, &blob).await?;
+
+    Ok(blob)
 }
 
-pub fn save_dynamic_image(
-    images_dir: &Path,
-    image: &DynamicImage,
-    kind: ImageKind,
-    size: ImageSize,
-) -> Result<(), Error> {
-    let output_filename = image_filename(kind, size, "png");
-    tracing::debug!("file: {}", &output_filename);
-    tracing::debug!("resizing: {} - size: {}", kind.as_str(), size.as_str());
-    let output_path = images_dir.join(output_filename);
+fn save_resized_image(blob_dir: &Path, image: &DynamicImage, size: ImageSize) -> Result<(), Error> {
+    let output_path = blob_dir.join(blob_filename(size, "png"));
+    tracing::debug!("resizing: {:?} - size: {}", &output_path, size.as_str());
     let (width, height) = size
         .get_width_height()
         .ok_or(Error::InvalidImageSize(size))?;
@@ -258,26 +352,32 @@ pub fn save_dynamic_image(
     Ok(())
 }
 
-pub fn resize_and_save_image(
-    original_path: &Path,
-    images_dir: &Path,
-    kind: ImageKind,
-    size: ImageSize,
-) -> Result<(), Error> {
-    let output_filename = image_filename(kind, size, "png");
-    tracing::debug!("file: {}", &output_filename);
-    tracing::debug!("resizing: {} - size: {}", kind.as_str(), size.as_str());
-    let output_path = images_dir.join(output_filename);
-    let image = image::open(original_path)?;
-    let (width, height) = size
-        .get_width_height()
-        .ok_or(Error::InvalidImageSize(size))?;
-    let resized_image = image.resize(width, height, image::imageops::FilterType::Lanczos3);
-
-    // Save the resized image as a PNG, regardless of the input format
-    resized_image.save_with_format(output_path, ImageFormat::Png)?;
+fn header_string(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
 
-    Ok(())
+/// How long a response can be used without revalidation, from its Cache-Control header
+fn max_age_secs(headers: &HeaderMap) -> i64 {
+    let Some(cache_control) = header_string(headers, reqwest::header::CACHE_CONTROL) else {
+        return DEFAULT_IMAGE_MAX_AGE_SECS;
+    };
+
+    let mut max_age = DEFAULT_IMAGE_MAX_AGE_SECS;
+    for directive in cache_control.split(',').map(str::trim) {
+        if directive.eq_ignore_ascii_case("no-cache") || directive.eq_ignore_ascii_case("no-store")
+        {
+            return 0;
+        }
+        if let Some(value) = directive.strip_prefix("max-age=") {
+            if let Ok(secs) = value.trim_matches('"').parse() {
+                max_age = secs;
+            }
+        }
+    }
+    max_age
 }
 
 #[derive(Deserialize, Debug)]
@@ -315,7 +415,11 @@ pub async fn fetch_latest_version(client: reqwest::Client) -> Result<String, Err
     Ok(first_release.tag_name.clone())
 }
 
+/// Largest image downloaded, avatars and banners are far smaller
+pub const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
 const IMAGES_FOLDER_NAME: &str = "images";
+const BLOBS_FOLDER_NAME: &str = "blobs";
+const DEFAULT_IMAGE_MAX_AGE_SECS: i64 = 24 * 60 * 60;
 
 fn image_type_from_base64(s: &str) -> Option<&str> {
     let parts: Vec<&str> = s.split(';').collect();
@@ -0,0 +1,238 @@
+use std::str::FromStr;
+
+use nostr::nips::nip04;
+use nostr::secp256k1::{SecretKey, XOnlyPublicKey};
+use nostr::{EventBuilder, EventId, Keys, Kind, Tag};
+use serde::Deserialize;
+use thiserror::Error;
+use url::Url;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Not a nostr+walletconnect URI")]
+    InvalidScheme,
+
+    #[error("Invalid wallet connect URI: {0}")]
+    InvalidUri(#[from] url::ParseError),
+
+    #[error("Wallet connect URI without the {0} parameter")]
+    MissingParam(&'static str),
+
+    #[error("Invalid wallet public key: {0}")]
+    InvalidWalletPubkey(String),
+
+    #[error("Invalid wallet connect secret")]
+    InvalidSecret,
+
+    #[error("Wallet relay not in the relay pool: {0}")]
+    RelayNotInPool(Url),
+
+    #[error("JSON (de)serialization error: {0}")]
+    FromSerdeJson(#[from] serde_json::Error),
+
+    #[error("Nostr Sdk Event Builder Error: {0}")]
+    NostrSdkEventBuilder(#[from] nostr::prelude::builder::Error),
+
+    #[error("Encryption error: {0}")]
+    Nip04(#[from] nip04::Error),
+
+    #[error("Response not from the connected wallet: {0}")]
+    UnknownWallet(EventId),
+
+    #[error("Wallet response without the request tag: {0}")]
+    MissingRequestTag(EventId),
+
+    #[error("Unexpected wallet response: {0}")]
+    UnexpectedResponse(String),
+}
+
+/// Connection to a NIP-47 wallet service, read from a
+/// `nostr+walletconnect://<wallet pubkey>?relay=..&secret=..` URI
+#[derive(Clone, PartialEq, Eq)]
+pub struct WalletConnectUri {
+    pub wallet_pubkey: XOnlyPublicKey,
+    pub relay_url: Url,
+    /// Signs the requests, it is not the account key
+    pub secret: SecretKey,
+    pub lud16: Option<String>,
+}
+impl WalletConnectUri {
+    pub fn keys(&self) -> Keys {
+        Keys::new(self.secret)
+    }
+}
+impl FromStr for WalletConnectUri {
+    type Err = Error;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let url = Url::parse(uri.trim())?;
+        if url.scheme() != NWC_SCHEME {
+            return Err(Error::InvalidScheme);
+        }
+        // some wallets leave out the `//`, so the pubkey is the path
+        let wallet_pubkey = url
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .unwrap_or_else(|| url.path().trim_matches('/'));
+        let wallet_pubkey = XOnlyPublicKey::from_str(wallet_pubkey)
+            .map_err(|_| Error::InvalidWalletPubkey(wallet_pubkey.to_owned()))?;
+
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        let relay_url = Url::parse(&param("relay").ok_or(Error::MissingParam("relay"))?)?;
+        let secret = param("secret").ok_or(Error::MissingParam("secret"))?;
+        let secret = SecretKey::from_str(&secret).map_err(|_| Error::InvalidSecret)?;
+
+        Ok(Self {
+            wallet_pubkey,
+            relay_url,
+            secret,
+            lud16: param("lud16"),
+        })
+    }
+}
+impl std::fmt::Display for WalletConnectUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut url = Url::parse(&format!("{}://{}", NWC_SCHEME, self.wallet_pubkey))
+            .map_err(|_| std::fmt::Error)?;
+        url.query_pairs_mut()
+            .append_pair("relay", self.relay_url.as_str())
+            .append_pair("secret", &self.secret.display_secret().to_string());
+        if let Some(lud16) = &self.lud16 {
+            url.query_pairs_mut().append_pair("lud16", lud16);
+        }
+        write!(f, "{}", url)
+    }
+}
+impl std::fmt::Debug for WalletConnectUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WalletConnectUri")
+            .field("wallet_pubkey", &self.wallet_pubkey)
+            .field("relay_url", &self.relay_url)
+            .field("lud16", &self.lud16)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalletRequest {
+    PayInvoice(String),
+    GetBalance,
+}
+impl WalletRequest {
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            WalletRequest::PayInvoice(invoice) => serde_json::json!({
+                "method": "pay_invoice",
+                "params": { "invoice": invoice },
+            }),
+            WalletRequest::GetBalance => serde_json::json!({
+                "method": "get_balance",
+                "params": {},
+            }),
+        }
+    }
+
+    /// Kind 23194 event for the wallet, encrypted and signed with the
+    /// connection secret
+    pub fn to_event(&self, uri: &WalletConnectUri) -> Result<nostr::Event, Error> {
+        let content = nip04::encrypt(&uri.secret, &uri.wallet_pubkey, self.to_json().to_string())?;
+        let tags = [Tag::PubKey(uri.wallet_pubkey, None)];
+        let ns_event = EventBuilder::new(Kind::from(NWC_REQUEST_KIND), content, &tags)
+            .to_event(&uri.keys())?;
+        Ok(ns_event)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalletResult {
+    PayInvoice { preimage: String },
+    GetBalance { balance_msats: u64 },
+}
+
+/// Kind 23195 answer of the wallet to one of the requests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletResponse {
+    pub request_hash: EventId,
+    /// The error message of the wallet when it fails
+    pub result: Result<WalletResult, String>,
+}
+impl WalletResponse {
+    pub fn from_event(uri: &WalletConnectUri, ns_event: &nostr::Event) -> Result<Self, Error> {
+        if ns_event.pubkey != uri.wallet_pubkey {
+            return Err(Error::UnknownWallet(ns_event.id));
+        }
+        let request_hash = ns_event
+            .tags
+            .iter()
+            .find_map(|tag| match tag {
+                Tag::Event(event_hash, _, _) => Some(event_hash.to_owned()),
+                _ => None,
+            })
+            .ok_or(Error::MissingRequestTag(ns_event.id))?;
+
+        let content = nip04::decrypt(&uri.secret, &uri.wallet_pubkey, &ns_event.content)?;
+        let content: ResponseContent = serde_json::from_str(&content)?;
+
+        let result = match (content.error, content.result) {
+            (Some(error), _) => Err(format!("{}: {}", error.code, error.message)),
+            (None, Some(result)) => Ok(WalletResult::parse(&content.result_type, result)?),
+            (None, None) => return Err(Error::UnexpectedResponse(content.result_type)),
+        };
+
+        Ok(Self {
+            request_hash,
+            result,
+        })
+    }
+}
+impl WalletResult {
+    fn parse(result_type: &str, result: serde_json::Value) -> Result<Self, Error> {
+        match result_type {
+            "pay_invoice" => {
+                let result: PayInvoiceResult = serde_json::from_value(result)?;
+                Ok(Self::PayInvoice {
+                    preimage: result.preimage,
+                })
+            }
+            "get_balance" => {
+                let result: GetBalanceResult = serde_json::from_value(result)?;
+                Ok(Self::GetBalance {
+                    balance_msats: result.balance,
+                })
+            }
+            other => Err(Error::UnexpectedResponse(other.to_owned())),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ResponseContent {
+    result_type: String,
+    error: Option<ResponseError>,
+    result: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+    code: String,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct PayInvoiceResult {
+    preimage: String,
+}
+
+#[derive(Deserialize)]
+struct GetBalanceResult {
+    /// In msats
+    balance: u64,
+}
+
+pub const NWC_REQUEST_KIND: u64 = 23194;
+pub const NWC_RESPONSE_KIND: u64 = 23195;
+const NWC_SCHEME: &str = "nostr+walletconnect";
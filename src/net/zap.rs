@@ -0,0 +1,232 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use bech32::{FromBase32, ToBase32, Variant};
+use nostr::secp256k1::XOnlyPublicKey;
+use nostr::{EventBuilder, EventId, Keys, Kind, Metadata, Tag, TagKind};
+use serde::Deserialize;
+use thiserror::Error;
+use url::Url;
+
+use crate::utils::invoice_amount_msats;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+
+    #[error("Request error: {0}")]
+    FromReqwest(#[from] reqwest::Error),
+
+    #[error("JSON (de)serialization error: {0}")]
+    FromSerdeJson(#[from] serde_json::Error),
+
+    #[error("Nostr Sdk Event Builder Error: {0}")]
+    NostrSdkEventBuilder(#[from] nostr::prelude::builder::Error),
+
+    #[error("Invalid LNURL: {0}")]
+    Bech32(#[from] bech32::Error),
+
+    #[error("Profile without a lightning address")]
+    NoLightningAddress,
+
+    #[error("Invalid lightning address: {0}")]
+    InvalidLightningAddress(String),
+
+    #[error("The recipient's wallet doesn't support zaps")]
+    NoZapSupport,
+
+    #[error("Amount must be between {min} and {max} msats")]
+    AmountOutOfRange { min: u64, max: u64 },
+
+    #[error("LNURL server error: {0}")]
+    Lnurl(String),
+
+    #[error("Invoice amount doesn't match the zap")]
+    InvoiceAmountMismatch,
+}
+
+/// Zap about to be requested to the recipient's LNURL server
+#[derive(Debug, Clone)]
+pub struct ZapRequest {
+    pub recipient: XOnlyPublicKey,
+    /// Zapped message, None when zapping the profile
+    pub event_hash: Option<EventId>,
+    pub amount_msats: u64,
+    pub comment: String,
+    /// Where the LNURL server publishes the receipt
+    pub relays: Vec<String>,
+}
+
+/// BOLT11 invoice paying a zap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZapInvoice {
+    pub invoice: String,
+    pub amount_msats: u64,
+    /// `nostrPubkey` of the LNURL server, the only valid signer of the receipt
+    pub nostr_pubkey: XOnlyPublicKey,
+}
+
+/// LNURL-pay parameters served at the recipient's endpoint (LUD-06)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PayParams {
+    callback: String,
+    min_sendable: u64,
+    max_sendable: u64,
+    #[serde(default)]
+    allows_nostr: bool,
+    #[serde(default)]
+    nostr_pubkey: Option<String>,
+}
+
+impl PayParams {
+    fn zap_signer(&self) -> Result<XOnlyPublicKey, Error> {
+        if !self.allows_nostr {
+            return Err(Error::NoZapSupport);
+        }
+        self.nostr_pubkey
+            .as_deref()
+            .and_then(|pubkey| XOnlyPublicKey::from_str(pubkey).ok())
+            .ok_or(Error::NoZapSupport)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CallbackResponse {
+    pr: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LnurlError {
+    status: String,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// LNURL-pay endpoint of the profile, from its lightning address (`lud16`)
+/// or its bech32 LNURL (`lud06`)
+pub fn lnurl_pay_url(metadata: &Metadata) -> Result<Url, Error> {
+    if let Some(address) = metadata.lud16.as_deref().filter(|s| !s.trim().is_empty()) {
+        let (name, domain) = address
+            .trim()
+            .split_once('@')
+            .filter(|(name, domain)| !name.is_empty() && !domain.is_empty())
+            .ok_or_else(|| Error::InvalidLightningAddress(address.to_owned()))?;
+        let host = domain.split(':').next().unwrap_or_default();
+        // onion services and local servers have no certificates
+        let scheme = if host.ends_with(".onion") || host == "localhost" || host == "127.0.0.1" {
+            "http"
+        } else {
+            "https"
+        };
+        return Ok(Url::parse(&format!(
+            "{}://{}/.well-known/lnurlp/{}",
+            scheme, domain, name
+        ))?);
+    }
+    if let Some(lnurl) = metadata.lud06.as_deref().filter(|s| !s.trim().is_empty()) {
+        let (_hrp, data, _variant) = bech32::decode(&lnurl.trim().to_lowercase())?;
+        let bytes = Vec::<u8>::from_base32(&data)?;
+        let url = String::from_utf8(bytes)
+            .map_err(|_| Error::InvalidLightningAddress(lnurl.to_owned()))?;
+        return Ok(Url::parse(&url)?);
+    }
+    Err(Error::NoLightningAddress)
+}
+
+/// Asks the recipient's LNURL server for an invoice paying the zap.
+///
+/// The signed kind 9734 zap request goes along, so once paid the server
+/// publishes a kind 9735 receipt to the zap relays.
+pub async fn fetch_zap_invoice(
+    client: &reqwest::Client,
+    keys: &Keys,
+    metadata: &Metadata,
+    zap: &ZapRequest,
+) -> Result<ZapInvoice, Error> {
+    let pay_url = lnurl_pay_url(metadata)?;
+    let params: PayParams = get_json(client, pay_url.clone()).await?;
+    let nostr_pubkey = params.zap_signer()?;
+    if zap.amount_msats < params.min_sendable || zap.amount_msats > params.max_sendable {
+        return Err(Error::AmountOutOfRange {
+            min: params.min_sendable,
+            max: params.max_sendable,
+        });
+    }
+
+    let lnurl = bech32::encode(
+        LNURL_HRP,
+        pay_url.as_str().as_bytes().to_base32(),
+        Variant::Bech32,
+    )?;
+    let zap_request = zap_request_event(keys, zap, &lnurl)?;
+
+    let mut callback = Url::parse(&params.callback)?;
+    callback
+        .query_pairs_mut()
+        .append_pair("amount", &zap.amount_msats.to_string())
+        .append_pair("nostr", &zap_request.as_json())
+        .append_pair("lnurl", &lnurl);
+    let response: CallbackResponse = get_json(client, callback).await?;
+
+    if invoice_amount_msats(&response.pr) != Some(zap.amount_msats) {
+        return Err(Error::InvoiceAmountMismatch);
+    }
+
+    Ok(ZapInvoice {
+        invoice: response.pr,
+        amount_msats: zap.amount_msats,
+        nostr_pubkey,
+    })
+}
+
+/// Public key the recipient's LNURL server signs zap receipts with
+pub async fn fetch_zap_signer(
+    client: &reqwest::Client,
+    metadata: &Metadata,
+) -> Result<XOnlyPublicKey, Error> {
+    let params: PayParams = get_json(client, lnurl_pay_url(metadata)?).await?;
+    params.zap_signer()
+}
+
+fn zap_request_event(keys: &Keys, zap: &ZapRequest, lnurl: &str) -> Result<nostr::Event, Error> {
+    let mut tags = vec![
+        Tag::Generic(TagKind::Custom("relays".to_owned()), zap.relays.to_owned()),
+        Tag::Generic(
+            TagKind::Custom("amount".to_owned()),
+            vec![zap.amount_msats.to_string()],
+        ),
+        Tag::Generic(TagKind::Custom("lnurl".to_owned()), vec![lnurl.to_owned()]),
+        Tag::PubKey(zap.recipient, None),
+    ];
+    if let Some(event_hash) = zap.event_hash {
+        tags.push(Tag::Event(event_hash, None, None));
+    }
+    Ok(EventBuilder::new(Kind::from(ZAP_REQUEST_KIND), &zap.comment, &tags).to_event(keys)?)
+}
+
+/// LNURL servers answer errors with a `{"status": "ERROR"}` body
+async fn get_json<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    url: Url,
+) -> Result<T, Error> {
+    let body = client
+        .get(url)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await?
+        .text()
+        .await?;
+    if let Ok(error) = serde_json::from_str::<LnurlError>(&body) {
+        if error.status.eq_ignore_ascii_case("ERROR") {
+            return Err(Error::Lnurl(error.reason.unwrap_or_default()));
+        }
+    }
+    Ok(serde_json::from_str(&body)?)
+}
+
+pub const ZAP_REQUEST_KIND: u64 = 9734;
+pub const ZAP_RECEIPT_KIND: u64 = 9735;
+const LNURL_HRP: &str = "lnurl";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
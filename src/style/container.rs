@@ -16,6 +16,7 @@ pub enum Container {
     Frame,
     SentMessage,
     ReceivedMessage,
+    MentionedMessage,
     ChatDateDivider,
     StatusBar,
     TooltipBg,
@@ -96,6 +97,13 @@ impl container::StyleSheet for Theme {
                 border_radius: 10.0,
                 ..def
             },
+            Container::MentionedMessage => container::Appearance {
+                background: self.palette().base.foreground.into(),
+                border_color: self.palette().normal.primary,
+                border_width: 2.0,
+                border_radius: 10.0,
+                ..def
+            },
             Container::ChatDateDivider => container::Appearance {
                 background: self.palette().base.foreground.into(),
                 text_color: self.palette().base.text.into(),
@@ -0,0 +1,191 @@
+use nostr::{Tag, TagKind};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// File shared in a message, described by a NIP-92 `imeta` tag.
+///
+/// Public attachments have their URL in the message content too, so other
+/// clients show it as a link. Encrypted ones only travel inside the payload
+/// of a direct message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub url: String,
+    pub mime_type: Option<String>,
+    /// Hex SHA-256 of the file
+    pub sha256: Option<String>,
+    pub size: Option<u64>,
+    /// Dimensions of images and videos, `<width>x<height>`
+    pub dim: Option<String>,
+    /// The original file name
+    pub alt: Option<String>,
+    /// Key of the file when it was encrypted before the upload
+    #[serde(default)]
+    pub encryption: Option<FileEncryption>,
+}
+
+/// AES-GCM key of an encrypted file, never sent outside an encrypted payload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileEncryption {
+    /// Hex 256 bits key
+    pub key: String,
+    /// Hex 96 bits nonce
+    pub nonce: String,
+    /// Hex SHA-256 of the decrypted file, `sha256` is the one of the upload
+    pub original_sha256: Option<String>,
+}
+impl Attachment {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_owned(),
+            mime_type: None,
+            sha256: None,
+            size: None,
+            dim: None,
+            alt: None,
+            encryption: None,
+        }
+    }
+
+    /// Attachments described in the `imeta` tags of an event
+    pub fn from_tags(tags: &[Tag]) -> Vec<Self> {
+        tags.iter()
+            .filter_map(|tag| {
+                let values = tag.as_vec();
+                match values.split_first() {
+                    Some((name, fields)) if name == IMETA_TAG => Self::from_fields(fields),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Direct message payload with the encrypted attachments after the text,
+    /// one JSON `imeta` tag per line. Their keys can't go in the event tags,
+    /// those are public.
+    pub fn to_dm_payload(content: &str, attachments: &[Self]) -> String {
+        attachments
+            .iter()
+            .fold(content.to_owned(), |payload, attachment| {
+                let tag = serde_json::to_string(&attachment.to_tag().as_vec())
+                    .expect("a list of strings is always valid JSON");
+                format!("{}\n{}", payload, tag)
+            })
+    }
+
+    /// Splits a decrypted direct message payload into its text and attachments
+    pub fn split_dm_payload(payload: &str) -> (&str, Vec<Self>) {
+        let mut content = payload;
+        let mut attachments = vec![];
+        while let Some((rest, line)) = content.rsplit_once('\n') {
+            match Self::from_json_tag(line) {
+                Some(attachment) => attachments.push(attachment),
+                None => break,
+            }
+            content = rest;
+        }
+        attachments.reverse();
+        (content, attachments)
+    }
+
+    fn from_json_tag(line: &str) -> Option<Self> {
+        let values: Vec<String> = serde_json::from_str(line).ok()?;
+        match values.split_first() {
+            Some((name, fields)) if name == IMETA_TAG => Self::from_fields(fields),
+            _ => None,
+        }
+    }
+
+    /// Each field is a `<key> <value>` pair, the `url` is required
+    fn from_fields(fields: &[String]) -> Option<Self> {
+        let mut url = None;
+        let mut algorithm = None;
+        let mut key = None;
+        let mut nonce = None;
+        let mut original_sha256 = None;
+        let mut attachment = Self::new("");
+        for field in fields {
+            let Some((key, value)) = field.split_once(' ') else {
+                continue;
+            };
+            let value = value.trim().to_owned();
+            match key {
+                "url" => url = Some(value),
+                "m" => attachment.mime_type = Some(value),
+                "x" => attachment.sha256 = Some(value),
+                "size" => attachment.size = value.parse().ok(),
+                "dim" => attachment.dim = Some(value),
+                "alt" => attachment.alt = Some(value),
+                "encryption-algorithm" => algorithm = Some(value),
+                "decryption-key" => key = Some(value),
+                "decryption-nonce" => nonce = Some(value),
+                "ox" => original_sha256 = Some(value),
+                _ => (),
+            }
+        }
+        let url = url.filter(|url| Url::parse(url).is_ok())?;
+        if let (Some(key), Some(nonce)) = (key, nonce) {
+            // can't be decrypted, saving it would only give garbage
+            if algorithm.map_or(false, |algorithm| algorithm != ENCRYPTION_ALGORITHM) {
+                return None;
+            }
+            attachment.encryption = Some(FileEncryption {
+                key,
+                nonce,
+                original_sha256,
+            });
+        }
+        Some(Self { url, ..attachment })
+    }
+
+    pub fn to_tag(&self) -> Tag {
+        let mut fields = vec![format!("url {}", self.url)];
+        if let Some(mime_type) = &self.mime_type {
+            fields.push(format!("m {}", mime_type));
+        }
+        if let Some(sha256) = &self.sha256 {
+            fields.push(format!("x {}", sha256));
+        }
+        if let Some(size) = self.size {
+            fields.push(format!("size {}", size));
+        }
+        if let Some(dim) = &self.dim {
+            fields.push(format!("dim {}", dim));
+        }
+        if let Some(alt) = &self.alt {
+            fields.push(format!("alt {}", alt));
+        }
+        if let Some(encryption) = &self.encryption {
+            fields.push(format!("encryption-algorithm {}", ENCRYPTION_ALGORITHM));
+            fields.push(format!("decryption-key {}", encryption.key));
+            fields.push(format!("decryption-nonce {}", encryption.nonce));
+            if let Some(original_sha256) = &encryption.original_sha256 {
+                fields.push(format!("ox {}", original_sha256));
+            }
+        }
+        Tag::Generic(TagKind::Custom(IMETA_TAG.to_owned()), fields)
+    }
+
+    /// Name shown on the file card and suggested when saving it
+    pub fn file_name(&self) -> String {
+        self.alt
+            .clone()
+            .or_else(|| {
+                Url::parse(&self.url)
+                    .ok()?
+                    .path_segments()?
+                    .last()
+                    .filter(|segment| !segment.is_empty())
+                    .map(str::to_owned)
+            })
+            .unwrap_or_else(|| "file".to_owned())
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime_type
+            .as_ref()
+            .map_or(false, |mime_type| mime_type.starts_with("image/"))
+    }
+}
+
+const IMETA_TAG: &str = "imeta";
+const ENCRYPTION_ALGORITHM: &str = "aes-gcm";
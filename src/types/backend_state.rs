@@ -1,15 +1,22 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 use chrono::NaiveDateTime;
-use nostr::{Contact, EventBuilder, EventId, Keys, Metadata, Timestamp};
+use nostr::nips::nip04;
+use nostr::{Contact, EventBuilder, EventId, Keys, Kind, Metadata, Tag, Timestamp, UnsignedEvent};
 use ns_client::RelayPool;
 use sqlx::SqlitePool;
 use thiserror::Error;
 use url::Url;
 
 use crate::{
-    db::{Database, DbContact, UserConfig},
-    net::ntp::system_now_microseconds,
+    db::{Database, DbContact, DbRelay, ExpirationTimer, NotificationChat, UserConfig},
+    net::{
+        mine, nip05::NIP05_INTERVAL, ntp::system_now_microseconds, DbusSink, MiningProgress,
+        MuteList, NotificationEngine, RateLimiter, TaskOutput,
+    },
     utils::{
         channel_creation_builder, channel_metadata_builder, channel_msg_builder, naive_to_event_tt,
         ns_event_to_naive, NipData,
@@ -17,7 +24,7 @@ use crate::{
     views::login::BasicProfile,
 };
 
-use super::ChannelMetadata;
+use super::{Attachment, ChannelMetadata, ChannelSearchTerm};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -35,6 +42,24 @@ pub enum Error {
 
     #[error("Invalid timestamp: {0}")]
     InvalidTimestamp(Timestamp),
+
+    #[error("{0}")]
+    FromKeys(#[from] nostr::key::Error),
+
+    #[error("Encryption error: {0}")]
+    Nip04(#[from] nip04::Error),
+
+    #[error("{0}")]
+    FromMuteList(#[from] crate::net::mute_list::Error),
+
+    #[error("{0}")]
+    FromDbRelay(#[from] crate::db::relay::Error),
+
+    #[error("{0}")]
+    FromUserConfig(#[from] crate::db::user_config::Error),
+
+    #[error("{0}")]
+    FromChatExpiration(#[from] crate::db::chat_expiration::Error),
 }
 
 #[derive(Debug, Clone)]
@@ -57,15 +82,34 @@ impl PendingEvent {
     }
 }
 
+pub type TasksSender = tokio::sync::mpsc::Sender<Result<TaskOutput, crate::error::Error>>;
+
+/// Signed event waiting for its proof of work
+struct MiningJob {
+    cancel: Arc<AtomicBool>,
+    unmined: nostr::Event,
+}
+
 pub struct BackendState {
     pub req_client: reqwest::Client,
     pub nostr: RelayPool,
     pub nips_data: Vec<NipData>,
     pub create_account: Option<BasicProfile>,
     pub pending_events: HashMap<EventId, PendingEvent>,
+    /// Shared by the link preview tasks
+    pub link_limiter: Arc<RateLimiter>,
+    /// Shared by the NIP-05 verification tasks
+    pub nip05_limiter: Arc<RateLimiter>,
+    /// Relays whose NIP-11 document arrived, and whether they support NIP-50 search
+    pub search_support: HashMap<Url, bool>,
+    /// Last channel search, results from relays are matched against it
+    pub channel_search: Option<ChannelSearchTerm>,
+    pub notifications: NotificationEngine,
     db_client: Database,
     ntp_offset: Option<i64>,
     ntp_server: Option<String>,
+    /// Events waiting for their proof of work, by the id they have before mining
+    mining: HashMap<EventId, MiningJob>,
 }
 impl BackendState {
     pub fn new(
@@ -82,8 +126,14 @@ impl BackendState {
             nips_data,
             create_account,
             pending_events: HashMap::new(),
+            link_limiter: Arc::new(RateLimiter::default()),
+            nip05_limiter: Arc::new(RateLimiter::new(NIP05_INTERVAL)),
+            search_support: HashMap::new(),
+            channel_search: None,
+            notifications: NotificationEngine::new(Arc::new(DbusSink)),
             ntp_offset: None,
             ntp_server: None,
+            mining: HashMap::new(),
         }
     }
 
@@ -113,119 +163,186 @@ impl BackendState {
         let pool = &self.db_client.pool;
 
         let builder = EventBuilder::auth(challenge, relay_url.to_owned());
-        let ns_event = event_with_time(pool, keys, builder).await?;
-        self.nostr.send_auth(relay_url, ns_event)?;
+        let unsigned = unsigned_with_time(pool, keys, builder, None).await;
+        self.nostr.send_auth(relay_url, sign(unsigned, keys)?)?;
         Ok(())
     }
 
     pub async fn new_profile_event(
         &mut self,
         keys: &Keys,
+        task_tx: &TasksSender,
         metadata: &Metadata,
     ) -> Result<(), Error> {
         tracing::debug!("send_profile");
-        let pool = &self.db_client.pool;
-
         let builder = EventBuilder::set_metadata(metadata.clone());
-        let ns_event = event_with_time(pool, keys, builder).await?;
-        self.nostr.send_event(ns_event.clone())?;
-
-        self.insert_pending(PendingEvent::new(ns_event));
+        self.publish(keys, task_tx, builder).await?;
 
         Ok(())
     }
 
-    pub async fn new_contact_list_event(&mut self, keys: &Keys) -> Result<PendingEvent, Error> {
+    pub async fn new_contact_list_event(
+        &mut self,
+        keys: &Keys,
+        task_tx: &TasksSender,
+    ) -> Result<PendingEvent, Error> {
         tracing::debug!("build_contact_list_event");
-        let pool = &self.db_client.pool;
-        let list = DbContact::fetch_basic(&self.db_client.pool).await?;
+        let list = DbContact::fetch_listed(self.pool()).await?;
         let c_list: Vec<Contact> = list.iter().map(|c| c.into()).collect();
 
         let builder = EventBuilder::set_contact_list(c_list);
-        let ns_event = event_with_time(pool, keys, builder).await?;
-        self.nostr.send_event(ns_event.clone())?;
-
-        let pending_event = PendingEvent::new(ns_event);
-        self.insert_pending(pending_event.clone());
+        self.publish(keys, task_tx, builder).await
+    }
 
-        Ok(pending_event)
+    pub async fn new_mute_list_event(
+        &mut self,
+        keys: &Keys,
+        task_tx: &TasksSender,
+        mute_list: &MuteList,
+    ) -> Result<PendingEvent, Error> {
+        tracing::debug!("build_mute_list_event");
+        let builder = mute_list.to_builder(keys)?;
+        self.publish(keys, task_tx, builder).await
     }
 
     pub async fn new_dm(
         &mut self,
         keys: &Keys,
+        task_tx: &TasksSender,
         db_contact: &DbContact,
         content: &str,
+        attachments: &[Attachment],
     ) -> Result<PendingEvent, Error> {
         tracing::debug!("build_dm");
-        let pool = &self.db_client.pool;
-
-        let builder =
-            EventBuilder::new_encrypted_direct_msg(keys, db_contact.pubkey().to_owned(), content)?;
-        let ns_event = event_with_time(pool, keys, builder).await?;
-        self.nostr.send_event(ns_event.clone())?;
-
-        let pending_event = PendingEvent::new(ns_event);
-        self.insert_pending(pending_event.clone());
-
-        Ok(pending_event)
+        // the attachments carry their keys, so they are encrypted with the text
+        let payload = Attachment::to_dm_payload(content, attachments);
+        let encrypted_content = nip04::encrypt(&keys.secret_key()?, db_contact.pubkey(), payload)?;
+        let tags = [Tag::PubKey(db_contact.pubkey().to_owned(), None)];
+        let builder = EventBuilder::new(Kind::EncryptedDirectMessage, encrypted_content, &tags);
+        let chat = NotificationChat::Dm(db_contact.pubkey().to_owned());
+        let expires_in = ExpirationTimer::fetch(self.pool(), &chat).await?.duration();
+        self.publish_expiring(keys, task_tx, builder, expires_in)
+            .await
     }
 
     pub(crate) async fn new_channel_msg(
         &mut self,
         keys: &Keys,
+        task_tx: &TasksSender,
         channel_id: &EventId,
         recommended_relay: Option<&Url>,
         content: &str,
     ) -> Result<PendingEvent, Error> {
-        let pool = &self.db_client.pool;
         let builder = channel_msg_builder(channel_id, recommended_relay, content);
-
-        let ns_event = event_with_time(pool, keys, builder).await?;
-        self.nostr.send_event(ns_event.clone())?;
-
-        let pending_event = PendingEvent::new(ns_event);
-        self.insert_pending(pending_event.clone());
-
-        Ok(pending_event)
+        let chat = NotificationChat::Channel(channel_id.to_owned());
+        let expires_in = ExpirationTimer::fetch(self.pool(), &chat).await?.duration();
+        self.publish_expiring(keys, task_tx, builder, expires_in)
+            .await
     }
 
     pub(crate) async fn new_channel(
         &mut self,
         keys: &Keys,
+        task_tx: &TasksSender,
         metadata: &ChannelMetadata,
     ) -> Result<PendingEvent, Error> {
-        let pool = &self.db_client.pool;
         let builder = channel_creation_builder(metadata);
-
-        let ns_event = event_with_time(pool, keys, builder).await?;
-        self.nostr.send_event(ns_event.clone())?;
-
-        let pending_event = PendingEvent::new(ns_event);
-        self.insert_pending(pending_event.clone());
-
-        Ok(pending_event)
+        self.publish(keys, task_tx, builder).await
     }
 
     pub(crate) async fn new_channel_metadata(
         &mut self,
         keys: &Keys,
+        task_tx: &TasksSender,
         channel_id: &EventId,
         recommended_relay: Option<&Url>,
         metadata: &ChannelMetadata,
     ) -> Result<PendingEvent, Error> {
-        let pool = &self.db_client.pool;
         let builder = channel_metadata_builder(channel_id, recommended_relay, metadata);
+        self.publish(keys, task_tx, builder).await
+    }
 
-        let ns_event = event_with_time(pool, keys, builder).await?;
-        self.nostr.send_event(ns_event.clone())?;
+    /// Sends the event, or starts mining it first when a proof of work
+    /// difficulty is set. The mining reports to `task_tx`, mined events keep
+    /// the unmined id as pending until `send_mined` is called with the result.
+    async fn publish(
+        &mut self,
+        keys: &Keys,
+        task_tx: &TasksSender,
+        builder: EventBuilder,
+    ) -> Result<PendingEvent, Error> {
+        self.publish_expiring(keys, task_tx, builder, None).await
+    }
+
+    /// Like `publish`, with a NIP-40 expiration `expires_in` after the event's time.
+    ///
+    /// Events go to every write relay as a single event, so they are mined to
+    /// the highest difficulty among the user's setting and those relays.
+    /// Relays asking for less wait for the same mined event.
+    async fn publish_expiring(
+        &mut self,
+        keys: &Keys,
+        task_tx: &TasksSender,
+        builder: EventBuilder,
+        expires_in: Option<Duration>,
+    ) -> Result<PendingEvent, Error> {
+        let pool = &self.db_client.pool;
+        let difficulty = UserConfig::get_pow_difficulty(pool)
+            .await?
+            .max(DbRelay::fetch_max_pow_difficulty(pool).await?);
+        let unsigned = unsigned_with_time(pool, keys, builder, expires_in).await;
+
+        if difficulty == 0 {
+            return self.send_pending(sign(unsigned, keys)?);
+        }
+
+        let pending_event = PendingEvent::new(sign(unsigned.clone(), keys)?);
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.mining.insert(
+            pending_event.event_hash(),
+            MiningJob {
+                cancel: cancel.clone(),
+                unmined: pending_event.ns_event().to_owned(),
+            },
+        );
+        spawn_mining(task_tx.clone(), unsigned, difficulty, cancel);
 
+        Ok(pending_event)
+    }
+
+    fn send_pending(&mut self, ns_event: nostr::Event) -> Result<PendingEvent, Error> {
+        self.nostr.send_event(ns_event.clone())?;
         let pending_event = PendingEvent::new(ns_event);
         self.insert_pending(pending_event.clone());
-
         Ok(pending_event)
     }
 
+    /// Sends a mined event in place of the one pending under `pending_hash`.
+    /// A cancelled mining (`mined` None) sends the event without the proof
+    /// of work. Returns the id the event is sent with.
+    pub fn send_mined(
+        &mut self,
+        keys: &Keys,
+        pending_hash: &EventId,
+        mined: Option<UnsignedEvent>,
+    ) -> Result<Option<EventId>, Error> {
+        let Some(job) = self.mining.remove(pending_hash) else {
+            return Ok(None);
+        };
+        let ns_event = match mined {
+            Some(unsigned) => sign(unsigned, keys)?,
+            None => job.unmined,
+        };
+        let pending_event = self.send_pending(ns_event)?;
+        Ok(Some(pending_event.event_hash()))
+    }
+
+    pub fn cancel_mining(&self, pending_hash: &EventId) {
+        if let Some(job) = self.mining.get(pending_hash) {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
     pub async fn logout(&self) -> Result<(), Error> {
         tracing::info!("Database Logging out");
         self.db_client.pool.close().await;
@@ -243,25 +360,60 @@ impl BackendState {
     }
 }
 
-async fn event_with_time(
+fn spawn_mining(
+    tasks_tx: TasksSender,
+    unsigned: UnsignedEvent,
+    difficulty: u8,
+    cancel: Arc<AtomicBool>,
+) {
+    let pending_hash = unsigned.id;
+    tokio::task::spawn_blocking(move || {
+        let report = |progress: MiningProgress| {
+            // progress is dropped when the channel is busy
+            _ = tasks_tx.try_send(Ok(TaskOutput::MiningProgress(progress)));
+        };
+        report(MiningProgress {
+            pending_hash,
+            target: difficulty,
+            best: 0,
+            attempts: 0,
+        });
+        let mined = mine(unsigned, difficulty, &cancel, report);
+        _ = tasks_tx.blocking_send(Ok(TaskOutput::EventMined {
+            pending_hash,
+            mined,
+        }));
+    });
+}
+
+async fn unsigned_with_time(
     pool: &SqlitePool,
     keys: &Keys,
     builder: EventBuilder,
-) -> Result<nostr::Event, Error> {
-    let mut ns_event = builder.to_unsigned_event(keys.public_key());
+    expires_in: Option<Duration>,
+) -> UnsignedEvent {
+    let mut unsigned = builder.to_unsigned_event(keys.public_key());
     if let Ok(utc_now) = UserConfig::get_corrected_time(pool).await {
-        ns_event.created_at = naive_to_event_tt(utc_now);
+        unsigned.created_at = naive_to_event_tt(utc_now);
+    }
+    if let Some(expires_in) = expires_in {
+        let expires_at = unsigned.created_at.as_i64() as u64 + expires_in.as_secs();
+        unsigned
+            .tags
+            .push(Tag::Expiration(Timestamp::from(expires_at)));
     }
-    let updated_id = EventId::new(
+    unsigned.id = EventId::new(
         &keys.public_key(),
-        ns_event.created_at,
-        &ns_event.kind,
-        &ns_event.tags,
-        &ns_event.content,
+        unsigned.created_at,
+        &unsigned.kind,
+        &unsigned.tags,
+        &unsigned.content,
     );
-    ns_event.id = updated_id;
-    let ns_event = ns_event
+    unsigned
+}
+
+fn sign(unsigned: UnsignedEvent, keys: &Keys) -> Result<nostr::Event, Error> {
+    unsigned
         .sign(keys)
-        .map_err(|e| Error::SigningEvent(e.to_string()))?;
-    Ok(ns_event)
+        .map_err(|e| Error::SigningEvent(e.to_string()))
 }
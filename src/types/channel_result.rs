@@ -49,7 +49,6 @@ impl ChannelResult {
             conn.send(ToBackend::DownloadImage {
                 image_url: image_url.to_owned(),
                 kind: ImageKind::Channel,
-                identifier: self.cache.channel_id.to_string(),
                 event_hash: new_cache.last_event_hash().to_owned(),
             })?;
         } else {
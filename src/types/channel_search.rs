@@ -0,0 +1,161 @@
+use chrono::NaiveDateTime;
+use nostr::prelude::FromBech32;
+use nostr::EventId;
+
+use crate::db::ChannelCache;
+
+/// What was typed in the find channels search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelSearchTerm {
+    Id(EventId),
+    /// Without the leading `#`, lowercase
+    Hashtag(String),
+    /// Lowercase words, all of them must match. No words matches every channel.
+    Text(Vec<String>),
+}
+impl ChannelSearchTerm {
+    pub fn parse(term: &str) -> Self {
+        let term = term.trim();
+        if let Ok(id) = EventId::from_hex(term).or_else(|_| EventId::from_bech32(term)) {
+            return Self::Id(id);
+        }
+        match term.strip_prefix('#') {
+            Some(tag) if !tag.is_empty() && !tag.contains(char::is_whitespace) => {
+                Self::Hashtag(tag.to_lowercase())
+            }
+            _ => Self::Text(term.split_whitespace().map(str::to_lowercase).collect()),
+        }
+    }
+
+    /// How well the channel matches, `None` when it doesn't.
+    /// `hashtags` are the `t` tags of the channel's event, if known.
+    pub fn relevance(&self, cache: &ChannelCache, hashtags: &[String]) -> Option<u32> {
+        let name = cache
+            .metadata
+            .name
+            .as_deref()
+            .unwrap_or_default()
+            .to_lowercase();
+        let about = cache
+            .metadata
+            .about
+            .as_deref()
+            .unwrap_or_default()
+            .to_lowercase();
+        match self {
+            Self::Id(id) => (&cache.channel_id == id).then_some(100),
+            Self::Hashtag(tag) => {
+                if hashtags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                    Some(60)
+                } else if name.contains(tag.as_str()) {
+                    Some(40)
+                } else if about.contains(&format!("#{}", tag)) {
+                    Some(30)
+                } else {
+                    None
+                }
+            }
+            Self::Text(words) if words.is_empty() => Some(0),
+            Self::Text(words) => {
+                let phrase = words.join(" ");
+                if name == phrase {
+                    Some(100)
+                } else if name.starts_with(&phrase) {
+                    Some(60)
+                } else if words.iter().all(|word| name.contains(word.as_str())) {
+                    Some(40)
+                } else if words
+                    .iter()
+                    .all(|word| name.contains(word.as_str()) || about.contains(word.as_str()))
+                {
+                    Some(20)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Text for a [NIP-50](https://github.com/nostr-protocol/nips/blob/master/50.md) `search` filter
+    pub fn search_text(&self) -> Option<String> {
+        match self {
+            Self::Text(words) if !words.is_empty() => Some(words.join(" ")),
+            _ => None,
+        }
+    }
+}
+
+/// Latest change to the channel that is known
+pub fn channel_last_activity(cache: &ChannelCache) -> NaiveDateTime {
+    cache.updated_at.map_or(cache.created_at, |updated_at| {
+        updated_at.max(cache.created_at)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ChannelMetadata;
+    use nostr::Keys;
+
+    const CHANNEL_ID: &str = "8233a5d8e27a9415d22c974d70935011664ada55ae3152bd10d697d3a3c74f67";
+
+    fn cache(name: &str, about: &str) -> ChannelCache {
+        ChannelCache {
+            channel_id: EventId::from_hex(CHANNEL_ID).unwrap(),
+            creator_pubkey: Keys::generate().public_key(),
+            created_at: NaiveDateTime::default(),
+            updated_event_hash: None,
+            updated_at: None,
+            metadata: ChannelMetadata::new().name(name).about(about),
+            image_cache: None,
+            members: vec![],
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let id = EventId::from_hex(CHANNEL_ID).unwrap();
+        assert_eq!(
+            ChannelSearchTerm::parse(CHANNEL_ID),
+            ChannelSearchTerm::Id(id)
+        );
+        assert_eq!(
+            ChannelSearchTerm::parse(" #Nostr "),
+            ChannelSearchTerm::Hashtag("nostr".into())
+        );
+        assert_eq!(
+            ChannelSearchTerm::parse("Rust  Dev"),
+            ChannelSearchTerm::Text(vec!["rust".into(), "dev".into()])
+        );
+        assert_eq!(
+            ChannelSearchTerm::parse(""),
+            ChannelSearchTerm::Text(vec![])
+        );
+    }
+
+    #[test]
+    fn test_text_relevance_order() {
+        let term = ChannelSearchTerm::parse("rust dev");
+        let exact = term.relevance(&cache("Rust Dev", ""), &[]);
+        let prefix = term.relevance(&cache("Rust developers", ""), &[]);
+        let in_name = term.relevance(&cache("dev talk about rust", ""), &[]);
+        let in_about = term.relevance(&cache("Programming", "rust and dev tools"), &[]);
+
+        assert!(exact > prefix && prefix > in_name && in_name > in_about);
+        assert!(in_about.is_some());
+        assert_eq!(term.relevance(&cache("Python", "dev"), &[]), None);
+    }
+
+    #[test]
+    fn test_hashtag_relevance() {
+        let term = ChannelSearchTerm::parse("#bitcoin");
+        assert!(term
+            .relevance(&cache("Chat", ""), &["Bitcoin".into()])
+            .is_some());
+        assert!(term
+            .relevance(&cache("Chat", "all about #bitcoin"), &[])
+            .is_some());
+        assert_eq!(term.relevance(&cache("Chat", "bitcoin"), &[]), None);
+    }
+}
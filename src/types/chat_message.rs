@@ -1,4 +1,7 @@
+use std::path::PathBuf;
+
 use chrono::NaiveDateTime;
+use iced::widget::image::{Handle, Image};
 use iced::widget::{button, column, container, row, text};
 use iced::Point;
 use iced::{alignment, Length};
@@ -6,18 +9,26 @@ use nostr::secp256k1::XOnlyPublicKey;
 use nostr::EventId;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use url::Url;
 
-use crate::components::MouseArea;
-use crate::db::{DbChannelMessage, MessageStatus};
-use crate::icon::{check_icon, double_check_icon, xmark_icon};
-use crate::utils::{from_naive_utc_to_local, hide_string};
+use crate::components::{rich_text, MouseArea};
+use crate::db::{DbChannelMessage, LinkPreview, MessageStatus};
+use crate::icon::{
+    bolt_icon, check_icon, double_check_icon, download_icon, file_icon_regular, xmark_icon,
+};
+use crate::net::ImageSize;
+use crate::utils::{
+    format_file_size, format_sats, from_naive_utc_to_local, hide_string, image_urls, link_urls,
+};
 use crate::widget::{Element, Text};
 use crate::{
     db::{DbContact, DbMessage},
     style,
 };
 
-use super::PendingEvent;
+use super::{
+    Attachment, DownloadState, MediaPreviews, MediaState, PendingEvent, ZapTarget, ZapTotals,
+};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -29,6 +40,12 @@ pub enum Error {
 pub enum Message {
     ChatRightClick(ChatMessage, Point),
     UserNameClick(XOnlyPublicKey),
+    MediaPressed(PathBuf),
+    LoadMediaPressed(String),
+    LinkPressed(String),
+    DownloadAttachmentPressed(Attachment),
+    /// Zap the message, with the name of its author
+    ZapPressed(ZapTarget, String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,12 +54,14 @@ pub enum UserMessage {
         event_hash: EventId,
         content: String,
         display_time: Option<NaiveDateTime>,
+        attachments: Vec<Attachment>,
     },
     Confirmed {
         content: String,
         display_time: NaiveDateTime,
         event_id: i64,
         status: MessageStatus,
+        attachments: Vec<Attachment>,
     },
 }
 
@@ -56,6 +75,7 @@ pub enum ChatMessage {
         display_time: NaiveDateTime,
         event_id: i64,
         status: MessageStatus,
+        attachments: Vec<Attachment>,
     },
 }
 
@@ -76,6 +96,18 @@ impl ChatMessage {
         }
         false
     }
+    /// Follows a pending message sent with another id after mining
+    pub fn replace_pending_hash(&mut self, pending_hash: &EventId, event_hash: &EventId) {
+        if let Self::UserMessage(UserMessage::Pending {
+            event_hash: current,
+            ..
+        }) = self
+        {
+            if current == pending_hash {
+                *current = event_hash.to_owned();
+            }
+        }
+    }
     pub fn event_id(&self) -> Option<i64> {
         match self {
             Self::UserMessage(user) => match user {
@@ -85,11 +117,29 @@ impl ChatMessage {
             Self::ContactMessage { event_id, .. } => Some(*event_id),
         }
     }
+    /// None for the user's messages
+    pub fn author(&self) -> Option<&XOnlyPublicKey> {
+        match self {
+            Self::UserMessage(_) => None,
+            Self::ContactMessage { author, .. } => Some(author),
+        }
+    }
     pub fn pending(pending: PendingEvent, content: &str) -> Self {
         let user_msg = UserMessage::Pending {
             event_hash: pending.event_hash().to_owned(),
             content: content.to_owned(),
             display_time: pending.display_time().ok(),
+            attachments: Attachment::from_tags(&pending.ns_event().tags),
+        };
+        Self::UserMessage(user_msg)
+    }
+    /// The attachments of direct messages are in the encrypted content
+    pub fn pending_dm(pending: PendingEvent, content: &str, attachments: Vec<Attachment>) -> Self {
+        let user_msg = UserMessage::Pending {
+            event_hash: pending.event_hash().to_owned(),
+            content: content.to_owned(),
+            display_time: pending.display_time().ok(),
+            attachments,
         };
         Self::UserMessage(user_msg)
     }
@@ -100,6 +150,7 @@ impl ChatMessage {
             display_time: db_message.created_at.to_owned(),
             event_id: db_message.event_id,
             status: db_message.status,
+            attachments: db_message.attachments.to_owned(),
         };
         Self::UserMessage(user_msg)
     }
@@ -111,6 +162,7 @@ impl ChatMessage {
             display_name: contact.select_name(),
             event_id: db_message.event_id,
             status: db_message.status,
+            attachments: db_message.attachments.to_owned(),
         }
     }
 
@@ -135,8 +187,17 @@ impl ChatMessage {
         }
     }
 
-    fn style(&self) -> style::Container {
+    /// True when a message from someone else mentions the given npub
+    pub fn mentions(&self, npub: &str) -> bool {
+        match self {
+            ChatMessage::ContactMessage { content, .. } => content.contains(npub),
+            ChatMessage::UserMessage(_) => false,
+        }
+    }
+
+    fn style(&self, mentioned: bool) -> style::Container {
         match self {
+            ChatMessage::ContactMessage { .. } if mentioned => style::Container::MentionedMessage,
             ChatMessage::ContactMessage { .. } => style::Container::ReceivedMessage,
             ChatMessage::UserMessage(_) => style::Container::SentMessage,
         }
@@ -202,14 +263,98 @@ impl ChatMessage {
         }
     }
 
-    pub fn view(&self, show_name: bool) -> Element<'_, Message> {
+    pub fn is_attachment(&self, url: &str) -> bool {
+        self.attachments()
+            .iter()
+            .any(|attachment| attachment.url == url)
+    }
+
+    pub fn attachments(&self) -> &[Attachment] {
+        match self {
+            ChatMessage::UserMessage(user) => match user {
+                UserMessage::Pending { attachments, .. } => attachments,
+                UserMessage::Confirmed { attachments, .. } => attachments,
+            },
+            ChatMessage::ContactMessage { attachments, .. } => attachments,
+        }
+    }
+
+    /// Image thumbnails and the cards of the other attached files
+    fn media<'a>(&'a self, media: &'a MediaPreviews) -> Element<'a, Message> {
+        let image_urls = image_urls(self.content());
+        let files = self
+            .attachments()
+            .iter()
+            .filter(|attachment| !image_urls.contains(&attachment.url));
+        let col = files.fold(column![].spacing(5), |col, attachment| {
+            col.push(attachment_card(attachment, media))
+        });
+        image_urls
+            .into_iter()
+            .fold(col, |col, media_url| {
+                col.push(media_preview(media_url, media))
+            })
+            .into()
+    }
+
+    /// Preview of the first link in the message
+    fn link_preview<'a>(&'a self, media: &'a MediaPreviews) -> Element<'a, Message> {
+        let preview = link_urls(self.content())
+            .into_iter()
+            .find(|link_url| !self.is_attachment(link_url))
+            .and_then(|link_url| media.link_preview(&link_url));
+        match preview {
+            Some(preview) => link_preview_card(preview, media),
+            None => text("").into(),
+        }
+    }
+
+    /// Amount zapped to the message, and the button to zap the contact's ones
+    fn zaps<'a>(&'a self, zaps: &ZapTotals) -> Element<'a, Message> {
+        let mut zaps_row = row![].spacing(2).align_items(alignment::Alignment::Center);
+        if let Some(total_msats) = self.event_id().and_then(|event_id| zaps.get(event_id)) {
+            zaps_row = zaps_row.push(
+                text(format_sats(total_msats))
+                    .size(14)
+                    .style(style::Text::Alpha(0.5)),
+            );
+        }
+        if let ChatMessage::ContactMessage {
+            author,
+            display_name,
+            event_id,
+            ..
+        } = self
+        {
+            zaps_row = zaps_row.push(
+                button(bolt_icon().size(14).style(style::Text::Alpha(0.5)))
+                    .padding(0)
+                    .style(style::Button::Invisible)
+                    .on_press(Message::ZapPressed(
+                        ZapTarget::message(*author, *event_id),
+                        display_name.to_owned(),
+                    )),
+            );
+        }
+        zaps_row.into()
+    }
+
+    pub fn view<'a>(
+        &'a self,
+        show_name: bool,
+        mentioned: bool,
+        media: &'a MediaPreviews,
+        zaps: &ZapTotals,
+    ) -> Element<'a, Message> {
         make_chat_view(
             self.alignment(),
-            self.style(),
+            self.style(mentioned),
             self.name(show_name),
-            self.status(),
+            row![self.status(), self.zaps(zaps)].spacing(5),
             self.local_time(),
             self.content(),
+            self.media(media),
+            self.link_preview(media),
             |p| Message::ChatRightClick(self.clone(), p),
         )
     }
@@ -240,6 +385,117 @@ fn make_local_time<'a>(display_time: Option<&NaiveDateTime>) -> Text<'a> {
     }
 }
 
+fn media_preview<'a>(media_url: String, media: &MediaPreviews) -> Element<'a, Message> {
+    match media.get(&media_url) {
+        Some(MediaState::Loaded(image)) => button(
+            Image::new(Handle::from_path(image.sized_image(ImageSize::Medium)))
+                .width(MEDIA_THUMBNAIL_SIZE)
+                .height(MEDIA_THUMBNAIL_SIZE),
+        )
+        .padding(0)
+        .style(style::Button::Invisible)
+        .on_press(Message::MediaPressed(image.path.to_owned()))
+        .into(),
+        Some(MediaState::Loading) => text("Loading image...")
+            .size(14)
+            .style(style::Text::Alpha(0.5))
+            .into(),
+        Some(MediaState::Failed) => button(text("Failed to load image, retry").size(14))
+            .style(style::Button::Bordered)
+            .on_press(Message::LoadMediaPressed(media_url))
+            .into(),
+        Some(MediaState::Hidden) | None => button(text("Load image").size(14))
+            .style(style::Button::Bordered)
+            .on_press(Message::LoadMediaPressed(media_url))
+            .into(),
+    }
+}
+
+fn attachment_card<'a>(attachment: &'a Attachment, media: &MediaPreviews) -> Element<'a, Message> {
+    let info = [
+        attachment.size.map(format_file_size),
+        attachment.mime_type.to_owned(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" - ");
+    let details = column![
+        text(attachment.file_name()).size(16),
+        text(info).size(12).style(style::Text::Alpha(0.5))
+    ]
+    .spacing(2)
+    .width(Length::Fill);
+
+    let download_btn = button(download_icon())
+        .style(style::Button::Invisible)
+        .on_press(Message::DownloadAttachmentPressed(attachment.to_owned()));
+    let status: Element<_> = match media.download_state(&attachment.url) {
+        Some(DownloadState::Downloading) => text("Downloading...")
+            .size(14)
+            .style(style::Text::Alpha(0.5))
+            .into(),
+        Some(DownloadState::Saved(path)) => text(format!("Saved to {}", path.display()))
+            .size(12)
+            .style(style::Text::Alpha(0.5))
+            .into(),
+        Some(DownloadState::Failed) => row![
+            text("Download failed").size(14).style(style::Text::Danger),
+            download_btn
+        ]
+        .align_items(alignment::Alignment::Center)
+        .into(),
+        None => download_btn.into(),
+    };
+
+    container(
+        row![file_icon_regular(), details, status]
+            .spacing(10)
+            .align_items(alignment::Alignment::Center),
+    )
+    .padding(8)
+    .max_width(CHAT_MESSAGE_MAX_WIDTH)
+    .style(style::Container::Frame)
+    .into()
+}
+
+fn link_preview_card<'a>(preview: &'a LinkPreview, media: &MediaPreviews) -> Element<'a, Message> {
+    let mut details = column![].spacing(2);
+    if let Some(title) = &preview.title {
+        details = details.push(text(title).size(16));
+    }
+    if let Some(description) = &preview.description {
+        details = details.push(text(description).size(14).style(style::Text::Alpha(0.7)));
+    }
+    let host = Url::parse(&preview.url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_owned))
+        .unwrap_or_default();
+    details = details.push(text(host).size(12).style(style::Text::Alpha(0.5)));
+
+    let image: Element<_> = match preview.image_url.as_ref().and_then(|url| media.get(url)) {
+        Some(MediaState::Loaded(image)) => {
+            Image::new(Handle::from_path(image.sized_image(ImageSize::Small)))
+                .width(LINK_PREVIEW_IMAGE_SIZE)
+                .height(LINK_PREVIEW_IMAGE_SIZE)
+                .into()
+        }
+        _ => text("").into(),
+    };
+
+    let card = container(row![image, details].spacing(10))
+        .padding(8)
+        .max_width(CHAT_MESSAGE_MAX_WIDTH)
+        .style(style::Container::Frame);
+
+    button(card)
+        .padding(0)
+        .style(style::Button::Invisible)
+        .on_press(Message::LinkPressed(preview.url.to_owned()))
+        .into()
+}
+
+#[allow(clippy::too_many_arguments)]
 fn make_chat_view<'a, F>(
     alignment: alignment::Horizontal,
     container_style: style::Container,
@@ -247,14 +503,16 @@ fn make_chat_view<'a, F>(
     status: impl Into<Element<'a, Message>>,
     local_time: impl Into<Element<'a, Message>>,
     content: &'a str,
+    media: impl Into<Element<'a, Message>>,
+    link_preview: impl Into<Element<'a, Message>>,
     on_right_press: F,
 ) -> Element<'a, Message>
 where
     F: 'a + Fn(Point) -> Message,
 {
-    let content = text(content).size(18);
+    let content = rich_text(content, 18, Message::LinkPressed);
     let status_row = row![local_time.into(), status.into()].spacing(5);
-    let message_container = column![name.into(), content, status_row]
+    let message_container = column![name.into(), content, media.into(), status_row]
         // this works but all the items are aligned to the right
         // and I cant realign them to the left after this
         // .align_items(alignment::Alignment::End)
@@ -266,8 +524,15 @@ where
         .style(container_style);
 
     let mouse_area = MouseArea::new(message_container).on_right_release(on_right_press);
+    let items_alignment = match alignment {
+        alignment::Horizontal::Right => alignment::Alignment::End,
+        _ => alignment::Alignment::Start,
+    };
+    let message_with_preview = column![mouse_area, link_preview.into()]
+        .spacing(2)
+        .align_items(items_alignment);
 
-    container(mouse_area)
+    container(message_with_preview)
         .width(Length::Fill)
         .center_y()
         .align_x(alignment)
@@ -283,6 +548,7 @@ impl From<DbChannelMessage> for ChatMessage {
                 display_time: ch_msg.created_at,
                 event_id: ch_msg.event_id,
                 status: MessageStatus::Delivered,
+                attachments: ch_msg.attachments,
             })
         } else {
             let display_name = hide_string(&ch_msg.display_name(), 6);
@@ -293,9 +559,12 @@ impl From<DbChannelMessage> for ChatMessage {
                 display_name,
                 event_id: ch_msg.event_id,
                 status: MessageStatus::Delivered,
+                attachments: ch_msg.attachments,
             }
         }
     }
 }
 
 const CHAT_MESSAGE_MAX_WIDTH: f32 = 450.0;
+const MEDIA_THUMBNAIL_SIZE: f32 = 200.0;
+const LINK_PREVIEW_IMAGE_SIZE: f32 = 50.0;
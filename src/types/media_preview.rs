@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use nostr::secp256k1::XOnlyPublicKey;
+
+use crate::db::{ImageDownloaded, LinkPreview};
+use crate::error::BackendClosed;
+use crate::net::{BackEndConnection, BackendEvent, ToBackend};
+use crate::utils::{image_urls, link_urls};
+
+use super::{Attachment, ChatMessage};
+
+#[derive(Debug, Clone)]
+pub enum MediaState {
+    /// Waiting for the user to load it
+    Hidden,
+    Loading,
+    Loaded(ImageDownloaded),
+    Failed,
+}
+
+#[derive(Debug, Clone)]
+pub enum DownloadState {
+    Downloading,
+    Saved(PathBuf),
+    Failed,
+}
+
+/// Images, link previews and attachment downloads of the messages of a chat
+#[derive(Debug, Default)]
+pub struct MediaPreviews {
+    media: HashMap<String, MediaState>,
+    /// None while the preview is being fetched
+    links: HashMap<String, Option<LinkPreview>>,
+    downloads: HashMap<String, DownloadState>,
+}
+impl MediaPreviews {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, media_url: &str) -> Option<&MediaState> {
+        self.media.get(media_url)
+    }
+
+    pub fn download_state(&self, attachment_url: &str) -> Option<&DownloadState> {
+        self.downloads.get(attachment_url)
+    }
+
+    /// Preview of the link, if the page had any metadata
+    pub fn link_preview(&self, link_url: &str) -> Option<&LinkPreview> {
+        self.links
+            .get(link_url)
+            .and_then(Option::as_ref)
+            .filter(|preview| !preview.is_empty())
+    }
+
+    /// Downloads the images and link previews of the messages whose author
+    /// can auto-load media, the images of the others are hidden until the
+    /// user loads them.
+    ///
+    /// The user's own messages are always loaded.
+    pub fn add_messages<'a, F>(
+        &mut self,
+        messages: impl IntoIterator<Item = &'a ChatMessage>,
+        can_auto_load: F,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed>
+    where
+        F: Fn(&XOnlyPublicKey) -> bool,
+    {
+        for message in messages {
+            let auto_load = message.author().map_or(true, &can_auto_load);
+            for media_url in image_urls(message.content()) {
+                match self.media.get(&media_url) {
+                    Some(MediaState::Hidden) | None if auto_load => self.load(&media_url, conn)?,
+                    None => {
+                        self.media.insert(media_url, MediaState::Hidden);
+                    }
+                    // already loaded or failed, which is retried by the user
+                    _ => (),
+                }
+            }
+            if auto_load {
+                let first_link = link_urls(message.content())
+                    .into_iter()
+                    .find(|link_url| !message.is_attachment(link_url));
+                if let Some(link_url) = first_link {
+                    self.load_link_preview(link_url, conn)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load(
+        &mut self,
+        media_url: &str,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        if let Some(MediaState::Loading | MediaState::Loaded(_)) = self.media.get(media_url) {
+            return Ok(());
+        }
+        self.media.insert(media_url.to_owned(), MediaState::Loading);
+        conn.send(ToBackend::DownloadMedia(media_url.to_owned()))
+    }
+
+    /// Asks where to save the attachment, then downloads it
+    pub fn download(
+        &mut self,
+        attachment: &Attachment,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        if let Some(DownloadState::Downloading) = self.downloads.get(&attachment.url) {
+            return Ok(());
+        }
+        self.downloads
+            .insert(attachment.url.to_owned(), DownloadState::Downloading);
+        conn.send(ToBackend::DownloadAttachment(attachment.to_owned()))
+    }
+
+    fn load_link_preview(
+        &mut self,
+        link_url: String,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        if self.links.contains_key(&link_url) {
+            return Ok(());
+        }
+        self.links.insert(link_url.clone(), None);
+        conn.send(ToBackend::FetchLinkPreview(link_url))
+    }
+
+    pub fn backend_event(
+        &mut self,
+        event: &BackendEvent,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        match event {
+            BackendEvent::MediaDownloaded { media_url, image } => {
+                if let Some(state) = self.media.get_mut(media_url) {
+                    *state = MediaState::Loaded(image.to_owned());
+                }
+            }
+            BackendEvent::MediaFailed(media_url) => {
+                if let Some(state) = self.media.get_mut(media_url) {
+                    *state = MediaState::Failed;
+                }
+            }
+            BackendEvent::AttachmentSaved { url, path } => {
+                self.downloads
+                    .insert(url.to_owned(), DownloadState::Saved(path.to_owned()));
+            }
+            BackendEvent::AttachmentFailed(url) => {
+                self.downloads.insert(url.to_owned(), DownloadState::Failed);
+            }
+            BackendEvent::AttachmentCanceled(url) => {
+                self.downloads.remove(url);
+            }
+            BackendEvent::GotLinkPreview(preview) => {
+                if let Some(link) = self.links.get_mut(&preview.url) {
+                    *link = Some(preview.to_owned());
+                    // only previewed for messages that can auto-load, so is its image
+                    if let Some(image_url) = &preview.image_url {
+                        self.load(image_url, conn)?;
+                    }
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
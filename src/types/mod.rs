@@ -1,13 +1,21 @@
+mod attachment;
 pub(crate) mod backend_state;
 pub(crate) mod channel_metadata;
 mod channel_result;
+pub(crate) mod channel_search;
 pub(crate) mod chat_message;
 mod event;
+mod media_preview;
 mod subscription_type;
+mod zaps;
 
+pub use attachment::{Attachment, FileEncryption};
 pub use backend_state::{BackendState, PendingEvent};
 pub use channel_metadata::ChannelMetadata;
 pub(crate) use channel_result::ChannelResult;
+pub use channel_search::{channel_last_activity, ChannelSearchTerm};
 pub use chat_message::{ChatMessage, UserMessage};
 pub(crate) use event::UncheckedEvent;
+pub use media_preview::{DownloadState, MediaPreviews, MediaState};
 pub use subscription_type::{PrefixedId, SubName};
+pub use zaps::{ZapTarget, ZapTotals};
@@ -26,6 +26,10 @@ pub enum SubName {
     SearchChannelsDetails(PrefixedId),
     ChannelMembersMetadata(PrefixedId),
     Channels,
+    ZapReceipts,
+    WalletResponses,
+    Followers,
+    MuteList,
 }
 impl SubName {
     pub fn src_channel_details(channel_id: &nostr::EventId) -> Self {
@@ -43,6 +47,10 @@ impl SubName {
             "Messages" => Some(SubName::Messages),
             "Channels" => Some(SubName::Channels),
             "SearchChannels" => Some(SubName::SearchChannels),
+            "ZapReceipts" => Some(SubName::ZapReceipts),
+            "WalletResponses" => Some(SubName::WalletResponses),
+            "Followers" => Some(SubName::Followers),
+            "MuteList" => Some(SubName::MuteList),
             _ => {
                 if str.starts_with("SrcChannelDts_") {
                     let (_, hex) = str.split_at("SrcChannelDts_".len());
@@ -67,6 +75,10 @@ impl std::fmt::Display for SubName {
             SubName::Messages => write!(f, "Messages"),
             SubName::Channels => write!(f, "Channels"),
             SubName::SearchChannels => write!(f, "SearchChannels"),
+            SubName::ZapReceipts => write!(f, "ZapReceipts"),
+            SubName::WalletResponses => write!(f, "WalletResponses"),
+            SubName::Followers => write!(f, "Followers"),
+            SubName::MuteList => write!(f, "MuteList"),
             SubName::ChannelMembersMetadata(prefixed) => {
                 write!(f, "ChannelMembersMeta_{}", &prefixed)
             }
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use nostr::secp256k1::XOnlyPublicKey;
+
+use crate::error::BackendClosed;
+use crate::net::{BackEndConnection, BackendEvent, ToBackend};
+
+use super::ChatMessage;
+
+/// Who gets the zap, and the message zapped if not the profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZapTarget {
+    pub recipient: XOnlyPublicKey,
+    pub event_id: Option<i64>,
+}
+impl ZapTarget {
+    pub fn profile(recipient: XOnlyPublicKey) -> Self {
+        Self {
+            recipient,
+            event_id: None,
+        }
+    }
+    pub fn message(recipient: XOnlyPublicKey, event_id: i64) -> Self {
+        Self {
+            recipient,
+            event_id: Some(event_id),
+        }
+    }
+}
+
+/// Amount zapped to the messages of a chat, in msats
+#[derive(Debug, Default)]
+pub struct ZapTotals {
+    totals: HashMap<i64, u64>,
+}
+impl ZapTotals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, event_id: i64) -> Option<u64> {
+        self.totals.get(&event_id).copied()
+    }
+
+    /// Asks for the totals of the messages, and to be told about new zaps
+    pub fn fetch<'a>(
+        &self,
+        messages: impl IntoIterator<Item = &'a ChatMessage>,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        let event_ids: Vec<_> = messages
+            .into_iter()
+            .filter_map(ChatMessage::event_id)
+            .collect();
+        if event_ids.is_empty() {
+            return Ok(());
+        }
+        conn.send(ToBackend::FetchZapTotals(event_ids))
+    }
+
+    pub fn backend_event(&mut self, event: &BackendEvent) {
+        match event {
+            BackendEvent::GotZapTotals(totals) => {
+                self.totals.extend(totals.iter().copied());
+            }
+            BackendEvent::ZapReceived {
+                event_id,
+                total_msats,
+            } => {
+                self.totals.insert(*event_id, *total_msats);
+            }
+            _ => (),
+        }
+    }
+}
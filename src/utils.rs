@@ -4,7 +4,7 @@ use crate::{
     db::{DbContact, MessageStatus},
     net::ImageKind,
     style::{Theme, ThemeType},
-    types::ChannelMetadata,
+    types::{Attachment, ChannelMetadata},
 };
 use chrono::{DateTime, Local, NaiveDateTime, Offset};
 use iced::widget::image::Handle;
@@ -160,6 +160,9 @@ pub fn relay_doc_or_err(doc: &str, index: &str) -> Result<RelayInformationDocume
 pub fn message_status_or_err(status: i32, index: &str) -> Result<MessageStatus, sqlx::Error> {
     MessageStatus::from_i32(status).map_err(|e| handle_decode_error(e, index))
 }
+pub fn attachments_or_err(json: &str, index: &str) -> Result<Vec<Attachment>, sqlx::Error> {
+    serde_json::from_str(json).map_err(|e| handle_decode_error(e, index))
+}
 pub fn theme_or_err(theme: u8, index: &str) -> Result<Theme, sqlx::Error> {
     u8::try_into(theme).map_err(|e| handle_decode_error(e, index))
 }
@@ -189,19 +192,65 @@ pub fn channel_id_from_tags(tags: &[nostr::Tag]) -> Option<nostr::EventId> {
     })
 }
 
+/// `t` tags of the event
+pub fn event_hashtags(tags: &[nostr::Tag]) -> Vec<String> {
+    tags.iter()
+        .filter_map(|tag| {
+            if let nostr::Tag::Hashtag(hashtag) = tag {
+                Some(hashtag.to_owned())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// NIP-40 expiration of the event, if any
+pub fn event_expiration(tags: &[nostr::Tag]) -> Option<nostr::Timestamp> {
+    tags.iter().find_map(|tag| {
+        if let nostr::Tag::Expiration(expires_at) = tag {
+            Some(expires_at.to_owned())
+        } else {
+            None
+        }
+    })
+}
+
 pub fn channel_msg_builder(
     channel_id: &EventId,
     recommended_relay: Option<&Url>,
     content: &str,
 ) -> EventBuilder {
-    let tags = &[nostr::Tag::Event(
+    let mut tags = vec![nostr::Tag::Event(
         channel_id.to_owned(),
         recommended_relay
             .as_ref()
             .map(|url| nostr::UncheckedUrl::new(url.to_string())),
         Some(Marker::Root),
     )];
-    EventBuilder::new(nostr::Kind::ChannelMessage, content, tags)
+    tags.extend(
+        mentioned_pubkeys(content)
+            .into_iter()
+            .map(|pubkey| nostr::Tag::PubKey(pubkey, None)),
+    );
+    EventBuilder::new(nostr::Kind::ChannelMessage, content, &tags)
+}
+
+/// Public keys mentioned as `nostr:npub` in a message
+pub fn mentioned_pubkeys(content: &str) -> Vec<XOnlyPublicKey> {
+    let mut pubkeys: Vec<XOnlyPublicKey> = vec![];
+    for (start, _) in content.match_indices("nostr:npub1") {
+        let npub: String = content[start + "nostr:".len()..]
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect();
+        if let Ok(pubkey) = XOnlyPublicKey::from_bech32(&npub) {
+            if !pubkeys.contains(&pubkey) {
+                pubkeys.push(pubkey);
+            }
+        }
+    }
+    pubkeys
 }
 
 pub fn channel_creation_builder(metadata: &ChannelMetadata) -> EventBuilder {
@@ -255,6 +304,61 @@ pub fn add_ellipsis_trunc(s: &str, max_length: usize) -> String {
     }
 }
 
+/// Size in the largest unit that keeps it above one, e.g. `1.5 MB`
+pub fn format_file_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Amount of a BOLT11 invoice in millisatoshis, None when it has no amount.
+///
+/// The amount is in the human readable part, e.g. `lnbc2500u1...` is 2500 micro-bitcoins.
+pub fn invoice_amount_msats(invoice: &str) -> Option<u64> {
+    let invoice = invoice.trim().to_lowercase();
+    let invoice = invoice.strip_prefix("lightning:").unwrap_or(&invoice);
+    // the data part can't have a `1`, so the last one is the separator
+    let (hrp, _data) = invoice.rsplit_once('1')?;
+    let amount = hrp
+        .strip_prefix("ln")?
+        .trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    let (digits, multiplier) = match amount.chars().last()? {
+        last if last.is_ascii_digit() => (amount, None),
+        last => (&amount[..amount.len() - 1], Some(last)),
+    };
+    let value: u64 = digits.parse().ok()?;
+    match multiplier {
+        None => value.checked_mul(MSATS_PER_BTC),
+        Some('m') => value.checked_mul(MSATS_PER_BTC / 1_000),
+        Some('u') => value.checked_mul(MSATS_PER_BTC / 1_000_000),
+        Some('n') => value.checked_mul(MSATS_PER_BTC / 1_000_000_000),
+        // a tenth of a millisatoshi, only valid when it is a whole one
+        Some('p') if value % 10 == 0 => Some(value / 10),
+        _ => None,
+    }
+}
+
+/// Millisatoshis as whole sats, e.g. `1,000 sats`
+pub fn format_sats(msats: u64) -> String {
+    let sats = (msats / 1000).to_string();
+    let mut formatted = String::new();
+    for (i, digit) in sats.chars().enumerate() {
+        if i > 0 && (sats.len() - i) % 3 == 0 {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    format!("{} sats", formatted)
+}
+
 pub fn change_color_by_type(theme_type: ThemeType, color: iced::Color, amount: f32) -> iced::Color {
     match theme_type {
         ThemeType::Light => darken_color(color, amount),
@@ -349,10 +453,120 @@ pub fn hide_string(string: &str, open: usize) -> String {
     format!("{}...{}", prefix, suffix.chars().rev().collect::<String>())
 }
 
+/// Links in a message, in the order they first appear
+pub fn message_urls(content: &str) -> Vec<String> {
+    let mut urls: Vec<String> = vec![];
+    for word in content.split_whitespace() {
+        let word = word.trim_end_matches(|c| matches!(c, ',' | '.' | ';' | ':' | '!' | '?' | ')'));
+        if !(word.starts_with("https://") || word.starts_with("http://")) {
+            continue;
+        }
+        if Url::parse(word).is_ok() && !urls.iter().any(|url| url == word) {
+            urls.push(word.to_owned());
+        }
+    }
+    urls
+}
+
+/// Links to images in a message
+pub fn image_urls(content: &str) -> Vec<String> {
+    message_urls(content)
+        .into_iter()
+        .filter(|url| is_image_url(url))
+        .collect()
+}
+
+/// Links to pages in a message, i.e. not to images
+pub fn link_urls(content: &str) -> Vec<String> {
+    message_urls(content)
+        .into_iter()
+        .filter(|url| !is_image_url(url))
+        .collect()
+}
+
+fn is_image_url(url: &str) -> bool {
+    let path = url
+        .split(|c| c == '?' || c == '#')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    IMAGE_EXTENSIONS.iter().any(|ext| path.ends_with(ext))
+}
+
+const IMAGE_EXTENSIONS: [&str; 5] = [".png", ".jpg", ".jpeg", ".gif", ".webp"];
+const MSATS_PER_BTC: u64 = 100_000_000_000;
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_image_urls() {
+        let content = "look https://example.com/cat.png, and http://example.com/a/DOG.JPG?size=2 \
+            https://example.com/cat.png https://example.com/page.html www.example.com/x.gif";
+        assert_eq!(
+            image_urls(content),
+            vec![
+                "https://example.com/cat.png".to_owned(),
+                "http://example.com/a/DOG.JPG?size=2".to_owned(),
+            ]
+        );
+
+        assert!(image_urls("no links here").is_empty());
+        assert!(image_urls("https://example.com/png").is_empty());
+    }
+
+    #[test]
+    fn test_mentioned_pubkeys() {
+        let pubkey = Keys::generate().public_key();
+        let npub = pubkey.to_bech32().unwrap();
+        let content = format!(
+            "hi nostr:{0}, and again (nostr:{0}) but not {0} or nostr:npub1invalid",
+            npub
+        );
+        assert_eq!(mentioned_pubkeys(&content), vec![pubkey]);
+        assert!(mentioned_pubkeys("no mentions").is_empty());
+    }
+
+    #[test]
+    fn test_link_urls() {
+        let content = "read https://example.com/post?id=1. https://example.com/cat.png";
+        assert_eq!(
+            link_urls(content),
+            vec!["https://example.com/post?id=1".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_format_file_size() {
+        assert_eq!(format_file_size(512), "512 B");
+        assert_eq!(format_file_size(1536), "1.5 KB");
+        assert_eq!(format_file_size(5 * 1024 * 1024), "5.0 MB");
+    }
+
+    #[test]
+    fn test_invoice_amount_msats() {
+        assert_eq!(
+            invoice_amount_msats("lnbc2500u1pvjluezpp5qqq"),
+            Some(250_000_000)
+        );
+        assert_eq!(invoice_amount_msats("LNBC210N1PJZAP"), Some(21_000));
+        assert_eq!(
+            invoice_amount_msats("lightning:lntb20m1pvjluez"),
+            Some(2_000_000_000)
+        );
+        assert_eq!(invoice_amount_msats("lnbc10p1pvjluez"), Some(1));
+        assert_eq!(invoice_amount_msats("lnbc1pvjluezpp5qqq"), None);
+        assert_eq!(invoice_amount_msats("not an invoice"), None);
+    }
+
+    #[test]
+    fn test_format_sats() {
+        assert_eq!(format_sats(21_000), "21 sats");
+        assert_eq!(format_sats(1_234_567_000), "1,234,567 sats");
+        assert_eq!(format_sats(999), "0 sats");
+    }
+
     #[test]
     fn test_parse_nips_markdown() {
         let markdown_content = "
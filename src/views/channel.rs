@@ -1,12 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use chrono::NaiveDateTime;
 use iced::widget::{
     button, column, container,
     image::{Handle, Image},
     row, scrollable, text, text_input, Space,
 };
-use iced::{alignment, Color, Length};
-use nostr::{secp256k1::XOnlyPublicKey, EventId};
+use iced::{alignment, Color, Length, Subscription};
+use nostr::{prelude::ToBech32, secp256k1::XOnlyPublicKey, EventId};
 use once_cell::sync::Lazy;
 
 use crate::{
@@ -14,16 +15,25 @@ use crate::{
         chat_view::{self, ChatView},
         common_scrollable, inform_card,
     },
-    consts::default_profile_image,
-    db::{ChannelCache, ProfileCache},
+    consts::{default_profile_image, YMD_FORMAT},
+    db::{
+        ChannelCache, ChannelMemberStats, DbContact, MessageSearchResult, NotificationChat,
+        ProfileCache, SearchChat,
+    },
     error::BackendClosed,
+    icon::{add_friend_icon, ban_icon, circle_check_icon, circle_xmark_icon, envelope_icon},
     net::{BackEndConnection, BackendEvent, ImageSize, ToBackend},
     style::{self, Theme},
-    types::ChatMessage,
-    utils::hide_string,
+    types::{ChatMessage, MediaPreviews, ZapTotals},
+    utils::{from_naive_utc_to_local, hide_string},
     widget::Element,
 };
 
+use super::modal::{
+    chat_expiration, chat_notifications, image_viewer, message_search, mute_user, zap,
+    ChatExpirationModal, ChatNotificationsModal, ImageViewer, MessageSearch, ModalView,
+    MuteUserModal, ZapModal,
+};
 use super::{route::Route, RouterCommand};
 
 static CHAT_SCROLLABLE_ID: Lazy<scrollable::Id> = Lazy::new(scrollable::Id::unique);
@@ -31,11 +41,35 @@ static CHAT_INPUT_ID: Lazy<text_input::Id> = Lazy::new(text_input::Id::unique);
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    MemberPressed(XOnlyPublicKey),
+    MemberSortPressed(MemberSort),
+    DMMemberPressed(XOnlyPublicKey),
+    AddMemberContactPressed(XOnlyPublicKey),
+    MuteMemberPressed(XOnlyPublicKey),
     ChatView(chat_view::Message),
     BackPressed,
     EnterChannelPressed,
+    ModalMessageSearch(Box<message_search::CMessage<Message>>),
+    ModalImageViewer(Box<image_viewer::CMessage<Message>>),
+    ModalZap(Box<zap::CMessage<Message>>),
+    ModalChatNotifications(Box<chat_notifications::CMessage<Message>>),
+    ModalChatExpiration(Box<chat_expiration::CMessage<Message>>),
+    ModalMuteUser(Box<mute_user::CMessage<Message>>),
+}
+/// Order of the members panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MemberSort {
+    #[default]
+    LastActive,
+    MessageCount,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Nip05Status {
+    Checking,
+    Verified,
+    Invalid,
 }
+
 pub struct Member {
     pub pubkey: XOnlyPublicKey,
     pub profile: Option<ProfileCache>,
@@ -66,6 +100,18 @@ impl Member {
             profile: Some(profile),
         }
     }
+
+    fn nip05(&self) -> Option<&str> {
+        self.profile.as_ref()?.metadata.nip05.as_deref()
+    }
+
+    fn to_contact(&self) -> DbContact {
+        let contact = DbContact::new(&self.pubkey);
+        match &self.profile {
+            Some(profile) => contact.with_profile_cache(profile),
+            None => contact,
+        }
+    }
 }
 pub enum State {
     Loading,
@@ -73,40 +119,173 @@ pub enum State {
         cache: ChannelCache,
         chat_view: ChatView,
         messages: Vec<ChatMessage>,
+        media: MediaPreviews,
+        zaps: ZapTotals,
         members: HashMap<XOnlyPublicKey, Member>,
+        member_stats: HashMap<XOnlyPublicKey, ChannelMemberStats>,
+        member_sort: MemberSort,
+        nip05_status: HashMap<XOnlyPublicKey, Nip05Status>,
+        /// Contacts whose images are downloaded without asking
+        auto_load_authors: HashSet<XOnlyPublicKey>,
+        contacts: HashSet<XOnlyPublicKey>,
     },
 }
+enum ModalState {
+    Off,
+    MessageSearch(MessageSearch<Message>),
+    ImageViewer(ImageViewer<Message>),
+    Zap(ZapModal<Message>),
+    ChatNotifications(ChatNotificationsModal<Message>),
+    ChatExpiration(ChatExpirationModal<Message>),
+    MuteUser(MuteUserModal<Message>),
+}
+impl ModalState {
+    fn view<'a>(&'a self, underlay: impl Into<Element<'a, Message>>) -> Element<'a, Message> {
+        match self {
+            ModalState::Off => underlay.into(),
+            ModalState::MessageSearch(state) => state
+                .view(underlay)
+                .map(|m| Message::ModalMessageSearch(Box::new(m))),
+            ModalState::ImageViewer(state) => state
+                .view(underlay)
+                .map(|m| Message::ModalImageViewer(Box::new(m))),
+            ModalState::Zap(state) => state.view(underlay).map(|m| Message::ModalZap(Box::new(m))),
+            ModalState::ChatNotifications(state) => state
+                .view(underlay)
+                .map(|m| Message::ModalChatNotifications(Box::new(m))),
+            ModalState::ChatExpiration(state) => state
+                .view(underlay)
+                .map(|m| Message::ModalChatExpiration(Box::new(m))),
+            ModalState::MuteUser(state) => state
+                .view(underlay)
+                .map(|m| Message::ModalMuteUser(Box::new(m))),
+        }
+    }
+    fn backend_event(
+        &mut self,
+        event: BackendEvent,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        match self {
+            ModalState::MessageSearch(state) => state.backend_event(event, conn)?,
+            ModalState::Zap(state) => state.backend_event(event, conn)?,
+            ModalState::ChatNotifications(state) => state.backend_event(event, conn)?,
+            ModalState::ChatExpiration(state) => state.backend_event(event, conn)?,
+            ModalState::MuteUser(state) => state.backend_event(event, conn)?,
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
 pub struct Channel {
     msgs_scroll_offset: scrollable::RelativeOffset,
     is_subscribed: bool,
     channel_id: EventId,
     state: State,
+    modal_state: ModalState,
+    /// Message to show once the channel messages are loaded
+    jump_to: Option<(i64, NaiveDateTime)>,
+    user_pubkey: Option<XOnlyPublicKey>,
+    /// Used to highlight the messages mentioning the user
+    user_npub: Option<String>,
 }
 impl Channel {
     pub fn matches_id(&self, channel_id: &EventId) -> bool {
         &self.channel_id == channel_id
     }
+    /// Keeps the composer text as the channel's draft
+    fn save_draft(
+        &mut self,
+        text: String,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        if let State::Loaded { chat_view, .. } = &mut self.state {
+            chat_view.update_dm_msg(text.clone());
+            chat_view.draft_saved();
+            let chat = NotificationChat::Channel(self.channel_id);
+            conn.send(ToBackend::SaveDraft(chat, text))?;
+        }
+        Ok(())
+    }
+    /// Saves the typed draft once the typing paused
+    fn flush_draft(&mut self, conn: &mut BackEndConnection) -> Result<(), BackendClosed> {
+        if let State::Loaded { chat_view, .. } = &mut self.state {
+            if let Some(text) = chat_view.unsaved_draft(false) {
+                let chat = NotificationChat::Channel(self.channel_id);
+                conn.send(ToBackend::SaveDraft(chat, text))?;
+            }
+        }
+        Ok(())
+    }
     pub fn load(
         channel_id: EventId,
         is_subscribed: bool,
         conn: &mut BackEndConnection,
     ) -> Result<Self, BackendClosed> {
         conn.send(ToBackend::FetchChannelCache(channel_id))?;
+        if is_subscribed {
+            conn.send(ToBackend::MarkChannelRead(channel_id))?;
+        }
 
         Ok(Self {
             msgs_scroll_offset: scrollable::RelativeOffset::default(),
             is_subscribed,
             channel_id,
             state: State::Loading,
+            modal_state: ModalState::Off,
+            jump_to: None,
+            user_pubkey: None,
+            user_npub: None,
         })
     }
+    /// Loads the channel showing the message of a search result
+    pub fn load_at(
+        result: &MessageSearchResult,
+        channel_id: EventId,
+        is_subscribed: bool,
+        conn: &mut BackEndConnection,
+    ) -> Result<Self, BackendClosed> {
+        let mut channel = Self::load(channel_id, is_subscribed, conn)?;
+        channel.jump_to = Some((result.event_id, result.created_at));
+        Ok(channel)
+    }
+    /// Search results from other channels are opened by the home view
+    pub fn other_channel_result(
+        &self,
+        message: &Message,
+    ) -> Option<(EventId, MessageSearchResult)> {
+        if let Message::ModalMessageSearch(modal_msg) = message {
+            if let message_search::CMessage::ResultPressed(result) = modal_msg.as_ref() {
+                if let SearchChat::Channel(channel_id) = &result.chat {
+                    if !self.matches_id(channel_id) {
+                        return Some((channel_id.to_owned(), result.to_owned()));
+                    }
+                }
+            }
+        }
+        None
+    }
     fn loaded(
         cache: ChannelCache,
         is_subscribed: bool,
+        jump_to: Option<(i64, NaiveDateTime)>,
         conn: &mut BackEndConnection,
     ) -> Result<Self, BackendClosed> {
-        conn.send(ToBackend::FetchChannelMessages(cache.channel_id))?;
+        match jump_to {
+            Some((_, created_at)) => conn.send(ToBackend::FetchChannelMessagesAround(
+                cache.channel_id,
+                created_at,
+            ))?,
+            None => conn.send(ToBackend::FetchChannelMessages(cache.channel_id))?,
+        }
         conn.send(ToBackend::SubscribeChannelMembersMeta(cache.channel_id))?;
+        conn.send(ToBackend::FetchContacts)?;
+        conn.send(ToBackend::FetchDraft(NotificationChat::Channel(
+            cache.channel_id,
+        )))?;
+        conn.send(ToBackend::FetchKeys)?;
+        conn.send(ToBackend::FetchChannelMemberStats(cache.channel_id))?;
 
         let members = cache
             .members
@@ -118,14 +297,82 @@ impl Channel {
             msgs_scroll_offset: scrollable::RelativeOffset::default(),
             channel_id: cache.channel_id,
             is_subscribed,
+            modal_state: ModalState::Off,
+            jump_to,
+            user_pubkey: None,
+            user_npub: None,
             state: State::Loaded {
                 cache,
                 chat_view: ChatView::new(),
                 messages: vec![],
+                media: MediaPreviews::new(),
+                zaps: ZapTotals::new(),
                 members,
+                member_stats: HashMap::new(),
+                member_sort: MemberSort::default(),
+                nip05_status: HashMap::new(),
+                auto_load_authors: HashSet::new(),
+                contacts: HashSet::new(),
             },
         })
     }
+    fn open_mute_user(
+        &mut self,
+        pubkey: &XOnlyPublicKey,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        let name = match &self.state {
+            State::Loaded { members, .. } => members.get(pubkey).map(|member| member.name()),
+            State::Loading => None,
+        }
+        .unwrap_or_else(|| Member::new(pubkey).name());
+        self.modal_state = ModalState::MuteUser(MuteUserModal::new(pubkey, &name, conn)?);
+        Ok(())
+    }
+    /// Members whose names match the `@` being typed
+    fn mention_suggestions(&self) -> Vec<(XOnlyPublicKey, String)> {
+        let State::Loaded {
+            chat_view, members, ..
+        } = &self.state
+        else {
+            return vec![];
+        };
+        let Some(query) = chat_view.mention_query() else {
+            return vec![];
+        };
+        let query = query.to_lowercase();
+        let mut suggestions: Vec<_> = members
+            .values()
+            .map(|member| (member.pubkey.to_owned(), member.name()))
+            .filter(|(_, name)| name.to_lowercase().starts_with(&query))
+            .collect();
+        suggestions.sort_by(|(_, a), (_, b)| a.cmp(b));
+        suggestions.truncate(MAX_MENTION_SUGGESTIONS);
+        suggestions
+    }
+    fn open_message_search(&mut self) {
+        let names = match &self.state {
+            State::Loading => return,
+            State::Loaded { members, .. } => members
+                .values()
+                .map(|member| (member.pubkey.to_owned(), member.name()))
+                .collect(),
+        };
+        let chat = SearchChat::Channel(self.channel_id.to_owned());
+        self.modal_state = ModalState::MessageSearch(MessageSearch::new(chat, names));
+    }
+    fn jump_to_message(
+        &mut self,
+        result: MessageSearchResult,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        conn.send(ToBackend::FetchChannelMessagesAround(
+            self.channel_id.to_owned(),
+            result.created_at,
+        ))?;
+        self.jump_to = Some((result.event_id, result.created_at));
+        Ok(())
+    }
     fn update_cache(&mut self, new_cache: ChannelCache) {
         match &mut self.state {
             State::Loading { .. } => (),
@@ -139,6 +386,23 @@ impl Channel {
             }
         }
     }
+    /// Downloads the images in the messages whose author allows it
+    fn add_media(&mut self, conn: &mut BackEndConnection) -> Result<(), BackendClosed> {
+        if let State::Loaded {
+            messages,
+            media,
+            auto_load_authors,
+            ..
+        } = &mut self.state
+        {
+            media.add_messages(
+                messages.iter(),
+                |author| auto_load_authors.contains(author),
+                conn,
+            )?;
+        }
+        Ok(())
+    }
     fn name(&self) -> String {
         match &self.state {
             State::Loading { .. } => "Loading...".into(),
@@ -153,6 +417,13 @@ impl Channel {
 impl Route for Channel {
     type Message = Message;
 
+    fn subscription(&self) -> Subscription<Self::Message> {
+        match &self.state {
+            State::Loaded { chat_view, .. } => chat_view.subscription().map(Message::ChatView),
+            _ => Subscription::none(),
+        }
+    }
+
     fn backend_event(
         &mut self,
         event: crate::net::BackendEvent,
@@ -160,10 +431,16 @@ impl Route for Channel {
     ) -> Result<super::RouterCommand<Self::Message>, BackendClosed> {
         let mut command = RouterCommand::new();
 
+        self.modal_state.backend_event(event.clone(), conn)?;
+        if let State::Loaded { media, zaps, .. } = &mut self.state {
+            media.backend_event(&event, conn)?;
+            zaps.backend_event(&event);
+        }
+
         match event {
             BackendEvent::GotChannelCache(cache) => {
                 if self.matches_id(&cache.channel_id) {
-                    *self = Self::loaded(cache, self.is_subscribed, conn)?;
+                    *self = Self::loaded(cache, self.is_subscribed, self.jump_to, conn)?;
                 }
             }
             BackendEvent::ChannelCacheUpdated(cache) => {
@@ -174,6 +451,7 @@ impl Route for Channel {
             BackendEvent::ChannelSubscribed(channel_id) => {
                 if self.matches_id(&channel_id) {
                     self.is_subscribed = true;
+                    conn.send(ToBackend::MarkChannelRead(channel_id))?;
                 }
             }
             BackendEvent::ChannelUnsubscribed(channel_id) => {
@@ -190,13 +468,21 @@ impl Route for Channel {
                 if self.matches_id(&channel_id) {
                     match &mut self.state {
                         State::Loading => (),
-                        State::Loaded { messages, .. } => {
+                        State::Loaded { messages, zaps, .. } => {
                             *messages = new_messages;
+                            zaps.fetch(messages.iter(), conn)?;
                         }
                     }
+                    self.add_media(conn)?;
                 }
 
-                self.msgs_scroll_offset = scrollable::RelativeOffset::END;
+                self.msgs_scroll_offset = match (self.jump_to.take(), &self.state) {
+                    (Some((event_id, _)), State::Loaded { messages, .. }) => {
+                        chat_view::message_scroll_offset(messages, event_id)
+                            .unwrap_or(scrollable::RelativeOffset::END)
+                    }
+                    _ => scrollable::RelativeOffset::END,
+                };
                 command.push(scrollable::snap_to(
                     CHAT_SCROLLABLE_ID.clone(),
                     self.msgs_scroll_offset,
@@ -219,6 +505,85 @@ impl Route for Channel {
                             messages.sort_by(|a, b| a.display_time().cmp(&b.display_time()))
                         }
                     }
+                    conn.send(ToBackend::FetchChannelMemberStats(channel_id))?;
+                    if self.is_subscribed {
+                        conn.send(ToBackend::MarkChannelRead(channel_id))?;
+                    }
+                    self.add_media(conn)?;
+                }
+            }
+            BackendEvent::GotDraft(NotificationChat::Channel(channel_id), draft) => {
+                if self.matches_id(&channel_id) {
+                    if let State::Loaded { chat_view, .. } = &mut self.state {
+                        // keep what was typed before the draft arrived
+                        if chat_view.dm_msg().is_empty() {
+                            chat_view.update_dm_msg(draft);
+                        }
+                    }
+                }
+            }
+            BackendEvent::MessagesExpired(event_ids) => {
+                if let State::Loaded { messages, .. } = &mut self.state {
+                    messages.retain(|m| m.event_id().map_or(true, |id| !event_ids.contains(&id)));
+                }
+            }
+            BackendEvent::GotKeys(keys) => {
+                self.user_pubkey = Some(keys.public_key());
+                self.user_npub = keys.public_key().to_bech32().ok();
+            }
+            BackendEvent::GotChannelMemberStats(channel_id, stats) => {
+                if self.matches_id(&channel_id) {
+                    if let State::Loaded { member_stats, .. } = &mut self.state {
+                        *member_stats = stats
+                            .into_iter()
+                            .map(|stats| (stats.author.to_owned(), stats))
+                            .collect();
+                    }
+                }
+            }
+            BackendEvent::GotNip05Status(pubkey, verified) => {
+                if let State::Loaded { nip05_status, .. } = &mut self.state {
+                    if let Some(status) = nip05_status.get_mut(&pubkey) {
+                        *status = if verified {
+                            Nip05Status::Verified
+                        } else {
+                            Nip05Status::Invalid
+                        };
+                    }
+                }
+            }
+            BackendEvent::GotContacts(db_contacts) => {
+                if let State::Loaded {
+                    auto_load_authors,
+                    contacts,
+                    ..
+                } = &mut self.state
+                {
+                    *auto_load_authors = db_contacts
+                        .iter()
+                        .filter(|contact| contact.auto_load_media())
+                        .map(DbContact::pubkey)
+                        .cloned()
+                        .collect();
+                    *contacts = db_contacts
+                        .iter()
+                        .filter(|contact| contact.is_known())
+                        .map(DbContact::pubkey)
+                        .cloned()
+                        .collect();
+                }
+                self.add_media(conn)?;
+            }
+            BackendEvent::ContactCreated(db_contact) | BackendEvent::ContactUpdated(db_contact) => {
+                if let State::Loaded { contacts, .. } = &mut self.state {
+                    if db_contact.is_known() {
+                        contacts.insert(db_contact.pubkey().to_owned());
+                    }
+                }
+            }
+            BackendEvent::ContactDeleted(db_contact) => {
+                if let State::Loaded { contacts, .. } = &mut self.state {
+                    contacts.remove(db_contact.pubkey());
                 }
             }
 
@@ -233,7 +598,10 @@ impl Route for Channel {
             BackendEvent::GotProfileCache(pubkey, profile) => match &mut self.state {
                 State::Loading => (),
                 State::Loaded {
-                    members, messages, ..
+                    members,
+                    messages,
+                    nip05_status,
+                    ..
                 } => {
                     if let Some(member) = members.get_mut(&pubkey) {
                         *member = Member::with_profile(profile);
@@ -241,6 +609,14 @@ impl Route for Channel {
                         messages.iter_mut().for_each(|m| {
                             m.update_display_name(&member.pubkey, member.name());
                         });
+
+                        // verified once per member while the channel is open
+                        if let Some(nip05) = member.nip05() {
+                            if !nip05_status.contains_key(&pubkey) {
+                                nip05_status.insert(pubkey, Nip05Status::Checking);
+                                conn.send(ToBackend::VerifyNip05(pubkey, nip05.to_owned()))?;
+                            }
+                        }
                     }
                 }
             },
@@ -258,10 +634,28 @@ impl Route for Channel {
         let mut command = RouterCommand::new();
 
         match message {
-            Message::MemberPressed(member) => {
-                // show modal?
-                tracing::info!("Member pressed: {:?}", member)
+            Message::MemberSortPressed(sort) => {
+                if let State::Loaded { member_sort, .. } = &mut self.state {
+                    *member_sort = sort;
+                }
             }
+            Message::DMMemberPressed(pubkey) => {
+                if let State::Loaded { members, .. } = &self.state {
+                    let contact = members
+                        .get(&pubkey)
+                        .map(Member::to_contact)
+                        .unwrap_or_else(|| DbContact::new(&pubkey));
+                    command.change_route(super::GoToView::ChatTo(contact));
+                }
+            }
+            Message::AddMemberContactPressed(pubkey) => {
+                if let State::Loaded { members, .. } = &self.state {
+                    if let Some(member) = members.get(&pubkey) {
+                        conn.send(ToBackend::AddContact(member.to_contact()))?;
+                    }
+                }
+            }
+            Message::MuteMemberPressed(pubkey) => self.open_mute_user(&pubkey, conn)?,
             Message::BackPressed => {
                 // Todo: make go back work
                 command.change_route(super::GoToView::Chat);
@@ -269,32 +663,192 @@ impl Route for Channel {
             Message::EnterChannelPressed => {
                 conn.send(ToBackend::SubscribeToChannel(self.channel_id.to_owned()))?;
             }
-            Message::ChatView(ch_msg) => match ch_msg {
-                chat_view::Message::DMSentPress(_) => tracing::info!("DM sent!"),
-                chat_view::Message::DMNMessageChange(_) => {
-                    tracing::info!("DMNMessageChange")
+            Message::ModalMessageSearch(modal_msg) => {
+                if let ModalState::MessageSearch(state) = &mut self.modal_state {
+                    match *modal_msg {
+                        message_search::CMessage::UnderlayMessage(message) => {
+                            return self.update(message, conn);
+                        }
+                        message_search::CMessage::ResultPressed(result) => {
+                            self.modal_state = ModalState::Off;
+                            self.jump_to_message(result, conn)?;
+                        }
+                        other => {
+                            let (cmd, close_modal) = state.update(other, conn)?;
+                            if close_modal {
+                                self.modal_state = ModalState::Off;
+                            }
+                            command.push(cmd.map(|m| Message::ModalMessageSearch(Box::new(m))));
+                        }
+                    }
                 }
-                chat_view::Message::GotChatSize(_, _) => tracing::info!("GotChatSize"),
-                chat_view::Message::Scrolled(_) => tracing::info!("Scrolled"),
-                chat_view::Message::OpenContactProfile => {
-                    tracing::info!("OpenContactProfile")
+            }
+            Message::ModalImageViewer(modal_msg) => {
+                if let ModalState::ImageViewer(state) = &mut self.modal_state {
+                    match *modal_msg {
+                        image_viewer::CMessage::UnderlayMessage(message) => {
+                            return self.update(message, conn);
+                        }
+                        other => {
+                            let (cmd, close_modal) = state.update(other, conn)?;
+                            if close_modal {
+                                self.modal_state = ModalState::Off;
+                            }
+                            command.push(cmd.map(|m| Message::ModalImageViewer(Box::new(m))));
+                        }
+                    }
                 }
-                chat_view::Message::ChatRightClick(_, _) => {
-                    tracing::info!("ChatRightClick")
+            }
+            Message::ModalZap(modal_msg) => {
+                if let ModalState::Zap(state) = &mut self.modal_state {
+                    match *modal_msg {
+                        zap::CMessage::UnderlayMessage(message) => {
+                            return self.update(message, conn);
+                        }
+                        other => {
+                            let (cmd, close_modal) = state.update(other, conn)?;
+                            if close_modal {
+                                self.modal_state = ModalState::Off;
+                            }
+                            command.push(cmd.map(|m| Message::ModalZap(Box::new(m))));
+                        }
+                    }
                 }
-                chat_view::Message::ChannelOpenModalPressed => {
-                    tracing::info!("ChannelOpenModalPressed")
+            }
+            Message::ModalChatNotifications(modal_msg) => {
+                if let ModalState::ChatNotifications(state) = &mut self.modal_state {
+                    match *modal_msg {
+                        chat_notifications::CMessage::UnderlayMessage(message) => {
+                            return self.update(message, conn);
+                        }
+                        other => {
+                            let (cmd, close_modal) = state.update(other, conn)?;
+                            if close_modal {
+                                self.modal_state = ModalState::Off;
+                            }
+                            command.push(cmd.map(|m| Message::ModalChatNotifications(Box::new(m))));
+                        }
+                    }
                 }
-                chat_view::Message::ChannelSearchPressed => {
-                    tracing::info!("ChannelSearchPressed")
+            }
+            Message::ModalChatExpiration(modal_msg) => {
+                if let ModalState::ChatExpiration(state) = &mut self.modal_state {
+                    match *modal_msg {
+                        chat_expiration::CMessage::UnderlayMessage(message) => {
+                            return self.update(message, conn);
+                        }
+                        other => {
+                            let (cmd, close_modal) = state.update(other, conn)?;
+                            if close_modal {
+                                self.modal_state = ModalState::Off;
+                            }
+                            command.push(cmd.map(|m| Message::ModalChatExpiration(Box::new(m))));
+                        }
+                    }
                 }
-                chat_view::Message::ChannelMenuPressed => {
-                    tracing::info!("ChannelMenuPressed")
+            }
+            Message::ModalMuteUser(modal_msg) => {
+                if let ModalState::MuteUser(state) = &mut self.modal_state {
+                    match *modal_msg {
+                        mute_user::CMessage::UnderlayMessage(message) => {
+                            return self.update(message, conn);
+                        }
+                        other => {
+                            let (cmd, close_modal) = state.update(other, conn)?;
+                            if close_modal {
+                                self.modal_state = ModalState::Off;
+                            }
+                            command.push(cmd.map(|m| Message::ModalMuteUser(Box::new(m))));
+                        }
+                    }
                 }
-                chat_view::Message::ChannelUserNamePressed(author) => {
-                    tracing::info!("ChannelUserNamePressed: {}", author)
+            }
+            Message::ChatView(ch_msg) => {
+                match ch_msg {
+                    chat_view::Message::DMSentPress(content) => {
+                        if let State::Loaded { chat_view, .. } = &self.state {
+                            if !content.is_empty() {
+                                let content = chat_view.encode_mentions(&content);
+                                conn.send(ToBackend::SendChannelMessage(self.channel_id, content))?;
+                                self.save_draft("".into(), conn)?;
+                            }
+                        }
+                    }
+                    chat_view::Message::MentionPressed(pubkey, name) => {
+                        if let State::Loaded { chat_view, .. } = &mut self.state {
+                            chat_view.complete_mention(pubkey, name);
+                            let text = chat_view.dm_msg().to_owned();
+                            self.save_draft(text, conn)?;
+                        }
+                        command.push(text_input::focus(CHAT_INPUT_ID.clone()));
+                    }
+                    chat_view::Message::DMNMessageChange(text) => {
+                        if let State::Loaded { chat_view, .. } = &mut self.state {
+                            chat_view.edit_draft(text);
+                        }
+                    }
+                    chat_view::Message::DraftTick => self.flush_draft(conn)?,
+                    chat_view::Message::GotChatSize(_, _) => tracing::info!("GotChatSize"),
+                    chat_view::Message::Scrolled(_) => tracing::info!("Scrolled"),
+                    chat_view::Message::OpenContactProfile => {
+                        tracing::info!("OpenContactProfile")
+                    }
+                    chat_view::Message::ChatRightClick(_, _) => {
+                        tracing::info!("ChatRightClick")
+                    }
+                    chat_view::Message::ChannelOpenModalPressed => {
+                        tracing::info!("ChannelOpenModalPressed")
+                    }
+                    chat_view::Message::ChatSearchPressed
+                    | chat_view::Message::ChannelSearchPressed => {
+                        self.open_message_search();
+                    }
+                    chat_view::Message::ChannelMenuPressed => {
+                        tracing::info!("ChannelMenuPressed")
+                    }
+                    chat_view::Message::NotificationsPressed => {
+                        let chat = NotificationChat::Channel(self.channel_id.to_owned());
+                        self.modal_state = ModalState::ChatNotifications(
+                            ChatNotificationsModal::new(chat, &self.name(), conn)?,
+                        );
+                    }
+                    chat_view::Message::ExpirationPressed => {
+                        let chat = NotificationChat::Channel(self.channel_id.to_owned());
+                        self.modal_state = ModalState::ChatExpiration(ChatExpirationModal::new(
+                            chat,
+                            &self.name(),
+                            conn,
+                        )?);
+                    }
+                    chat_view::Message::ChannelUserNamePressed(author) => {
+                        self.open_mute_user(&author, conn)?
+                    }
+                    chat_view::Message::MediaPressed(path) => {
+                        self.modal_state = ModalState::ImageViewer(ImageViewer::new(&path));
+                    }
+                    chat_view::Message::LoadMediaPressed(media_url) => {
+                        if let State::Loaded { media, .. } = &mut self.state {
+                            media.load(&media_url, conn)?;
+                        }
+                    }
+                    chat_view::Message::DownloadAttachmentPressed(attachment) => {
+                        if let State::Loaded { media, .. } = &mut self.state {
+                            media.download(&attachment, conn)?;
+                        }
+                    }
+                    // channels have no attachments, their input has no attach button
+                    chat_view::Message::AttachPressed
+                    | chat_view::Message::RemoveAttachmentPressed(_) => (),
+                    chat_view::Message::LinkPressed(link_url) => {
+                        if let Err(e) = webbrowser::open(&link_url) {
+                            tracing::error!("Failed to open link: {}", e);
+                        }
+                    }
+                    chat_view::Message::ZapPressed(target, name) => {
+                        self.modal_state = ModalState::Zap(ZapModal::new(target, &name));
+                    }
                 }
-            },
+            }
         }
 
         Ok(command)
@@ -304,20 +858,41 @@ impl Route for Channel {
         match &self.state {
             State::Loading { .. } => inform_card("Loading Channel", "Please wait"),
             State::Loaded {
+                cache,
                 chat_view,
                 messages,
+                media,
+                zaps,
                 members,
+                member_stats,
+                member_sort,
+                nip05_status,
+                contacts,
                 ..
             } => {
-                // let members_list = make_member_list(self.channel.members.iter(), Message::MemberPressed);
+                let mut sorted: Vec<_> = members.values().collect();
+                sort_members(&mut sorted, member_stats, *member_sort);
 
-                let members_list = members
-                    .iter()
-                    .fold(column![].spacing(5), |col, (_, member)| {
-                        col.push(member_btn(member))
+                let members_list = sorted
+                    .into_iter()
+                    .fold(column![].spacing(5), |col, member| {
+                        let is_user = self.user_pubkey.as_ref() == Some(&member.pubkey);
+                        col.push(member_card(
+                            member,
+                            member_stats.get(&member.pubkey),
+                            nip05_status.get(&member.pubkey).copied(),
+                            member.pubkey == cache.creator_pubkey,
+                            !is_user,
+                            !is_user && !contacts.contains(&member.pubkey),
+                        ))
                     });
                 let members_list = container(common_scrollable(
-                    column![text("Members").size(24), members_list].spacing(10),
+                    column![
+                        text("Members").size(24),
+                        member_sort_buttons(*member_sort),
+                        members_list
+                    ]
+                    .spacing(10),
                 ))
                 .padding(10)
                 .height(Length::Fill)
@@ -329,9 +904,13 @@ impl Route for Channel {
                         &CHAT_SCROLLABLE_ID,
                         &CHAT_INPUT_ID,
                         messages,
+                        media,
+                        zaps,
                         &self.name(),
                         members.len() as i32,
                         !self.is_subscribed,
+                        &self.mention_suggestions(),
+                        self.user_npub.as_deref(),
                     )
                     .map(Message::ChatView);
 
@@ -363,28 +942,138 @@ impl Route for Channel {
                     .into()
                 };
 
-                column![show_join, content].into()
+                self.modal_state.view(column![show_join, content])
             }
         }
     }
 }
 
-fn member_btn(member: &Member) -> Element<'_, Message> {
-    let content = row![
-        container(Image::new(Handle::from_memory(default_profile_image(
-            ImageSize::Small
-        ))))
-        .width(30)
-        .height(30),
-        text(member.name()).size(15)
+/// Most active first, members who never posted go last by name
+fn sort_members(
+    members: &mut [&Member],
+    stats: &HashMap<XOnlyPublicKey, ChannelMemberStats>,
+    sort: MemberSort,
+) {
+    members.sort_by_cached_key(|member| {
+        let stats = stats.get(&member.pubkey);
+        let activity = match sort {
+            MemberSort::LastActive => stats.map(|s| s.last_active.timestamp_millis()),
+            MemberSort::MessageCount => stats.map(|s| s.message_count),
+        };
+        (std::cmp::Reverse(activity), member.name().to_lowercase())
+    });
+}
+
+fn member_sort_buttons<'a>(selected: MemberSort) -> Element<'a, Message> {
+    let sort_btn = |label: &str, sort: MemberSort| {
+        let btn_style = if sort == selected {
+            style::Button::ActiveMenuBtn
+        } else {
+            style::Button::MenuBtn
+        };
+        button(text(label).size(14))
+            .style(btn_style)
+            .on_press(Message::MemberSortPressed(sort))
+    };
+    row![
+        sort_btn("Last active", MemberSort::LastActive),
+        sort_btn("Messages", MemberSort::MessageCount)
     ]
-    .spacing(5);
+    .spacing(5)
+    .into()
+}
+
+fn member_card<'a>(
+    member: &'a Member,
+    stats: Option<&ChannelMemberStats>,
+    nip05_status: Option<Nip05Status>,
+    is_creator: bool,
+    can_dm: bool,
+    can_add: bool,
+) -> Element<'a, Message> {
+    let mut name_row = row![text(member.name()).size(15)]
+        .spacing(5)
+        .align_items(alignment::Alignment::Center);
+    if is_creator {
+        name_row = name_row.push(
+            container(text("Creator").size(11).style(style::Text::Primary))
+                .padding([1, 4])
+                .style(style::Container::Bordered),
+        );
+    }
+
+    let mut details = column![name_row].spacing(2);
+    if let Some(nip05) = member.nip05() {
+        let status: Element<_> = match nip05_status {
+            Some(Nip05Status::Verified) => circle_check_icon()
+                .size(12)
+                .style(style::Text::Primary)
+                .into(),
+            Some(Nip05Status::Invalid) => circle_xmark_icon()
+                .size(12)
+                .style(style::Text::Danger)
+                .into(),
+            Some(Nip05Status::Checking) | None => text("").into(),
+        };
+        details = details.push(
+            row![status, text(nip05).size(12).style(style::Text::Alpha(0.5))]
+                .spacing(2)
+                .align_items(alignment::Alignment::Center),
+        );
+    }
+    let activity = match stats {
+        Some(stats) => format!(
+            "{} messages, last {}",
+            stats.message_count,
+            from_naive_utc_to_local(stats.last_active).format(YMD_FORMAT)
+        ),
+        None => "No messages".into(),
+    };
+    details = details.push(text(activity).size(12).style(style::Text::Alpha(0.5)));
+
+    let action_btn = |icon: crate::widget::Text<'a>, message: Message| {
+        button(icon.size(14))
+            .style(style::Button::Invisible)
+            .on_press(message)
+    };
+    let mut actions = row![].spacing(2);
+    if can_dm {
+        actions = actions.push(action_btn(
+            envelope_icon(),
+            Message::DMMemberPressed(member.pubkey),
+        ));
+    }
+    if can_add {
+        actions = actions.push(action_btn(
+            add_friend_icon(),
+            Message::AddMemberContactPressed(member.pubkey),
+        ));
+    }
+    actions = actions.push(action_btn(
+        ban_icon(),
+        Message::MuteMemberPressed(member.pubkey),
+    ));
+
+    let avatar = container(Image::new(Handle::from_memory(default_profile_image(
+        ImageSize::Small,
+    ))))
+    .width(30)
+    .height(30);
 
-    button(content)
-        .on_press(Message::MemberPressed(member.pubkey.to_owned()))
-        .style(style::Button::ContactCard)
-        .width(Length::Fill)
-        .into()
+    container(
+        column![
+            row![avatar, details].spacing(5),
+            container(actions)
+                .width(Length::Fill)
+                .align_x(alignment::Horizontal::Right)
+        ]
+        .spacing(2),
+    )
+    .padding(5)
+    .width(Length::Fill)
+    .style(style::Container::Frame)
+    .into()
 }
 
-const MEMBERS_LIST_WIDTH: u16 = 200;
+const MEMBERS_LIST_WIDTH: u16 = 240;
+const MAX_MENTION_SUGGESTIONS: usize = 5;
@@ -4,17 +4,18 @@ use iced::widget::{button, column, container, row, scrollable, text, text_input,
 use iced::Size;
 use iced::{Alignment, Command, Length};
 use iced_native::widget::scrollable::RelativeOffset;
-use nostr::secp256k1::XOnlyPublicKey;
 
 use crate::components::chat_contact::{ChatContact, CARD_HEIGHT};
 use crate::components::floating_element::{Anchor, FloatingElement, Offset};
 use crate::components::{chat_contact, chat_view, contact_list};
-use crate::db::{DbContact, DbRelay, DbRelayResponse};
+use crate::db::{
+    DbContact, DbRelay, DbRelayResponse, MessageSearchResult, NotificationChat, SearchChat,
+};
 use crate::error::BackendClosed;
 use crate::icon::{copy_icon, reply_icon, satellite_icon};
 use crate::net::{BackEndConnection, BackendEvent, ToBackend};
 use crate::style;
-use crate::types::ChatMessage;
+use crate::types::{ChatMessage, MediaPreviews, ZapTotals};
 use crate::widget::Element;
 use once_cell::sync::Lazy;
 
@@ -22,7 +23,9 @@ use self::chat_view::ChatView;
 use self::contact_list::ContactList;
 
 use super::modal::{
-    basic_contact, relays_confirmation, ContactDetails, ModalView, RelaysConfirmation,
+    basic_contact, chat_expiration, chat_notifications, image_viewer, message_search,
+    relays_confirmation, zap, ChatExpirationModal, ChatNotificationsModal, ContactDetails,
+    ImageViewer, MessageSearch, ModalView, RelaysConfirmation, ZapModal,
 };
 use super::route::Route;
 use super::{GoToView, RouterCommand};
@@ -38,6 +41,11 @@ pub enum ModalState {
     Off,
     BasicProfile(ContactDetails<Message>),
     RelaysConfirmation(RelaysConfirmation<Message>),
+    MessageSearch(MessageSearch<Message>),
+    ImageViewer(ImageViewer<Message>),
+    Zap(ZapModal<Message>),
+    ChatNotifications(ChatNotificationsModal<Message>),
+    ChatExpiration(ChatExpirationModal<Message>),
 }
 impl ModalState {
     pub fn basic_profile(
@@ -55,6 +63,19 @@ impl ModalState {
             ModalState::BasicProfile(state) => state
                 .view(underlay)
                 .map(|m| Message::ModalBasicContact(Box::new(m))),
+            ModalState::MessageSearch(state) => state
+                .view(underlay)
+                .map(|m| Message::ModalMessageSearch(Box::new(m))),
+            ModalState::ImageViewer(state) => state
+                .view(underlay)
+                .map(|m| Message::ModalImageViewer(Box::new(m))),
+            ModalState::Zap(state) => state.view(underlay).map(|m| Message::ModalZap(Box::new(m))),
+            ModalState::ChatNotifications(state) => state
+                .view(underlay)
+                .map(|m| Message::ModalChatNotifications(Box::new(m))),
+            ModalState::ChatExpiration(state) => state
+                .view(underlay)
+                .map(|m| Message::ModalChatExpiration(Box::new(m))),
         }
     }
     fn backend_event(
@@ -62,8 +83,13 @@ impl ModalState {
         event: BackendEvent,
         conn: &mut BackEndConnection,
     ) -> Result<(), BackendClosed> {
-        if let ModalState::BasicProfile(state) = self {
-            state.backend_event(event, conn)?
+        match self {
+            ModalState::BasicProfile(state) => state.backend_event(event, conn)?,
+            ModalState::MessageSearch(state) => state.backend_event(event, conn)?,
+            ModalState::Zap(state) => state.backend_event(event, conn)?,
+            ModalState::ChatNotifications(state) => state.backend_event(event, conn)?,
+            ModalState::ChatExpiration(state) => state.backend_event(event, conn)?,
+            _ => (),
         }
         Ok(())
     }
@@ -76,7 +102,15 @@ pub enum Message {
     RelaysConfirmationPress,
     ModalBasicContact(Box<basic_contact::CMessage<Message>>),
     ModalRelaysConfirmation(Box<relays_confirmation::CMessage<Message>>),
+    ModalMessageSearch(Box<message_search::CMessage<Message>>),
+    ModalImageViewer(Box<image_viewer::CMessage<Message>>),
+    ModalZap(Box<zap::CMessage<Message>>),
+    ModalChatNotifications(Box<chat_notifications::CMessage<Message>>),
+    ModalChatExpiration(Box<chat_expiration::CMessage<Message>>),
     OnVerResize(u16),
+    AcceptRequestPressed { add_to_list: bool },
+    BlockRequestPressed,
+    DeleteRequestPressed,
     CloseModal,
     CloseCtxMenu,
     DebugPressed,
@@ -92,6 +126,8 @@ pub struct State {
     chats: Vec<ChatContact>,
     active_idx: Option<i32>,
     messages: Vec<ChatMessage>,
+    media: MediaPreviews,
+    zaps: ZapTotals,
     show_only_profile: bool,
     msgs_scroll_offset: scrollable::RelativeOffset,
     modal_state: ModalState,
@@ -101,7 +137,10 @@ pub struct State {
     hide_context_menu: bool,
     chat_message_pressed: Option<ChatMessage>,
     last_relays_response: Option<RelaysResponse>,
-    focus_pubkey: Option<XOnlyPublicKey>,
+    /// Contact to open once the chats arrive, added to them when missing
+    focus_contact: Option<DbContact>,
+    /// Message to scroll to once the chat messages arrive
+    jump_to_event: Option<i64>,
 }
 
 impl State {
@@ -113,6 +152,8 @@ impl State {
 
             chats: Vec::new(),
             messages: vec![],
+            media: MediaPreviews::new(),
+            zaps: ZapTotals::new(),
             ver_divider_position: Some(300),
             active_idx: None,
             show_only_profile: false,
@@ -124,7 +165,8 @@ impl State {
             hide_context_menu: true,
             chat_message_pressed: None,
             last_relays_response: None,
-            focus_pubkey: None,
+            focus_contact: None,
+            jump_to_event: None,
         })
     }
     pub(crate) fn chat_to(
@@ -132,12 +174,12 @@ impl State {
         conn: &mut BackEndConnection,
     ) -> Result<Self, BackendClosed> {
         let mut state = Self::new(conn)?;
-        state.focus_pubkey = Some(db_contact.pubkey().to_owned());
+        state.focus_contact = Some(db_contact);
         Ok(state)
     }
 
     pub fn subscription(&self) -> Subscription<Message> {
-        Subscription::none()
+        self.chat_view.subscription().map(Message::ChatView)
     }
 
     fn active_chat(&self) -> Option<&ChatContact> {
@@ -166,7 +208,14 @@ impl State {
         &mut self,
         conn: &mut BackEndConnection,
     ) -> Result<Option<Vec<Command<Message>>>, BackendClosed> {
-        if let Some(pubkey) = self.focus_pubkey.take() {
+        if let Some(focus_contact) = self.focus_contact.take() {
+            let pubkey = focus_contact.pubkey().to_owned();
+            self.contact_list.show_requests(!focus_contact.is_known());
+            if !self.chats.iter().any(|c| c.contact.pubkey() == &pubkey) {
+                // not a contact yet, the chat is stored once a message is sent
+                let new_chat = ChatContact::new(self.chats.len() as i32, &focus_contact, conn)?;
+                self.chats.push(new_chat);
+            }
             let idx = self
                 .chats
                 .iter()
@@ -198,6 +247,14 @@ impl State {
             .sort_by_key(|b| std::cmp::Reverse(b.last_message_date()));
     }
 
+    /// Downloads the images in the messages, if the active contact allows it
+    fn add_media(&mut self, conn: &mut BackEndConnection) -> Result<(), BackendClosed> {
+        let auto_load = self
+            .active_chat()
+            .map_or(false, |chat| chat.contact.auto_load_media());
+        self.media.add_messages(&self.messages, |_| auto_load, conn)
+    }
+
     fn close_modal(&mut self) -> Command<Message> {
         self.modal_state = ModalState::Off;
         scrollable::snap_to(CHAT_SCROLLABLE_ID.clone(), self.msgs_scroll_offset)
@@ -209,16 +266,77 @@ impl State {
         idx: i32,
         conn: &mut BackEndConnection,
     ) -> Result<Command<Message>, BackendClosed> {
+        self.flush_draft(true, conn)?;
         if let Some(chat) = self.chats.iter().find(|c| c.id == idx) {
             conn.send(ToBackend::FetchMessages(chat.contact.to_owned()))?;
             self.messages = vec![];
-            self.chat_view.update_dm_msg("".into());
+            self.chat_view.update_dm_msg(chat.draft().to_owned());
             self.active_idx = Some(idx);
             return Ok(text_input::focus(CHAT_INPUT_ID.clone()));
         }
         Ok(Command::none())
     }
 
+    /// Keeps the composer text as the active chat's draft
+    fn save_draft(
+        &mut self,
+        text: String,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        self.chat_view.update_dm_msg(text.clone());
+        self.chat_view.draft_saved();
+        if let Some(chat) = self.active_chat_mut() {
+            let chat_id = NotificationChat::Dm(chat.contact.pubkey().to_owned());
+            chat.set_draft(text.clone());
+            conn.send(ToBackend::SaveDraft(chat_id, text))?;
+        }
+        Ok(())
+    }
+
+    /// Saves the draft typed in the active chat once the typing paused,
+    /// or right away when `now`, before leaving the chat
+    fn flush_draft(
+        &mut self,
+        now: bool,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        let Some(text) = self.chat_view.unsaved_draft(now) else {
+            return Ok(());
+        };
+        if let Some(chat) = self.active_chat() {
+            let chat_id = NotificationChat::Dm(chat.contact.pubkey().to_owned());
+            conn.send(ToBackend::SaveDraft(chat_id, text))?;
+        }
+        Ok(())
+    }
+
+    /// Opens the chat of the search result, loading the messages around it
+    fn jump_to_message(
+        &mut self,
+        result: MessageSearchResult,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        let SearchChat::Contact(chat_pubkey) = result.chat else {
+            return Ok(());
+        };
+        self.flush_draft(true, conn)?;
+        if let Some(chat) = self
+            .chats
+            .iter()
+            .find(|c| c.contact.pubkey() == &chat_pubkey)
+        {
+            conn.send(ToBackend::FetchMessagesAround(
+                chat.contact.to_owned(),
+                result.created_at,
+            ))?;
+            self.active_idx = Some(chat.id);
+            self.messages = vec![];
+            self.chat_view.update_dm_msg(chat.draft().to_owned());
+            self.jump_to_event = Some(result.event_id);
+        }
+        Ok(())
+    }
+
     fn calculate_ctx_menu_pos(&mut self, point: iced_native::Point) {
         let total_h = self.chat_total_size.height;
         let window_h = self.chat_window_size.height;
@@ -264,6 +382,9 @@ impl State {
 
         // push into chat messages
         self.messages.push(chat_message.clone());
+        if active_chatting {
+            self.add_media(conn)?;
+        }
 
         // update chat card headers
         if let Some(contact_card) = self
@@ -318,9 +439,17 @@ impl Route for State {
                 &CHAT_SCROLLABLE_ID,
                 &CHAT_INPUT_ID,
                 &self.messages,
+                &self.media,
+                &self.zaps,
                 self.active_chat(),
             )
             .map(Message::ChatView);
+        let second_split: Element<_> = match self.active_chat() {
+            Some(chat) if !chat.contact.is_known() => {
+                column![request_bar(&chat.contact), second_split].into()
+            }
+            _ => second_split,
+        };
 
         let main_content = iced_aw::split::Split::new(
             first_split,
@@ -352,6 +481,8 @@ impl Route for State {
         let mut commands = RouterCommand::new();
 
         self.modal_state.backend_event(event.clone(), conn)?;
+        self.media.backend_event(&event, conn)?;
+        self.zaps.backend_event(&event);
 
         match event {
             BackendEvent::ImageDownloaded(image) => {
@@ -377,11 +508,19 @@ impl Route for State {
                     .iter_mut()
                     .find(|c| c.contact.pubkey() == db_contact.pubkey())
                 {
-                    contact_card.update_contact(db_contact, conn)?;
+                    contact_card.update_contact(db_contact.to_owned(), conn)?;
                 } else {
                     let new_chat = ChatContact::new(self.chats.len() as i32, &db_contact, conn)?;
                     self.chats.push(new_chat);
                 }
+                // an accepted request leaves the requests list
+                if self.active_matches(&db_contact) && db_contact.is_known() {
+                    self.contact_list.show_requests(false);
+                }
+                // the auto-load media setting may have changed
+                if self.active_matches(&db_contact) {
+                    self.add_media(conn)?;
+                }
             }
             BackendEvent::ContactDeleted(db_contact) => {
                 self.chats
@@ -434,7 +573,18 @@ impl Route for State {
             }
             BackendEvent::GotChatMessages(db_contact, chat_msgs) => {
                 if self.active_matches(&db_contact) {
-                    if self.messages.is_empty() {
+                    if let Some(event_id) = self.jump_to_event.take() {
+                        self.messages = chat_msgs;
+                        self.messages
+                            .sort_by(|a, b| a.display_time().cmp(&b.display_time()));
+                        self.msgs_scroll_offset =
+                            chat_view::message_scroll_offset(&self.messages, event_id)
+                                .unwrap_or(scrollable::RelativeOffset::END);
+                        commands.push(scrollable::snap_to(
+                            CHAT_SCROLLABLE_ID.clone(),
+                            self.msgs_scroll_offset,
+                        ));
+                    } else if self.messages.is_empty() {
                         self.messages = chat_msgs;
                         self.msgs_scroll_offset = scrollable::RelativeOffset::END;
                         commands.push(scrollable::snap_to(
@@ -448,6 +598,8 @@ impl Route for State {
 
                     self.messages
                         .sort_by(|a, b| a.display_time().cmp(&b.display_time()));
+                    self.add_media(conn)?;
+                    self.zaps.fetch(&self.messages, conn)?;
                     if let Some(c) = self.active_chat_mut() {
                         c.reset_unseen()
                     }
@@ -470,6 +622,32 @@ impl Route for State {
                     // conn.send(ToBackend::MessageSeen(message.msg_id))?;
                 }
             }
+            BackendEvent::MiningDone {
+                pending_hash,
+                event_hash,
+            } => {
+                for message in &mut self.messages {
+                    message.replace_pending_hash(&pending_hash, &event_hash);
+                }
+            }
+            BackendEvent::GotDraft(NotificationChat::Dm(pubkey), draft) => {
+                let active_pubkey = self.active_chat().map(|c| c.contact.pubkey().to_owned());
+                if let Some(chat) = self
+                    .chats
+                    .iter_mut()
+                    .find(|c| c.contact.pubkey() == &pubkey)
+                {
+                    chat.set_draft(draft.clone());
+                }
+                // the chat was opened before the draft arrived
+                if active_pubkey == Some(pubkey) && self.chat_view.dm_msg().is_empty() {
+                    self.chat_view.update_dm_msg(draft);
+                }
+            }
+            BackendEvent::MessagesExpired(event_ids) => {
+                self.messages
+                    .retain(|m| m.event_id().map_or(true, |id| !event_ids.contains(&id)));
+            }
             BackendEvent::PendingDM(db_contact, chat_message)
             | BackendEvent::ReceivedDM {
                 chat_message,
@@ -503,7 +681,8 @@ impl Route for State {
                         {
                             // If the position has changed and the updated contact is the currently focused contact
                             if current_position != prev_pos
-                                && self.focus_pubkey.as_ref() == Some(db_contact.pubkey())
+                                && self.focus_contact.as_ref().map(|c| c.pubkey())
+                                    == Some(db_contact.pubkey())
                             {
                                 // Recalculate the scroll offset
                                 let list_height: f32 = self.chats.iter().map(|c| c.height()).sum();
@@ -526,6 +705,20 @@ impl Route for State {
                     );
                 }
             }
+            BackendEvent::RFDPickedFile(path) => {
+                if self.chat_view.file_picked() {
+                    conn.send(ToBackend::UploadFile(path))?;
+                }
+            }
+            BackendEvent::RFDCancelPick => {
+                self.chat_view.pick_canceled();
+            }
+            BackendEvent::FileUploaded(attachment) => {
+                self.chat_view.upload_finished(Ok(attachment));
+            }
+            BackendEvent::UploadFailed(e) => {
+                self.chat_view.upload_finished(Err(e));
+            }
 
             _ => (),
         };
@@ -570,6 +763,29 @@ impl Route for State {
                     ));
                 }
             }
+            Message::AcceptRequestPressed { add_to_list } => {
+                if let Some(chat) = self.active_chat() {
+                    conn.send(ToBackend::AcceptRequest {
+                        db_contact: chat.contact.to_owned(),
+                        add_to_list,
+                    })?;
+                }
+            }
+            Message::BlockRequestPressed => {
+                if let Some(chat) = self.active_chat() {
+                    let db_contact = chat.contact.to_owned();
+                    conn.send(ToBackend::MutePubkey {
+                        pubkey: db_contact.pubkey().to_owned(),
+                        private: true,
+                    })?;
+                    conn.send(ToBackend::DeleteRequest(db_contact))?;
+                }
+            }
+            Message::DeleteRequestPressed => {
+                if let Some(chat) = self.active_chat() {
+                    conn.send(ToBackend::DeleteRequest(chat.contact.to_owned()))?;
+                }
+            }
             Message::CloseModal => {
                 commands.push(self.close_modal());
             }
@@ -596,6 +812,9 @@ impl Route for State {
                         basic_contact::CMessage::UnderlayMessage(message) => {
                             return self.update(message, conn);
                         }
+                        basic_contact::CMessage::ZapPressed(target, name) => {
+                            self.modal_state = ModalState::Zap(ZapModal::new(target, &name));
+                        }
                         other => {
                             let (cmd, close_modal) = state.update(other, conn)?;
                             if close_modal {
@@ -606,6 +825,91 @@ impl Route for State {
                     }
                 }
             }
+            Message::ModalMessageSearch(modal_msg) => {
+                if let ModalState::MessageSearch(state) = &mut self.modal_state {
+                    match *modal_msg {
+                        message_search::CMessage::UnderlayMessage(message) => {
+                            return self.update(message, conn);
+                        }
+                        message_search::CMessage::ResultPressed(result) => {
+                            self.modal_state = ModalState::Off;
+                            self.jump_to_message(result, conn)?;
+                        }
+                        other => {
+                            let (cmd, close_modal) = state.update(other, conn)?;
+                            if close_modal {
+                                commands.push(self.close_modal())
+                            }
+                            commands.push(cmd.map(|m| Message::ModalMessageSearch(Box::new(m))));
+                        }
+                    }
+                }
+            }
+            Message::ModalImageViewer(modal_msg) => {
+                if let ModalState::ImageViewer(state) = &mut self.modal_state {
+                    match *modal_msg {
+                        image_viewer::CMessage::UnderlayMessage(message) => {
+                            return self.update(message, conn);
+                        }
+                        other => {
+                            let (cmd, close_modal) = state.update(other, conn)?;
+                            if close_modal {
+                                commands.push(self.close_modal())
+                            }
+                            commands.push(cmd.map(|m| Message::ModalImageViewer(Box::new(m))));
+                        }
+                    }
+                }
+            }
+            Message::ModalZap(modal_msg) => {
+                if let ModalState::Zap(state) = &mut self.modal_state {
+                    match *modal_msg {
+                        zap::CMessage::UnderlayMessage(message) => {
+                            return self.update(message, conn);
+                        }
+                        other => {
+                            let (cmd, close_modal) = state.update(other, conn)?;
+                            if close_modal {
+                                commands.push(self.close_modal())
+                            }
+                            commands.push(cmd.map(|m| Message::ModalZap(Box::new(m))));
+                        }
+                    }
+                }
+            }
+            Message::ModalChatNotifications(modal_msg) => {
+                if let ModalState::ChatNotifications(state) = &mut self.modal_state {
+                    match *modal_msg {
+                        chat_notifications::CMessage::UnderlayMessage(message) => {
+                            return self.update(message, conn);
+                        }
+                        other => {
+                            let (cmd, close_modal) = state.update(other, conn)?;
+                            if close_modal {
+                                commands.push(self.close_modal())
+                            }
+                            commands
+                                .push(cmd.map(|m| Message::ModalChatNotifications(Box::new(m))));
+                        }
+                    }
+                }
+            }
+            Message::ModalChatExpiration(modal_msg) => {
+                if let ModalState::ChatExpiration(state) = &mut self.modal_state {
+                    match *modal_msg {
+                        chat_expiration::CMessage::UnderlayMessage(message) => {
+                            return self.update(message, conn);
+                        }
+                        other => {
+                            let (cmd, close_modal) = state.update(other, conn)?;
+                            if close_modal {
+                                commands.push(self.close_modal())
+                            }
+                            commands.push(cmd.map(|m| Message::ModalChatExpiration(Box::new(m))));
+                        }
+                    }
+                }
+            }
             Message::OnVerResize(position) => {
                 if position > 200 && position < 400 {
                     self.ver_divider_position = Some(position);
@@ -627,14 +931,34 @@ impl Route for State {
 
             Message::ChatView(chat_msg) => match chat_msg {
                 chat_view::Message::DMSentPress(dm_msg) => {
-                    if let (Some(chat_contact), false) = (self.active_chat(), dm_msg.is_empty()) {
-                        conn.send(ToBackend::SendDM(chat_contact.contact.to_owned(), dm_msg))?;
-                        self.chat_view.update_dm_msg("".into());
+                    let has_content = !dm_msg.is_empty() || self.chat_view.has_attachments();
+                    if let (Some(contact), true) = (
+                        self.active_chat().map(|c| c.contact.to_owned()),
+                        has_content,
+                    ) {
+                        let attachments = self.chat_view.take_attachments();
+                        conn.send(ToBackend::SendDM(contact, dm_msg, attachments))?;
+                        self.save_draft("".into(), conn)?;
                     }
                 }
+                chat_view::Message::AttachPressed => {
+                    self.chat_view.choosing_file();
+                    conn.send(ToBackend::ChooseFile(None))?;
+                }
+                chat_view::Message::RemoveAttachmentPressed(index) => {
+                    self.chat_view.remove_attachment(index);
+                }
+                chat_view::Message::DownloadAttachmentPressed(attachment) => {
+                    self.media.download(&attachment, conn)?;
+                }
                 chat_view::Message::DMNMessageChange(text) => {
-                    self.chat_view.update_dm_msg(text);
+                    // the chat list shows it right away, the database waits for a pause
+                    if let Some(chat) = self.active_chat_mut() {
+                        chat.set_draft(text.clone());
+                    }
+                    self.chat_view.edit_draft(text);
                 }
+                chat_view::Message::DraftTick => self.flush_draft(false, conn)?,
                 chat_view::Message::GotChatSize(size, child_size) => {
                     self.chat_window_size = size;
                     self.chat_total_size = child_size;
@@ -671,8 +995,53 @@ impl Route for State {
                 }
                 chat_view::Message::ChannelMenuPressed => {}
                 chat_view::Message::ChannelOpenModalPressed => {}
+                chat_view::Message::ChatSearchPressed => {
+                    if let Some(chat_contact) = self.active_chat() {
+                        let chat = SearchChat::Contact(chat_contact.contact.pubkey().to_owned());
+                        let names = self
+                            .chats
+                            .iter()
+                            .map(|c| (c.contact.pubkey().to_owned(), c.contact.select_name()))
+                            .collect();
+                        self.modal_state =
+                            ModalState::MessageSearch(MessageSearch::new(chat, names));
+                    }
+                }
+                chat_view::Message::NotificationsPressed => {
+                    if let Some(chat_contact) = self.active_chat() {
+                        let chat = NotificationChat::Dm(chat_contact.contact.pubkey().to_owned());
+                        let name = chat_contact.contact.select_name();
+                        self.modal_state = ModalState::ChatNotifications(
+                            ChatNotificationsModal::new(chat, &name, conn)?,
+                        );
+                    }
+                }
+                chat_view::Message::ExpirationPressed => {
+                    if let Some(chat_contact) = self.active_chat() {
+                        let chat = NotificationChat::Dm(chat_contact.contact.pubkey().to_owned());
+                        let name = chat_contact.contact.select_name();
+                        self.modal_state = ModalState::ChatExpiration(ChatExpirationModal::new(
+                            chat, &name, conn,
+                        )?);
+                    }
+                }
                 chat_view::Message::ChannelSearchPressed => {}
                 chat_view::Message::ChannelUserNamePressed(_) => {}
+                chat_view::Message::MentionPressed(_, _) => {}
+                chat_view::Message::MediaPressed(path) => {
+                    self.modal_state = ModalState::ImageViewer(ImageViewer::new(&path));
+                }
+                chat_view::Message::LoadMediaPressed(media_url) => {
+                    self.media.load(&media_url, conn)?;
+                }
+                chat_view::Message::LinkPressed(link_url) => {
+                    if let Err(e) = webbrowser::open(&link_url) {
+                        tracing::error!("Failed to open link: {}", e);
+                    }
+                }
+                chat_view::Message::ZapPressed(target, name) => {
+                    self.modal_state = ModalState::Zap(ZapModal::new(target, &name));
+                }
             },
 
             Message::ContactList(ct_msg) => match ct_msg {
@@ -685,6 +1054,9 @@ impl Route for State {
                 contact_list::Message::ContactPress(idx) => {
                     commands.push(self.set_active_contact(idx, conn)?);
                 }
+                contact_list::Message::RequestsPress => {
+                    self.contact_list.toggle_requests();
+                }
             },
         }
 
@@ -692,6 +1064,38 @@ impl Route for State {
     }
 }
 
+/// Actions on a message request, shown above its chat
+fn request_bar<'a>(db_contact: &DbContact) -> Element<'a, Message> {
+    let info = text(format!(
+        "{} is not in your contacts. Replying accepts the request.",
+        db_contact.select_name()
+    ))
+    .size(16)
+    .width(Length::Fill);
+    let accept_btn = button("Accept")
+        .style(style::Button::Primary)
+        .on_press(Message::AcceptRequestPressed { add_to_list: false });
+    let add_btn = button("Add to contacts")
+        .style(style::Button::Bordered)
+        .on_press(Message::AcceptRequestPressed { add_to_list: true });
+    let block_btn = button("Block")
+        .style(style::Button::Danger)
+        .on_press(Message::BlockRequestPressed);
+    let delete_btn = button("Delete")
+        .style(style::Button::Bordered)
+        .on_press(Message::DeleteRequestPressed);
+
+    container(
+        row![info, accept_btn, add_btn, block_btn, delete_btn]
+            .spacing(5)
+            .align_items(Alignment::Center),
+    )
+    .padding(10)
+    .width(Length::Fill)
+    .style(style::Container::Frame)
+    .into()
+}
+
 fn make_context_menu<'a>(response: &Option<RelaysResponse>) -> Element<'a, Message> {
     let copy_btn = button(
         row![
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use iced::widget::{button, column, container, image, row, text, Space};
 use iced::Length;
@@ -8,9 +8,11 @@ use nostr::EventId;
 use crate::components::common_scrollable;
 use crate::components::text::title;
 use crate::consts::{MEDIUM_CHANNEL_IMG_HEIGHT, MEDIUM_CHANNEL_IMG_WIDTH, YMD_FORMAT};
+use crate::db::ChannelPreviewMessage;
 use crate::error::BackendClosed;
 use crate::net::{BackEndConnection, BackendEvent, ToBackend};
-use crate::types::ChannelResult;
+use crate::types::{channel_last_activity, ChannelResult, ChannelSearchTerm};
+use crate::utils::{from_naive_utc_to_local, hide_string};
 use crate::views::RouterCommand;
 use crate::widget::Rule;
 use crate::{icon::search_icon, style, widget::Element};
@@ -22,18 +24,31 @@ pub enum Message {
     SearchInputChanged(String),
     SubmitPress,
     ChannelPressed(ChannelResult),
+    PreviewPressed(EventId),
 }
 pub struct State {
     search_results: HashMap<EventId, ChannelResult>,
     search_input_value: String,
+    /// Ranks the results
+    search_term: ChannelSearchTerm,
+    /// `t` tags of the results' events, which the search also matches
+    hashtags: HashMap<EventId, Vec<String>>,
     searching: bool,
+    /// Latest messages of each result, from the cache
+    previews: HashMap<EventId, Vec<ChannelPreviewMessage>>,
+    /// Results showing their preview
+    expanded_previews: HashSet<EventId>,
 }
 impl State {
     pub fn new(_conn: &mut BackEndConnection) -> Self {
         Self {
             search_results: HashMap::new(),
             search_input_value: String::new(),
+            search_term: ChannelSearchTerm::parse(""),
+            hashtags: HashMap::new(),
             searching: false,
+            previews: HashMap::new(),
+            expanded_previews: HashSet::new(),
         }
     }
     pub fn update(
@@ -45,12 +60,23 @@ impl State {
             Message::ChannelPressed(result) => {
                 return Ok(Some(HomeGoTo::Channel(result)));
             }
+            Message::PreviewPressed(channel_id) => {
+                if !self.expanded_previews.remove(&channel_id) {
+                    self.expanded_previews.insert(channel_id);
+                    // authors' names are shown once their profiles arrive
+                    conn.send(ToBackend::SubscribeChannelMembersMeta(channel_id))?;
+                }
+            }
             Message::SearchInputChanged(text) => {
                 self.search_input_value = text;
             }
             Message::SubmitPress => {
                 self.searching = true;
                 self.search_results = HashMap::new();
+                self.hashtags = HashMap::new();
+                self.previews = HashMap::new();
+                self.expanded_previews = HashSet::new();
+                self.search_term = ChannelSearchTerm::parse(&self.search_input_value);
                 conn.send(ToBackend::FindChannels(self.search_input_value.clone()))?;
             }
         }
@@ -65,7 +91,14 @@ impl State {
         let commands = RouterCommand::new();
 
         match event {
-            BackendEvent::ChannelSearchCacheCreation(url, cache) => {
+            BackendEvent::ChannelSearchCacheCreation(url, cache, hashtags) => {
+                // creation and metadata events can have different tags
+                let known = self.hashtags.entry(cache.channel_id).or_default();
+                for hashtag in hashtags {
+                    if !known.contains(&hashtag) {
+                        known.push(hashtag);
+                    }
+                }
                 self.search_results
                     .insert(cache.channel_id, ChannelResult::from_cache(url, cache));
             }
@@ -96,6 +129,22 @@ impl State {
 
                 if let Some(channel_id) = channel_id {
                     conn.send(ToBackend::FetchChannelCache(channel_id))?;
+                    conn.send(ToBackend::FetchChannelPreview(channel_id))?;
+                }
+            }
+            BackendEvent::GotChannelPreview(channel_id, messages) => {
+                if self.search_results.contains_key(&channel_id) {
+                    self.previews.insert(channel_id, messages);
+                }
+            }
+            BackendEvent::UpdatedMetadata(pubkey) => {
+                for channel_id in &self.expanded_previews {
+                    let has_author = self.previews.get(channel_id).map_or(false, |messages| {
+                        messages.iter().any(|msg| msg.author == pubkey)
+                    });
+                    if has_author {
+                        conn.send(ToBackend::FetchChannelPreview(*channel_id))?;
+                    }
                 }
             }
             BackendEvent::ImageDownloaded(image) => {
@@ -123,7 +172,7 @@ impl State {
 
         let search_input = container(
             row![
-                text_input("Channel id", &self.search_input_value)
+                text_input("Name, #hashtag or channel id", &self.search_input_value)
                     .width(Length::Fill)
                     .on_input(Message::SearchInputChanged)
                     .on_submit(Message::SubmitPress)
@@ -138,15 +187,32 @@ impl State {
         )
         .max_width(MAX_WIDTH_RESULT);
 
-        let results_container = self
-            .search_results
-            .iter()
-            .fold(column![], |acc, (_, result)| {
-                acc.push(channel_card(
-                    result,
-                    Message::ChannelPressed(result.to_owned()),
-                ))
-            });
+        // most relevant first, then the most recently active
+        let mut results: Vec<_> = self.search_results.values().collect();
+        results.sort_by_cached_key(|result| {
+            let hashtags = self
+                .hashtags
+                .get(&result.cache.channel_id)
+                .map_or(&[][..], |tags| tags.as_slice());
+            let relevance = self.search_term.relevance(&result.cache, hashtags);
+            (
+                std::cmp::Reverse(relevance),
+                std::cmp::Reverse(channel_last_activity(&result.cache)),
+            )
+        });
+        let results_container = results.into_iter().fold(column![], |acc, result| {
+            let channel_id = &result.cache.channel_id;
+            let preview = channel_preview(
+                channel_id,
+                self.previews.get(channel_id),
+                self.expanded_previews.contains(channel_id),
+            );
+            acc.push(channel_card(
+                result,
+                Message::ChannelPressed(result.to_owned()),
+                preview,
+            ))
+        });
 
         common_scrollable(
             container(column![
@@ -162,7 +228,11 @@ impl State {
     }
 }
 
-fn channel_card<'a, M: 'a + Clone>(channel: &ChannelResult, on_channel_press: M) -> Element<'a, M> {
+fn channel_card<'a, M: 'a + Clone>(
+    channel: &ChannelResult,
+    on_channel_press: M,
+    preview: Element<'a, M>,
+) -> Element<'a, M> {
     let image_container = container(image(channel.image_handle.to_owned()))
         .width(MEDIUM_CHANNEL_IMG_WIDTH)
         .height(MEDIUM_CHANNEL_IMG_HEIGHT)
@@ -218,6 +288,7 @@ fn channel_card<'a, M: 'a + Clone>(channel: &ChannelResult, on_channel_press: M)
                 .padding(0)
                 .style(style::Button::Invisible)
                 .on_press(on_channel_press),
+            preview,
             Rule::horizontal(10)
         ]
         .spacing(10),
@@ -227,5 +298,70 @@ fn channel_card<'a, M: 'a + Clone>(channel: &ChannelResult, on_channel_press: M)
     .into()
 }
 
+/// Read-only latest messages, to judge the channel before subscribing
+fn channel_preview<'a>(
+    channel_id: &EventId,
+    messages: Option<&'a Vec<ChannelPreviewMessage>>,
+    expanded: bool,
+) -> Element<'a, Message> {
+    let Some(messages) = messages else {
+        return text("").into();
+    };
+    if messages.is_empty() {
+        return text("No recent messages")
+            .size(14)
+            .style(style::Text::Alpha(0.8))
+            .into();
+    }
+
+    let toggle_text = if expanded {
+        "Hide messages".to_owned()
+    } else {
+        format!("Preview latest {} messages", messages.len())
+    };
+    let toggle_btn = button(text(toggle_text).size(14))
+        .style(style::Button::Link)
+        .on_press(Message::PreviewPressed(channel_id.to_owned()));
+
+    if !expanded {
+        return toggle_btn.into();
+    }
+
+    let messages_list = messages.iter().fold(column![].spacing(8), |acc, msg| {
+        acc.push(preview_message(msg))
+    });
+
+    column![
+        toggle_btn,
+        container(messages_list)
+            .padding(10)
+            .width(Length::Fill)
+            .style(style::Container::Bordered)
+    ]
+    .spacing(5)
+    .into()
+}
+
+fn preview_message<'a>(msg: &'a ChannelPreviewMessage) -> Element<'a, Message> {
+    let author = msg
+        .author_name
+        .clone()
+        .unwrap_or_else(|| hide_string(&msg.author.to_string(), 4));
+    let date = from_naive_utc_to_local(msg.created_at)
+        .format("%Y-%m-%d %H:%M")
+        .to_string();
+
+    column![
+        row![
+            text(author).size(14).style(style::Text::Primary),
+            text(date).size(12).style(style::Text::Alpha(0.8)),
+        ]
+        .spacing(10),
+        text(&msg.content).size(16),
+    ]
+    .spacing(2)
+    .into()
+}
+
 const BOTTOM_ROW_HEIGHT: u16 = 20;
 const MAX_WIDTH_RESULT: u16 = 800;
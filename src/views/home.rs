@@ -1,16 +1,17 @@
-use iced::widget::{button, column, container, image, image::Handle, row, Rule};
+use iced::widget::{button, column, container, image, image::Handle, row, text, tooltip, Rule};
 use iced::{alignment, Length, Subscription};
 use nostr::EventId;
 use status_bar::StatusBar;
 
 use crate::components::{invisible_scrollable, status_bar};
 use crate::consts::default_channel_image;
-use crate::db::{ChannelCache, DbContact};
+use crate::db::{ChannelActivity, ChannelCache, DbContact};
 use crate::error::BackendClosed;
 use crate::icon::{settings_icon, wand_icon};
 use crate::net::{BackEndConnection, BackendEvent, ImageSize, ToBackend};
 
 use crate::types::ChannelResult;
+use crate::utils::add_ellipsis_trunc;
 use crate::widget::Text;
 use crate::{
     icon::{home_icon, search_icon},
@@ -98,12 +99,35 @@ impl Route for State {
                 self.channels_subscribed = channels
                     .into_iter()
                     .map(ChannelMenuBtn::with_cache)
-                    .collect()
+                    .collect();
+                for btn in &self.channels_subscribed {
+                    conn.send(ToBackend::FetchChannelActivity(btn.channel_id))?;
+                }
             }
             BackendEvent::ChannelSubscribed(channel_id) => {
                 self.channels_subscribed
                     .push(ChannelMenuBtn::new(channel_id));
                 conn.send(ToBackend::FetchChannelCache(channel_id))?;
+                conn.send(ToBackend::FetchChannelActivity(channel_id))?;
+            }
+            BackendEvent::GotChannelActivity(activity) => {
+                if let Some(btn) = self
+                    .channels_subscribed
+                    .iter_mut()
+                    .find(|btn| btn.channel_id == activity.channel_id)
+                {
+                    btn.activity = Some(activity);
+                }
+            }
+            BackendEvent::ReceivedChannelMessage(channel_id, _) => {
+                // the open channel marks its messages as read
+                let is_subscribed = self
+                    .channels_subscribed
+                    .iter()
+                    .any(|btn| btn.channel_id == channel_id);
+                if is_subscribed && !self.active_view.is_channel_selected(&channel_id) {
+                    conn.send(ToBackend::FetchChannelActivity(channel_id))?;
+                }
             }
             BackendEvent::ChannelUnsubscribed(channel_id) => {
                 self.channels_subscribed
@@ -204,6 +228,21 @@ impl Route for State {
             }
             Message::Channel(msg) => {
                 if let ViewState::Channel { state } = &mut self.active_view {
+                    if let Some((channel_id, result)) = state.other_channel_result(&msg) {
+                        let is_subscribed = self
+                            .channels_subscribed
+                            .iter()
+                            .any(|btn| btn.channel_id == channel_id);
+                        self.active_view = ViewState::Channel {
+                            state: channel::Channel::load_at(
+                                &result,
+                                channel_id,
+                                is_subscribed,
+                                conn,
+                            )?,
+                        };
+                        return Ok(commands);
+                    }
                     return Ok(state.update(msg, conn)?.map(Message::Channel));
                 }
             }
@@ -242,6 +281,8 @@ impl Route for State {
                     col.push(make_channel_menu_btn(
                         is_active,
                         btn.image_handle.to_owned(),
+                        btn.unread_count(),
+                        btn.tooltip_text(),
                         message,
                     ))
                 });
@@ -315,6 +356,8 @@ fn make_menu_btn<'a, M: 'a + Clone>(
 fn make_channel_menu_btn<'a, M: 'a + Clone>(
     is_active: bool,
     image_handle: Handle,
+    unread_count: i64,
+    tooltip_text: Option<String>,
     message: M,
 ) -> Element<'a, M> {
     let style = if is_active {
@@ -323,17 +366,45 @@ fn make_channel_menu_btn<'a, M: 'a + Clone>(
         style::Button::MenuBtn
     };
 
-    container(
-        button(image(image_handle).width(Length::Fill).height(Length::Fill))
-            .style(style)
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .on_press(message),
-    )
-    .padding([PADDING_V, PADDING_H])
-    .width(NAVBAR_WIDTH)
-    .height(NAVBAR_WIDTH)
-    .into()
+    let channel_image = image(image_handle).width(Length::Fill).height(Length::Fill);
+    let content: Element<_> = match unread_count {
+        0 => channel_image.into(),
+        count => {
+            let count_txt = match count {
+                1..=99 => count.to_string(),
+                _ => "99+".into(),
+            };
+            column![
+                channel_image,
+                container(
+                    button(text(count_txt).size(UNREAD_BADGE_SIZE))
+                        .padding([0, 4])
+                        .style(style::Button::Notification)
+                )
+                .width(Length::Fill)
+                .center_x()
+            ]
+            .into()
+        }
+    };
+
+    let channel_btn = button(content)
+        .style(style)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .on_press(message);
+    let channel_btn: Element<_> = match tooltip_text {
+        Some(tooltip_text) => tooltip(channel_btn, tooltip_text, tooltip::Position::Right)
+            .style(style::Container::TooltipBg)
+            .into(),
+        None => channel_btn.into(),
+    };
+
+    container(channel_btn)
+        .padding([PADDING_V, PADDING_H])
+        .width(NAVBAR_WIDTH)
+        .height(NAVBAR_WIDTH)
+        .into()
 }
 
 pub enum ViewState {
@@ -406,6 +477,7 @@ pub struct ChannelMenuBtn {
     channel_id: EventId,
     cache: Option<ChannelCache>,
     image_handle: Handle,
+    activity: Option<ChannelActivity>,
 }
 impl ChannelMenuBtn {
     pub fn new(channel_id: EventId) -> Self {
@@ -413,6 +485,7 @@ impl ChannelMenuBtn {
             channel_id,
             cache: None,
             image_handle: Handle::from_memory(default_channel_image(IMAGE_SIZE)),
+            activity: None,
         }
     }
     pub fn with_cache(cache: ChannelCache) -> Self {
@@ -428,9 +501,34 @@ impl ChannelMenuBtn {
             channel_id: cache.channel_id,
             cache: Some(cache),
             image_handle,
+            activity: None,
         }
     }
 
+    fn unread_count(&self) -> i64 {
+        self.activity
+            .as_ref()
+            .map_or(0, |activity| activity.unread_count)
+    }
+
+    /// Channel name and a snippet of its last message
+    fn tooltip_text(&self) -> Option<String> {
+        let name = self.cache.as_ref()?.metadata.name.clone();
+        let name = name.unwrap_or_else(|| "Channel".to_owned());
+        let last_message = self
+            .activity
+            .as_ref()
+            .and_then(|activity| activity.last_message.as_deref());
+        Some(match last_message {
+            Some(last_message) => format!(
+                "{}\n{}",
+                name,
+                add_ellipsis_trunc(last_message, LAST_MESSAGE_SNIPPET_LEN)
+            ),
+            None => name,
+        })
+    }
+
     fn update_cache(&mut self, cache: ChannelCache) {
         let image_handle = cache
             .image_cache
@@ -449,5 +547,7 @@ const NAVBAR_WIDTH: u16 = 55;
 const PADDING_V: u16 = 6;
 const PADDING_H: u16 = 6;
 const ICON_SIZE: u16 = 26;
+const UNREAD_BADGE_SIZE: u16 = 12;
+const LAST_MESSAGE_SNIPPET_LEN: usize = 40;
 
 const IMAGE_SIZE: ImageSize = ImageSize::Small;
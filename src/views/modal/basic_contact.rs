@@ -5,10 +5,11 @@ use crate::components::{card, common_scrollable};
 use crate::consts::{MEDIUM_PROFILE_IMG_HEIGHT, MEDIUM_PROFILE_IMG_WIDTH, YMD_FORMAT};
 use crate::db::DbContact;
 use crate::error::BackendClosed;
-use crate::icon::{copy_icon, edit_icon};
+use crate::icon::{bolt_icon, copy_icon, edit_icon};
 use crate::net::{self, BackEndConnection, BackendEvent, ImageSize};
+use crate::types::ZapTarget;
 use crate::utils::{from_naive_utc_to_local, hide_string};
-use iced::widget::{button, column, container, image, row, text, tooltip, Space};
+use iced::widget::{button, checkbox, column, container, image, row, text, tooltip, Space};
 use iced::{alignment, clipboard};
 use iced::{Alignment, Command, Length};
 use iced_aw::Modal;
@@ -30,18 +31,22 @@ pub enum CMessage<M: Clone + Debug> {
     PetNameInputChange(String),
     PubKeyInputChange(String),
     RecRelayInputChange(String),
+    AutoLoadMediaToggled(bool),
     SubmitContact,
     CloseModal,
     EditMode,
     UnderlayMessage(M),
     CopyPubkey,
     DeleteContact,
+    /// Intercepted by the route, which opens the zap modal
+    ZapPressed(ZapTarget, String),
 }
 pub struct ContactDetails<M: Clone + Debug> {
     db_contact: Option<DbContact>,
     petname_input: String,
     pubkey_input: String,
     rec_relay_input: String,
+    auto_load_media_input: bool,
     mode: Mode,
     is_pub_invalid: bool,
     is_relay_invalid: bool,
@@ -56,6 +61,7 @@ impl<M: Clone + Debug> ContactDetails<M> {
             petname_input: "".into(),
             pubkey_input: "".into(),
             rec_relay_input: "".into(),
+            auto_load_media_input: false,
             mode: Mode::Add,
             is_pub_invalid: false,
             is_relay_invalid: false,
@@ -81,6 +87,7 @@ impl<M: Clone + Debug> ContactDetails<M> {
                 .get_relay_url()
                 .map(|url| url.to_string())
                 .unwrap_or("".into()),
+            auto_load_media_input: db_contact.auto_load_media(),
             mode: Mode::Edit,
             is_pub_invalid: false,
             is_relay_invalid: false,
@@ -116,6 +123,7 @@ impl<M: Clone + Debug> ContactDetails<M> {
 
         match submit_result {
             Ok(db_contact) => {
+                let db_contact = db_contact.with_auto_load_media(self.auto_load_media_input);
                 match self.mode {
                     Mode::Edit => conn.send(net::ToBackend::UpdateContact(db_contact))?,
                     Mode::Add => conn.send(net::ToBackend::AddContact(db_contact))?,
@@ -189,10 +197,17 @@ impl<M: Clone + Debug + 'static + Send> ModalView for ContactDetails<M> {
                         rec_relay_input = rec_relay_input.invalid("Invalid Relay URL");
                     }
 
+                    let auto_load_media = checkbox(
+                        "Load images sent by this contact automatically",
+                        self.auto_load_media_input,
+                        CMessage::AutoLoadMediaToggled,
+                    );
+
                     column![
                         pubkey_input.build(),
                         petname_input.build(),
-                        rec_relay_input.build()
+                        rec_relay_input.build(),
+                        auto_load_media
                     ]
                     .spacing(4)
                     .into()
@@ -246,7 +261,21 @@ impl<M: Clone + Debug + 'static + Send> ModalView for ContactDetails<M> {
                             .style(style::Container::Frame),
                     ]
                     .spacing(2);
-                    let middle = column![pubkey_group, petname_group, relay_group].spacing(4);
+                    let auto_load_text = if self.auto_load_media_input {
+                        "Loaded automatically"
+                    } else {
+                        "Loaded on request"
+                    };
+                    let auto_load_group = column![
+                        text("Images"),
+                        container(text(auto_load_text))
+                            .padding([2, 8])
+                            .style(style::Container::Frame),
+                    ]
+                    .spacing(2);
+                    let middle = column![pubkey_group, petname_group, relay_group, auto_load_group]
+                        .push(make_lightning_group(self.db_contact.as_ref()))
+                        .spacing(4);
                     let profile_top = make_profile_top_row(
                         self.db_contact.as_ref(),
                         self.profile_img_handle.as_ref(),
@@ -341,6 +370,9 @@ impl<M: Clone + Debug + 'static + Send> ModalView for ContactDetails<M> {
                 self.rec_relay_input = text;
                 self.is_relay_invalid = false;
             }
+            CMessage::AutoLoadMediaToggled(auto_load_media) => {
+                self.auto_load_media_input = auto_load_media;
+            }
             CMessage::SubmitContact => {
                 let is_close = self.handle_submit_contact(conn)?;
                 return Ok((command, is_close));
@@ -348,13 +380,57 @@ impl<M: Clone + Debug + 'static + Send> ModalView for ContactDetails<M> {
             CMessage::CloseModal => {
                 return Ok((command, true));
             }
-            CMessage::UnderlayMessage(_) => (),
+            CMessage::UnderlayMessage(_) | CMessage::ZapPressed(..) => (),
         }
 
         Ok((command, false))
     }
 }
 
+/// Lightning address of the contact's profile, with a button to zap them
+fn make_lightning_group<'a, M: 'a + Clone + Debug>(
+    db_contact: Option<&DbContact>,
+) -> Element<'a, CMessage<M>> {
+    let Some(db_contact) = db_contact else {
+        return text("").into();
+    };
+    let address = db_contact.get_profile_cache().and_then(|profile| {
+        [profile.metadata.lud16, profile.metadata.lud06]
+            .into_iter()
+            .flatten()
+            .find(|address| !address.trim().is_empty())
+    });
+    let Some(address) = address else {
+        return text("").into();
+    };
+
+    let zap_btn = button(
+        row![bolt_icon().size(14), text("Zap").size(16)]
+            .spacing(5)
+            .align_items(Alignment::Center),
+    )
+    .style(style::Button::Primary)
+    .on_press(CMessage::ZapPressed(
+        ZapTarget::profile(db_contact.pubkey().to_owned()),
+        db_contact.select_name(),
+    ));
+    column![
+        text("Lightning"),
+        container(
+            row![
+                container(text(hide_string(&address, 16))).width(Length::Fill),
+                zap_btn
+            ]
+            .align_items(Alignment::Center)
+            .spacing(5)
+        )
+        .padding([2, 4, 2, 8])
+        .width(Length::Fill),
+    ]
+    .spacing(2)
+    .into()
+}
+
 fn make_profile_top_row<'a, M: 'a + Clone>(
     db_contact: Option<&'a DbContact>,
     img_handle: Option<&image::Handle>,
@@ -0,0 +1,144 @@
+use crate::components::card;
+use crate::db::{ExpirationTimer, NotificationChat};
+use crate::net::{BackEndConnection, BackendEvent, ToBackend};
+use crate::style;
+use crate::widget::Element;
+use iced::alignment;
+use iced::widget::{button, column, container, row, text};
+use iced::{Command, Length};
+use iced_aw::Modal;
+use std::fmt::Debug;
+
+use super::ModalView;
+
+#[derive(Debug, Clone)]
+pub enum CMessage<M: Clone + Debug> {
+    TimerPressed(ExpirationTimer),
+    CloseModal,
+    UnderlayMessage(M),
+}
+
+/// Expiration timer of the messages sent to one chat
+pub struct ChatExpirationModal<M: Clone + Debug> {
+    chat_name: String,
+    chat: NotificationChat,
+    /// None until the backend answers
+    timer: Option<ExpirationTimer>,
+    phantom: std::marker::PhantomData<M>,
+}
+impl<M: Clone + Debug> ChatExpirationModal<M> {
+    pub fn new(
+        chat: NotificationChat,
+        chat_name: &str,
+        conn: &mut BackEndConnection,
+    ) -> Result<Self, crate::error::BackendClosed> {
+        conn.send(ToBackend::FetchChatExpiration(chat.to_owned()))?;
+        Ok(Self {
+            chat_name: chat_name.to_owned(),
+            chat,
+            timer: None,
+            phantom: std::marker::PhantomData,
+        })
+    }
+
+    fn timer_view<'a>(&'a self, timer: ExpirationTimer) -> Element<'a, CMessage<M>> {
+        let status = match timer {
+            ExpirationTimer::Off => text("New messages don't expire"),
+            other => {
+                text(format!("New messages expire after {}", other)).style(style::Text::Primary)
+            }
+        };
+
+        let timer_buttons =
+            ExpirationTimer::ALL
+                .iter()
+                .fold(column![].spacing(5), |buttons, option| {
+                    let style = if *option == timer {
+                        style::Button::Primary
+                    } else {
+                        style::Button::Bordered
+                    };
+                    buttons.push(
+                        button(text(option.to_string()))
+                            .width(Length::Fill)
+                            .style(style)
+                            .on_press(CMessage::TimerPressed(*option)),
+                    )
+                });
+
+        let note = text("Relays and clients without NIP-40 support may keep expired messages")
+            .size(14)
+            .style(style::Text::Alpha(0.8));
+
+        column![status.size(16), timer_buttons, note]
+            .spacing(10)
+            .into()
+    }
+}
+
+impl<M: Clone + Debug + 'static + Send> ModalView for ChatExpirationModal<M> {
+    type UnderlayMessage = M;
+    type Message = CMessage<M>;
+
+    fn backend_event(
+        &mut self,
+        event: BackendEvent,
+        _conn: &mut BackEndConnection,
+    ) -> Result<(), crate::error::BackendClosed> {
+        if let BackendEvent::GotChatExpiration(chat, timer) = event {
+            if chat == self.chat {
+                self.timer = Some(timer);
+            }
+        }
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        message: Self::Message,
+        conn: &mut BackEndConnection,
+    ) -> Result<(Command<Self::Message>, bool), crate::error::BackendClosed> {
+        let command = Command::none();
+        match message {
+            CMessage::TimerPressed(timer) => {
+                conn.send(ToBackend::SetChatExpiration(self.chat.to_owned(), timer))?;
+            }
+            CMessage::UnderlayMessage(_) => (),
+            CMessage::CloseModal => return Ok((command, true)),
+        }
+        Ok((command, false))
+    }
+
+    fn view<'a>(
+        &'a self,
+        underlay: impl Into<Element<'a, Self::UnderlayMessage>>,
+    ) -> Element<'a, Self::Message> {
+        let underlay_component = underlay.into().map(CMessage::UnderlayMessage);
+        Modal::new(true, underlay_component, move || {
+            let title =
+                container(text(format!("Disappearing messages in {}", self.chat_name)).size(22))
+                    .center_x();
+            let content = match self.timer {
+                Some(timer) => self.timer_view(timer),
+                None => text("Loading...").into(),
+            };
+            let card_body = container(column![title, content].spacing(15))
+                .center_x()
+                .padding(20);
+
+            let card_footer =
+                row![
+                    button(text("Close").horizontal_alignment(alignment::Horizontal::Center))
+                        .width(Length::Fill)
+                        .on_press(CMessage::CloseModal)
+                ];
+
+            card(card_body, card_footer).max_width(MODAL_WIDTH).into()
+        })
+        .backdrop(CMessage::CloseModal)
+        .on_esc(CMessage::CloseModal)
+        .into()
+    }
+}
+
+const MODAL_WIDTH: f32 = 400.0;
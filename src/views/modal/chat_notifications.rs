@@ -0,0 +1,181 @@
+use crate::components::card;
+use crate::db::{ChatNotification, Mute, NotificationChat};
+use crate::net::{BackEndConnection, BackendEvent, ToBackend};
+use crate::style;
+use crate::utils::from_naive_utc_to_local;
+use crate::widget::Element;
+use chrono::{Duration, Utc};
+use iced::alignment;
+use iced::widget::{button, checkbox, column, container, row, text};
+use iced::{Command, Length};
+use iced_aw::Modal;
+use std::fmt::Debug;
+
+use super::ModalView;
+
+#[derive(Debug, Clone)]
+pub enum CMessage<M: Clone + Debug> {
+    MutePressed(Option<Duration>),
+    UnmutePressed,
+    MentionsOnlyToggled(bool),
+    CloseModal,
+    UnderlayMessage(M),
+}
+
+/// Mute and mention rules of one chat
+pub struct ChatNotificationsModal<M: Clone + Debug> {
+    chat_name: String,
+    chat: NotificationChat,
+    /// None until the backend answers
+    rules: Option<ChatNotification>,
+    phantom: std::marker::PhantomData<M>,
+}
+impl<M: Clone + Debug> ChatNotificationsModal<M> {
+    pub fn new(
+        chat: NotificationChat,
+        chat_name: &str,
+        conn: &mut BackEndConnection,
+    ) -> Result<Self, crate::error::BackendClosed> {
+        conn.send(ToBackend::FetchChatNotification(chat.to_owned()))?;
+        Ok(Self {
+            chat_name: chat_name.to_owned(),
+            chat,
+            rules: None,
+            phantom: std::marker::PhantomData,
+        })
+    }
+
+    fn save(
+        &mut self,
+        conn: &mut BackEndConnection,
+        change: impl FnOnce(&mut ChatNotification),
+    ) -> Result<(), crate::error::BackendClosed> {
+        if let Some(rules) = &mut self.rules {
+            change(rules);
+            conn.send(ToBackend::SetChatNotification(rules.to_owned()))?;
+        }
+        Ok(())
+    }
+
+    fn rules_view<'a>(&'a self, rules: &'a ChatNotification) -> Element<'a, CMessage<M>> {
+        let status = match rules.mute {
+            _ if !rules.is_muted(Utc::now().naive_utc()) => {
+                text("Notifications are on").style(style::Text::Primary)
+            }
+            Mute::Until(until) => text(format!(
+                "Muted until {}",
+                from_naive_utc_to_local(until).format("%Y-%m-%d %H:%M")
+            )),
+            Mute::Off | Mute::Forever => text("Muted until you unmute"),
+        };
+
+        let mute_buttons =
+            MUTE_OPTIONS
+                .iter()
+                .fold(column![].spacing(5), |buttons, (label, hours)| {
+                    buttons.push(
+                        button(text(*label))
+                            .width(Length::Fill)
+                            .style(style::Button::Bordered)
+                            .on_press(CMessage::MutePressed(hours.map(Duration::hours))),
+                    )
+                });
+        let mut unmute_btn = button("Unmute")
+            .width(Length::Fill)
+            .style(style::Button::Primary);
+        if rules.is_muted(Utc::now().naive_utc()) {
+            unmute_btn = unmute_btn.on_press(CMessage::UnmutePressed);
+        }
+
+        let mut content = column![status.size(16), mute_buttons, unmute_btn].spacing(10);
+        if let NotificationChat::Channel(_) = rules.chat {
+            content = content.push(checkbox(
+                "Only notify when I'm mentioned",
+                rules.mentions_only,
+                CMessage::MentionsOnlyToggled,
+            ));
+        }
+        content.into()
+    }
+}
+
+impl<M: Clone + Debug + 'static + Send> ModalView for ChatNotificationsModal<M> {
+    type UnderlayMessage = M;
+    type Message = CMessage<M>;
+
+    fn backend_event(
+        &mut self,
+        event: BackendEvent,
+        _conn: &mut BackEndConnection,
+    ) -> Result<(), crate::error::BackendClosed> {
+        if let BackendEvent::GotChatNotification(rules) = event {
+            if rules.chat == self.chat {
+                self.rules = Some(rules);
+            }
+        }
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        message: Self::Message,
+        conn: &mut BackEndConnection,
+    ) -> Result<(Command<Self::Message>, bool), crate::error::BackendClosed> {
+        let command = Command::none();
+        match message {
+            CMessage::MutePressed(duration) => {
+                let mute = match duration {
+                    Some(duration) => Mute::Until(Utc::now().naive_utc() + duration),
+                    None => Mute::Forever,
+                };
+                self.save(conn, |rules| rules.mute = mute)?;
+            }
+            CMessage::UnmutePressed => self.save(conn, |rules| rules.mute = Mute::Off)?,
+            CMessage::MentionsOnlyToggled(mentions_only) => {
+                self.save(conn, |rules| rules.mentions_only = mentions_only)?
+            }
+            CMessage::UnderlayMessage(_) => (),
+            CMessage::CloseModal => return Ok((command, true)),
+        }
+        Ok((command, false))
+    }
+
+    fn view<'a>(
+        &'a self,
+        underlay: impl Into<Element<'a, Self::UnderlayMessage>>,
+    ) -> Element<'a, Self::Message> {
+        let underlay_component = underlay.into().map(CMessage::UnderlayMessage);
+        Modal::new(true, underlay_component, move || {
+            let title = container(text(format!("Notifications for {}", self.chat_name)).size(22))
+                .center_x();
+            let content = match &self.rules {
+                Some(rules) => self.rules_view(rules),
+                None => text("Loading...").into(),
+            };
+            let card_body = container(column![title, content].spacing(15))
+                .center_x()
+                .padding(20);
+
+            let card_footer =
+                row![
+                    button(text("Close").horizontal_alignment(alignment::Horizontal::Center))
+                        .width(Length::Fill)
+                        .on_press(CMessage::CloseModal)
+                ];
+
+            card(card_body, card_footer).max_width(MODAL_WIDTH).into()
+        })
+        .backdrop(CMessage::CloseModal)
+        .on_esc(CMessage::CloseModal)
+        .into()
+    }
+}
+
+/// Label and hours, None mutes until unmuted
+const MUTE_OPTIONS: [(&str, Option<i64>); 4] = [
+    ("Mute for 1 hour", Some(1)),
+    ("Mute for 8 hours", Some(8)),
+    ("Mute for 1 week", Some(24 * 7)),
+    ("Mute until I unmute", None),
+];
+const MODAL_WIDTH: f32 = 400.0;
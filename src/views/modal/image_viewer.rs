@@ -0,0 +1,81 @@
+use crate::net::BackEndConnection;
+use crate::style;
+use crate::widget::Element;
+use iced::alignment;
+use iced::widget::image::{Handle, Image};
+use iced::widget::{button, column, container, text};
+use iced::{Command, Length};
+use iced_aw::Modal;
+use std::fmt::Debug;
+use std::path::Path;
+
+use super::ModalView;
+
+#[derive(Debug, Clone)]
+pub enum CMessage<M: Clone + Debug> {
+    CloseModal,
+    UnderlayMessage(M),
+}
+
+/// Shows a chat image in its original size
+pub struct ImageViewer<M: Clone + Debug> {
+    handle: Handle,
+    phantom: std::marker::PhantomData<M>,
+}
+impl<M: Clone + Debug> ImageViewer<M> {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            handle: Handle::from_path(path),
+            phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<M: Clone + Debug + 'static + Send> ModalView for ImageViewer<M> {
+    type UnderlayMessage = M;
+    type Message = CMessage<M>;
+
+    fn update(
+        &mut self,
+        message: Self::Message,
+        conn: &mut BackEndConnection,
+    ) -> Result<(Command<Self::Message>, bool), crate::error::BackendClosed> {
+        let command = Command::none();
+        match message {
+            CMessage::UnderlayMessage(_) => (),
+            CMessage::CloseModal => return Ok((command, true)),
+        }
+        Ok((command, false))
+    }
+
+    fn view<'a>(
+        &'a self,
+        underlay: impl Into<Element<'a, Self::UnderlayMessage>>,
+    ) -> Element<'a, Self::Message> {
+        let underlay_component = underlay.into().map(CMessage::UnderlayMessage);
+        Modal::new(true, underlay_component, move || {
+            let image = container(Image::new(self.handle.to_owned()))
+                .max_width(VIEWER_MAX_WIDTH)
+                .max_height(VIEWER_MAX_HEIGHT)
+                .center_x()
+                .center_y();
+
+            let close_btn =
+                button(text("Close").horizontal_alignment(alignment::Horizontal::Center))
+                    .width(Length::Fill)
+                    .on_press(CMessage::CloseModal);
+
+            container(column![image, close_btn].spacing(10))
+                .padding(10)
+                .max_width(VIEWER_MAX_WIDTH)
+                .style(style::Container::CardBody)
+                .into()
+        })
+        .backdrop(CMessage::CloseModal)
+        .on_esc(CMessage::CloseModal)
+        .into()
+    }
+}
+
+const VIEWER_MAX_WIDTH: f32 = 800.0;
+const VIEWER_MAX_HEIGHT: f32 = 600.0;
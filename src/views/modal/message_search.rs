@@ -0,0 +1,327 @@
+use super::ModalView;
+use crate::components::text_input_group::TextInputGroup;
+use crate::components::{card, common_scrollable};
+use crate::consts::YMD_FORMAT;
+use crate::db::{MessageSearchQuery, MessageSearchResult, SearchChat, SearchScope};
+use crate::error::BackendClosed;
+use crate::net::{BackEndConnection, BackendEvent, ToBackend};
+use crate::style;
+use crate::utils::{from_naive_utc_to_local, hide_string};
+use crate::widget::Element;
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use iced::alignment::Horizontal;
+use iced::widget::{button, column, container, radio, row, text, Space};
+use iced::{Command, Length};
+use iced_aw::Modal;
+use nostr::prelude::FromBech32;
+use nostr::secp256k1::XOnlyPublicKey;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::str::FromStr;
+
+#[derive(Debug, Clone)]
+pub enum CMessage<M: Clone + Debug> {
+    CloseModal,
+    UnderlayMessage(M),
+    TextInputChange(String),
+    AuthorInputChange(String),
+    SinceInputChange(String),
+    UntilInputChange(String),
+    ScopeChanged(ScopeChoice),
+    SearchPressed,
+    ResultPressed(MessageSearchResult),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeChoice {
+    ThisChat,
+    AllChats,
+}
+
+pub struct MessageSearch<M: Clone + Debug> {
+    chat: SearchChat,
+    /// Names used to show and filter authors
+    names: HashMap<XOnlyPublicKey, String>,
+    scope: ScopeChoice,
+    text_input: String,
+    author_input: String,
+    since_input: String,
+    until_input: String,
+    invalid_author: bool,
+    invalid_since: bool,
+    invalid_until: bool,
+    searching: bool,
+    results: Option<Vec<MessageSearchResult>>,
+    phantom: std::marker::PhantomData<M>,
+}
+impl<M: Clone + Debug> MessageSearch<M> {
+    pub fn new(chat: SearchChat, names: HashMap<XOnlyPublicKey, String>) -> Self {
+        Self {
+            chat,
+            names,
+            scope: ScopeChoice::ThisChat,
+            text_input: "".into(),
+            author_input: "".into(),
+            since_input: "".into(),
+            until_input: "".into(),
+            invalid_author: false,
+            invalid_since: false,
+            invalid_until: false,
+            searching: false,
+            results: None,
+            phantom: std::marker::PhantomData,
+        }
+    }
+
+    fn search_scope(&self) -> SearchScope {
+        match (self.scope, &self.chat) {
+            (ScopeChoice::ThisChat, chat) => SearchScope::Chat(chat.to_owned()),
+            (ScopeChoice::AllChats, SearchChat::Contact(_)) => SearchScope::DirectMessages,
+            (ScopeChoice::AllChats, SearchChat::Channel(_)) => SearchScope::Channels,
+        }
+    }
+
+    fn parse_author(&self) -> Result<Option<XOnlyPublicKey>, ()> {
+        let author = self.author_input.trim();
+        if author.is_empty() {
+            return Ok(None);
+        }
+        if let Some((pubkey, _)) = self
+            .names
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(author))
+        {
+            return Ok(Some(pubkey.to_owned()));
+        }
+        XOnlyPublicKey::from_bech32(author)
+            .or_else(|_| XOnlyPublicKey::from_str(author))
+            .map(Some)
+            .map_err(|_| ())
+    }
+
+    fn author_name(&self, author: &XOnlyPublicKey) -> String {
+        self.names
+            .get(author)
+            .cloned()
+            .unwrap_or_else(|| hide_string(&author.to_string(), 6))
+    }
+
+    fn make_query(&mut self) -> Option<MessageSearchQuery> {
+        let author = self.parse_author();
+        let since = parse_local_date(&self.since_input, NaiveTime::from_hms_opt(0, 0, 0));
+        let until = parse_local_date(
+            &self.until_input,
+            NaiveTime::from_hms_milli_opt(23, 59, 59, 999),
+        );
+
+        self.invalid_author = author.is_err();
+        self.invalid_since = since.is_err();
+        self.invalid_until = until.is_err();
+
+        Some(MessageSearchQuery {
+            text: self.text_input.clone(),
+            scope: self.search_scope(),
+            author: author.ok()?,
+            since: since.ok()?,
+            until: until.ok()?,
+        })
+    }
+}
+
+impl<M: Clone + Debug + 'static + Send> ModalView for MessageSearch<M> {
+    type UnderlayMessage = M;
+    type Message = CMessage<M>;
+
+    fn backend_event(
+        &mut self,
+        event: BackendEvent,
+        _conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        if let BackendEvent::GotSearchResults(results) = event {
+            self.searching = false;
+            self.results = Some(results);
+        }
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        message: Self::Message,
+        conn: &mut BackEndConnection,
+    ) -> Result<(Command<Self::Message>, bool), BackendClosed> {
+        let command = Command::none();
+        match message {
+            CMessage::UnderlayMessage(_) => (),
+            // handled by the underlay, that knows how to jump to the message
+            CMessage::ResultPressed(_) => return Ok((command, true)),
+            CMessage::CloseModal => return Ok((command, true)),
+            CMessage::TextInputChange(text) => self.text_input = text,
+            CMessage::AuthorInputChange(text) => {
+                self.invalid_author = false;
+                self.author_input = text;
+            }
+            CMessage::SinceInputChange(text) => {
+                self.invalid_since = false;
+                self.since_input = text;
+            }
+            CMessage::UntilInputChange(text) => {
+                self.invalid_until = false;
+                self.until_input = text;
+            }
+            CMessage::ScopeChanged(scope) => self.scope = scope,
+            CMessage::SearchPressed => {
+                if let Some(query) = self.make_query() {
+                    self.searching = true;
+                    conn.send(ToBackend::SearchMessages(query))?;
+                }
+            }
+        }
+        Ok((command, false))
+    }
+
+    fn view<'a>(
+        &'a self,
+        underlay: impl Into<Element<'a, Self::UnderlayMessage>>,
+    ) -> Element<'a, Self::Message> {
+        let underlay_component: Element<_> = underlay.into().map(CMessage::UnderlayMessage);
+
+        Modal::new(true, underlay_component, move || {
+            let title = container(text("Search Messages").size(22)).center_x();
+
+            let text_input =
+                TextInputGroup::new("Text", &self.text_input, CMessage::TextInputChange)
+                    .placeholder("Search...")
+                    .on_submit(CMessage::SearchPressed)
+                    .build();
+
+            let (this_label, all_label) = match &self.chat {
+                SearchChat::Contact(_) => ("This chat", "All chats"),
+                SearchChat::Channel(_) => ("This channel", "All channels"),
+            };
+            let scope_row = row![
+                radio(
+                    this_label,
+                    ScopeChoice::ThisChat,
+                    Some(self.scope),
+                    CMessage::ScopeChanged
+                ),
+                radio(
+                    all_label,
+                    ScopeChoice::AllChats,
+                    Some(self.scope),
+                    CMessage::ScopeChanged
+                ),
+            ]
+            .spacing(20);
+
+            let mut author_input =
+                TextInputGroup::new("Author", &self.author_input, CMessage::AuthorInputChange)
+                    .placeholder("Name, npub or hex")
+                    .on_submit(CMessage::SearchPressed);
+            if self.invalid_author {
+                author_input = author_input.invalid("Unknown author");
+            }
+
+            let mut since_input =
+                TextInputGroup::new("From", &self.since_input, CMessage::SinceInputChange)
+                    .placeholder("YYYY-MM-DD")
+                    .on_submit(CMessage::SearchPressed);
+            if self.invalid_since {
+                since_input = since_input.invalid("Invalid date");
+            }
+
+            let mut until_input =
+                TextInputGroup::new("To", &self.until_input, CMessage::UntilInputChange)
+                    .placeholder("YYYY-MM-DD")
+                    .on_submit(CMessage::SearchPressed);
+            if self.invalid_until {
+                until_input = until_input.invalid("Invalid date");
+            }
+
+            let dates_row = row![since_input.build(), until_input.build()].spacing(10);
+
+            let results: Element<_> = match (&self.results, self.searching) {
+                (_, true) => text("Searching...").into(),
+                (None, false) => text("").into(),
+                (Some(results), false) if results.is_empty() => text("No messages found")
+                    .style(style::Text::Placeholder)
+                    .into(),
+                (Some(results), false) => results
+                    .iter()
+                    .fold(column![].spacing(5), |col, result| {
+                        col.push(self.result_row(result))
+                    })
+                    .into(),
+            };
+
+            let card_body = common_scrollable(
+                container(
+                    column![
+                        title,
+                        text_input,
+                        scope_row,
+                        author_input.build(),
+                        dates_row,
+                        results
+                    ]
+                    .spacing(10),
+                )
+                .padding(20),
+            );
+
+            let card_footer = row![
+                button(text("Cancel").horizontal_alignment(Horizontal::Center))
+                    .style(style::Button::Bordered)
+                    .width(Length::Fill)
+                    .on_press(CMessage::CloseModal),
+                button(text("Search").horizontal_alignment(Horizontal::Center))
+                    .style(style::Button::Primary)
+                    .width(Length::Fill)
+                    .on_press(CMessage::SearchPressed)
+            ]
+            .spacing(10);
+
+            card(card_body, card_footer).max_width(MODAL_WIDTH).into()
+        })
+        .backdrop(CMessage::CloseModal)
+        .on_esc(CMessage::CloseModal)
+        .into()
+    }
+}
+
+impl<M: Clone + Debug + 'static + Send> MessageSearch<M> {
+    fn result_row<'a>(&self, result: &MessageSearchResult) -> Element<'a, CMessage<M>> {
+        let local_date = from_naive_utc_to_local(result.created_at);
+        let header = row![
+            text(self.author_name(&result.author)).size(16),
+            Space::with_width(Length::Fill),
+            text(local_date.format(YMD_FORMAT).to_string())
+                .size(14)
+                .style(style::Text::Placeholder)
+        ];
+
+        button(column![header, text(&result.snippet).size(16)].spacing(2))
+            .width(Length::Fill)
+            .padding(5)
+            .style(style::Button::ContactCard)
+            .on_press(CMessage::ResultPressed(result.to_owned()))
+            .into()
+    }
+}
+
+/// Empty input means no filter
+fn parse_local_date(input: &str, time: Option<NaiveTime>) -> Result<Option<NaiveDateTime>, ()> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let date = NaiveDate::parse_from_str(input, YMD_FORMAT).map_err(|_| ())?;
+    let time = time.ok_or(())?;
+    Local
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|date| Some(date.naive_utc()))
+        .ok_or(())
+}
+
+const MODAL_WIDTH: f32 = 500.0;
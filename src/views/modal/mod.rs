@@ -1,16 +1,28 @@
 #![allow(unused_variables)]
 
 pub(crate) mod basic_contact;
+pub(crate) mod chat_expiration;
+pub(crate) mod chat_notifications;
+pub(crate) mod image_viewer;
 pub(crate) mod import_contact_list;
+pub(crate) mod message_search;
+pub(crate) mod mute_user;
 pub(crate) mod relay_basic;
 pub(crate) mod relay_document;
 pub(crate) mod relays_confirmation;
+pub(crate) mod zap;
 
 pub(crate) use basic_contact::ContactDetails;
+pub(crate) use chat_expiration::ChatExpirationModal;
+pub(crate) use chat_notifications::ChatNotificationsModal;
+pub(crate) use image_viewer::ImageViewer;
 pub(crate) use import_contact_list::ImportContactList;
+pub(crate) use message_search::MessageSearch;
+pub(crate) use mute_user::MuteUserModal;
 pub(crate) use relay_basic::RelayBasic;
 pub(crate) use relay_document::RelayDocState;
 pub(crate) use relays_confirmation::RelaysConfirmation;
+pub(crate) use zap::ZapModal;
 
 use crate::{
     error::BackendClosed,
@@ -0,0 +1,152 @@
+use crate::components::card;
+use crate::db::DbMutedPubkey;
+use crate::net::{BackEndConnection, BackendEvent, ToBackend};
+use crate::style;
+use crate::widget::Element;
+use iced::alignment;
+use iced::widget::{button, column, container, row, text};
+use iced::{Command, Length};
+use iced_aw::Modal;
+use nostr::secp256k1::XOnlyPublicKey;
+use std::fmt::Debug;
+
+use super::ModalView;
+
+#[derive(Debug, Clone)]
+pub enum CMessage<M: Clone + Debug> {
+    /// Blocks are private mutes
+    MutePressed {
+        private: bool,
+    },
+    UnmutePressed,
+    CloseModal,
+    UnderlayMessage(M),
+}
+
+/// Mutes or blocks one user through the mute list
+pub struct MuteUserModal<M: Clone + Debug> {
+    pubkey: XOnlyPublicKey,
+    name: String,
+    /// None until the backend answers
+    muted: Option<Option<DbMutedPubkey>>,
+    phantom: std::marker::PhantomData<M>,
+}
+impl<M: Clone + Debug> MuteUserModal<M> {
+    pub fn new(
+        pubkey: &XOnlyPublicKey,
+        name: &str,
+        conn: &mut BackEndConnection,
+    ) -> Result<Self, crate::error::BackendClosed> {
+        conn.send(ToBackend::FetchMuteList)?;
+        Ok(Self {
+            pubkey: pubkey.to_owned(),
+            name: name.to_owned(),
+            muted: None,
+            phantom: std::marker::PhantomData,
+        })
+    }
+
+    fn status_view(&self, muted: &Option<DbMutedPubkey>) -> Element<'_, CMessage<M>> {
+        let (status, buttons) = match muted {
+            Some(muted) => {
+                let status = if muted.private {
+                    "Blocked. Only you can see it."
+                } else {
+                    "Muted. Your mute list is public."
+                };
+                let unmute_btn = button(text(if muted.private { "Unblock" } else { "Unmute" }))
+                    .width(Length::Fill)
+                    .style(style::Button::Primary)
+                    .on_press(CMessage::UnmutePressed);
+                (status, column![unmute_btn])
+            }
+            None => {
+                let mute_btn = button("Mute")
+                    .width(Length::Fill)
+                    .style(style::Button::Bordered)
+                    .on_press(CMessage::MutePressed { private: false });
+                let block_btn = button("Block")
+                    .width(Length::Fill)
+                    .style(style::Button::Danger)
+                    .on_press(CMessage::MutePressed { private: true });
+                (
+                    "Their direct messages and channel messages will be dropped. \
+                    Mutes are public in your mute list, blocks are encrypted.",
+                    column![mute_btn, block_btn].spacing(5),
+                )
+            }
+        };
+        column![text(status).style(style::Text::Alpha(0.8)), buttons]
+            .spacing(10)
+            .into()
+    }
+}
+
+impl<M: Clone + Debug + 'static + Send> ModalView for MuteUserModal<M> {
+    type UnderlayMessage = M;
+    type Message = CMessage<M>;
+
+    fn backend_event(
+        &mut self,
+        event: BackendEvent,
+        _conn: &mut BackEndConnection,
+    ) -> Result<(), crate::error::BackendClosed> {
+        if let BackendEvent::GotMuteList(muted) = event {
+            self.muted = Some(muted.into_iter().find(|m| m.pubkey == self.pubkey));
+        }
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        message: Self::Message,
+        conn: &mut BackEndConnection,
+    ) -> Result<(Command<Self::Message>, bool), crate::error::BackendClosed> {
+        let command = Command::none();
+        match message {
+            CMessage::MutePressed { private } => {
+                conn.send(ToBackend::MutePubkey {
+                    pubkey: self.pubkey,
+                    private,
+                })?;
+            }
+            CMessage::UnmutePressed => {
+                conn.send(ToBackend::UnmutePubkey(self.pubkey))?;
+            }
+            CMessage::UnderlayMessage(_) => (),
+            CMessage::CloseModal => return Ok((command, true)),
+        }
+        Ok((command, false))
+    }
+
+    fn view<'a>(
+        &'a self,
+        underlay: impl Into<Element<'a, Self::UnderlayMessage>>,
+    ) -> Element<'a, Self::Message> {
+        let underlay_component = underlay.into().map(CMessage::UnderlayMessage);
+        Modal::new(true, underlay_component, move || {
+            let title = container(text(format!("Mute {}", self.name)).size(22)).center_x();
+            let content = match &self.muted {
+                Some(muted) => self.status_view(muted),
+                None => text("Loading...").into(),
+            };
+            let card_body = container(column![title, content].spacing(15))
+                .center_x()
+                .padding(20);
+
+            let card_footer =
+                row![
+                    button(text("Close").horizontal_alignment(alignment::Horizontal::Center))
+                        .width(Length::Fill)
+                        .on_press(CMessage::CloseModal)
+                ];
+
+            card(card_body, card_footer).max_width(MODAL_WIDTH).into()
+        })
+        .backdrop(CMessage::CloseModal)
+        .on_esc(CMessage::CloseModal)
+        .into()
+    }
+}
+
+const MODAL_WIDTH: f32 = 400.0;
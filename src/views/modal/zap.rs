@@ -0,0 +1,327 @@
+use crate::components::{card, copy_btn};
+use crate::db::PaymentStatus;
+use crate::icon::bolt_icon;
+use crate::net::{BackEndConnection, BackendEvent, ToBackend, ZapInvoice};
+use crate::style;
+use crate::types::ZapTarget;
+use crate::utils::{format_sats, hide_string, qr_code_handle};
+use crate::widget::Element;
+use iced::widget::image::Handle;
+use iced::widget::{button, column, container, image as iced_image, row, text, text_input};
+use iced::{alignment, clipboard};
+use iced::{Alignment, Command, Length};
+use iced_aw::Modal;
+use std::fmt::Debug;
+
+use super::ModalView;
+
+#[derive(Debug, Clone)]
+pub enum CMessage<M: Clone + Debug> {
+    AmountChange(String),
+    PresetPressed(u64),
+    CommentChange(String),
+    RequestInvoice,
+    CopyInvoice,
+    OpenWallet,
+    PayWithWallet,
+    EditPressed,
+    CloseModal,
+    UnderlayMessage(M),
+}
+
+enum ZapState {
+    Editing,
+    Fetching,
+    Invoice {
+        invoice: ZapInvoice,
+        qr_code: Option<Handle>,
+        payment: WalletPayment,
+    },
+}
+
+/// Payment of the invoice through the connected wallet
+enum WalletPayment {
+    Idle,
+    Paying,
+    Paid,
+    Failed(String),
+}
+
+/// Asks the recipient's LNURL server for an invoice and shows it to be paid
+pub struct ZapModal<M: Clone + Debug> {
+    target: ZapTarget,
+    recipient_name: String,
+    /// In sats
+    amount_input: String,
+    comment_input: String,
+    state: ZapState,
+    error: Option<String>,
+    phantom: std::marker::PhantomData<M>,
+}
+impl<M: Clone + Debug> ZapModal<M> {
+    pub fn new(target: ZapTarget, recipient_name: &str) -> Self {
+        Self {
+            target,
+            recipient_name: recipient_name.to_owned(),
+            amount_input: ZAP_PRESETS[0].to_string(),
+            comment_input: "".into(),
+            state: ZapState::Editing,
+            error: None,
+            phantom: std::marker::PhantomData,
+        }
+    }
+
+    fn amount_msats(&self) -> Option<u64> {
+        self.amount_input
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .filter(|sats| *sats > 0)
+            .and_then(|sats| sats.checked_mul(1000))
+    }
+
+    fn editing_view<'a>(&'a self) -> Element<'a, CMessage<M>> {
+        let presets = ZAP_PRESETS.iter().fold(row![].spacing(5), |presets, sats| {
+            presets.push(
+                button(text(sats.to_string()).size(16))
+                    .style(style::Button::Bordered)
+                    .on_press(CMessage::PresetPressed(*sats)),
+            )
+        });
+        let amount_input = text_input("Amount in sats", &self.amount_input)
+            .on_input(CMessage::AmountChange)
+            .on_submit(CMessage::RequestInvoice);
+        let comment_input = text_input("Comment (optional)", &self.comment_input)
+            .on_input(CMessage::CommentChange)
+            .on_submit(CMessage::RequestInvoice);
+
+        let mut zap_btn = button(
+            row![bolt_icon(), text("Zap")]
+                .spacing(5)
+                .align_items(Alignment::Center),
+        )
+        .style(style::Button::Primary);
+        if let (ZapState::Editing, Some(_)) = (&self.state, self.amount_msats()) {
+            zap_btn = zap_btn.on_press(CMessage::RequestInvoice);
+        }
+        let status: Element<_> = match (&self.state, &self.error) {
+            (ZapState::Fetching, _) => text("Fetching invoice...")
+                .size(14)
+                .style(style::Text::Alpha(0.5))
+                .into(),
+            (_, Some(error)) => text(error).size(14).style(style::Text::Danger).into(),
+            _ => text("").into(),
+        };
+
+        column![
+            presets,
+            amount_input,
+            comment_input,
+            row![
+                status,
+                container(zap_btn)
+                    .width(Length::Fill)
+                    .align_x(alignment::Horizontal::Right)
+            ]
+            .align_items(Alignment::Center)
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn invoice_view<'a>(
+        &'a self,
+        invoice: &'a ZapInvoice,
+        qr_code: &'a Option<Handle>,
+        payment: &'a WalletPayment,
+    ) -> Element<'a, CMessage<M>> {
+        let qr_code: Element<_> = match qr_code {
+            Some(handle) => iced_image(handle.to_owned())
+                .width(QR_CODE_SIZE)
+                .height(QR_CODE_SIZE)
+                .into(),
+            None => text("").into(),
+        };
+        let amount = text(format_sats(invoice.amount_msats)).size(20);
+        let invoice_row = row![
+            text(hide_string(&invoice.invoice, 12))
+                .size(16)
+                .style(style::Text::Placeholder),
+            copy_btn("Copy invoice", CMessage::CopyInvoice)
+        ]
+        .spacing(5)
+        .align_items(Alignment::Center);
+        let mut pay_btn = button("Pay with wallet").style(style::Button::Primary);
+        if let WalletPayment::Idle | WalletPayment::Failed(_) = payment {
+            pay_btn = pay_btn.on_press(CMessage::PayWithWallet);
+        }
+        let buttons = row![
+            button("Edit")
+                .style(style::Button::Bordered)
+                .on_press(CMessage::EditPressed),
+            button("Open wallet")
+                .style(style::Button::Bordered)
+                .on_press(CMessage::OpenWallet),
+            pay_btn
+        ]
+        .spacing(5);
+        let payment_status = match payment {
+            WalletPayment::Idle => text(""),
+            WalletPayment::Paying => text("Paying...").style(style::Text::Alpha(0.5)),
+            WalletPayment::Paid => text("Paid").style(style::Text::Primary),
+            WalletPayment::Failed(error) => text(error).style(style::Text::Danger),
+        };
+
+        column![
+            container(qr_code).width(Length::Fill).center_x(),
+            amount,
+            invoice_row,
+            buttons,
+            payment_status.size(14)
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into()
+    }
+}
+
+impl<M: Clone + Debug + 'static + Send> ModalView for ZapModal<M> {
+    type UnderlayMessage = M;
+    type Message = CMessage<M>;
+
+    fn backend_event(
+        &mut self,
+        event: BackendEvent,
+        _conn: &mut BackEndConnection,
+    ) -> Result<(), crate::error::BackendClosed> {
+        match (&mut self.state, event) {
+            (ZapState::Fetching, BackendEvent::GotZapInvoice(invoice)) => {
+                let qr_code = qr_code_handle(&format!("lightning:{}", invoice.invoice)).ok();
+                self.state = ZapState::Invoice {
+                    invoice,
+                    qr_code,
+                    payment: WalletPayment::Idle,
+                };
+            }
+            (ZapState::Fetching, BackendEvent::ZapFailed(error)) => {
+                self.error = Some(error);
+                self.state = ZapState::Editing;
+            }
+            (
+                ZapState::Invoice {
+                    invoice, payment, ..
+                },
+                BackendEvent::WalletPaymentUpdated(db_payment),
+            ) if db_payment.invoice == invoice.invoice => {
+                *payment = match db_payment.status {
+                    PaymentStatus::Pending => WalletPayment::Paying,
+                    PaymentStatus::Paid { .. } => WalletPayment::Paid,
+                    PaymentStatus::Failed { error } => WalletPayment::Failed(error),
+                };
+            }
+            (
+                ZapState::Invoice {
+                    payment: payment @ WalletPayment::Paying,
+                    ..
+                },
+                BackendEvent::WalletError(error),
+            ) => {
+                *payment = WalletPayment::Failed(error);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        message: Self::Message,
+        conn: &mut BackEndConnection,
+    ) -> Result<(Command<Self::Message>, bool), crate::error::BackendClosed> {
+        let command = Command::none();
+        match message {
+            CMessage::AmountChange(amount) => {
+                if amount.chars().all(|c| c.is_ascii_digit()) {
+                    self.amount_input = amount;
+                }
+            }
+            CMessage::PresetPressed(sats) => self.amount_input = sats.to_string(),
+            CMessage::CommentChange(comment) => self.comment_input = comment,
+            CMessage::RequestInvoice => {
+                if let (ZapState::Editing, Some(amount_msats)) = (&self.state, self.amount_msats())
+                {
+                    self.error = None;
+                    self.state = ZapState::Fetching;
+                    conn.send(ToBackend::RequestZapInvoice {
+                        target: self.target.to_owned(),
+                        amount_msats,
+                        comment: self.comment_input.trim().to_owned(),
+                    })?;
+                }
+            }
+            CMessage::CopyInvoice => {
+                if let ZapState::Invoice { invoice, .. } = &self.state {
+                    return Ok((clipboard::write(invoice.invoice.to_owned()), false));
+                }
+            }
+            CMessage::OpenWallet => {
+                if let ZapState::Invoice { invoice, .. } = &self.state {
+                    if let Err(e) = webbrowser::open(&format!("lightning:{}", invoice.invoice)) {
+                        tracing::error!("Failed to open wallet: {}", e);
+                    }
+                }
+            }
+            CMessage::PayWithWallet => {
+                if let ZapState::Invoice {
+                    invoice, payment, ..
+                } = &mut self.state
+                {
+                    *payment = WalletPayment::Paying;
+                    conn.send(ToBackend::PayInvoice(invoice.invoice.to_owned()))?;
+                }
+            }
+            CMessage::EditPressed => self.state = ZapState::Editing,
+            CMessage::UnderlayMessage(_) => (),
+            CMessage::CloseModal => return Ok((command, true)),
+        }
+        Ok((command, false))
+    }
+
+    fn view<'a>(
+        &'a self,
+        underlay: impl Into<Element<'a, Self::UnderlayMessage>>,
+    ) -> Element<'a, Self::Message> {
+        let underlay_component = underlay.into().map(CMessage::UnderlayMessage);
+        Modal::new(true, underlay_component, move || {
+            let title = container(text(format!("Zap {}", self.recipient_name)).size(22)).center_x();
+            let content = match &self.state {
+                ZapState::Invoice {
+                    invoice,
+                    qr_code,
+                    payment,
+                } => self.invoice_view(invoice, qr_code, payment),
+                ZapState::Editing | ZapState::Fetching => self.editing_view(),
+            };
+            let card_body = container(column![title, content].spacing(15))
+                .center_x()
+                .padding(20);
+
+            let card_footer =
+                row![
+                    button(text("Close").horizontal_alignment(alignment::Horizontal::Center))
+                        .width(Length::Fill)
+                        .on_press(CMessage::CloseModal)
+                ];
+
+            card(card_body, card_footer).max_width(MODAL_WIDTH).into()
+        })
+        .backdrop(CMessage::CloseModal)
+        .on_esc(CMessage::CloseModal)
+        .into()
+    }
+}
+
+/// In sats
+const ZAP_PRESETS: [u64; 4] = [21, 100, 1000, 5000];
+const QR_CODE_SIZE: f32 = 250.0;
+const MODAL_WIDTH: f32 = 400.0;
@@ -63,7 +63,7 @@ impl State {
     pub fn backend_event(&mut self, event: BackendEvent, _conn: &mut BackEndConnection) {
         match event {
             BackendEvent::GotContacts(contact_list) => {
-                self.contacts = contact_list;
+                self.contacts = contact_list.into_iter().filter(|c| c.is_listed()).collect();
             }
             BackendEvent::GotAllMessages(all_messages) => {
                 self.messages = all_messages;
@@ -1,13 +1,14 @@
 use iced::widget::{button, column, container, row, text, text_input, tooltip, Space};
 use iced::{Alignment, Length};
+use nostr::secp256k1::XOnlyPublicKey;
 
 use crate::components::{common_scrollable, contact_row, ContactRow};
-use crate::db::{DbRelay, DbRelayResponse};
+use crate::db::{DbFollower, DbMutedPubkey, DbRelay, DbRelayResponse};
 use crate::error::BackendClosed;
-use crate::icon::{import_icon, plus_icon, satellite_icon};
+use crate::icon::{import_icon, plus_icon, reply_icon, satellite_icon};
 use crate::net::{self, BackEndConnection, BackendEvent};
 use crate::style;
-use crate::utils::contact_matches_search_full;
+use crate::utils::{contact_matches_search_full, from_naive_utc_to_local, hide_string};
 use crate::views::GoToView;
 use crate::widget::Element;
 use crate::{components::text::title, db::DbContact};
@@ -24,6 +25,17 @@ pub enum Message {
     SearchContactInputChange(String),
     RelaysConfirmationPress(Option<ContactsRelaysResponse>),
     SendDMTo(DbContact),
+    TabPressed(Tab),
+    FollowBack(DbContact),
+    MessageFollower(DbContact),
+    UnmutePressed(XOnlyPublicKey),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tab {
+    Contacts,
+    Followers,
+    Muted,
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +57,11 @@ impl ContactsRelaysResponse {
 
 pub struct State {
     contacts: Vec<DbContact>,
+    /// None until the followers tab is opened
+    followers: Option<Vec<(DbFollower, DbContact)>>,
+    /// None until the muted tab is opened
+    muted: Option<Vec<DbMutedPubkey>>,
+    active_tab: Tab,
     search_contact_input: String,
     relays_response: Option<ContactsRelaysResponse>,
 }
@@ -54,6 +71,9 @@ impl State {
         conn.send(net::ToBackend::FetchRelayResponsesContactList)?;
         Ok(Self {
             contacts: vec![],
+            followers: None,
+            muted: None,
+            active_tab: Tab::Contacts,
             search_contact_input: "".into(),
             relays_response: None,
         })
@@ -75,11 +95,25 @@ impl State {
                 self.relays_response = Some(ContactsRelaysResponse::new(responses, all_relays));
             }
             BackendEvent::GotContacts(db_contacts) => {
-                self.contacts = db_contacts;
+                // message requests are listed in the chats
+                self.contacts = db_contacts.into_iter().filter(|c| c.is_known()).collect();
+            }
+            BackendEvent::GotFollowers(followers) => {
+                self.followers = Some(followers);
+            }
+            BackendEvent::GotMuteList(muted) => {
+                self.muted = Some(muted);
+            }
+            BackendEvent::FollowerUpdated(_) => {
+                if self.followers.is_some() {
+                    conn.send(net::ToBackend::FetchFollowers)?;
+                }
             }
             BackendEvent::UpdatedMetadata(pubkey) => {
                 if self.contacts.iter().any(|c| c.pubkey() == &pubkey) {
                     conn.send(net::ToBackend::FetchContactWithMetadata(pubkey))?;
+                } else if self.is_follower(&pubkey) {
+                    conn.send(net::ToBackend::FetchFollowers)?;
                 }
             }
             BackendEvent::GotSingleContact(_pubkey, Some(db_contact)) => {
@@ -114,6 +148,25 @@ impl State {
         conn: &mut BackEndConnection,
     ) -> Result<Option<SettingsRouterMessage>, BackendClosed> {
         match message {
+            Message::TabPressed(tab) => {
+                match tab {
+                    Tab::Contacts => (),
+                    Tab::Followers => conn.send(net::ToBackend::FetchFollowers)?,
+                    Tab::Muted => conn.send(net::ToBackend::FetchMuteList)?,
+                }
+                self.active_tab = tab;
+            }
+            Message::FollowBack(db_contact) => {
+                conn.send(net::ToBackend::AddContact(db_contact))?;
+            }
+            Message::MessageFollower(db_contact) => {
+                return Ok(Some(SettingsRouterMessage::RouterMessage(
+                    GoToView::ChatTo(db_contact),
+                )));
+            }
+            Message::UnmutePressed(pubkey) => {
+                conn.send(net::ToBackend::UnmutePubkey(pubkey))?;
+            }
             Message::SendDMTo(_) => (),
             Message::RelaysConfirmationPress(_) => (),
             Message::OpenProfileModal(db_contact) => {
@@ -139,6 +192,9 @@ impl State {
                 contact_row::Message::EditContact(contact) => {
                     return Ok(Some(SettingsRouterMessage::OpenEditContactModal(contact)));
                 }
+                contact_row::Message::MuteContact(contact) => {
+                    return Ok(Some(SettingsRouterMessage::OpenMuteUserModal(contact)));
+                }
             },
             Message::DeleteContact(contact) => {
                 conn.send(net::ToBackend::DeleteContact(contact))?;
@@ -148,6 +204,124 @@ impl State {
         Ok(None)
     }
 
+    fn is_follower(&self, pubkey: &XOnlyPublicKey) -> bool {
+        self.followers.as_ref().map_or(false, |followers| {
+            followers.iter().any(|(f, _)| &f.pubkey == pubkey)
+        })
+    }
+
+    fn tab_button(&self, label: &str, tab: Tab) -> Element<'static, Message> {
+        let style = if self.active_tab == tab {
+            style::Button::ActiveMenuBtn
+        } else {
+            style::Button::MenuBtn
+        };
+        button(text(label).size(18))
+            .padding([5, 10])
+            .style(style)
+            .on_press(Message::TabPressed(tab))
+            .into()
+    }
+
+    fn follower_row<'a>(
+        &self,
+        follower: &DbFollower,
+        db_contact: &'a DbContact,
+    ) -> Element<'a, Message> {
+        let since = from_naive_utc_to_local(follower.followed_at).format("%Y-%m-%d");
+        let name = db_contact.select_name();
+        let follow_back: Element<_> = if self
+            .contacts
+            .iter()
+            .any(|c| c.pubkey() == db_contact.pubkey())
+        {
+            text("Following")
+                .size(14)
+                .style(style::Text::Placeholder)
+                .into()
+        } else {
+            button(text("Follow back").size(14))
+                .padding(5)
+                .style(style::Button::Primary)
+                .on_press(Message::FollowBack(DbContact::new(db_contact.pubkey())))
+                .into()
+        };
+        row![
+            container(text(hide_string(&db_contact.pubkey().to_string(), 6)))
+                .width(Length::Fixed(PUBKEY_CELL_WIDTH)),
+            container(text(name)).width(Length::Fill),
+            container(text(format!("Since {}", since)).style(style::Text::Placeholder))
+                .width(Length::Fixed(SINCE_CELL_WIDTH)),
+            container(follow_back).width(Length::Fixed(FOLLOW_BACK_WIDTH)),
+            tooltip(
+                button(reply_icon().size(16))
+                    .on_press(Message::MessageFollower(db_contact.to_owned())),
+                "Send Message",
+                tooltip::Position::Left
+            )
+            .style(style::Container::TooltipBg),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(5)
+        .into()
+    }
+
+    fn followers_view(&self) -> Element<Message> {
+        let Some(followers) = &self.followers else {
+            return text("Loading...").into();
+        };
+        if followers.is_empty() {
+            return text("Nobody follows you yet")
+                .style(style::Text::Placeholder)
+                .into();
+        }
+        let follower_list: Element<_> = followers
+            .iter()
+            .filter(|(_, c)| contact_matches_search_full(c, &self.search_contact_input))
+            .fold(
+                column![].padding([0, 20, 0, 0]).spacing(5),
+                |col, (follower, c)| col.push(self.follower_row(follower, c)),
+            )
+            .into();
+        column![
+            text(format!("{} followers", followers.len())).style(style::Text::Placeholder),
+            common_scrollable(follower_list)
+        ]
+        .spacing(5)
+        .into()
+    }
+
+    fn muted_view(&self) -> Element<Message> {
+        let Some(muted) = &self.muted else {
+            return text("Loading...").into();
+        };
+        if muted.is_empty() {
+            return text("Nobody is muted")
+                .style(style::Text::Placeholder)
+                .into();
+        }
+        let muted_list: Element<_> = muted
+            .iter()
+            .fold(column![].padding([0, 20, 0, 0]).spacing(5), |col, entry| {
+                let kind = if entry.private { "Blocked" } else { "Muted" };
+                col.push(
+                    row![
+                        container(text(hide_string(&entry.pubkey.to_string(), 6)))
+                            .width(Length::Fill),
+                        container(text(kind).style(style::Text::Placeholder))
+                            .width(Length::Fixed(SINCE_CELL_WIDTH)),
+                        button(text("Unmute").size(14))
+                            .padding(5)
+                            .on_press(Message::UnmutePressed(entry.pubkey)),
+                    ]
+                    .align_items(Alignment::Center)
+                    .spacing(5),
+                )
+            })
+            .into();
+        common_scrollable(muted_list).into()
+    }
+
     fn make_relays_response<'a>(&self) -> Element<'a, Message> {
         if let Some(response) = &self.relays_response {
             let resp_txt = format!(
@@ -218,15 +392,34 @@ impl State {
         )
         .style(style::Container::TooltipBg);
 
-        let utils_row = row![
-            search_contact,
-            Space::with_width(Length::Fill),
-            add_contact_btn,
-            import_btn,
+        let tabs_row = row![
+            self.tab_button("Contacts", Tab::Contacts),
+            self.tab_button("Followers", Tab::Followers),
+            self.tab_button("Muted", Tab::Muted)
         ]
-        .padding([0, 20, 0, 0])
-        .spacing(5)
-        .width(Length::Fill);
+        .spacing(5);
+
+        let mut utils_row = row![search_contact, Space::with_width(Length::Fill)]
+            .padding([0, 20, 0, 0])
+            .spacing(5)
+            .width(Length::Fill);
+        if self.active_tab == Tab::Contacts {
+            utils_row = utils_row.push(add_contact_btn).push(import_btn);
+        }
+
+        let tab_view = match self.active_tab {
+            Tab::Contacts => None,
+            Tab::Followers => Some(self.followers_view()),
+            Tab::Muted => Some(self.muted_view()),
+        };
+        if let Some(tab_view) = tab_view {
+            let content: Element<_> = column![title_group, tabs_row, utils_row, tab_view]
+                .spacing(10)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into();
+            return container(content).center_x().center_y().into();
+        }
 
         let contact_list: Element<_> = self
             .contacts
@@ -242,7 +435,7 @@ impl State {
             container(ContactRow::header()).padding([0, 20, 0, 0]),
             common_scrollable(contact_list)
         ];
-        let content: Element<_> = column![title_group, utils_row, contact_list_scroller]
+        let content: Element<_> = column![title_group, tabs_row, utils_row, contact_list_scroller]
             .spacing(10)
             .width(Length::Fill)
             .height(Length::Fill)
@@ -253,3 +446,6 @@ impl State {
 }
 
 const SEARCH_CONTACT_WIDTH: f32 = 200.0;
+const PUBKEY_CELL_WIDTH: f32 = 100.0;
+const SINCE_CELL_WIDTH: f32 = 130.0;
+const FOLLOW_BACK_WIDTH: f32 = 110.0;
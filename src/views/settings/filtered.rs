@@ -0,0 +1,178 @@
+use crate::components::common_scrollable;
+use crate::components::text::title;
+use crate::db::DbFilteredMessage;
+use crate::error::BackendClosed;
+use crate::net::{self, BackEndConnection, BackendEvent};
+use crate::style;
+use crate::utils::{add_ellipsis_trunc, from_naive_utc_to_local, hide_string};
+use crate::widget::Element;
+use iced::widget::{button, column, container, row, text, text_input, Space};
+use iced::{Alignment, Length};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ThresholdInputChange(String),
+    SaveThreshold,
+    DeletePress(i64),
+    ClearPress,
+}
+
+pub struct State {
+    threshold_input: String,
+    /// None until the backend answers
+    messages: Option<Vec<DbFilteredMessage>>,
+}
+impl State {
+    pub fn new(conn: &mut BackEndConnection) -> Result<Self, BackendClosed> {
+        conn.send(net::ToBackend::FetchIncomingPowThreshold)?;
+        conn.send(net::ToBackend::FetchFilteredMessages)?;
+        Ok(Self {
+            threshold_input: "".into(),
+            messages: None,
+        })
+    }
+
+    pub fn backend_event(&mut self, event: BackendEvent, _conn: &mut BackEndConnection) {
+        match event {
+            BackendEvent::GotIncomingPowThreshold(threshold) => {
+                self.threshold_input = threshold.to_string();
+            }
+            BackendEvent::GotFilteredMessages(messages) => self.messages = Some(messages),
+            BackendEvent::MessageFiltered(message) => {
+                if let Some(messages) = &mut self.messages {
+                    messages.insert(0, message);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        message: Message,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        match message {
+            Message::ThresholdInputChange(input) => {
+                if input.chars().all(|c| c.is_ascii_digit()) {
+                    self.threshold_input = input;
+                }
+            }
+            Message::SaveThreshold => match self.threshold_input.parse::<u8>() {
+                Ok(threshold) => conn.send(net::ToBackend::SetIncomingPowThreshold(threshold))?,
+                Err(_) => conn.send(net::ToBackend::FetchIncomingPowThreshold)?,
+            },
+            Message::DeletePress(event_id) => {
+                conn.send(net::ToBackend::DeleteFilteredMessage(event_id))?;
+            }
+            Message::ClearPress => {
+                conn.send(net::ToBackend::ClearFilteredMessages)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn view(&self) -> Element<Message> {
+        let page_title = title("Spam filter").height(HEADER_HEIGHT);
+        let description = text(
+            "Direct and channel messages from people who aren't your contacts are \
+            filtered unless they carry NIP-13 proof of work of at least this difficulty. \
+            0 lets every message through.",
+        )
+        .style(style::Text::Alpha(0.8));
+
+        let threshold_input = text_input("0", &self.threshold_input)
+            .on_input(Message::ThresholdInputChange)
+            .on_submit(Message::SaveThreshold)
+            .width(THRESHOLD_INPUT_WIDTH);
+        let threshold_row = row![
+            text("Minimum difficulty").width(200),
+            threshold_input,
+            button("Save")
+                .style(style::Button::Primary)
+                .on_press(Message::SaveThreshold)
+        ]
+        .spacing(5)
+        .align_items(Alignment::Center);
+
+        let content: Element<_> = match &self.messages {
+            None => text("Loading...").into(),
+            Some(messages) => messages_view(messages),
+        };
+
+        container(common_scrollable(
+            column![page_title, description, threshold_row, content]
+                .spacing(10)
+                .padding([20, 20, 0, 0]),
+        ))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+}
+
+fn messages_view(messages: &[DbFilteredMessage]) -> Element<Message> {
+    let mut clear_btn = button("Clear all").style(style::Button::Danger);
+    if !messages.is_empty() {
+        clear_btn = clear_btn.on_press(Message::ClearPress);
+    }
+    let header = row![
+        text(format!("Filtered messages ({})", messages.len())).size(24),
+        Space::with_width(Length::Fill),
+        clear_btn
+    ]
+    .align_items(Alignment::Center);
+
+    let rows: Element<_> = if messages.is_empty() {
+        text("No filtered messages")
+            .style(style::Text::Alpha(0.5))
+            .into()
+    } else {
+        messages
+            .iter()
+            .fold(column![].spacing(4), |col, message| {
+                col.push(message_row(message))
+            })
+            .into()
+    };
+
+    column![header, rows].spacing(5).into()
+}
+
+fn message_row(message: &DbFilteredMessage) -> Element<Message> {
+    let date = from_naive_utc_to_local(message.created_at)
+        .format("%Y-%m-%d %H:%M")
+        .to_string();
+    let place = match &message.channel_id {
+        Some(channel_id) => format!("Channel {}", hide_string(&channel_id.to_hex(), 6)),
+        None => "Direct message".to_owned(),
+    };
+    let details = text(format!(
+        "{} - {} - {} - difficulty {}",
+        date,
+        hide_string(&message.pubkey.to_string(), 8),
+        place,
+        message.difficulty
+    ))
+    .size(14)
+    .style(style::Text::Alpha(0.6));
+
+    row![
+        column![
+            details,
+            text(add_ellipsis_trunc(&message.content, CONTENT_MAX_CHARS))
+        ]
+        .spacing(2)
+        .width(Length::Fill),
+        button("Delete")
+            .style(style::Button::Bordered)
+            .on_press(Message::DeletePress(message.event_id))
+    ]
+    .spacing(5)
+    .align_items(Alignment::Center)
+    .into()
+}
+
+const HEADER_HEIGHT: f32 = 50.0;
+const THRESHOLD_INPUT_WIDTH: f32 = 80.0;
+const CONTENT_MAX_CHARS: usize = 200;
@@ -9,8 +9,9 @@ use crate::style;
 use crate::widget::{Button, Element};
 
 use super::modal::{
-    basic_contact, import_contact_list, relay_basic, relay_document, relays_confirmation,
-    ContactDetails, ImportContactList, ModalView, RelayBasic, RelayDocState, RelaysConfirmation,
+    basic_contact, import_contact_list, mute_user, relay_basic, relay_document,
+    relays_confirmation, ContactDetails, ImportContactList, ModalView, MuteUserModal, RelayBasic,
+    RelayDocState, RelaysConfirmation,
 };
 use super::route::Route;
 use super::{GoToView, RouterCommand};
@@ -20,7 +21,10 @@ mod account;
 pub mod appearance;
 mod backup;
 mod contacts;
+mod filtered;
 mod network;
+mod notifications;
+mod wallet;
 
 pub enum SettingsRouterMessage {
     OpenRelayBasicModal,
@@ -30,6 +34,7 @@ pub enum SettingsRouterMessage {
     OpenImportContactModal,
     OpenAddContactModal,
     OpenRelayDocument(DbRelay),
+    OpenMuteUserModal(DbContact),
 }
 
 #[derive(Debug, Clone)]
@@ -38,6 +43,9 @@ pub enum Message {
     Network(network::Message),
     Backup(backup::Message),
     Contacts(contacts::Message),
+    Wallet(wallet::Message),
+    Notifications(notifications::Message),
+    Filtered(filtered::Message),
     About(about::Message),
 
     ModalContactDetails(Box<basic_contact::CMessage<Message>>),
@@ -45,6 +53,7 @@ pub enum Message {
     ModalRelaysConfirmation(Box<relays_confirmation::CMessage<Message>>),
     ModalRelayDocument(Box<relay_document::CMessage<Message>>),
     ModalRelayBasic(Box<relay_basic::CMessage<Message>>),
+    ModalMuteUser(Box<mute_user::CMessage<Message>>),
 
     // Navigation
     MenuAccountPress,
@@ -52,6 +61,9 @@ pub enum Message {
     MenuNetworkPress,
     MenuBackupPress,
     MenuContactsPress,
+    MenuWalletPress,
+    MenuNotificationsPress,
+    MenuFilteredPress,
     MenuAboutPress,
     LogoutPress,
     NavEscPress,
@@ -72,6 +84,9 @@ pub enum MenuState {
     Network { state: network::State } = 2,
     Backup { state: backup::State } = 3,
     Contacts { state: contacts::State } = 4,
+    Wallet { state: wallet::State } = 5,
+    Notifications { state: notifications::State } = 6,
+    Filtered { state: filtered::State } = 7,
     About { state: about::State } = 10,
 }
 
@@ -81,6 +96,9 @@ impl MenuState {
     const NETWORK: u8 = 2;
     const BACKUP: u8 = 3;
     const CONTACTS: u8 = 4;
+    const WALLET: u8 = 5;
+    const NOTIFICATIONS: u8 = 6;
+    const FILTERED: u8 = 7;
     const ABOUT: u8 = 10;
 
     pub fn is_same_type(&self, other: u8) -> bool {
@@ -91,6 +109,9 @@ impl MenuState {
                 | (MenuState::Network { .. }, Self::NETWORK)
                 | (MenuState::Backup { .. }, Self::BACKUP)
                 | (MenuState::Contacts { .. }, Self::CONTACTS)
+                | (MenuState::Wallet { .. }, Self::WALLET)
+                | (MenuState::Notifications { .. }, Self::NOTIFICATIONS)
+                | (MenuState::Filtered { .. }, Self::FILTERED)
                 | (MenuState::About { .. }, Self::ABOUT)
         )
     }
@@ -114,6 +135,21 @@ impl MenuState {
             state: contacts::State::new(conn)?,
         })
     }
+    fn wallet(conn: &mut BackEndConnection) -> Result<Self, BackendClosed> {
+        Ok(Self::Wallet {
+            state: wallet::State::new(conn)?,
+        })
+    }
+    fn notifications(conn: &mut BackEndConnection) -> Result<Self, BackendClosed> {
+        Ok(Self::Notifications {
+            state: notifications::State::new(conn)?,
+        })
+    }
+    fn filtered(conn: &mut BackEndConnection) -> Result<Self, BackendClosed> {
+        Ok(Self::Filtered {
+            state: filtered::State::new(conn)?,
+        })
+    }
     fn backup(conn: &mut BackEndConnection) -> Result<Self, BackendClosed> {
         Ok(Self::Backup {
             state: backup::State::new(conn)?,
@@ -131,6 +167,9 @@ impl MenuState {
             Self::Network { state } => state.view().map(Message::Network),
             Self::Backup { state } => state.view().map(Message::Backup),
             Self::Contacts { state } => state.view().map(Message::Contacts),
+            Self::Wallet { state } => state.view().map(Message::Wallet),
+            Self::Notifications { state } => state.view().map(Message::Notifications),
+            Self::Filtered { state } => state.view().map(Message::Filtered),
             Self::About { state } => state.view().map(Message::About),
         }
     }
@@ -185,6 +224,18 @@ impl Settings {
                 MenuState::Contacts { .. } => (),
                 _ => self.menu_state = MenuState::contacts(conn)?,
             },
+            Message::MenuWalletPress => match self.menu_state {
+                MenuState::Wallet { .. } => (),
+                _ => self.menu_state = MenuState::wallet(conn)?,
+            },
+            Message::MenuNotificationsPress => match self.menu_state {
+                MenuState::Notifications { .. } => (),
+                _ => self.menu_state = MenuState::notifications(conn)?,
+            },
+            Message::MenuFilteredPress => match self.menu_state {
+                MenuState::Filtered { .. } => (),
+                _ => self.menu_state = MenuState::filtered(conn)?,
+            },
             Message::MenuAboutPress => match self.menu_state {
                 MenuState::About { .. } => (),
                 _ => self.menu_state = MenuState::about(conn),
@@ -219,6 +270,13 @@ impl Settings {
                 self.modal_state =
                     ModalState::ContactDetails(ContactDetails::viewer(&contact, conn)?)
             }
+            SettingsRouterMessage::OpenMuteUserModal(contact) => {
+                self.modal_state = ModalState::MuteUser(MuteUserModal::new(
+                    contact.pubkey(),
+                    &contact.select_name(),
+                    conn,
+                )?)
+            }
             SettingsRouterMessage::RouterMessage(router_msg) => {
                 router_message = Some(router_msg);
             }
@@ -290,6 +348,15 @@ impl Route for Settings {
             MenuState::Contacts { state } => {
                 state.backend_event(event, conn)?;
             }
+            MenuState::Wallet { state } => {
+                state.backend_event(event, conn);
+            }
+            MenuState::Notifications { state } => {
+                state.backend_event(event, conn);
+            }
+            MenuState::Filtered { state } => {
+                state.backend_event(event, conn);
+            }
         }
 
         Ok(commands)
@@ -351,12 +418,30 @@ impl Route for Settings {
                     commands.change_route(router_message);
                 }
             }
+            Message::Wallet(msg) => {
+                if let MenuState::Wallet { state } = &mut self.menu_state {
+                    state.update(msg, conn)?;
+                }
+            }
+            Message::Notifications(msg) => {
+                if let MenuState::Notifications { state } = &mut self.menu_state {
+                    state.update(msg, conn)?;
+                }
+            }
+            Message::Filtered(msg) => {
+                if let MenuState::Filtered { state } = &mut self.menu_state {
+                    state.update(msg, conn)?;
+                }
+            }
             Message::NavEscPress => commands.change_route(GoToView::Chat),
             Message::MenuAccountPress
             | Message::MenuAppearancePress
             | Message::MenuNetworkPress
             | Message::MenuBackupPress
             | Message::MenuContactsPress
+            | Message::MenuWalletPress
+            | Message::MenuNotificationsPress
+            | Message::MenuFilteredPress
             | Message::MenuAboutPress => {
                 self.handle_menu_press(message, conn)?;
             }
@@ -388,6 +473,20 @@ impl Route for Settings {
             create_menu_button("Backup", &self.menu_state, 3, Message::MenuBackupPress);
         let contacts_btn =
             create_menu_button("Contacts", &self.menu_state, 4, Message::MenuContactsPress);
+        let wallet_btn =
+            create_menu_button("Wallet", &self.menu_state, 5, Message::MenuWalletPress);
+        let notifications_btn = create_menu_button(
+            "Notifications",
+            &self.menu_state,
+            6,
+            Message::MenuNotificationsPress,
+        );
+        let filtered_btn = create_menu_button(
+            "Spam filter",
+            &self.menu_state,
+            7,
+            Message::MenuFilteredPress,
+        );
         let about_btn = create_menu_button("About", &self.menu_state, 10, Message::MenuAboutPress);
         let logout_btn = button("Logout")
             .padding(10)
@@ -407,6 +506,9 @@ impl Route for Settings {
                 network_btn,
                 backup_btn,
                 contacts_btn,
+                wallet_btn,
+                notifications_btn,
+                filtered_btn,
                 about_btn,
                 Space::with_height(Length::Fill),
                 logout_btn
@@ -441,6 +543,7 @@ enum ModalState {
     ImportList(ImportContactList<Message>),
     RelayDocument(RelayDocState<Message>),
     RelayBasic(RelayBasic<Message>),
+    MuteUser(MuteUserModal<Message>),
     Off,
 }
 
@@ -475,6 +578,9 @@ impl ModalState {
             ModalState::RelayBasic(state) => {
                 state.backend_event(event, conn)?;
             }
+            ModalState::MuteUser(state) => {
+                state.backend_event(event, conn)?;
+            }
             ModalState::Off => (),
         }
         Ok(command)
@@ -571,6 +677,22 @@ impl ModalState {
                     }
                 }
             }
+            Message::ModalMuteUser(modal_msg) => {
+                if let ModalState::MuteUser(state) = self {
+                    match *modal_msg {
+                        mute_user::CMessage::UnderlayMessage(message) => {
+                            return self.update(message, conn);
+                        }
+                        other => {
+                            let (cmd, close_modal) = state.update(other, conn)?;
+                            if close_modal {
+                                *self = ModalState::Off
+                            }
+                            command = cmd.map(|m| Message::ModalMuteUser(Box::new(m)));
+                        }
+                    }
+                }
+            }
             _ => (),
         }
 
@@ -594,6 +716,9 @@ impl ModalState {
             ModalState::ImportList(state) => state
                 .view(underlay)
                 .map(|m| Message::ModalImportContactList(Box::new(m))),
+            ModalState::MuteUser(state) => state
+                .view(underlay)
+                .map(|m| Message::ModalMuteUser(Box::new(m))),
             ModalState::Off => underlay.into(),
         };
 
@@ -21,6 +21,8 @@ pub enum Message {
     SearchInputChange(String),
     Tick,
     SyncWithNTP,
+    PowInputChange(String),
+    SubmitPow,
 }
 
 pub struct NtpInfo {
@@ -33,6 +35,7 @@ pub struct State {
     search_input: String,
     ntp_info: Option<NtpInfo>,
     ntp_btn_enabled: bool,
+    pow_input: String,
 }
 impl State {
     pub fn subscription(&self) -> Subscription<Message> {
@@ -41,11 +44,13 @@ impl State {
     pub fn new(conn: &mut BackEndConnection) -> Result<Self, BackendClosed> {
         conn.send(net::ToBackend::FetchRelays)?;
         conn.send(net::ToBackend::GetNtpInfo)?;
+        conn.send(net::ToBackend::FetchPowDifficulty)?;
         Ok(Self {
             relays: vec![],
             search_input: "".into(),
             ntp_info: None,
             ntp_btn_enabled: false,
+            pow_input: "".into(),
         })
     }
 
@@ -64,6 +69,9 @@ impl State {
                     ntp_server,
                 })
             }
+            BackendEvent::GotPowDifficulty(difficulty) => {
+                self.pow_input = difficulty.to_string();
+            }
             BackendEvent::RelayUpdated(db_relay) => {
                 if let Some(row) = self
                     .relays
@@ -125,6 +133,18 @@ impl State {
                 self.ntp_btn_enabled = false;
                 conn.send(net::ToBackend::SyncWithNTP)?;
             }
+            Message::PowInputChange(input) => {
+                if input.chars().all(|c| c.is_ascii_digit()) {
+                    self.pow_input = input;
+                }
+            }
+            Message::SubmitPow => {
+                if let Ok(difficulty) = self.pow_input.parse::<u8>() {
+                    conn.send(net::ToBackend::SetPowDifficulty(difficulty))?;
+                } else {
+                    conn.send(net::ToBackend::FetchPowDifficulty)?;
+                }
+            }
         }
 
         Ok(None)
@@ -166,6 +186,25 @@ impl State {
         };
         let ntp_gp = column![ntp_title, ntp_content,].spacing(10);
 
+        let pow_title = text("Proof of work").size(24);
+        let pow_input = text_input("0", &self.pow_input)
+            .on_input(Message::PowInputChange)
+            .on_submit(Message::SubmitPow)
+            .style(style::TextInput::ChatSearch)
+            .width(POW_INPUT_WIDTH);
+        let pow_gp = column![
+            pow_title,
+            text(
+                "Leading zero bits mined into every event you send, for relays that ask for \
+                proof of work. Relays can set their own difficulty below. 0 turns it off."
+            )
+            .style(style::Text::Alpha(0.8)),
+            row![text("Difficulty").width(200), pow_input]
+                .align_items(Alignment::Center)
+                .spacing(5),
+        ]
+        .spacing(10);
+
         let relays_title = text("Relays").size(24);
 
         let add_btn = tooltip(
@@ -201,7 +240,7 @@ impl State {
         let relays_gp = column![relays_title, utils_row, relays_table].spacing(5);
 
         container(common_scrollable(
-            column![page_title, ntp_gp, relays_gp]
+            column![page_title, ntp_gp, pow_gp, relays_gp]
                 .spacing(10)
                 .padding([20, 20, 0, 0]),
         ))
@@ -213,4 +252,5 @@ impl State {
 
 const HEADER_HEIGHT: f32 = 50.0;
 const SEARCH_WIDTH: f32 = 200.0;
+const POW_INPUT_WIDTH: f32 = 80.0;
 const TICK_INTERVAL_MILLIS: u64 = 500;
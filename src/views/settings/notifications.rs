@@ -0,0 +1,153 @@
+use crate::components::common_scrollable;
+use crate::components::text::title;
+use crate::db::DoNotDisturb;
+use crate::error::BackendClosed;
+use crate::net::{self, BackEndConnection, BackendEvent};
+use crate::style;
+use crate::widget::Element;
+use chrono::NaiveTime;
+use iced::widget::{button, checkbox, column, container, row, text, text_input};
+use iced::{Alignment, Length};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    DndToggled(bool),
+    StartInputChange(String),
+    EndInputChange(String),
+    SavePress,
+}
+
+pub struct State {
+    /// None until the backend answers
+    dnd: Option<DoNotDisturb>,
+    start_input: String,
+    end_input: String,
+    error: Option<String>,
+}
+impl State {
+    pub fn new(conn: &mut BackEndConnection) -> Result<Self, BackendClosed> {
+        conn.send(net::ToBackend::FetchDoNotDisturb)?;
+        Ok(Self {
+            dnd: None,
+            start_input: "".into(),
+            end_input: "".into(),
+            error: None,
+        })
+    }
+
+    pub fn backend_event(&mut self, event: BackendEvent, _conn: &mut BackEndConnection) {
+        if let BackendEvent::GotDoNotDisturb(dnd) = event {
+            self.start_input = dnd.start.format(TIME_FORMAT).to_string();
+            self.end_input = dnd.end.format(TIME_FORMAT).to_string();
+            self.error = None;
+            self.dnd = Some(dnd);
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        message: Message,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        match message {
+            Message::DndToggled(enabled) => {
+                if let Some(dnd) = &self.dnd {
+                    conn.send(net::ToBackend::SetDoNotDisturb(DoNotDisturb {
+                        enabled,
+                        ..*dnd
+                    }))?;
+                }
+            }
+            Message::StartInputChange(start) => {
+                self.start_input = start;
+                self.error = None;
+            }
+            Message::EndInputChange(end) => {
+                self.end_input = end;
+                self.error = None;
+            }
+            Message::SavePress => {
+                let Some(dnd) = &self.dnd else {
+                    return Ok(());
+                };
+                let start = NaiveTime::parse_from_str(self.start_input.trim(), TIME_FORMAT);
+                let end = NaiveTime::parse_from_str(self.end_input.trim(), TIME_FORMAT);
+                match (start, end) {
+                    (Ok(start), Ok(end)) => {
+                        conn.send(net::ToBackend::SetDoNotDisturb(DoNotDisturb {
+                            start,
+                            end,
+                            ..*dnd
+                        }))?;
+                    }
+                    _ => self.error = Some("Times must be in the HH:MM format".into()),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn view(&self) -> Element<Message> {
+        let page_title = title("Notifications").height(HEADER_HEIGHT);
+        let description = text(
+            "Messages are notified while the window is unfocused. \
+            Each chat can be muted from the bell in its header.",
+        )
+        .style(style::Text::Alpha(0.8));
+
+        let content: Element<_> = match &self.dnd {
+            None => text("Loading...").into(),
+            Some(dnd) => self.dnd_view(dnd),
+        };
+
+        container(common_scrollable(
+            column![page_title, description, content]
+                .spacing(10)
+                .padding([20, 20, 0, 0]),
+        ))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn dnd_view(&self, dnd: &DoNotDisturb) -> Element<Message> {
+        let start_input = text_input("22:00", &self.start_input)
+            .on_input(Message::StartInputChange)
+            .on_submit(Message::SavePress)
+            .width(TIME_INPUT_WIDTH);
+        let end_input = text_input("07:00", &self.end_input)
+            .on_input(Message::EndInputChange)
+            .on_submit(Message::SavePress)
+            .width(TIME_INPUT_WIDTH);
+        let schedule = row![
+            text("From"),
+            start_input,
+            text("to"),
+            end_input,
+            button("Save")
+                .style(style::Button::Primary)
+                .on_press(Message::SavePress)
+        ]
+        .spacing(5)
+        .align_items(Alignment::Center);
+
+        let mut content = column![
+            text("Do not disturb").size(24),
+            checkbox(
+                "Silence notifications every day",
+                dnd.enabled,
+                Message::DndToggled
+            ),
+            schedule,
+        ]
+        .spacing(10);
+        if let Some(error) = &self.error {
+            content = content.push(text(error).size(14).style(style::Text::Danger));
+        }
+        content.into()
+    }
+}
+
+const HEADER_HEIGHT: f32 = 50.0;
+const TIME_INPUT_WIDTH: f32 = 80.0;
+const TIME_FORMAT: &str = "%H:%M";
@@ -0,0 +1,245 @@
+use crate::components::common_scrollable;
+use crate::components::text::title;
+use crate::db::{DbWalletPayment, PaymentStatus};
+use crate::error::BackendClosed;
+use crate::net::{self, BackEndConnection, BackendEvent, WalletConnectUri};
+use crate::style;
+use crate::utils::{format_sats, from_naive_utc_to_local, hide_string};
+use crate::widget::Element;
+use iced::widget::{button, column, container, row, text, text_input, Space};
+use iced::{Alignment, Length};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    UriInputChange(String),
+    ConnectPress,
+    DisconnectPress,
+    RefreshBalance,
+}
+
+pub struct State {
+    /// None until the backend answers
+    wallet: Option<Option<WalletConnectUri>>,
+    uri_input: String,
+    uri_error: Option<String>,
+    /// In msats
+    balance: Option<u64>,
+    wallet_error: Option<String>,
+    payments: Vec<DbWalletPayment>,
+}
+impl State {
+    pub fn new(conn: &mut BackEndConnection) -> Result<Self, BackendClosed> {
+        conn.send(net::ToBackend::FetchWalletConnect)?;
+        conn.send(net::ToBackend::FetchWalletBalance)?;
+        conn.send(net::ToBackend::FetchWalletPayments)?;
+        Ok(Self {
+            wallet: None,
+            uri_input: "".into(),
+            uri_error: None,
+            balance: None,
+            wallet_error: None,
+            payments: vec![],
+        })
+    }
+
+    pub fn backend_event(&mut self, event: BackendEvent, _conn: &mut BackEndConnection) {
+        match event {
+            BackendEvent::GotWalletConnect(uri) => {
+                if uri.is_none() {
+                    self.balance = None;
+                }
+                self.wallet = Some(uri);
+            }
+            BackendEvent::GotWalletBalance(balance) => {
+                self.balance = Some(balance);
+                self.wallet_error = None;
+            }
+            BackendEvent::GotWalletPayments(payments) => self.payments = payments,
+            BackendEvent::WalletPaymentUpdated(payment) => {
+                if let Some(row) = self
+                    .payments
+                    .iter_mut()
+                    .find(|row| row.request_hash == payment.request_hash)
+                {
+                    *row = payment;
+                } else {
+                    self.payments.insert(0, payment);
+                }
+            }
+            BackendEvent::WalletError(error) => self.wallet_error = Some(error),
+            _ => (),
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        message: Message,
+        conn: &mut BackEndConnection,
+    ) -> Result<(), BackendClosed> {
+        match message {
+            Message::UriInputChange(uri) => {
+                self.uri_input = uri;
+                self.uri_error = None;
+            }
+            Message::ConnectPress => match self.uri_input.parse::<WalletConnectUri>() {
+                Ok(uri) => {
+                    self.uri_input.clear();
+                    self.wallet_error = None;
+                    conn.send(net::ToBackend::SaveWalletConnect(uri))?;
+                }
+                Err(e) => self.uri_error = Some(e.to_string()),
+            },
+            Message::DisconnectPress => {
+                self.wallet_error = None;
+                conn.send(net::ToBackend::RemoveWalletConnect)?;
+            }
+            Message::RefreshBalance => {
+                conn.send(net::ToBackend::FetchWalletBalance)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn view(&self) -> Element<Message> {
+        let page_title = title("Wallet").height(HEADER_HEIGHT);
+        let description = text("Pay zaps from a wallet that supports Nostr Wallet Connect")
+            .style(style::Text::Alpha(0.8));
+
+        let content: Element<_> = match &self.wallet {
+            None => text("Loading...").into(),
+            Some(None) => self.connect_view(),
+            Some(Some(uri)) => column![self.wallet_view(uri), self.payments_view()]
+                .spacing(20)
+                .into(),
+        };
+
+        container(common_scrollable(
+            column![page_title, description, content]
+                .spacing(10)
+                .padding([20, 20, 0, 0]),
+        ))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn connect_view(&self) -> Element<Message> {
+        let uri_input = text_input("nostr+walletconnect://...", &self.uri_input)
+            .on_input(Message::UriInputChange)
+            .on_submit(Message::ConnectPress)
+            .password();
+        let uri_input = match self.uri_error {
+            Some(_) => uri_input.style(style::TextInput::Invalid),
+            None => uri_input,
+        };
+        let mut connect_btn = button("Connect").style(style::Button::Primary);
+        if !self.uri_input.trim().is_empty() {
+            connect_btn = connect_btn.on_press(Message::ConnectPress);
+        }
+
+        let mut content = column![
+            text("Connection URI").size(24),
+            row![uri_input, connect_btn]
+                .spacing(5)
+                .align_items(Alignment::Center)
+        ]
+        .spacing(5);
+        if let Some(error) = &self.uri_error {
+            content = content.push(text(error).size(14).style(style::Text::Danger));
+        }
+        content.into()
+    }
+
+    fn wallet_view<'a>(&'a self, uri: &'a WalletConnectUri) -> Element<'a, Message> {
+        let balance = match self.balance {
+            Some(balance) => text(format_sats(balance)).size(24),
+            None => text("Loading balance...").size(24),
+        };
+        let balance_row = row![
+            balance,
+            Space::with_width(Length::Fill),
+            button("Refresh")
+                .style(style::Button::Bordered)
+                .on_press(Message::RefreshBalance),
+            button("Disconnect")
+                .style(style::Button::Danger)
+                .on_press(Message::DisconnectPress)
+        ]
+        .spacing(5)
+        .align_items(Alignment::Center);
+
+        let mut content = column![
+            balance_row,
+            info_row("Relay", uri.relay_url.to_string()),
+            info_row("Wallet", hide_string(&uri.wallet_pubkey.to_string(), 8)),
+        ]
+        .spacing(5);
+        if let Some(lud16) = &uri.lud16 {
+            content = content.push(info_row("Lightning address", lud16.to_owned()));
+        }
+        if let Some(error) = &self.wallet_error {
+            content = content.push(text(error).size(14).style(style::Text::Danger));
+        }
+        content.into()
+    }
+
+    fn payments_view(&self) -> Element<Message> {
+        let header = row![
+            text("Date").width(DATE_WIDTH),
+            text("Amount").width(AMOUNT_WIDTH),
+            text("Status").width(Length::Fill),
+        ]
+        .spacing(5);
+
+        let rows: Element<_> = if self.payments.is_empty() {
+            text("No payments yet")
+                .style(style::Text::Alpha(0.5))
+                .into()
+        } else {
+            self.payments
+                .iter()
+                .fold(column![].spacing(4), |col, payment| {
+                    col.push(payment_row(payment))
+                })
+                .into()
+        };
+
+        column![text("Payments").size(24), header, rows]
+            .spacing(5)
+            .into()
+    }
+}
+
+fn info_row<'a>(label: &'a str, value: String) -> Element<'a, Message> {
+    row![
+        text(label).width(INFO_LABEL_WIDTH),
+        text(value).style(style::Text::Placeholder)
+    ]
+    .spacing(5)
+    .into()
+}
+
+fn payment_row(payment: &DbWalletPayment) -> Element<Message> {
+    let date = from_naive_utc_to_local(payment.created_at)
+        .format("%Y-%m-%d %H:%M")
+        .to_string();
+    let status = match &payment.status {
+        PaymentStatus::Pending => text("Pending").style(style::Text::Alpha(0.5)),
+        PaymentStatus::Paid { .. } => text("Paid").style(style::Text::Primary),
+        PaymentStatus::Failed { error } => {
+            text(format!("Failed: {}", error)).style(style::Text::Danger)
+        }
+    };
+    row![
+        text(date).width(DATE_WIDTH),
+        text(format_sats(payment.amount_msats)).width(AMOUNT_WIDTH),
+        status.width(Length::Fill),
+    ]
+    .spacing(5)
+    .into()
+}
+
+const HEADER_HEIGHT: f32 = 50.0;
+const INFO_LABEL_WIDTH: f32 = 200.0;
+const DATE_WIDTH: f32 = 160.0;
+const AMOUNT_WIDTH: f32 = 160.0;
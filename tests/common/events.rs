@@ -102,3 +102,9 @@ pub fn random_contact_channel_msg_event(
     let keys = Keys::generate();
     make_channel_msg_event(&keys, channel_id, None, content)
 }
+
+pub fn make_metadata_event(keys: &Keys, name: &str) -> nostr::Event {
+    let builder = EventBuilder::set_metadata(nostr::Metadata::new().name(name));
+    let event = builder.to_event(keys).unwrap();
+    event
+}
@@ -0,0 +1,76 @@
+use chrono::Duration;
+use nostr::Keys;
+use nostrtalk::db::DbChannelMessage;
+use nostrtalk::types::ChannelMetadata;
+use nostrtalk::utils::channel_msg_builder;
+
+use crate::common::{event_with_time, make_channel_creation_event, make_channel_msg_event};
+use crate::spawn_app;
+
+/// Tests for the activity of channel members, computed from their messages
+
+#[tokio::test]
+async fn member_stats_count_and_last_active() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let cache = test_app.insert_random_channel_cache().await;
+    let channel_id = cache.channel_id;
+    let chatty_keys = Keys::generate();
+    let quiet_keys = Keys::generate();
+    let now = chrono::Utc::now().naive_utc();
+
+    for (hours_ago, content) in [(3, "first"), (1, "latest"), (2, "second")] {
+        let builder = channel_msg_builder(&channel_id, None, content);
+        let time = now - Duration::hours(hours_ago);
+        let ns_event = event_with_time(&chatty_keys, builder, time);
+        test_app.receive(ns_event).await;
+    }
+    let ns_event = make_channel_msg_event(&quiet_keys, &channel_id, None, "hi");
+    test_app.receive(ns_event).await;
+
+    // PERFORM
+    let stats = DbChannelMessage::member_stats(test_app.pool(), &channel_id)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert_eq!(stats.len(), 2);
+    let chatty = stats
+        .iter()
+        .find(|s| s.author == chatty_keys.public_key())
+        .unwrap();
+    assert_eq!(chatty.message_count, 3);
+    assert_eq!(
+        chatty.last_active.timestamp(),
+        (now - Duration::hours(1)).timestamp()
+    );
+    let quiet = stats
+        .iter()
+        .find(|s| s.author == quiet_keys.public_key())
+        .unwrap();
+    assert_eq!(quiet.message_count, 1);
+}
+
+/// Messages of other channels don't count
+#[tokio::test]
+async fn member_stats_only_for_channel() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let channel_id = test_app.insert_random_channel_cache().await.channel_id;
+    let metadata = ChannelMetadata::new().name("other_channel");
+    let creation_event = make_channel_creation_event(&Keys::generate(), &metadata);
+    let other_channel_id = test_app
+        .insert_channel_cache(creation_event)
+        .await
+        .channel_id;
+    let ns_event = make_channel_msg_event(&Keys::generate(), &other_channel_id, None, "elsewhere");
+    test_app.receive(ns_event).await;
+
+    // PERFORM
+    let stats = DbChannelMessage::member_stats(test_app.pool(), &channel_id)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert!(stats.is_empty());
+}
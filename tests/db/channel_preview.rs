@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use nostr::{EventId, Keys};
+use nostrtalk::db::{CacheBudget, CacheEviction, ChannelPreviewMessage};
+use nostrtalk::utils::channel_msg_builder;
+
+use crate::common::event_with_time;
+use crate::spawn_app;
+
+/// Tests for the messages of channels found while searching
+
+const CHANNEL_ID: &str = "8233a5d8e27a9415d22c974d70935011664ada55ae3152bd10d697d3a3c74f67";
+
+fn preview_event(keys: &Keys, channel_id: &EventId, content: &str, secs: i64) -> nostr::Event {
+    let time = NaiveDateTime::from_timestamp_opt(1_680_000_000 + secs, 0).unwrap();
+    event_with_time(keys, channel_msg_builder(channel_id, None, content), time)
+}
+
+#[tokio::test]
+async fn fetch_latest_returns_newest_messages_oldest_first() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let cache_pool = test_app.cache_pool();
+    let channel_id = EventId::from_hex(CHANNEL_ID).unwrap();
+    let keys = Keys::generate();
+    let now = Utc::now().naive_utc();
+
+    for (secs, content) in [(30, "third"), (10, "first"), (20, "second")] {
+        let ns_event = preview_event(&keys, &channel_id, content, secs);
+        ChannelPreviewMessage::insert(cache_pool, &channel_id, &ns_event, now)
+            .await
+            .unwrap();
+    }
+
+    // PERFORM
+    let messages = ChannelPreviewMessage::fetch_latest(cache_pool, &channel_id, 2)
+        .await
+        .unwrap();
+
+    // ASSERT
+    let contents: Vec<_> = messages.iter().map(|msg| msg.content.as_str()).collect();
+    assert_eq!(contents, vec!["second", "third"]);
+    assert!(messages.iter().all(|msg| msg.author == keys.public_key()));
+}
+
+#[tokio::test]
+async fn insert_ignores_repeated_messages() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let cache_pool = test_app.cache_pool();
+    let channel_id = EventId::from_hex(CHANNEL_ID).unwrap();
+    let ns_event = preview_event(&Keys::generate(), &channel_id, "hello", 0);
+    let now = Utc::now().naive_utc();
+
+    // PERFORM
+    for _ in 0..2 {
+        ChannelPreviewMessage::insert(cache_pool, &channel_id, &ns_event, now)
+            .await
+            .unwrap();
+    }
+
+    // ASSERT
+    let messages = ChannelPreviewMessage::fetch_latest(cache_pool, &channel_id, 10)
+        .await
+        .unwrap();
+    assert_eq!(messages.len(), 1);
+}
+
+#[tokio::test]
+async fn sweep_evicts_expired_channel_previews() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let cache_pool = test_app.cache_pool();
+    let channel_id = EventId::from_hex(CHANNEL_ID).unwrap();
+    let keys = Keys::generate();
+    let now = Utc::now().naive_utc();
+
+    let old = preview_event(&keys, &channel_id, "old", 0);
+    ChannelPreviewMessage::insert(cache_pool, &channel_id, &old, now - Duration::days(2))
+        .await
+        .unwrap();
+    let new = preview_event(&keys, &channel_id, "new", 10);
+    ChannelPreviewMessage::insert(cache_pool, &channel_id, &new, now)
+        .await
+        .unwrap();
+
+    let budget = CacheBudget {
+        max_image_bytes: 1000,
+        profile_max_age: Duration::days(30),
+    };
+
+    // PERFORM
+    let stats = CacheEviction::sweep(cache_pool, &HashSet::new(), &budget)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert_eq!(stats.channel_previews_evicted, 1);
+    let messages = ChannelPreviewMessage::fetch_latest(cache_pool, &channel_id, 10)
+        .await
+        .unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].content, "new");
+}
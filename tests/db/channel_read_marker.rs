@@ -0,0 +1,144 @@
+use chrono::Duration;
+use nostr::{EventId, Keys};
+use nostrtalk::db::ChannelSubscription;
+use nostrtalk::utils::channel_msg_builder;
+
+use crate::common::event_with_time;
+use crate::{spawn_app, TestApp};
+
+/// Tests for the unread messages of subscribed channels
+
+async fn receive_at(
+    test_app: &mut TestApp,
+    keys: &Keys,
+    channel_id: &EventId,
+    content: &str,
+    minutes_ago: i64,
+) {
+    let time = chrono::Utc::now().naive_utc() - Duration::minutes(minutes_ago);
+    let builder = channel_msg_builder(channel_id, None, content);
+    test_app.receive(event_with_time(keys, builder, time)).await;
+}
+
+async fn subscribe_minutes_ago(test_app: &TestApp, channel_id: &EventId, minutes_ago: i64) {
+    ChannelSubscription::insert(test_app.pool(), channel_id)
+        .await
+        .unwrap();
+    let subscribed_at = chrono::Utc::now().naive_utc() - Duration::minutes(minutes_ago);
+    sqlx::query("UPDATE channel_subscription SET subscribed_at = ? WHERE channel_id = ?")
+        .bind(subscribed_at.timestamp_millis())
+        .bind(channel_id.to_string())
+        .execute(test_app.pool())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn unread_counts_others_messages_since_subscribing() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let channel_id = test_app.insert_random_channel_cache().await.channel_id;
+    subscribe_minutes_ago(&test_app, &channel_id, 120).await;
+    let other_keys = Keys::generate();
+    let user_keys = test_app.keys.clone();
+
+    receive_at(&mut test_app, &other_keys, &channel_id, "before", 180).await;
+    receive_at(&mut test_app, &other_keys, &channel_id, "first", 60).await;
+    receive_at(&mut test_app, &other_keys, &channel_id, "second", 30).await;
+    receive_at(&mut test_app, &user_keys, &channel_id, "my reply", 10).await;
+
+    // PERFORM
+    let activity = ChannelSubscription::fetch_activity(test_app.pool(), &channel_id)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert_eq!(activity.channel_id, channel_id);
+    assert_eq!(activity.unread_count, 2);
+    assert_eq!(activity.last_message.as_deref(), Some("my reply"));
+    assert!(activity.last_message_at.is_some());
+}
+
+#[tokio::test]
+async fn mark_read_resets_unread_count() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let channel_id = test_app.insert_random_channel_cache().await.channel_id;
+    subscribe_minutes_ago(&test_app, &channel_id, 120).await;
+    let other_keys = Keys::generate();
+    receive_at(&mut test_app, &other_keys, &channel_id, "unread", 30).await;
+    // timestamped ahead of the local clock
+    receive_at(
+        &mut test_app,
+        &other_keys,
+        &channel_id,
+        "from the future",
+        -5,
+    )
+    .await;
+
+    // PERFORM
+    ChannelSubscription::mark_read(test_app.pool(), &channel_id)
+        .await
+        .unwrap();
+
+    // ASSERT
+    let activity = ChannelSubscription::fetch_activity(test_app.pool(), &channel_id)
+        .await
+        .unwrap();
+    assert_eq!(activity.unread_count, 0);
+    assert_eq!(activity.last_message.as_deref(), Some("from the future"));
+
+    let subscriptions = ChannelSubscription::fetch(test_app.pool()).await.unwrap();
+    assert!(subscriptions[0].last_read_at.is_some());
+    assert!(subscriptions[0].last_read_event_id.is_some());
+}
+
+/// A message dated ahead must not hide the ones received after reading
+#[tokio::test]
+async fn future_message_doesnt_hide_later_ones() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let channel_id = test_app.insert_random_channel_cache().await.channel_id;
+    subscribe_minutes_ago(&test_app, &channel_id, 120).await;
+    let other_keys = Keys::generate();
+    // a day ahead of the local clock
+    receive_at(
+        &mut test_app,
+        &other_keys,
+        &channel_id,
+        "from tomorrow",
+        -24 * 60,
+    )
+    .await;
+    ChannelSubscription::mark_read(test_app.pool(), &channel_id)
+        .await
+        .unwrap();
+
+    // PERFORM
+    receive_at(&mut test_app, &other_keys, &channel_id, "after reading", 1).await;
+
+    // ASSERT
+    let activity = ChannelSubscription::fetch_activity(test_app.pool(), &channel_id)
+        .await
+        .unwrap();
+    assert_eq!(activity.unread_count, 1);
+}
+
+/// Messages of channels the user isn't subscribed to are never unread
+#[tokio::test]
+async fn unread_count_zero_when_not_subscribed() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let channel_id = test_app.insert_random_channel_cache().await.channel_id;
+    receive_at(&mut test_app, &Keys::generate(), &channel_id, "hello", 1).await;
+
+    // PERFORM
+    let activity = ChannelSubscription::fetch_activity(test_app.pool(), &channel_id)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert_eq!(activity.unread_count, 0);
+    assert_eq!(activity.last_message.as_deref(), Some("hello"));
+}
@@ -0,0 +1,98 @@
+use nostr::Keys;
+use nostrtalk::db::DbContact;
+
+use crate::spawn_app;
+
+#[tokio::test]
+async fn auto_load_media_is_saved() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let pubkey = Keys::generate().public_key();
+    DbContact::insert(test_app.pool(), &pubkey).await.unwrap();
+    let contact = DbContact::new(&pubkey).with_auto_load_media(true);
+
+    // PERFORM
+    DbContact::update(test_app.pool(), &contact).await.unwrap();
+
+    // ASSERT
+    let saved = DbContact::fetch_one(test_app.pool(), test_app.cache_pool(), &pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(saved.auto_load_media());
+}
+
+#[tokio::test]
+async fn contact_list_upsert_keeps_auto_load_media() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let pubkey = Keys::generate().public_key();
+    DbContact::insert(test_app.pool(), &pubkey).await.unwrap();
+    let contact = DbContact::new(&pubkey).with_auto_load_media(true);
+    DbContact::update(test_app.pool(), &contact).await.unwrap();
+
+    // PERFORM
+    let from_contact_list = DbContact::new(&pubkey).with_petname("bob");
+    DbContact::upsert_contact(test_app.pool(), &from_contact_list)
+        .await
+        .unwrap();
+
+    // ASSERT
+    let saved = DbContact::fetch_one(test_app.pool(), test_app.cache_pool(), &pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(saved.get_petname(), Some("bob".into()));
+    assert!(saved.auto_load_media());
+}
+
+#[tokio::test]
+async fn accept_request_without_listing() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let pubkey = Keys::generate().public_key();
+    let request = DbContact::fetch_insert(test_app.pool(), test_app.cache_pool(), &pubkey)
+        .await
+        .unwrap();
+    assert!(!request.is_known());
+
+    // PERFORM
+    DbContact::accept(test_app.pool(), &pubkey, false)
+        .await
+        .unwrap();
+
+    // ASSERT
+    let saved = DbContact::fetch_one(test_app.pool(), test_app.cache_pool(), &pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(saved.is_known());
+    assert!(!saved.is_listed());
+    let listed = DbContact::fetch_listed(test_app.pool()).await.unwrap();
+    assert!(listed.is_empty());
+}
+
+#[tokio::test]
+async fn only_listed_contacts_are_published() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let added = Keys::generate().public_key();
+    let accepted = Keys::generate().public_key();
+    let request = Keys::generate().public_key();
+    DbContact::insert(test_app.pool(), &added).await.unwrap();
+    for pubkey in [&accepted, &request] {
+        DbContact::fetch_insert(test_app.pool(), test_app.cache_pool(), pubkey)
+            .await
+            .unwrap();
+    }
+
+    // PERFORM
+    DbContact::accept(test_app.pool(), &accepted, true)
+        .await
+        .unwrap();
+
+    // ASSERT
+    let listed = DbContact::fetch_listed(test_app.pool()).await.unwrap();
+    assert_eq!(listed.len(), 2);
+    assert!(listed.iter().all(|c| c.pubkey() != &request));
+}
@@ -0,0 +1,62 @@
+use nostr::{EventId, Keys};
+use nostrtalk::db::{DbDraft, NotificationChat};
+
+use crate::spawn_app;
+
+#[tokio::test]
+async fn chat_without_draft_is_empty() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let chat = NotificationChat::Dm(Keys::generate().public_key());
+
+    // PERFORM
+    let draft = DbDraft::fetch(test_app.pool(), &chat).await.unwrap();
+
+    // ASSERT
+    assert_eq!(draft, "");
+}
+
+#[tokio::test]
+async fn drafts_are_kept_per_chat() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let dm = NotificationChat::Dm(Keys::generate().public_key());
+    let channel = NotificationChat::Channel(EventId::from_hex("c".repeat(64)).unwrap());
+
+    // PERFORM
+    DbDraft::save(test_app.pool(), &dm, "half a tho")
+        .await
+        .unwrap();
+    DbDraft::save(test_app.pool(), &dm, "half a thought")
+        .await
+        .unwrap();
+    DbDraft::save(test_app.pool(), &channel, "gm all")
+        .await
+        .unwrap();
+
+    // ASSERT
+    let dm_draft = DbDraft::fetch(test_app.pool(), &dm).await.unwrap();
+    assert_eq!(dm_draft, "half a thought");
+    let channel_draft = DbDraft::fetch(test_app.pool(), &channel).await.unwrap();
+    assert_eq!(channel_draft, "gm all");
+}
+
+#[tokio::test]
+async fn empty_draft_is_deleted() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let chat = NotificationChat::Dm(Keys::generate().public_key());
+    DbDraft::save(test_app.pool(), &chat, "sent soon")
+        .await
+        .unwrap();
+
+    // PERFORM
+    DbDraft::save(test_app.pool(), &chat, "").await.unwrap();
+
+    // ASSERT
+    let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM draft")
+        .fetch_one(test_app.pool())
+        .await
+        .unwrap();
+    assert_eq!(rows, 0);
+}
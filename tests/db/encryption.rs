@@ -0,0 +1,79 @@
+use nostr::Keys;
+use nostrtalk::db::{connect_encrypted, upgrade_db, DbContact, DbKey};
+use sqlx::SqlitePool;
+use tempfile::TempDir;
+
+/// Tests for the encryption at rest of the account database
+
+fn is_plaintext(path: &std::path::Path) -> bool {
+    let bytes = std::fs::read(path).unwrap();
+    bytes.starts_with(b"SQLite format 3\0")
+}
+
+#[tokio::test]
+async fn new_database_is_encrypted() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("account.db3");
+    let db_key = DbKey::from_keys(&Keys::generate()).unwrap();
+
+    // PERFORM
+    let pool = connect_encrypted(&db_path, &db_key).await.unwrap();
+    upgrade_db(&pool).await.unwrap();
+    pool.close().await;
+
+    // ASSERT
+    assert!(!is_plaintext(&db_path));
+}
+
+#[tokio::test]
+async fn plaintext_database_is_migrated() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("account.db3");
+    let db_url = format!("sqlite://{}?mode=rwc", db_path.to_str().unwrap());
+    let contact_pubkey = Keys::generate().public_key();
+
+    let plain_pool = SqlitePool::connect(&db_url).await.unwrap();
+    upgrade_db(&plain_pool).await.unwrap();
+    DbContact::insert(&plain_pool, &contact_pubkey)
+        .await
+        .unwrap();
+    plain_pool.close().await;
+    assert!(is_plaintext(&db_path));
+
+    let db_key = DbKey::from_keys(&Keys::generate()).unwrap();
+
+    // PERFORM
+    let pool = connect_encrypted(&db_path, &db_key).await.unwrap();
+    // must not run the initial setup again
+    upgrade_db(&pool).await.unwrap();
+    let contacts = DbContact::fetch_basic(&pool).await.unwrap();
+    pool.close().await;
+
+    // ASSERT
+    assert!(!is_plaintext(&db_path));
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0].pubkey(), &contact_pubkey);
+}
+
+#[tokio::test]
+async fn wrong_key_fails_to_open() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("account.db3");
+    let db_key = DbKey::from_keys(&Keys::generate()).unwrap();
+    let pool = connect_encrypted(&db_path, &db_key).await.unwrap();
+    upgrade_db(&pool).await.unwrap();
+    pool.close().await;
+
+    // PERFORM
+    let other_key = DbKey::from_keys(&Keys::generate()).unwrap();
+    let result = match connect_encrypted(&db_path, &other_key).await {
+        Ok(pool) => upgrade_db(&pool).await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+
+    // ASSERT
+    assert!(result.is_err());
+}
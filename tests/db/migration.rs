@@ -0,0 +1,745 @@
+use nostrtalk::db::{
+    backup_path, curr_db_version, upgrade_cache_db, upgrade_cache_db_to, upgrade_db, upgrade_db_to,
+    MessageSearch, MessageSearchQuery, SearchScope, CACHE_DB_VERSION, DB_VERSION,
+};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Tests upgrading databases from every historical schema version
+
+const DB_V1: &str = include_str!("../fixtures/db_v1.sql");
+const CACHE_V1: &str = include_str!("../fixtures/cache_v1.sql");
+
+const ALICE: &str = "8a2c6e4f0d7e2b1a9c3d5f7e6b8a0c2d4e6f8a1b3c5d7e9f0a2b4c6d8e0f1a3b";
+const BOB: &str = "b0b6e4f0d7e2b1a9c3d5f7e6b8a0c2d4e6f8a1b3c5d7e9f0a2b4c6d8e0f1a3b0";
+const CAROL: &str = "ca201e4f0d7e2b1a9c3d5f7e6b8a0c2d4e6f8a1b3c5d7e9f0a2b4c6d8e0f1a3b";
+const DAVE: &str = "da7e1e4f0d7e2b1a9c3d5f7e6b8a0c2d4e6f8a1b3c5d7e9f0a2b4c6d8e0f1a3b";
+const USER: &str = "05e71e4f0d7e2b1a9c3d5f7e6b8a0c2d4e6f8a1b3c5d7e9f0a2b4c6d8e0f1a3b";
+const CHANNEL_ID: &str = "5b1c3d7e9f0a2b4c6d8e0f1a3b8a2c6e4f0d7e2b1a9c3d5f7e6b8a0c2d4e6f8a";
+
+async fn fixture_pool(dir: &TempDir, fixture: Option<&str>) -> (SqlitePool, PathBuf) {
+    let db_path = dir.path().join("test.db3");
+    let db_url = format!("sqlite://{}?mode=rwc", db_path.to_str().unwrap());
+    let pool = SqlitePool::connect(&db_url).await.unwrap();
+    if let Some(sql) = fixture {
+        sqlx::query(sql).execute(&pool).await.unwrap();
+    }
+    (pool, db_path)
+}
+
+/// Account database at `version` with the rows of `seed`, before the next migration
+async fn seeded_db(dir: &TempDir, version: usize, seed: &str) -> SqlitePool {
+    let (pool, _) = fixture_pool(dir, None).await;
+    upgrade_db_to(&pool, version).await.unwrap();
+    sqlx::query(seed).execute(&pool).await.unwrap();
+    pool
+}
+
+/// Cache database at `version` with the rows of `seed`, before the next migration
+async fn seeded_cache_db(dir: &TempDir, version: usize, seed: &str) -> SqlitePool {
+    let (pool, _) = fixture_pool(dir, None).await;
+    upgrade_cache_db_to(&pool, version).await.unwrap();
+    sqlx::query(seed).execute(&pool).await.unwrap();
+    pool
+}
+
+async fn count(pool: &SqlitePool, table: &str) -> i64 {
+    sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+fn insert_contact(pubkey: &str) -> String {
+    format!(
+        "INSERT INTO contact (pubkey, created_at, updated_at) VALUES ('{}', 1686000000000, 1686000000000);",
+        pubkey
+    )
+}
+
+fn insert_message(event_id: i64, chat_pubkey: &str, is_users: bool) -> String {
+    format!(
+        "INSERT INTO message (event_id, content, chat_pubkey, is_users, created_at, status, relay_url)
+        VALUES ({}, 'encrypted', '{}', {}, 1686000000000, 1, 'wss://relay.example.com');",
+        event_id, chat_pubkey, is_users as u8
+    )
+}
+
+fn insert_channel_message(event_id: i64, content: &str) -> String {
+    format!(
+        "INSERT INTO channel_message (event_id, channel_id, author, is_users, created_at, relay_url, content)
+        VALUES ({}, '{}', '{}', 0, 1686000000000, 'wss://relay.example.com', '{}');",
+        event_id, CHANNEL_ID, ALICE, content
+    )
+}
+
+fn insert_event(event_id: i64) -> String {
+    format!(
+        "INSERT INTO event (event_id, event_hash, pubkey, created_at, kind, content, tags, sig, relay_url)
+        VALUES ({}, 'hash{}', '{}', 1686000000000, 4, 'encrypted', '[]', 'sig', 'wss://relay.example.com');",
+        event_id, event_id, ALICE
+    )
+}
+
+/// Contact list (kind 3) of the user following `pubkeys`
+fn insert_contact_list(event_id: i64, pubkeys: &[&str]) -> String {
+    let tags: Vec<String> = pubkeys
+        .iter()
+        .map(|pubkey| format!(r#"["p","{}","",""]"#, pubkey))
+        .collect();
+    format!(
+        "INSERT INTO event (event_id, event_hash, pubkey, created_at, kind, content, tags, sig, relay_url)
+        VALUES ({}, 'hash{}', '{}', 1686000000000, 3, '', '[{}]', 'sig', 'wss://relay.example.com');",
+        event_id,
+        event_id,
+        USER,
+        tags.join(",")
+    )
+}
+
+async fn has_trigger(pool: &SqlitePool, name: &str) -> bool {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'trigger' AND name = ?",
+    )
+    .bind(name)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    count > 0
+}
+
+async fn file_version(path: &Path) -> usize {
+    let pool = SqlitePool::connect(path.to_str().unwrap()).await.unwrap();
+    let version = curr_db_version(&pool).await.unwrap();
+    pool.close().await;
+    version
+}
+
+#[tokio::test]
+async fn upgrade_db_from_v0() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let (pool, db_path) = fixture_pool(&dir, None).await;
+
+    // PERFORM
+    upgrade_db(&pool).await.unwrap();
+
+    // ASSERT
+    assert_eq!(curr_db_version(&pool).await.unwrap(), DB_VERSION);
+    assert!(has_trigger(&pool, "channel_message_search_insert").await);
+    // nothing to back up on a new database
+    assert!(!backup_path(&db_path, 0).exists());
+}
+
+#[tokio::test]
+async fn upgrade_db_from_v1() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let (pool, db_path) = fixture_pool(&dir, Some(DB_V1)).await;
+
+    // PERFORM
+    upgrade_db(&pool).await.unwrap();
+
+    // ASSERT
+    assert_eq!(curr_db_version(&pool).await.unwrap(), DB_VERSION);
+
+    let petname: String = sqlx::query_scalar("SELECT petname FROM contact")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(petname, "alice");
+
+    let results = MessageSearch::search(
+        &pool,
+        &MessageSearchQuery {
+            text: "upgrade".into(),
+            scope: SearchScope::Channels,
+            author: None,
+            since: None,
+            until: None,
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.len(), 1);
+
+    let backup = backup_path(&db_path, 1);
+    assert!(backup.exists());
+    assert_eq!(file_version(&backup).await, 1);
+}
+
+#[tokio::test]
+async fn upgrade_db_is_idempotent() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let (pool, _) = fixture_pool(&dir, None).await;
+    upgrade_db(&pool).await.unwrap();
+
+    // PERFORM
+    let result = upgrade_db(&pool).await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error upgrading: {:?}", result.err());
+    assert_eq!(curr_db_version(&pool).await.unwrap(), DB_VERSION);
+}
+
+/// A failing step must not leave the schema half upgraded
+#[tokio::test]
+async fn failed_migration_is_rolled_back() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let (pool, _) = fixture_pool(&dir, Some(DB_V1)).await;
+    // clashes with the search index created by the v1 -> v2 migration
+    sqlx::query("CREATE TABLE message_search (id INTEGER PRIMARY KEY);")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // PERFORM
+    let result = upgrade_db(&pool).await;
+
+    // ASSERT
+    assert!(result.is_err());
+    assert_eq!(curr_db_version(&pool).await.unwrap(), 1);
+    assert!(!has_trigger(&pool, "channel_message_search_insert").await);
+}
+
+#[tokio::test]
+async fn newer_db_version_is_rejected() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let (pool, _) = fixture_pool(&dir, None).await;
+    sqlx::query(&format!("PRAGMA user_version = {};", DB_VERSION + 1))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // PERFORM
+    let result = upgrade_db(&pool).await;
+
+    // ASSERT
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn upgrade_cache_db_from_v0() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let (pool, _) = fixture_pool(&dir, None).await;
+
+    // PERFORM
+    upgrade_cache_db(&pool).await.unwrap();
+
+    // ASSERT
+    assert_eq!(curr_db_version(&pool).await.unwrap(), CACHE_DB_VERSION);
+}
+
+#[tokio::test]
+async fn upgrade_cache_db_from_v1() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let (pool, db_path) = fixture_pool(&dir, Some(CACHE_V1)).await;
+
+    // PERFORM
+    let result = upgrade_cache_db(&pool).await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error upgrading: {:?}", result.err());
+    assert_eq!(curr_db_version(&pool).await.unwrap(), CACHE_DB_VERSION);
+    let backup = backup_path(&db_path, 1);
+    assert!(backup.exists());
+    assert_eq!(file_version(&backup).await, 1);
+}
+
+#[tokio::test]
+async fn migrate_db_v1_to_v2_indexes_channel_messages() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = [
+        insert_channel_message(1, "first channel message"),
+        insert_channel_message(2, "second channel message"),
+        insert_message(3, ALICE, false),
+    ]
+    .concat();
+    let pool = seeded_db(&dir, 1, &seed).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 2).await.unwrap();
+
+    // ASSERT
+    // direct messages are encrypted, indexed after decryption
+    let indexed: Vec<(i64, String)> =
+        sqlx::query_as("SELECT rowid, content FROM message_search ORDER BY rowid")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        indexed,
+        vec![
+            (1, "first channel message".to_owned()),
+            (2, "second channel message".to_owned())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn migrate_db_v2_to_v3_keeps_media_off_for_contacts() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = [insert_contact(ALICE), insert_contact(BOB)].concat();
+    let pool = seeded_db(&dir, 2, &seed).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 3).await.unwrap();
+
+    // ASSERT
+    let auto_load: Vec<bool> = sqlx::query_scalar("SELECT auto_load_media FROM contact")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(auto_load, vec![false, false]);
+}
+
+#[tokio::test]
+async fn migrate_db_v3_to_v4_gives_messages_no_attachments() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = [
+        insert_message(1, ALICE, false),
+        insert_channel_message(2, "hi"),
+    ]
+    .concat();
+    let pool = seeded_db(&dir, 3, &seed).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 4).await.unwrap();
+
+    // ASSERT
+    let dm_attachments: String = sqlx::query_scalar("SELECT attachments FROM message")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(dm_attachments, "[]");
+    let channel_attachments: String = sqlx::query_scalar("SELECT attachments FROM channel_message")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(channel_attachments, "[]");
+}
+
+#[tokio::test]
+async fn migrate_db_v4_to_v5_keeps_messages() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = [
+        insert_message(1, ALICE, false),
+        insert_channel_message(2, "hi"),
+    ]
+    .concat();
+    let pool = seeded_db(&dir, 4, &seed).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 5).await.unwrap();
+
+    // ASSERT
+    assert_eq!(count(&pool, "zap_receipt").await, 0);
+    assert_eq!(count(&pool, "message").await, 1);
+    assert_eq!(count(&pool, "channel_message").await, 1);
+}
+
+#[tokio::test]
+async fn migrate_db_v5_to_v6_starts_without_wallet() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let pool = seeded_db(&dir, 5, &insert_contact(ALICE)).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 6).await.unwrap();
+
+    // ASSERT
+    assert_eq!(count(&pool, "wallet_connect").await, 0);
+    assert_eq!(count(&pool, "wallet_payment").await, 0);
+    assert_eq!(count(&pool, "contact").await, 1);
+}
+
+#[tokio::test]
+async fn migrate_db_v6_to_v7_sets_default_dnd_schedule() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let pool = seeded_db(
+        &dir,
+        6,
+        "UPDATE user_config SET has_logged_in = 1, ntp_offset = 42 WHERE id = 1;",
+    )
+    .await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 7).await.unwrap();
+
+    // ASSERT
+    let (has_logged_in, ntp_offset, dnd_enabled, dnd_start, dnd_end): (bool, i64, bool, i64, i64) =
+        sqlx::query_as(
+            "SELECT has_logged_in, ntp_offset, dnd_enabled, dnd_start, dnd_end FROM user_config",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(has_logged_in);
+    assert_eq!(ntp_offset, 42);
+    assert!(!dnd_enabled);
+    // 22:00 to 07:00
+    assert_eq!(dnd_start, 1320);
+    assert_eq!(dnd_end, 420);
+    assert_eq!(count(&pool, "chat_notification").await, 0);
+}
+
+#[tokio::test]
+async fn migrate_db_v7_to_v8_starts_without_followers() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let pool = seeded_db(&dir, 7, &insert_contact(ALICE)).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 8).await.unwrap();
+
+    // ASSERT
+    // contacts are who the user follows, not who follows the user
+    assert_eq!(count(&pool, "follower").await, 0);
+    assert_eq!(count(&pool, "contact").await, 1);
+}
+
+#[tokio::test]
+async fn migrate_db_v8_to_v9_mutes_nobody() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let pool = seeded_db(&dir, 8, &insert_contact(ALICE)).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 9).await.unwrap();
+
+    // ASSERT
+    assert_eq!(count(&pool, "muted_pubkey").await, 0);
+}
+
+#[tokio::test]
+async fn migrate_db_v9_to_v10_turns_unanswered_senders_into_requests() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = [
+        // added by the user, no messages yet
+        insert_contact(ALICE),
+        // only sent messages to the user
+        insert_contact(BOB),
+        insert_message(1, BOB, false),
+        insert_message(2, BOB, false),
+        // the user replied
+        insert_contact(CAROL),
+        insert_message(3, CAROL, false),
+        insert_message(4, CAROL, true),
+    ]
+    .concat();
+    let pool = seeded_db(&dir, 9, &seed).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 10).await.unwrap();
+
+    // ASSERT
+    let contacts: Vec<(String, u8, bool)> =
+        sqlx::query_as("SELECT pubkey, status, listed FROM contact ORDER BY pubkey")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        contacts,
+        vec![
+            (ALICE.to_owned(), 1, true),
+            (BOB.to_owned(), 0, false),
+            (CAROL.to_owned(), 1, true),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn migrate_db_v9_to_v10_keeps_published_contacts_listed() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = [
+        // older list, replaced by the next one
+        insert_contact_list(1, &[CAROL, DAVE]),
+        insert_contact_list(2, &[ALICE, BOB]),
+        // on the list, no messages yet
+        insert_contact(ALICE),
+        // on the list, wrote first and never got a reply
+        insert_contact(BOB),
+        insert_message(3, BOB, false),
+        // not on the list, the user replied
+        insert_contact(CAROL),
+        insert_message(4, CAROL, false),
+        insert_message(5, CAROL, true),
+        // not on the list, never answered
+        insert_contact(DAVE),
+        insert_message(6, DAVE, false),
+    ]
+    .concat();
+    let pool = seeded_db(&dir, 9, &seed).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 10).await.unwrap();
+
+    // ASSERT
+    let contacts: Vec<(String, u8, bool)> =
+        sqlx::query_as("SELECT pubkey, status, listed FROM contact ORDER BY pubkey")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        contacts,
+        vec![
+            (ALICE.to_owned(), 1, true),
+            (BOB.to_owned(), 1, true),
+            (CAROL.to_owned(), 1, false),
+            (DAVE.to_owned(), 0, false),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn migrate_db_v10_to_v11_disables_proof_of_work() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let pool = seeded_db(
+        &dir,
+        10,
+        "INSERT INTO relay (url) VALUES ('wss://relay.example.com');",
+    )
+    .await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 11).await.unwrap();
+
+    // ASSERT
+    let user_difficulty: u8 = sqlx::query_scalar("SELECT pow_difficulty FROM user_config")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(user_difficulty, 0);
+    let relay_difficulty: u8 = sqlx::query_scalar("SELECT pow_difficulty FROM relay")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(relay_difficulty, 0);
+}
+
+#[tokio::test]
+async fn migrate_db_v11_to_v12_lets_every_message_through() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = [insert_event(1), insert_message(1, ALICE, false)].concat();
+    let pool = seeded_db(&dir, 11, &seed).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 12).await.unwrap();
+
+    // ASSERT
+    let threshold: u8 = sqlx::query_scalar("SELECT incoming_pow_threshold FROM user_config")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(threshold, 0);
+    // messages received before aren't held back
+    assert_eq!(count(&pool, "filtered_message").await, 0);
+    assert_eq!(count(&pool, "message").await, 1);
+}
+
+#[tokio::test]
+async fn migrate_db_v12_to_v13_keeps_events_forever() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = [insert_event(1), insert_event(2)].concat();
+    let pool = seeded_db(&dir, 12, &seed).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 13).await.unwrap();
+
+    // ASSERT
+    let expires_at: Vec<Option<i64>> =
+        sqlx::query_scalar("SELECT expires_at FROM event ORDER BY event_id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(expires_at, vec![None, None]);
+    assert_eq!(count(&pool, "chat_expiration").await, 0);
+}
+
+#[tokio::test]
+async fn migrate_db_v13_to_v14_starts_without_drafts() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = [insert_contact(ALICE), insert_message(1, ALICE, false)].concat();
+    let pool = seeded_db(&dir, 13, &seed).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 14).await.unwrap();
+
+    // ASSERT
+    assert_eq!(count(&pool, "draft").await, 0);
+    assert_eq!(count(&pool, "message").await, 1);
+}
+
+#[tokio::test]
+async fn migrate_db_v14_to_v15_leaves_channels_unread_since_subscribing() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = format!(
+        "INSERT INTO channel_subscription (channel_id, subscribed_at) VALUES ('{}', 1686000000000);",
+        CHANNEL_ID
+    );
+    let pool = seeded_db(&dir, 14, &seed).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 15).await.unwrap();
+
+    // ASSERT
+    let (subscribed_at, last_read_at): (i64, Option<i64>) =
+        sqlx::query_as("SELECT subscribed_at, last_read_at FROM channel_subscription")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(subscribed_at, 1686000000000);
+    assert_eq!(last_read_at, None);
+}
+
+#[tokio::test]
+async fn migrate_cache_db_v1_to_v2_counts_entries_as_accessed_now() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = format!(
+        "INSERT INTO image_cache (path, kind, event_hash) VALUES ('/images/a.png', 0, 'hash1');
+        INSERT INTO profile_meta_cache (public_key, updated_at, event_hash, from_relay, metadata)
+        VALUES ('{}', 1686000000000, 'hash2', 'wss://relay.example.com', '{{}}');",
+        ALICE
+    );
+    let pool = seeded_cache_db(&dir, 1, &seed).await;
+    let before = chrono::Utc::now().timestamp() * 1000;
+
+    // PERFORM
+    upgrade_cache_db_to(&pool, 2).await.unwrap();
+
+    // ASSERT
+    let (size_bytes, image_accessed_at): (i64, i64) =
+        sqlx::query_as("SELECT size_bytes, last_accessed_at FROM image_cache")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(size_bytes, 0);
+    assert!(image_accessed_at >= before);
+    let profile_accessed_at: i64 =
+        sqlx::query_scalar("SELECT last_accessed_at FROM profile_meta_cache")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert!(profile_accessed_at >= before);
+}
+
+#[tokio::test]
+async fn migrate_cache_db_v2_to_v3_leaves_old_images_out_of_blob_store() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed =
+        "INSERT INTO image_cache (path, kind, event_hash) VALUES ('/images/a.png', 0, 'hash1');";
+    let pool = seeded_cache_db(&dir, 2, seed).await;
+
+    // PERFORM
+    upgrade_cache_db_to(&pool, 3).await.unwrap();
+
+    // ASSERT
+    let (path, content_hash): (String, Option<String>) =
+        sqlx::query_as("SELECT path, content_hash FROM image_cache")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(path, "/images/a.png");
+    assert_eq!(content_hash, None);
+    assert_eq!(count(&pool, "image_blob").await, 0);
+    assert_eq!(count(&pool, "image_url").await, 0);
+}
+
+#[tokio::test]
+async fn migrate_cache_db_v3_to_v4_keeps_cached_images() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed =
+        "INSERT INTO image_cache (path, kind, event_hash) VALUES ('/images/a.png', 0, 'hash1');";
+    let pool = seeded_cache_db(&dir, 3, seed).await;
+
+    // PERFORM
+    upgrade_cache_db_to(&pool, 4).await.unwrap();
+
+    // ASSERT
+    assert_eq!(count(&pool, "link_preview").await, 0);
+    assert_eq!(count(&pool, "image_cache").await, 1);
+}
+
+#[tokio::test]
+async fn migrate_cache_db_v4_to_v5_keeps_cached_channels() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = format!(
+        "INSERT INTO channel_cache (creation_event_hash, creator_pubkey, created_at, metadata)
+        VALUES ('{}', '{}', 1686000000000, '{{\"name\":\"rust\"}}');",
+        CHANNEL_ID, ALICE
+    );
+    let pool = seeded_cache_db(&dir, 4, &seed).await;
+
+    // PERFORM
+    upgrade_cache_db_to(&pool, 5).await.unwrap();
+
+    // ASSERT
+    let metadata: String = sqlx::query_scalar("SELECT metadata FROM channel_cache")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(metadata, r#"{"name":"rust"}"#);
+    assert_eq!(count(&pool, "channel_preview_message").await, 0);
+}
+
+#[tokio::test]
+async fn migrate_db_v15_to_v16_trusts_no_zap_provider() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let pool = seeded_db(&dir, 15, &insert_contact(ALICE)).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 16).await.unwrap();
+
+    // ASSERT
+    // receipts are only counted once the recipient's LNURL server is resolved
+    assert_eq!(count(&pool, "zap_provider").await, 0);
+    assert_eq!(count(&pool, "contact").await, 1);
+}
+
+#[tokio::test]
+async fn migrate_db_v16_to_v17_keeps_read_time_of_channels() {
+    // PREPARE
+    let dir = TempDir::new().unwrap();
+    let seed = format!(
+        "INSERT INTO channel_subscription (channel_id, subscribed_at, last_read_at)
+        VALUES ('{}', 1686000000000, 1687000000000);",
+        CHANNEL_ID
+    );
+    let pool = seeded_db(&dir, 16, &seed).await;
+
+    // PERFORM
+    upgrade_db_to(&pool, 17).await.unwrap();
+
+    // ASSERT
+    // the read time is used until the channel is opened again
+    let (last_read_at, last_read_event_id): (Option<i64>, Option<i64>) =
+        sqlx::query_as("SELECT last_read_at, last_read_event_id FROM channel_subscription")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(last_read_at, Some(1687000000000));
+    assert_eq!(last_read_event_id, None);
+}
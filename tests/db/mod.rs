@@ -0,0 +1,11 @@
+mod cache_eviction;
+mod channel_member_stats;
+mod channel_preview;
+mod channel_read_marker;
+mod contact;
+mod draft;
+mod encryption;
+mod migration;
+mod muted_pubkey;
+mod notification;
+mod search;
@@ -0,0 +1,77 @@
+use chrono::Utc;
+use nostr::Keys;
+use nostrtalk::db::DbMutedPubkey;
+
+use crate::spawn_app;
+
+#[tokio::test]
+async fn mute_and_unmute() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let pubkey = Keys::generate().public_key();
+    let muted = DbMutedPubkey::new(&pubkey, false, Utc::now().naive_utc());
+
+    // PERFORM
+    DbMutedPubkey::insert(test_app.pool(), &muted)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert!(DbMutedPubkey::is_muted(test_app.pool(), &pubkey)
+        .await
+        .unwrap());
+    DbMutedPubkey::delete(test_app.pool(), &pubkey)
+        .await
+        .unwrap();
+    assert!(!DbMutedPubkey::is_muted(test_app.pool(), &pubkey)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn mute_again_changes_to_private() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let pubkey = Keys::generate().public_key();
+    let now = Utc::now().naive_utc();
+    DbMutedPubkey::insert(test_app.pool(), &DbMutedPubkey::new(&pubkey, false, now))
+        .await
+        .unwrap();
+
+    // PERFORM
+    DbMutedPubkey::insert(test_app.pool(), &DbMutedPubkey::new(&pubkey, true, now))
+        .await
+        .unwrap();
+
+    // ASSERT
+    let muted = DbMutedPubkey::fetch(test_app.pool()).await.unwrap();
+    assert_eq!(muted.len(), 1);
+    assert!(muted[0].private);
+}
+
+#[tokio::test]
+async fn replace_all_drops_old_entries() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let now = Utc::now().naive_utc();
+    let old = Keys::generate().public_key();
+    let new = Keys::generate().public_key();
+    DbMutedPubkey::insert(test_app.pool(), &DbMutedPubkey::new(&old, false, now))
+        .await
+        .unwrap();
+
+    // PERFORM
+    let mut tx = test_app.pool().begin().await.unwrap();
+    DbMutedPubkey::replace_all_with(&mut tx, &[DbMutedPubkey::new(&new, true, now)])
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    // ASSERT
+    assert!(!DbMutedPubkey::is_muted(test_app.pool(), &old)
+        .await
+        .unwrap());
+    assert!(DbMutedPubkey::is_muted(test_app.pool(), &new)
+        .await
+        .unwrap());
+}
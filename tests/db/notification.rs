@@ -0,0 +1,127 @@
+use chrono::{Duration, NaiveTime, Utc};
+use nostr::{EventId, Keys};
+use nostrtalk::db::{ChatNotification, DoNotDisturb, Mute, NotificationChat};
+
+use crate::spawn_app;
+
+#[tokio::test]
+async fn chat_without_rules_uses_defaults() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let chat = NotificationChat::Dm(Keys::generate().public_key());
+
+    // PERFORM
+    let rules = ChatNotification::fetch(test_app.pool(), &chat)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert_eq!(rules, ChatNotification::new(chat));
+    assert!(rules.allows(Utc::now().naive_utc(), false));
+}
+
+#[tokio::test]
+async fn chat_rules_are_saved() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let channel_id = EventId::from_hex("b".repeat(64)).unwrap();
+    let mut forever = ChatNotification::new(NotificationChat::Dm(Keys::generate().public_key()));
+    forever.mute = Mute::Forever;
+    let mut until = ChatNotification::new(NotificationChat::Channel(channel_id));
+    let muted_until = Utc::now().naive_utc() + Duration::hours(1);
+    until.mute = Mute::Until(muted_until);
+    until.mentions_only = true;
+
+    // PERFORM
+    ChatNotification::save(test_app.pool(), &forever)
+        .await
+        .unwrap();
+    ChatNotification::save(test_app.pool(), &until)
+        .await
+        .unwrap();
+
+    // ASSERT
+    let saved = ChatNotification::fetch(test_app.pool(), &forever.chat)
+        .await
+        .unwrap();
+    assert_eq!(saved.mute, Mute::Forever);
+
+    let saved = ChatNotification::fetch(test_app.pool(), &until.chat)
+        .await
+        .unwrap();
+    assert!(saved.mentions_only);
+    match saved.mute {
+        Mute::Until(saved_until) => {
+            assert_eq!(
+                saved_until.timestamp_millis(),
+                muted_until.timestamp_millis()
+            )
+        }
+        other => panic!("Unexpected mute: {:?}", other),
+    }
+}
+
+#[test]
+fn expired_mute_allows_notifications() {
+    // PREPARE
+    let now = Utc::now().naive_utc();
+    let mut rules = ChatNotification::new(NotificationChat::Dm(Keys::generate().public_key()));
+    rules.mute = Mute::Until(now - Duration::minutes(1));
+
+    // ASSERT
+    assert!(!rules.is_muted(now));
+    assert!(rules.allows(now, false));
+}
+
+#[test]
+fn mentions_only_channel_needs_a_mention() {
+    // PREPARE
+    let now = Utc::now().naive_utc();
+    let channel_id = EventId::from_hex("c".repeat(64)).unwrap();
+    let mut rules = ChatNotification::new(NotificationChat::Channel(channel_id));
+    rules.mentions_only = true;
+
+    // ASSERT
+    assert!(!rules.allows(now, false));
+    assert!(rules.allows(now, true));
+}
+
+#[tokio::test]
+async fn do_not_disturb_is_saved() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let dnd = DoNotDisturb {
+        enabled: true,
+        start: NaiveTime::from_hms_opt(23, 30, 0).unwrap(),
+        end: NaiveTime::from_hms_opt(6, 15, 0).unwrap(),
+    };
+
+    // PERFORM
+    DoNotDisturb::save(test_app.pool(), &dnd).await.unwrap();
+
+    // ASSERT
+    let saved = DoNotDisturb::fetch(test_app.pool()).await.unwrap();
+    assert_eq!(saved, dnd);
+}
+
+#[test]
+fn do_not_disturb_past_midnight() {
+    // PREPARE
+    let time = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+    let dnd = DoNotDisturb {
+        enabled: true,
+        start: time(22),
+        end: time(7),
+    };
+
+    // ASSERT
+    assert!(dnd.is_active(time(23)));
+    assert!(dnd.is_active(time(3)));
+    assert!(!dnd.is_active(time(7)));
+    assert!(!dnd.is_active(time(12)));
+    assert!(!DoNotDisturb {
+        enabled: false,
+        ..dnd
+    }
+    .is_active(time(23)));
+}
@@ -0,0 +1,179 @@
+use chrono::Duration;
+use nostr::EventBuilder;
+use nostr::Keys;
+use nostrtalk::db::{DbMessage, MessageSearch, MessageSearchQuery, SearchChat, SearchScope};
+
+use crate::common::{event_with_time, make_channel_msg_event, make_dm_event};
+use crate::spawn_app;
+
+/// Tests for the full-text search over direct and channel messages
+
+fn query(text: &str, scope: SearchScope) -> MessageSearchQuery {
+    MessageSearchQuery {
+        text: text.into(),
+        scope,
+        author: None,
+        since: None,
+        until: None,
+    }
+}
+
+/// Direct messages are indexed with the decrypted content
+#[tokio::test]
+async fn search_decrypted_dm() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sender_keys = Keys::generate();
+    let ns_event = make_dm_event(
+        &sender_keys,
+        test_app.keys.public_key(),
+        "the secret recipe",
+    );
+    test_app.receive(ns_event).await;
+    let ns_event = make_dm_event(&sender_keys, test_app.keys.public_key(), "something else");
+    test_app.receive(ns_event).await;
+
+    // PERFORM
+    let results = MessageSearch::search(
+        test_app.pool(),
+        &query("recip", SearchScope::DirectMessages),
+    )
+    .await
+    .unwrap();
+
+    // ASSERT
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].author, sender_keys.public_key());
+    assert_eq!(
+        results[0].chat,
+        SearchChat::Contact(sender_keys.public_key())
+    );
+    assert!(results[0].snippet.contains("secret recipe"));
+}
+
+#[tokio::test]
+async fn search_filters_by_chat_and_author() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let cache = test_app.insert_random_channel_cache().await;
+    let channel_id = cache.channel_id;
+    let author_keys = Keys::generate();
+    let other_keys = Keys::generate();
+
+    let ns_event = make_channel_msg_event(&author_keys, &channel_id, None, "hello channel");
+    test_app.receive(ns_event).await;
+    let ns_event = make_channel_msg_event(&other_keys, &channel_id, None, "hello there");
+    test_app.receive(ns_event).await;
+    let ns_event = make_dm_event(&author_keys, test_app.keys.public_key(), "hello friend");
+    test_app.receive(ns_event).await;
+
+    // PERFORM
+    let channel_results = MessageSearch::search(
+        test_app.pool(),
+        &query("hello", SearchScope::Chat(SearchChat::Channel(channel_id))),
+    )
+    .await
+    .unwrap();
+
+    let mut author_query = query("hello", SearchScope::Channels);
+    author_query.author = Some(author_keys.public_key());
+    let author_results = MessageSearch::search(test_app.pool(), &author_query)
+        .await
+        .unwrap();
+
+    let dm_results = MessageSearch::search(
+        test_app.pool(),
+        &query("hello", SearchScope::DirectMessages),
+    )
+    .await
+    .unwrap();
+
+    // ASSERT
+    assert_eq!(channel_results.len(), 2);
+    assert_eq!(author_results.len(), 1);
+    assert_eq!(author_results[0].author, author_keys.public_key());
+    assert_eq!(dm_results.len(), 1);
+    assert_eq!(
+        dm_results[0].chat,
+        SearchChat::Contact(author_keys.public_key())
+    );
+}
+
+#[tokio::test]
+async fn search_filters_by_date_range() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let cache = test_app.insert_random_channel_cache().await;
+    let channel_id = cache.channel_id;
+    let ns_event = make_channel_msg_event(&Keys::generate(), &channel_id, None, "dated message");
+    test_app.receive(ns_event).await;
+    let now = chrono::Utc::now().naive_utc();
+
+    // PERFORM
+    let mut in_range = query("dated", SearchScope::Channels);
+    in_range.since = Some(now - Duration::days(1));
+    in_range.until = Some(now + Duration::days(1));
+    let in_range = MessageSearch::search(test_app.pool(), &in_range)
+        .await
+        .unwrap();
+
+    let mut out_of_range = query("dated", SearchScope::Channels);
+    out_of_range.until = Some(now - Duration::days(1));
+    let out_of_range = MessageSearch::search(test_app.pool(), &out_of_range)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert_eq!(in_range.len(), 1);
+    assert!(out_of_range.is_empty());
+}
+
+/// Search syntax characters in the input must not break the query
+#[tokio::test]
+async fn search_with_special_characters() {
+    let test_app = spawn_app().await;
+
+    let result = MessageSearch::search(
+        test_app.pool(),
+        &query("\"unclosed * -not AND", SearchScope::DirectMessages),
+    )
+    .await;
+
+    assert!(result.is_ok(), "Error searching: {:?}", result.err());
+}
+
+/// Opening a search result loads the messages around it, oldest first
+#[tokio::test]
+async fn fetch_chat_around_is_ascending() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sender_keys = Keys::generate();
+    let now = chrono::Utc::now().naive_utc();
+    for minutes_ago in [30, 10, 50, 20, 40] {
+        let builder = EventBuilder::new_encrypted_direct_msg(
+            &sender_keys,
+            test_app.keys.public_key(),
+            format!("{} minutes ago", minutes_ago),
+        )
+        .unwrap();
+        let time = now - Duration::minutes(minutes_ago);
+        test_app
+            .receive(event_with_time(&sender_keys, builder, time))
+            .await;
+    }
+
+    // PERFORM
+    let messages = DbMessage::fetch_chat_around(
+        test_app.pool(),
+        &sender_keys.public_key(),
+        now - Duration::minutes(30),
+    )
+    .await
+    .unwrap();
+
+    // ASSERT
+    assert_eq!(messages.len(), 5);
+    assert!(messages
+        .windows(2)
+        .all(|pair| pair[0].created_at < pair[1].created_at));
+}
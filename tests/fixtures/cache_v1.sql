@@ -0,0 +1,47 @@
+-- Cache database at schema v1
+
+CREATE TABLE IF NOT EXISTS profile_meta_cache (
+    public_key BLOB PRIMARY KEY,
+    -- UNIX milliseconds
+    updated_at INTEGER NOT NULL,
+    event_hash TEXT NOT NULL,
+    from_relay TEXT NOT NULL,
+    -- METADATA JSON CONTENT
+    metadata TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS channel_cache (
+    -- channel_id is the hash of the channel's first event
+    creation_event_hash TEXT PRIMARY KEY,
+    creator_pubkey BLOB NOT NULL,
+    -- UNIX milliseconds
+    created_at INTEGER NOT NULL,
+    updated_event_hash BLOB,
+    -- UNIX milliseconds
+    updated_at INTEGER,
+    -- METADATA JSON CONTENT (name, about, picture)
+    metadata TEXT NOT NULL
+);
+
+-- Channel Cache Indexes
+CREATE INDEX IF NOT EXISTS updated_event_hash_index ON channel_cache(updated_event_hash);
+
+CREATE TABLE IF NOT EXISTS image_cache (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL,
+    kind INTEGER NOT NULL,
+    event_hash TEXT NOT NULL
+);
+
+-- Events Indexes
+CREATE INDEX IF NOT EXISTS event_hash_index ON image_cache(event_hash);
+
+CREATE INDEX IF NOT EXISTS event_hash_kind_index ON image_cache(event_hash, kind);
+
+CREATE TABLE IF NOT EXISTS channel_member_map (
+    channel_id TEXT NOT NULL,
+    public_key BLOB NOT NULL,
+    PRIMARY KEY (channel_id, public_key)
+);
+
+PRAGMA user_version = 1;
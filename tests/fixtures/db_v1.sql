@@ -0,0 +1,121 @@
+-- Account database at schema v1, before the message search index
+
+CREATE TABLE IF NOT EXISTS event (
+    event_id INTEGER PRIMARY KEY,
+    event_hash TEXT NOT NULL UNIQUE,
+    -- author pubkey
+    pubkey TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    kind INTEGER NOT NULL,
+    -- serialized json of event object 
+    content TEXT NOT NULL,
+    -- serialized json vector of strings
+    tags TEXT,
+    -- event signature
+    sig TEXT NOT NULL,
+    relay_url TEXT NOT NULL
+);
+
+-- Events Indexes
+CREATE INDEX IF NOT EXISTS event_hash_index ON event(event_hash);
+
+CREATE INDEX IF NOT EXISTS pubkey_index ON event(pubkey);
+
+CREATE INDEX IF NOT EXISTS kind_index ON event(kind);
+
+CREATE INDEX IF NOT EXISTS kind_pubkey_index ON event(kind, pubkey);
+
+CREATE TABLE relay (
+    id INTEGER PRIMARY KEY,
+    url TEXT NOT NULL UNIQUE,
+    read INTEGER NOT NULL DEFAULT 1,
+    write INTEGER NOT NULL DEFAULT 1,
+    advertise INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX url ON relay (url);
+
+CREATE TABLE contact (
+    id INTEGER PRIMARY KEY,
+    pubkey TEXT NOT NULL UNIQUE,
+    status INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    unseen_messages INTEGER NOT NULL DEFAULT 0,
+    petname TEXT,
+    relay_url TEXT,
+    last_message_content TEXT,
+    last_message_date INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS pubkey_index ON contact (pubkey);
+
+CREATE TABLE message (
+    event_id INTEGER PRIMARY KEY,
+    -- base64-encoded encrypted message
+    content TEXT NOT NULL,
+    -- what chat it belongs to
+    chat_pubkey TEXT NOT NULL,
+    is_users INTEGER NOT NULL,
+    -- UNIX timestamp as integer milliseconds
+    created_at INTEGER NOT NULL,
+    status INTEGER NOT NULL,
+    relay_url TEXT NOT NULL
+);
+
+-- -- Message Indexes
+CREATE INDEX IF NOT EXISTS chat_pubkey_index ON message(chat_pubkey);
+
+CREATE TABLE IF NOT EXISTS user_config (
+    id INTEGER PRIMARY KEY,
+    has_logged_in INTEGER NOT NULL DEFAULT 0,
+    ntp_offset INTEGER NOT NULL DEFAULT 0,
+    recommended_relay TEXT
+);
+
+CREATE TABLE IF NOT EXISTS relay_response (
+    event_id INTEGER NOT NULL,
+    event_hash TEXT NOT NULL,
+    relay_url TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    error_message TEXT,
+    PRIMARY KEY (event_id, event_hash, relay_url),
+    FOREIGN KEY (event_id) REFERENCES event(event_id) ON DELETE CASCADE
+);
+
+-- Relay Responses Indexes
+CREATE UNIQUE INDEX IF NOT EXISTS event_hash_relay_url_index ON relay_response(event_hash, relay_url);
+
+CREATE TABLE channel_message (
+    event_id INTEGER PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    author TEXT NOT NULL,
+    is_users INTEGER NOT NULL,
+    -- UNIX timestamp as integer milliseconds
+    created_at INTEGER NOT NULL,
+    relay_url TEXT NOT NULL,
+    content TEXT NOT NULL
+);
+
+-- -- Message Indexes
+CREATE INDEX IF NOT EXISTS channel_id_index ON channel_message(channel_id);
+
+CREATE TABLE IF NOT EXISTS channel_subscription (
+    id INTEGER PRIMARY KEY,
+    channel_id TEXT NOT NULL UNIQUE,
+    subscribed_at INTEGER NOT NULL
+);
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS channel_id_index ON channel_subscription(channel_id);
+
+INSERT INTO user_config (id, has_logged_in, ntp_offset, recommended_relay)
+VALUES (1, 1, 0, "");
+
+INSERT INTO contact (pubkey, status, created_at, updated_at, petname)
+VALUES ("8a2c6e4f0d7e2b1a9c3d5f7e6b8a0c2d4e6f8a1b3c5d7e9f0a2b4c6d8e0f1a3b", 0, 1686000000000, 1686000000000, "alice");
+
+INSERT INTO channel_message (event_id, channel_id, author, is_users, created_at, relay_url, content)
+VALUES (1, "5b1c3d7e9f0a2b4c6d8e0f1a3b8a2c6e4f0d7e2b1a9c3d5f7e6b8a0c2d4e6f8a", "8a2c6e4f0d7e2b1a9c3d5f7e6b8a0c2d4e6f8a1b3c5d7e9f0a2b4c6d8e0f1a3b", 0, 1686000000000, "wss://relay.example.com", "message from before the upgrade");
+
+PRAGMA user_version = 1;
@@ -0,0 +1,249 @@
+use chrono::Utc;
+use futures_util::StreamExt;
+use nostr::{EventBuilder, Keys, Kind, Tag};
+use nostrtalk::{
+    db::{ChannelCache, ChannelPreviewMessage, DbChannelMessage, DbMutedPubkey},
+    net::{handle_event, BackendEvent},
+    types::{ChannelMetadata, ChannelSearchTerm, SubName},
+};
+use url::Url;
+
+use super::assert_channel_timeout;
+use crate::common::{make_channel_creation_event, make_channel_msg_event};
+use crate::spawn_app;
+
+/// Tests for the channels sent by relays while searching
+
+fn search_subscription_id() -> nostr::SubscriptionId {
+    nostr::SubscriptionId::new(SubName::SearchChannels.to_string())
+}
+
+/// Relays without NIP-50 send recent channels, only the matching ones are results
+#[tokio::test]
+async fn search_result_matches_term() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, mut rx) = futures::channel::mpsc::channel(5);
+    let url = Url::parse("ws://192.168.15.15:8080").unwrap();
+    test_app.backend.channel_search = Some(ChannelSearchTerm::parse("rust"));
+
+    let metadata = ChannelMetadata::new()
+        .name("Programming")
+        .about("Talking about Rust");
+    let ns_event = make_channel_creation_event(&Keys::generate(), &metadata);
+    let channel_id = ns_event.id;
+
+    // PERFORM
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        url,
+        search_subscription_id(),
+        ns_event,
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    match rx.next().await {
+        Some(BackendEvent::ChannelSearchCacheCreation(_, cache, _)) => {
+            assert_eq!(cache.channel_id, channel_id);
+        }
+        other => panic!("Unexpected event: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn search_result_not_matching_term() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, mut rx) = futures::channel::mpsc::channel(5);
+    let url = Url::parse("ws://192.168.15.15:8080").unwrap();
+    test_app.backend.channel_search = Some(ChannelSearchTerm::parse("#bitcoin"));
+
+    let metadata = ChannelMetadata::new().name("Gardening").about("bitcoin");
+    let ns_event = make_channel_creation_event(&Keys::generate(), &metadata);
+
+    // PERFORM
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        url,
+        search_subscription_id(),
+        ns_event,
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    assert_channel_timeout(&mut rx).await;
+}
+
+/// Channels matched by their `t` tags come with them, to be ranked by them
+#[tokio::test]
+async fn search_result_matched_by_hashtag_has_tags() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, mut rx) = futures::channel::mpsc::channel(5);
+    let url = Url::parse("ws://192.168.15.15:8080").unwrap();
+    test_app.backend.channel_search = Some(ChannelSearchTerm::parse("#rust"));
+
+    let metadata = ChannelMetadata::new().name("Programming");
+    let ns_event = EventBuilder::new(
+        Kind::ChannelCreation,
+        metadata.as_json(),
+        &[Tag::Hashtag("rust".into())],
+    )
+    .to_event(&Keys::generate())
+    .unwrap();
+
+    // PERFORM
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        url,
+        search_subscription_id(),
+        ns_event,
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    match rx.next().await {
+        Some(BackendEvent::ChannelSearchCacheCreation(_, _, hashtags)) => {
+            assert_eq!(hashtags, vec!["rust".to_owned()]);
+        }
+        other => panic!("Unexpected event: {:?}", other),
+    }
+}
+
+/// Metadata of a channel that wasn't received is ignored
+#[tokio::test]
+async fn search_metadata_of_unknown_channel() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, mut rx) = futures::channel::mpsc::channel(5);
+    let url = Url::parse("ws://192.168.15.15:8080").unwrap();
+    test_app.backend.channel_search = Some(ChannelSearchTerm::parse(""));
+
+    let channel_id = nostr::EventId::from_hex(
+        "8233a5d8e27a9415d22c974d70935011664ada55ae3152bd10d697d3a3c74f67",
+    )
+    .unwrap();
+    let metadata = ChannelMetadata::new().name("Renamed");
+    let ns_event =
+        crate::common::make_channel_metadata_event(&Keys::generate(), &channel_id, None, &metadata);
+
+    // PERFORM
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        url,
+        search_subscription_id(),
+        ns_event,
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    assert_channel_timeout(&mut rx).await;
+}
+
+/// Messages of a channel found while searching are only kept in the cache
+#[tokio::test]
+async fn search_details_message_stored_as_preview() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, mut rx) = futures::channel::mpsc::channel(5);
+    let url = Url::parse("ws://192.168.15.15:8080").unwrap();
+
+    let creation = make_channel_creation_event(&Keys::generate(), &ChannelMetadata::new());
+    let channel_id = creation.id;
+    ChannelCache::fetch_insert(test_app.cache_pool(), &creation)
+        .await
+        .unwrap();
+    let author = Keys::generate();
+    let ns_event = make_channel_msg_event(&author, &channel_id, None, "hello");
+    let subscription_id =
+        nostr::SubscriptionId::new(SubName::src_channel_details(&channel_id).to_string());
+
+    // PERFORM
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        url,
+        subscription_id,
+        ns_event,
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    assert_channel_timeout(&mut rx).await;
+
+    let previews = ChannelPreviewMessage::fetch_latest(test_app.cache_pool(), &channel_id, 10)
+        .await
+        .unwrap();
+    assert_eq!(previews.len(), 1);
+    assert_eq!(previews[0].content, "hello");
+    assert_eq!(previews[0].author, author.public_key());
+
+    let cache = ChannelCache::fetch_by_channel_id(test_app.cache_pool(), &channel_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(cache.members.contains(&author.public_key()));
+
+    let messages = DbChannelMessage::fetch(test_app.pool(), &channel_id)
+        .await
+        .unwrap();
+    assert!(messages.is_empty());
+}
+
+/// Preview messages of muted users are dropped, as in joined channels
+#[tokio::test]
+async fn search_details_message_of_muted_not_stored() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, mut rx) = futures::channel::mpsc::channel(5);
+    let url = Url::parse("ws://192.168.15.15:8080").unwrap();
+
+    let creation = make_channel_creation_event(&Keys::generate(), &ChannelMetadata::new());
+    let channel_id = creation.id;
+    ChannelCache::fetch_insert(test_app.cache_pool(), &creation)
+        .await
+        .unwrap();
+    let muted = Keys::generate();
+    let entry = DbMutedPubkey::new(&muted.public_key(), false, Utc::now().naive_utc());
+    DbMutedPubkey::insert(test_app.pool(), &entry)
+        .await
+        .unwrap();
+    let ns_event = make_channel_msg_event(&muted, &channel_id, None, "hello");
+    let subscription_id =
+        nostr::SubscriptionId::new(SubName::src_channel_details(&channel_id).to_string());
+
+    // PERFORM
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        url,
+        subscription_id,
+        ns_event,
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    assert_channel_timeout(&mut rx).await;
+
+    let previews = ChannelPreviewMessage::fetch_latest(test_app.cache_pool(), &channel_id, 10)
+        .await
+        .unwrap();
+    assert!(previews.is_empty());
+}
@@ -0,0 +1,169 @@
+use nostr::{EventBuilder, Keys, Tag, Timestamp};
+use nostrtalk::{
+    db::{DbEvent, ExpirationTimer, NotificationChat},
+    net::{process_message, ToBackend},
+    utils::event_expiration,
+};
+use url::Url;
+
+use super::dm_helpers::*;
+use crate::common::{event_with_time, make_random_contact};
+use crate::{spawn_app, TestApp};
+
+/// Tests for NIP-40 expiring messages
+
+fn expiring_event(keys: &Keys, builder: EventBuilder, expires_at: Timestamp) -> nostr::Event {
+    let mut unsigned = builder.to_unsigned_event(keys.public_key());
+    unsigned.tags.push(Tag::Expiration(expires_at));
+    let time = chrono::Utc::now().naive_utc();
+    event_with_time(
+        keys,
+        EventBuilder::new(unsigned.kind, unsigned.content, &unsigned.tags),
+        time,
+    )
+}
+
+fn expiring_dm(test_app: &TestApp, content: &str, expires_at: Timestamp) -> nostr::Event {
+    let receiver = Keys::generate();
+    let builder =
+        EventBuilder::new_encrypted_direct_msg(&test_app.keys, receiver.public_key(), content)
+            .unwrap();
+    expiring_event(&test_app.keys, builder, expires_at)
+}
+
+fn seconds_from_now(secs: i64) -> Timestamp {
+    Timestamp::from((Timestamp::now().as_i64() + secs) as u64)
+}
+
+#[tokio::test]
+async fn already_expired_dm_is_not_stored() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let ns_event = expiring_dm(&test_app, "gone", seconds_from_now(-60));
+    let event_hash = ns_event.id;
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert_dm_not_stored(&test_app, &event_hash).await;
+}
+
+#[tokio::test]
+async fn unexpired_dm_is_stored_with_its_expiration() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let expires_at = seconds_from_now(60 * 60);
+    let ns_event = expiring_dm(&test_app, "still here", expires_at);
+    let event_hash = ns_event.id;
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    let db_event = assert_dm_in_database(&test_app, &event_hash, 1, "still here").await;
+    let stored: Option<i64> = sqlx::query_scalar("SELECT expires_at FROM event WHERE event_id = ?")
+        .bind(db_event.event_id)
+        .fetch_one(test_app.pool())
+        .await
+        .unwrap();
+    assert_eq!(stored, Some(expires_at.as_i64() * 1000));
+}
+
+#[tokio::test]
+async fn sweep_deletes_expired_messages() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let url = Url::parse("ws://192.168.15.15:8080").unwrap();
+    let channel_id = test_app.insert_random_channel_cache().await.channel_id;
+    let expires_at = seconds_from_now(60);
+    let dm = expiring_dm(&test_app, "bye", expires_at);
+    let channel_msg = expiring_event(
+        &test_app.keys,
+        nostrtalk::utils::channel_msg_builder(&channel_id, Some(&url), "bye all"),
+        expires_at,
+    );
+    let lasting_dm = expiring_dm(&test_app, "stay", seconds_from_now(60 * 60));
+    let lasting_hash = lasting_dm.id;
+    test_app.receive(dm).await;
+    test_app.receive(channel_msg).await;
+    test_app.receive(lasting_dm).await;
+
+    // PERFORM
+    let deleted = DbEvent::delete_expired(test_app.pool(), seconds_from_now(120))
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert_eq!(deleted.len(), 2);
+    let events = DbEvent::fetch(test_app.pool()).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_hash, lasting_hash);
+    let messages: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM message")
+        .fetch_one(test_app.pool())
+        .await
+        .unwrap();
+    assert_eq!(messages, 1);
+    let channel_messages: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM channel_message")
+        .fetch_one(test_app.pool())
+        .await
+        .unwrap();
+    assert_eq!(channel_messages, 0);
+}
+
+#[tokio::test]
+async fn sent_dm_carries_chat_expiration() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, _rx) = futures::channel::mpsc::channel(5);
+    let (tasks_tx, _) = tokio::sync::mpsc::channel(5);
+    let contact = make_random_contact(None);
+    let contact = nostrtalk::db::DbContact::new(&contact.pk);
+    let chat = NotificationChat::Dm(contact.pubkey().to_owned());
+    ExpirationTimer::save(test_app.pool(), &chat, ExpirationTimer::OneDay)
+        .await
+        .unwrap();
+    let before = Timestamp::now().as_i64();
+
+    // PERFORM
+    let result = process_message(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        &tasks_tx,
+        ToBackend::SendDM(contact, "see you tomorrow".into(), vec![]),
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    let pending = test_app.backend.pending_events.values().next().unwrap();
+    let expires_at = event_expiration(&pending.ns_event().tags).expect("Expiration tag");
+    let lifetime = expires_at.as_i64() - before;
+    assert!((24 * 60 * 60 - 5..=24 * 60 * 60 + 5).contains(&lifetime));
+}
+
+#[tokio::test]
+async fn chat_expiration_off_by_default() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let chat = NotificationChat::Dm(Keys::generate().public_key());
+
+    // PERFORM
+    ExpirationTimer::save(test_app.pool(), &chat, ExpirationTimer::SevenDays)
+        .await
+        .unwrap();
+    let saved = ExpirationTimer::fetch(test_app.pool(), &chat)
+        .await
+        .unwrap();
+    ExpirationTimer::save(test_app.pool(), &chat, ExpirationTimer::Off)
+        .await
+        .unwrap();
+    let turned_off = ExpirationTimer::fetch(test_app.pool(), &chat)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert_eq!(saved, ExpirationTimer::SevenDays);
+    assert_eq!(turned_off, ExpirationTimer::Off);
+}
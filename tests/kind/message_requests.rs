@@ -0,0 +1,100 @@
+use chrono::{Duration, Utc};
+use nostr::Keys;
+use nostrtalk::db::DbContact;
+
+use crate::common::{
+    event_with_time, make_dm_event, make_random_contact, users_contact_list_builder,
+};
+use crate::{spawn_app, TestApp};
+
+/// Tests for chats from unknown senders, kept as message requests
+
+async fn fetch_contact(test_app: &TestApp, keys: &Keys) -> DbContact {
+    DbContact::fetch_one(test_app.pool(), test_app.cache_pool(), &keys.public_key())
+        .await
+        .unwrap()
+        .expect("Contact should be in the database")
+}
+
+#[tokio::test]
+async fn dm_from_unknown_sender_is_a_request() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sender_keys = Keys::generate();
+    let ns_event = make_dm_event(&sender_keys, test_app.keys.public_key(), "hi there");
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    let db_contact = fetch_contact(&test_app, &sender_keys).await;
+    assert!(!db_contact.is_known());
+    assert!(!db_contact.is_listed());
+}
+
+#[tokio::test]
+async fn users_dm_to_new_pubkey_is_not_a_request() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let receiver_keys = Keys::generate();
+    let ns_event = make_dm_event(&test_app.keys, receiver_keys.public_key(), "hello");
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    let db_contact = fetch_contact(&test_app, &receiver_keys).await;
+    assert!(db_contact.is_known());
+    assert!(!db_contact.is_listed());
+}
+
+#[tokio::test]
+async fn users_reply_accepts_request() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sender_keys = Keys::generate();
+    let request = make_dm_event(&sender_keys, test_app.keys.public_key(), "hi there");
+    test_app.receive(request).await;
+
+    // PERFORM
+    let reply = make_dm_event(&test_app.keys, sender_keys.public_key(), "hello");
+    test_app.receive(reply).await;
+
+    // ASSERT
+    let db_contact = fetch_contact(&test_app, &sender_keys).await;
+    assert!(db_contact.is_known());
+}
+
+#[tokio::test]
+async fn new_contact_list_keeps_requests() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sender_keys = Keys::generate();
+    let request = make_dm_event(&sender_keys, test_app.keys.public_key(), "hi there");
+    test_app.receive(request).await;
+    let now = Utc::now().naive_utc();
+    let first_list = event_with_time(
+        &test_app.keys,
+        users_contact_list_builder(vec![make_random_contact(None)]),
+        now - Duration::minutes(1),
+    );
+    test_app.receive(first_list).await;
+
+    // PERFORM
+    let contact = make_random_contact(None);
+    let second_list = event_with_time(
+        &test_app.keys,
+        users_contact_list_builder(vec![contact.clone()]),
+        now,
+    );
+    test_app.receive(second_list).await;
+
+    // ASSERT
+    let db_contacts = DbContact::fetch_basic(test_app.pool()).await.unwrap();
+    assert_eq!(db_contacts.len(), 2);
+    let listed = DbContact::fetch_listed(test_app.pool()).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].pubkey(), &contact.pk);
+    assert!(listed[0].is_known());
+    assert!(!fetch_contact(&test_app, &sender_keys).await.is_known());
+}
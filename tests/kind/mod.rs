@@ -5,13 +5,22 @@ use futures_util::StreamExt;
 use nostr::Keys;
 use nostrtalk::{net::BackendEvent, types::ChannelMetadata};
 
+mod channel_search;
 mod contact_list_helpers;
 mod dm_helpers;
+mod expiration;
+mod message_requests;
+mod pow_filter;
 mod received_channel_creation;
 mod received_channel_metadata;
 mod received_channel_msg;
 mod received_contact_list;
 mod received_dm;
+mod received_mute_list;
+mod received_notification;
+mod received_other_contact_list;
+mod received_wallet_response;
+mod received_zap_receipt;
 mod sent_channel_msg;
 mod sent_contact_list;
 mod sent_dm;
@@ -0,0 +1,131 @@
+use std::sync::atomic::AtomicBool;
+
+use nostr::{EventBuilder, Keys};
+use nostrtalk::{
+    db::{DbContact, DbFilteredMessage, UserConfig},
+    net::{mine, BackendEvent},
+};
+
+use crate::common::{make_channel_msg_event, make_dm_event};
+use crate::{spawn_app, TestApp};
+
+/// Tests for the proof of work asked of messages from non-contacts
+
+async fn set_threshold(test_app: &TestApp, threshold: u8) {
+    UserConfig::set_incoming_pow_threshold(test_app.pool(), threshold)
+        .await
+        .unwrap();
+}
+
+async fn filtered_count(test_app: &TestApp) -> i64 {
+    DbFilteredMessage::count(test_app.pool()).await.unwrap()
+}
+
+#[tokio::test]
+async fn dm_from_stranger_without_work_is_filtered() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    // a random event id has 40 leading zero bits once in 2^40
+    set_threshold(&test_app, 40).await;
+    let sender_keys = Keys::generate();
+    let ns_event = make_dm_event(&sender_keys, test_app.keys.public_key(), "buy now");
+
+    // PERFORM
+    let mut rx = test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert_eq!(filtered_count(&test_app).await, 1);
+    // no message request is created
+    let contact = DbContact::fetch_one(
+        test_app.pool(),
+        test_app.cache_pool(),
+        &sender_keys.public_key(),
+    )
+    .await
+    .unwrap();
+    assert!(contact.is_none());
+    match rx.try_next() {
+        Ok(Some(BackendEvent::MessageFiltered(message))) => {
+            assert_eq!(message.content, "buy now");
+            assert_eq!(message.channel_id, None);
+        }
+        other => panic!("Expected a filtered message, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn dm_from_stranger_with_enough_work_goes_through() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    set_threshold(&test_app, 8).await;
+    let sender_keys = Keys::generate();
+    let unsigned =
+        EventBuilder::new_encrypted_direct_msg(&sender_keys, test_app.keys.public_key(), "hello")
+            .unwrap()
+            .to_unsigned_event(sender_keys.public_key());
+    let mined = mine(unsigned, 8, &AtomicBool::new(false), |_| ()).unwrap();
+    let ns_event = mined.sign(&sender_keys).unwrap();
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert_eq!(filtered_count(&test_app).await, 0);
+    let contact = DbContact::fetch_one(
+        test_app.pool(),
+        test_app.cache_pool(),
+        &sender_keys.public_key(),
+    )
+    .await
+    .unwrap();
+    assert!(contact.is_some());
+}
+
+#[tokio::test]
+async fn dm_from_contact_is_not_filtered() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    set_threshold(&test_app, 40).await;
+    let contact_keys = Keys::generate();
+    DbContact::insert(test_app.pool(), &contact_keys.public_key())
+        .await
+        .unwrap();
+    let ns_event = make_dm_event(&contact_keys, test_app.keys.public_key(), "hi");
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert_eq!(filtered_count(&test_app).await, 0);
+}
+
+#[tokio::test]
+async fn channel_msg_from_stranger_without_work_is_filtered() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    set_threshold(&test_app, 40).await;
+    let cache = test_app.insert_random_channel_cache().await;
+    let ns_event = make_channel_msg_event(&Keys::generate(), &cache.channel_id, None, "spam");
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    let filtered = DbFilteredMessage::fetch(test_app.pool()).await.unwrap();
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].channel_id, Some(cache.channel_id));
+    assert_eq!(filtered[0].content, "spam");
+}
+
+#[tokio::test]
+async fn disabled_threshold_filters_nothing() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let ns_event = make_dm_event(&Keys::generate(), test_app.keys.public_key(), "hello");
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert_eq!(filtered_count(&test_app).await, 0);
+}
@@ -0,0 +1,192 @@
+use chrono::{Duration, Utc};
+use nostr::nips::nip04;
+use nostr::{EventBuilder, Keys, Kind, Tag, TagKind};
+use nostrtalk::{
+    db::{DbEvent, DbMutedPubkey},
+    net::{handle_event, MuteList, MUTE_LIST_KIND},
+};
+use url::Url;
+
+use crate::common::{event_with_time, make_channel_msg_event, make_dm_event};
+use crate::{spawn_app, TestApp};
+
+/// Tests for the user's mute list and the events of muted pubkeys
+
+async fn mute(test_app: &TestApp, keys: &Keys) {
+    let muted = DbMutedPubkey::new(&keys.public_key(), false, Utc::now().naive_utc());
+    DbMutedPubkey::insert(test_app.pool(), &muted)
+        .await
+        .unwrap();
+}
+
+/// Mute list with a public and a private pubkey
+fn mute_list_event(
+    test_app: &TestApp,
+    public: &Keys,
+    private: &Keys,
+    time: chrono::NaiveDateTime,
+) -> nostr::Event {
+    let keys = &test_app.keys;
+    let private_json = serde_json::to_string(&vec![vec![
+        "p".to_owned(),
+        private.public_key().to_string(),
+    ]])
+    .unwrap();
+    let content = nip04::encrypt(
+        &keys.secret_key().unwrap(),
+        &keys.public_key(),
+        private_json,
+    )
+    .unwrap();
+    let tags = [Tag::PubKey(public.public_key(), None)];
+    let builder = EventBuilder::new(Kind::from(MUTE_LIST_KIND), content, &tags);
+    event_with_time(keys, builder, time)
+}
+
+#[tokio::test]
+async fn dm_from_muted_is_dropped() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let muted_keys = Keys::generate();
+    mute(&test_app, &muted_keys).await;
+    let ns_event = make_dm_event(&muted_keys, test_app.keys.public_key(), "hello");
+    let event_hash = ns_event.id;
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert!(!DbEvent::has_event(test_app.pool(), &event_hash)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn channel_msg_from_muted_is_dropped() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let muted_keys = Keys::generate();
+    mute(&test_app, &muted_keys).await;
+    let cache = test_app.insert_random_channel_cache().await;
+    let ns_event = make_channel_msg_event(&muted_keys, &cache.channel_id, None, "spam");
+    let event_hash = ns_event.id;
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert!(!DbEvent::has_event(test_app.pool(), &event_hash)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn users_mute_list_replaces_local_entries() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let old_keys = Keys::generate();
+    mute(&test_app, &old_keys).await;
+    let (public, private) = (Keys::generate(), Keys::generate());
+    let ns_event = mute_list_event(&test_app, &public, &private, Utc::now().naive_utc());
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    let muted = DbMutedPubkey::fetch(test_app.pool()).await.unwrap();
+    assert_eq!(muted.len(), 2);
+    let find = |keys: &Keys| muted.iter().find(|m| m.pubkey == keys.public_key());
+    assert!(!find(&public).unwrap().private);
+    assert!(find(&private).unwrap().private);
+    assert!(find(&old_keys).is_none());
+}
+
+#[tokio::test]
+async fn older_mute_list_is_ignored() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let now = Utc::now().naive_utc();
+    let (public, private) = (Keys::generate(), Keys::generate());
+    let newer = mute_list_event(&test_app, &public, &private, now);
+    let older = mute_list_event(
+        &test_app,
+        &Keys::generate(),
+        &Keys::generate(),
+        now - Duration::hours(1),
+    );
+
+    // PERFORM
+    test_app.receive(newer).await;
+    test_app.receive(older).await;
+
+    // ASSERT
+    let muted = DbMutedPubkey::fetch(test_app.pool()).await.unwrap();
+    assert_eq!(muted.len(), 2);
+    assert!(muted.iter().any(|m| m.pubkey == public.public_key()));
+}
+
+/// A newer list that can't be decrypted must not wipe the current one
+#[tokio::test]
+async fn unreadable_mute_list_keeps_current() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let now = Utc::now().naive_utc();
+    let (public, private) = (Keys::generate(), Keys::generate());
+    let current = mute_list_event(&test_app, &public, &private, now - Duration::hours(1));
+    let current_hash = current.id;
+    test_app.receive(current).await;
+    let builder = EventBuilder::new(Kind::from(MUTE_LIST_KIND), "not encrypted", &[]);
+    let unreadable = event_with_time(&test_app.keys, builder, now);
+    let (mut output, _rx) = futures::channel::mpsc::channel(5);
+
+    // PERFORM
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        Url::parse("ws://192.168.15.15:8080").unwrap(),
+        nostr::SubscriptionId::new("testing"),
+        unreadable,
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_err());
+    let muted = DbMutedPubkey::fetch(test_app.pool()).await.unwrap();
+    assert_eq!(muted.len(), 2);
+    assert!(DbEvent::has_event(test_app.pool(), &current_hash)
+        .await
+        .unwrap());
+}
+
+#[test]
+fn mute_list_keeps_other_entries() {
+    // PREPARE
+    let keys = Keys::generate();
+    let hashtag = Tag::Generic(TagKind::Custom("t".to_owned()), vec!["spam".to_owned()]);
+    let builder = EventBuilder::new(Kind::from(MUTE_LIST_KIND), "", &[hashtag.clone()]);
+    let ns_event = builder.to_event(&keys).unwrap();
+    let blocked = Keys::generate().public_key();
+
+    // PERFORM
+    let mute_list = MuteList::from_event(&keys, &ns_event)
+        .unwrap()
+        .with_muted(vec![DbMutedPubkey::new(
+            &blocked,
+            true,
+            Utc::now().naive_utc(),
+        )]);
+    let published = mute_list
+        .to_builder(&keys)
+        .unwrap()
+        .to_event(&keys)
+        .unwrap();
+
+    // ASSERT
+    assert!(published.tags.contains(&hashtag));
+    assert!(!published.content.is_empty());
+    let read_back = MuteList::from_event(&keys, &published).unwrap();
+    assert_eq!(read_back.muted.len(), 1);
+    assert_eq!(read_back.muted[0].pubkey, blocked);
+    assert!(read_back.muted[0].private);
+}
@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Local, Utc};
+use nostr::prelude::ToBech32;
+use nostr::Keys;
+use nostrtalk::{
+    db::{ChatNotification, DbContact, DoNotDisturb, Mute, NotificationChat},
+    net::MemorySink,
+};
+
+use crate::common::{make_channel_msg_event, make_dm_event};
+use crate::{spawn_app, TestApp};
+
+/// Tests for the notifications of received messages
+
+/// Notifications go to memory, as if the window was unfocused
+fn memory_sink(test_app: &mut TestApp) -> Arc<MemorySink> {
+    let sink = Arc::new(MemorySink::default());
+    test_app.backend.notifications.set_sink(sink.clone());
+    test_app.backend.notifications.set_window_focused(false);
+    sink
+}
+
+/// Contact added by the user, whose messages aren't requests
+async fn known_contact(test_app: &TestApp) -> Keys {
+    let contact_keys = Keys::generate();
+    DbContact::insert(test_app.pool(), &contact_keys.public_key())
+        .await
+        .unwrap();
+    contact_keys
+}
+
+#[tokio::test]
+async fn dm_notified_while_unfocused() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sink = memory_sink(&mut test_app);
+    let contact_keys = known_contact(&test_app).await;
+    let ns_event = make_dm_event(&contact_keys, test_app.keys.public_key(), "hello there");
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    let notifications = sink.notifications();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(
+        notifications[0].chat,
+        NotificationChat::Dm(contact_keys.public_key())
+    );
+    assert_eq!(notifications[0].body, "hello there");
+}
+
+#[tokio::test]
+async fn dm_not_notified_while_focused() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sink = memory_sink(&mut test_app);
+    test_app.backend.notifications.set_window_focused(true);
+    let contact_keys = known_contact(&test_app).await;
+    let ns_event = make_dm_event(&contact_keys, test_app.keys.public_key(), "hello");
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert!(sink.notifications().is_empty());
+}
+
+#[tokio::test]
+async fn request_dm_not_notified() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sink = memory_sink(&mut test_app);
+    let ns_event = make_dm_event(&Keys::generate(), test_app.keys.public_key(), "hello");
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert!(sink.notifications().is_empty());
+}
+
+#[tokio::test]
+async fn users_dm_not_notified() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sink = memory_sink(&mut test_app);
+    let ns_event = make_dm_event(&test_app.keys, Keys::generate().public_key(), "hello");
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert!(sink.notifications().is_empty());
+}
+
+#[tokio::test]
+async fn muted_dm_not_notified() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sink = memory_sink(&mut test_app);
+    let contact_keys = known_contact(&test_app).await;
+    let mut rules = ChatNotification::new(NotificationChat::Dm(contact_keys.public_key()));
+    rules.mute = Mute::Until(Utc::now().naive_utc() + Duration::hours(1));
+    ChatNotification::save(test_app.pool(), &rules)
+        .await
+        .unwrap();
+    let ns_event = make_dm_event(&contact_keys, test_app.keys.public_key(), "hello");
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert!(sink.notifications().is_empty());
+}
+
+#[tokio::test]
+async fn dm_not_notified_during_do_not_disturb() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sink = memory_sink(&mut test_app);
+    let now = Local::now().time();
+    let dnd = DoNotDisturb {
+        enabled: true,
+        start: now - Duration::hours(1),
+        end: now + Duration::hours(1),
+    };
+    DoNotDisturb::save(test_app.pool(), &dnd).await.unwrap();
+    let contact_keys = known_contact(&test_app).await;
+    let ns_event = make_dm_event(&contact_keys, test_app.keys.public_key(), "hello");
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert!(sink.notifications().is_empty());
+}
+
+#[tokio::test]
+async fn channel_msg_notified_with_channel_name() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sink = memory_sink(&mut test_app);
+    let cache = test_app.insert_random_channel_cache().await;
+    let ns_event = make_channel_msg_event(&Keys::generate(), &cache.channel_id, None, "hey all");
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    let notifications = sink.notifications();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].title, "test_channel");
+    assert_eq!(notifications[0].body, "hey all");
+}
+
+#[tokio::test]
+async fn mentions_only_channel_notifies_mentions() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let sink = memory_sink(&mut test_app);
+    let cache = test_app.insert_random_channel_cache().await;
+    let mut rules = ChatNotification::new(NotificationChat::Channel(cache.channel_id));
+    rules.mentions_only = true;
+    ChatNotification::save(test_app.pool(), &rules)
+        .await
+        .unwrap();
+    let npub = test_app.keys.public_key().to_bech32().unwrap();
+    let other_msg = make_channel_msg_event(&Keys::generate(), &cache.channel_id, None, "hey all");
+    let mention_msg = make_channel_msg_event(
+        &Keys::generate(),
+        &cache.channel_id,
+        None,
+        &format!("hey nostr:{}", npub),
+    );
+
+    // PERFORM
+    test_app.receive(other_msg).await;
+    test_app.receive(mention_msg).await;
+
+    // ASSERT
+    let notifications = sink.notifications();
+    assert_eq!(notifications.len(), 1);
+    assert!(notifications[0].body.contains(&npub));
+}
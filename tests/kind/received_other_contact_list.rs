@@ -0,0 +1,148 @@
+use chrono::{Duration, Utc};
+use nostr::{Contact, Keys};
+use nostrtalk::{
+    db::{DbContact, DbFollower},
+    net::BackendEvent,
+};
+
+use super::*;
+use crate::common::{event_with_time, make_random_contact, users_contact_list_builder};
+use crate::{spawn_app, TestApp};
+
+/// Tests for contact lists of other users, that build the followers list
+
+fn others_list(
+    test_app: &TestApp,
+    author: &Keys,
+    follows: bool,
+    time: chrono::NaiveDateTime,
+) -> nostr::Event {
+    let mut contacts = vec![make_random_contact(None)];
+    if follows {
+        contacts.push(Contact::new(test_app.keys.public_key(), None, None));
+    }
+    event_with_time(author, users_contact_list_builder(contacts), time)
+}
+
+#[tokio::test]
+async fn list_with_user_adds_follower() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let follower_keys = Keys::generate();
+    let ns_event = others_list(&test_app, &follower_keys, true, Utc::now().naive_utc());
+
+    // PERFORM
+    let mut rx = test_app.receive(ns_event).await;
+
+    // ASSERT
+    let followers = DbFollower::fetch(test_app.pool()).await.unwrap();
+    assert_eq!(followers.len(), 1);
+    assert_eq!(followers[0].pubkey, follower_keys.public_key());
+    match rx.try_next() {
+        Ok(Some(BackendEvent::FollowerUpdated(follower))) => {
+            assert_eq!(follower.pubkey, follower_keys.public_key())
+        }
+        other => panic!("Expected FollowerUpdated, got: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn newer_list_without_user_unfollows() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let follower_keys = Keys::generate();
+    let now = Utc::now().naive_utc();
+    let follow = others_list(&test_app, &follower_keys, true, now - Duration::hours(1));
+    let unfollow = others_list(&test_app, &follower_keys, false, now);
+
+    // PERFORM
+    test_app.receive(follow).await;
+    let mut rx = test_app.receive(unfollow).await;
+
+    // ASSERT
+    assert!(DbFollower::fetch(test_app.pool()).await.unwrap().is_empty());
+    match rx.try_next() {
+        Ok(Some(BackendEvent::FollowerUpdated(follower))) => {
+            assert!(!follower.is_following());
+            assert_eq!(
+                follower.unfollowed_at.map(|t| t.timestamp()),
+                Some(now.timestamp())
+            );
+        }
+        other => panic!("Expected FollowerUpdated, got: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn older_list_is_ignored() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let follower_keys = Keys::generate();
+    let now = Utc::now().naive_utc();
+    let follow = others_list(&test_app, &follower_keys, true, now);
+    let old_unfollow = others_list(&test_app, &follower_keys, false, now - Duration::hours(1));
+
+    // PERFORM
+    test_app.receive(follow).await;
+    let mut rx = test_app.receive(old_unfollow).await;
+
+    // ASSERT
+    assert_eq!(DbFollower::fetch(test_app.pool()).await.unwrap().len(), 1);
+    assert_channel_timeout(&mut rx).await;
+}
+
+#[tokio::test]
+async fn refollow_resets_follow_date() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let follower_keys = Keys::generate();
+    let now = Utc::now().naive_utc();
+    let lists = [
+        others_list(&test_app, &follower_keys, true, now - Duration::hours(2)),
+        others_list(&test_app, &follower_keys, false, now - Duration::hours(1)),
+        others_list(&test_app, &follower_keys, true, now),
+    ];
+
+    // PERFORM
+    for ns_event in lists {
+        test_app.receive(ns_event).await;
+    }
+
+    // ASSERT
+    let followers = DbFollower::fetch(test_app.pool()).await.unwrap();
+    assert_eq!(followers.len(), 1);
+    assert_eq!(followers[0].followed_at.timestamp(), now.timestamp());
+}
+
+#[tokio::test]
+async fn stranger_list_without_user_is_not_stored() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let ns_event = others_list(&test_app, &Keys::generate(), false, Utc::now().naive_utc());
+
+    // PERFORM
+    let mut rx = test_app.receive(ns_event).await;
+
+    // ASSERT
+    assert!(DbFollower::fetch(test_app.pool()).await.unwrap().is_empty());
+    assert_channel_timeout(&mut rx).await;
+}
+
+#[tokio::test]
+async fn others_list_keeps_user_contacts() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let contact_pubkey = Keys::generate().public_key();
+    DbContact::insert(test_app.pool(), &contact_pubkey)
+        .await
+        .unwrap();
+    let ns_event = others_list(&test_app, &Keys::generate(), true, Utc::now().naive_utc());
+
+    // PERFORM
+    test_app.receive(ns_event).await;
+
+    // ASSERT
+    let contacts = DbContact::fetch_basic(test_app.pool()).await.unwrap();
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0].pubkey(), &contact_pubkey);
+}
@@ -0,0 +1,169 @@
+use futures_util::StreamExt;
+use nostr::nips::nip04;
+use nostr::{EventBuilder, Keys, Kind, Tag};
+use nostrtalk::{
+    db::{DbWalletPayment, PaymentStatus, WalletConnect},
+    net::{handle_event, BackendEvent, WalletConnectUri, WalletRequest, NWC_RESPONSE_KIND},
+};
+use url::Url;
+
+use super::assert_channel_timeout;
+use crate::spawn_app;
+
+/// Tests for Received event of kind 23195, wallet connect responses
+
+fn make_uri(wallet_keys: &Keys) -> WalletConnectUri {
+    WalletConnectUri {
+        wallet_pubkey: wallet_keys.public_key(),
+        relay_url: Url::parse("wss://relay.example.com").unwrap(),
+        secret: Keys::generate().secret_key().unwrap(),
+        lud16: None,
+    }
+}
+
+fn make_response(
+    wallet_keys: &Keys,
+    uri: &WalletConnectUri,
+    request_hash: nostr::EventId,
+    content: &str,
+) -> nostr::Event {
+    let client_pubkey = uri.keys().public_key();
+    let content =
+        nip04::encrypt(&wallet_keys.secret_key().unwrap(), &client_pubkey, content).unwrap();
+    EventBuilder::new(
+        Kind::from(NWC_RESPONSE_KIND),
+        content,
+        &[
+            Tag::PubKey(client_pubkey, None),
+            Tag::Event(request_hash, None, None),
+        ],
+    )
+    .to_event(wallet_keys)
+    .unwrap()
+}
+
+#[tokio::test]
+async fn wallet_paid_invoice() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, mut rx) = futures::channel::mpsc::channel(5);
+    let url = Url::parse("wss://relay.example.com").unwrap();
+    let wallet_keys = Keys::generate();
+    let uri = make_uri(&wallet_keys);
+    WalletConnect::save(test_app.pool(), &uri).await.unwrap();
+
+    let request = WalletRequest::PayInvoice("lnbc210n1pjzapstub".into())
+        .to_event(&uri)
+        .unwrap();
+    let payment = DbWalletPayment::new(request.id, "lnbc210n1pjzapstub", 21_000);
+    DbWalletPayment::insert(test_app.pool(), &payment)
+        .await
+        .unwrap();
+    let response = make_response(
+        &wallet_keys,
+        &uri,
+        request.id,
+        r#"{"result_type": "pay_invoice", "result": {"preimage": "0123456789abcdef"}}"#,
+    );
+
+    // PERFORM
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        url,
+        nostr::SubscriptionId::new("testing"),
+        response,
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    let paid = PaymentStatus::Paid {
+        preimage: "0123456789abcdef".into(),
+    };
+
+    let db_payment = DbWalletPayment::fetch_request(test_app.pool(), &request.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(db_payment.status, paid);
+    assert_eq!(db_payment.amount_msats, 21_000);
+
+    match rx.next().await {
+        Some(BackendEvent::WalletPaymentUpdated(updated)) => {
+            assert_eq!(updated.request_hash, request.id);
+            assert_eq!(updated.status, paid);
+        }
+        event => panic!("Unexpected event: {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn wallet_balance() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, mut rx) = futures::channel::mpsc::channel(5);
+    let url = Url::parse("wss://relay.example.com").unwrap();
+    let wallet_keys = Keys::generate();
+    let uri = make_uri(&wallet_keys);
+    WalletConnect::save(test_app.pool(), &uri).await.unwrap();
+
+    let request = WalletRequest::GetBalance.to_event(&uri).unwrap();
+    let response = make_response(
+        &wallet_keys,
+        &uri,
+        request.id,
+        r#"{"result_type": "get_balance", "result": {"balance": 1234000}}"#,
+    );
+
+    // PERFORM
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        url,
+        nostr::SubscriptionId::new("testing"),
+        response,
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    match rx.next().await {
+        Some(BackendEvent::GotWalletBalance(balance)) => assert_eq!(balance, 1_234_000),
+        event => panic!("Unexpected event: {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn response_without_connected_wallet() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, mut rx) = futures::channel::mpsc::channel(5);
+    let url = Url::parse("wss://relay.example.com").unwrap();
+    let wallet_keys = Keys::generate();
+    let uri = make_uri(&wallet_keys);
+    let request = WalletRequest::GetBalance.to_event(&uri).unwrap();
+    let response = make_response(
+        &wallet_keys,
+        &uri,
+        request.id,
+        r#"{"result_type": "get_balance", "result": {"balance": 1234000}}"#,
+    );
+
+    // PERFORM
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        url,
+        nostr::SubscriptionId::new("testing"),
+        response,
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    assert_channel_timeout(&mut rx).await;
+}
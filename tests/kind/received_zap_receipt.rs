@@ -0,0 +1,255 @@
+use chrono::Utc;
+use futures_util::StreamExt;
+use nostr::{secp256k1::XOnlyPublicKey, EventBuilder, Keys, Kind, Tag, TagKind};
+use nostrtalk::{
+    db::{DbZapReceipt, ZapProvider},
+    net::{handle_event, BackendEvent, ZAP_RECEIPT_KIND, ZAP_REQUEST_KIND},
+};
+use sqlx::SqlitePool;
+use url::Url;
+
+use super::assert_channel_timeout;
+use crate::common::make_channel_msg_event;
+use crate::spawn_app;
+
+/// Tests for Received event of kind 9735, zap receipts
+
+fn make_zap_request(
+    recipient: &XOnlyPublicKey,
+    zapped_event: &nostr::EventId,
+    amount_msats: u64,
+) -> nostr::Event {
+    EventBuilder::new(
+        Kind::from(ZAP_REQUEST_KIND),
+        "",
+        &[
+            Tag::PubKey(recipient.to_owned(), None),
+            Tag::Event(zapped_event.to_owned(), None, None),
+            Tag::Generic(
+                TagKind::Custom("amount".into()),
+                vec![amount_msats.to_string()],
+            ),
+        ],
+    )
+    .to_event(&Keys::generate())
+    .unwrap()
+}
+
+/// Receipt signed by `provider` for the zap request
+fn make_zap_receipt(
+    provider: &Keys,
+    recipient: &XOnlyPublicKey,
+    zapped_event: &nostr::EventId,
+    invoice: &str,
+    zap_request: &nostr::Event,
+) -> nostr::Event {
+    EventBuilder::new(
+        Kind::from(ZAP_RECEIPT_KIND),
+        "",
+        &[
+            Tag::PubKey(recipient.to_owned(), None),
+            Tag::Event(zapped_event.to_owned(), None, None),
+            Tag::Generic(TagKind::Custom("bolt11".into()), vec![invoice.into()]),
+            Tag::Generic(
+                TagKind::Custom("description".into()),
+                vec![zap_request.as_json()],
+            ),
+        ],
+    )
+    .to_event(provider)
+    .unwrap()
+}
+
+/// The recipient's LNURL server, as stored when an invoice is fetched from it
+async fn insert_provider(pool: &SqlitePool, recipient: &XOnlyPublicKey) -> Keys {
+    let provider_keys = Keys::generate();
+    let provider = ZapProvider {
+        recipient: recipient.to_owned(),
+        nostr_pubkey: provider_keys.public_key(),
+        updated_at: Utc::now().naive_utc(),
+    };
+    ZapProvider::upsert(pool, &provider).await.unwrap();
+    provider_keys
+}
+
+async fn receipt_count(pool: &SqlitePool) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM zap_receipt")
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Handles a receipt that must be ignored
+async fn assert_receipt_rejected(test_app: &mut crate::TestApp, receipt: nostr::Event) {
+    let (mut output, mut rx) = futures::channel::mpsc::channel(5);
+    let url = Url::parse("ws://192.168.15.15:8080").unwrap();
+
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        url,
+        nostr::SubscriptionId::new("testing"),
+        receipt,
+    )
+    .await;
+
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    assert_channel_timeout(&mut rx).await;
+    assert_eq!(receipt_count(test_app.pool()).await, 0);
+}
+
+#[tokio::test]
+async fn zap_receipt_for_channel_msg() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, mut rx) = futures::channel::mpsc::channel(5);
+    let url = Url::parse("ws://192.168.15.15:8080").unwrap();
+    let subscription_id = nostr::SubscriptionId::new("testing");
+
+    let cache = test_app.insert_random_channel_cache().await;
+    let msg_event = make_channel_msg_event(&test_app.keys, &cache.channel_id, Some(&url), "zap me");
+    let msg_hash = msg_event.id;
+    handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        url.clone(),
+        subscription_id.clone(),
+        msg_event,
+    )
+    .await
+    .unwrap();
+    // channel cache and message
+    rx.next().await.unwrap();
+    rx.next().await.unwrap();
+
+    let recipient = test_app.keys.public_key();
+    let provider = insert_provider(test_app.pool(), &recipient).await;
+    let zap_request = make_zap_request(&recipient, &msg_hash, 21_000);
+    let receipt = make_zap_receipt(
+        &provider,
+        &recipient,
+        &msg_hash,
+        "lnbc210n1pjzapstub",
+        &zap_request,
+    );
+
+    // PERFORM
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        url,
+        subscription_id,
+        receipt,
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+
+    let (event_id, total_msats) = DbZapReceipt::fetch_event_total(test_app.pool(), &msg_hash)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(total_msats, 21_000);
+
+    match rx.next().await {
+        Some(BackendEvent::ZapReceived {
+            event_id: rcv_event_id,
+            total_msats,
+        }) => {
+            assert_eq!(rcv_event_id, event_id);
+            assert_eq!(total_msats, 21_000);
+        }
+        event => panic!("Unexpected event: {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn zap_receipt_amount_mismatch() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let recipient = test_app.keys.public_key();
+    let provider = insert_provider(test_app.pool(), &recipient).await;
+    let zapped_event = nostr::EventId::from_hex("a".repeat(64)).unwrap();
+    // invoice for 1000 sats, zap request for 21
+    let zap_request = make_zap_request(&recipient, &zapped_event, 21_000);
+    let receipt = make_zap_receipt(
+        &provider,
+        &recipient,
+        &zapped_event,
+        "lnbc10u1pjzapstub",
+        &zap_request,
+    );
+
+    // PERFORM & ASSERT
+    assert_receipt_rejected(&mut test_app, receipt).await;
+}
+
+/// Anyone can sign a receipt for a zap request they have seen
+#[tokio::test]
+async fn forged_zap_receipt_is_rejected() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let recipient = test_app.keys.public_key();
+    let _provider = insert_provider(test_app.pool(), &recipient).await;
+    let zapped_event = nostr::EventId::from_hex("a".repeat(64)).unwrap();
+    let zap_request = make_zap_request(&recipient, &zapped_event, 21_000);
+    let receipt = make_zap_receipt(
+        &Keys::generate(),
+        &recipient,
+        &zapped_event,
+        "lnbc210n1pjzapstub",
+        &zap_request,
+    );
+
+    // PERFORM & ASSERT
+    assert_receipt_rejected(&mut test_app, receipt).await;
+}
+
+#[tokio::test]
+async fn zap_receipt_of_unknown_provider_is_ignored() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let recipient = test_app.keys.public_key();
+    let zapped_event = nostr::EventId::from_hex("a".repeat(64)).unwrap();
+    let zap_request = make_zap_request(&recipient, &zapped_event, 21_000);
+    let receipt = make_zap_receipt(
+        &Keys::generate(),
+        &recipient,
+        &zapped_event,
+        "lnbc210n1pjzapstub",
+        &zap_request,
+    );
+
+    // PERFORM & ASSERT
+    assert_receipt_rejected(&mut test_app, receipt).await;
+}
+
+/// A zap request of another user or message can't be reused in a receipt
+#[tokio::test]
+async fn zap_receipt_tags_must_match_the_request() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let recipient = test_app.keys.public_key();
+    let provider = insert_provider(test_app.pool(), &recipient).await;
+    let zapped_event = nostr::EventId::from_hex("a".repeat(64)).unwrap();
+    let other_event = nostr::EventId::from_hex("b".repeat(64)).unwrap();
+
+    let other_recipient = make_zap_request(&Keys::generate().public_key(), &zapped_event, 21_000);
+    let other_message = make_zap_request(&recipient, &other_event, 21_000);
+
+    // PERFORM & ASSERT
+    for zap_request in [other_recipient, other_message] {
+        let receipt = make_zap_receipt(
+            &provider,
+            &recipient,
+            &zapped_event,
+            "lnbc210n1pjzapstub",
+            &zap_request,
+        );
+        assert_receipt_rejected(&mut test_app, receipt).await;
+    }
+}
@@ -1,3 +1,4 @@
+use nostr::prelude::ToBech32;
 use nostrtalk::db::DbChannelMessage;
 use nostrtalk::net::{process_message, ToBackend};
 use nostrtalk::utils::channel_id_from_tags;
@@ -61,3 +62,46 @@ async fn sent_channel_msg_no_confirmation_in_db() {
         }
     }
 }
+
+/// Mentioned members are tagged so their clients can notify them
+#[tokio::test]
+async fn sent_channel_msg_tags_mentioned_pubkeys() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, _rx) = futures::channel::mpsc::channel(5);
+    let (tasks_tx, _) = tokio::sync::mpsc::channel(5);
+
+    let channel_id = nostr::EventId::from_hex(
+        "8233a5d8e27a9415d22c974d70935011664ada55ae3152bd10d697d3a3c74f67",
+    )
+    .unwrap();
+    let member = nostr::Keys::generate().public_key();
+    let npub = member.to_bech32().unwrap();
+
+    let content = format!("Hey nostr:{} and nostr:{}!", npub, npub);
+    let message = ToBackend::SendChannelMessage(channel_id.clone(), content);
+
+    // PERFORM
+    let result = process_message(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        &tasks_tx,
+        message,
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    let (_, pending) = test_app.backend.pending_events.iter().next().unwrap();
+    let tagged: Vec<_> = pending
+        .ns_event()
+        .tags
+        .iter()
+        .filter_map(|tag| match tag {
+            nostr::Tag::PubKey(pubkey, _) => Some(pubkey.to_owned()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(tagged, vec![member]);
+}
@@ -1,5 +1,7 @@
-use nostrtalk::db::{DbContact, DbMessage};
-use nostrtalk::net::{process_message, ToBackend};
+use nostrtalk::db::{DbContact, DbMessage, MessageTagInfo, UserConfig};
+use nostrtalk::net::{event_difficulty, handle_event, process_message, TaskOutput, ToBackend};
+use nostrtalk::types::{Attachment, FileEncryption};
+use url::Url;
 
 use super::*;
 use crate::common::make_random_contact;
@@ -22,7 +24,7 @@ async fn sent_dm_no_confirmation_in_db() {
     let contact = DbContact::new(&contact.pk);
 
     let content: String = "Hey amigo!".into();
-    let message = ToBackend::SendDM(contact.clone(), content.clone());
+    let message = ToBackend::SendDM(contact.clone(), content.clone(), vec![]);
 
     // PERFORM
     let result = process_message(
@@ -50,3 +52,115 @@ async fn sent_dm_no_confirmation_in_db() {
         }
     }
 }
+
+/// The message is mined in the background, then sent in place of the pending one
+#[tokio::test]
+async fn sent_dm_is_mined_to_difficulty() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, _rx) = futures::channel::mpsc::channel(5);
+    let (tasks_tx, mut tasks_rx) = tokio::sync::mpsc::channel(5);
+    UserConfig::set_pow_difficulty(test_app.pool(), 8)
+        .await
+        .unwrap();
+    let contact = DbContact::new(&make_random_contact(None).pk);
+    let message = ToBackend::SendDM(contact, "Hey amigo!".into(), vec![]);
+
+    // PERFORM
+    let result = process_message(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        &tasks_tx,
+        message,
+    )
+    .await;
+    let (pending_hash, mined) = loop {
+        match tasks_rx.recv().await {
+            Some(Ok(TaskOutput::EventMined {
+                pending_hash,
+                mined,
+            })) => break (pending_hash, mined),
+            Some(_) => continue,
+            None => panic!("Mining ended without a result"),
+        }
+    };
+    let sent_hash = test_app
+        .backend
+        .send_mined(&test_app.keys, &pending_hash, mined)
+        .unwrap();
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    let sent_hash = sent_hash.expect("mining job for the pending message");
+    assert!(event_difficulty(&sent_hash) >= 8);
+    assert!(test_app.backend.pending_events.contains_key(&sent_hash));
+}
+
+/// Attachments are sent as imeta tags inside the encrypted content,
+/// and stored with the confirmed message
+#[tokio::test]
+async fn sent_dm_with_attachment() {
+    // PREPARE
+    let mut test_app = spawn_app().await;
+    let (mut output, _rx) = futures::channel::mpsc::channel(5);
+    let (tasks_tx, _) = tokio::sync::mpsc::channel(5);
+
+    let contact = make_random_contact(None);
+    let contact = DbContact::new(&contact.pk);
+    let attachment = Attachment {
+        url: "https://media.example.com/3f2a".into(),
+        mime_type: Some("application/pdf".into()),
+        sha256: Some("3f2a".repeat(16)),
+        size: Some(2048),
+        dim: None,
+        alt: Some("report.pdf".into()),
+        encryption: Some(FileEncryption {
+            key: "11".repeat(32),
+            nonce: "22".repeat(12),
+            original_sha256: Some("5b1c".repeat(16)),
+        }),
+    };
+    let content = "The report".to_owned();
+    let message = ToBackend::SendDM(contact, content.clone(), vec![attachment.clone()]);
+
+    // PERFORM
+    let result = process_message(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        &tasks_tx,
+        message,
+    )
+    .await;
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    let pending = test_app.backend.pending_events.values().next().unwrap();
+    let ns_event = pending.ns_event().to_owned();
+
+    // the relay sends the event back
+    let result = handle_event(
+        &mut output,
+        &test_app.keys,
+        &mut test_app.backend,
+        Url::parse("ws://192.168.15.15:8080").unwrap(),
+        nostr::SubscriptionId::new("testing"),
+        ns_event.clone(),
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+    assert!(
+        Attachment::from_tags(&ns_event.tags).is_empty(),
+        "The file key can't be in the public tags"
+    );
+    assert!(!ns_event.content.contains(&attachment.url));
+    let msgs = DbMessage::fetch(test_app.pool()).await.unwrap();
+    assert_eq!(msgs.len(), 1);
+    assert_eq!(msgs[0].attachments, vec![attachment]);
+
+    let tag_info =
+        MessageTagInfo::from_event_tags(&ns_event.id, &ns_event.pubkey, &ns_event.tags).unwrap();
+    let decrypted_content = msgs[0].decrypt_message(&test_app.keys, &tag_info).unwrap();
+    assert_eq!(decrypted_content, content);
+}
@@ -1,14 +1,19 @@
 use common::make_channel_creation_event;
+use futures::channel::mpsc::Receiver;
 use nostrtalk::{
     db::{upgrade_cache_db, upgrade_db, ChannelCache, Database, DbContact},
+    net::{handle_event, BackendEvent},
     types::{BackendState, ChannelMetadata},
 };
 use once_cell::sync::Lazy;
 use sqlx::SqlitePool;
 use tempfile::NamedTempFile;
+use url::Url;
 
 mod common;
+mod db;
 mod kind;
+mod net;
 
 // Ensure that the `tracing` stack is only initialised once using `once_cell`
 static TRACING: Lazy<()> = Lazy::new(|| nostrtalk::setup_logger());
@@ -41,6 +46,22 @@ impl TestApp {
         let creation_event = make_channel_creation_event(&self.keys, &metadata);
         self.insert_channel_cache(creation_event).await
     }
+    /// Handles the event as if a relay sent it, returns what the backend sent to the UI
+    pub async fn receive(&mut self, ns_event: nostr::Event) -> Receiver<BackendEvent> {
+        let (mut output, rx) = futures::channel::mpsc::channel(10);
+        let url = Url::parse("ws://192.168.15.15:8080").unwrap();
+        let result = handle_event(
+            &mut output,
+            &self.keys,
+            &mut self.backend,
+            url,
+            nostr::SubscriptionId::new("testing"),
+            ns_event,
+        )
+        .await;
+        assert!(result.is_ok(), "Error handling event: {:?}", result.err());
+        rx
+    }
 }
 
 pub async fn spawn_app() -> TestApp {
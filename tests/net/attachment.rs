@@ -0,0 +1,292 @@
+use std::sync::{Arc, Mutex};
+
+use base64::Engine;
+use nostr::hashes::{sha256, Hash};
+use nostr::Keys;
+use nostrtalk::net::{download_attachment, upload_encrypted_file};
+use nostrtalk::types::{Attachment, FileEncryption};
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use url::Url;
+
+/// Tests for sharing files through a NIP-96 media server, against a local HTTP stub
+
+const FILE_CONTENT: &[u8] = b"meeting notes";
+
+/// Authorization events received by the stub
+type AuthEvents = Arc<Mutex<Vec<nostr::Event>>>;
+/// Last file uploaded to the stub
+type Uploaded = Arc<Mutex<Option<Vec<u8>>>>;
+
+/// Serves the NIP-96 document, accepts uploads at `/api/upload`
+/// and serves the uploaded file, or `FILE_CONTENT`, at `/files/notes.txt`
+async fn spawn_media_server() -> (Url, AuthEvents, Uploaded) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+    let auth_events = AuthEvents::default();
+    let auth_events_1 = auth_events.clone();
+    let uploaded = Uploaded::default();
+    let uploaded_1 = uploaded.clone();
+    let base_url_1 = base_url.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let (head, body) = read_request(&mut socket).await;
+            let request_line = head.lines().next().unwrap_or_default().to_owned();
+
+            let (content_type, response_body) =
+                if request_line.starts_with("GET /.well-known/nostr/nip96.json") {
+                    (
+                        "application/json",
+                        r#"{"api_url": "/api/upload"}"#.as_bytes().to_vec(),
+                    )
+                } else if request_line.starts_with("POST /api/upload") {
+                    let auth = header(&head, "authorization")
+                        .and_then(|value| value.strip_prefix("Nostr "))
+                        .unwrap_or_default();
+                    let json = base64::engine::general_purpose::STANDARD
+                        .decode(auth.trim())
+                        .unwrap();
+                    let auth_event: nostr::Event = serde_json::from_slice(&json).unwrap();
+                    auth_events_1.lock().unwrap().push(auth_event);
+
+                    let file = file_part(&head, &body);
+                    let response = if !file.is_empty() {
+                        let response = format!(
+                            r#"{{"status": "success", "message": "Upload successful.",
+                        "nip94_event": {{"tags": [
+                            ["url", "{}files/notes.txt"],
+                            ["m", "text/plain"],
+                            ["x", "{}"],
+                            ["size", "{}"]
+                        ]}}}}"#,
+                            base_url_1,
+                            sha256::Hash::hash(&file),
+                            file.len()
+                        );
+                        *uploaded_1.lock().unwrap() = Some(file);
+                        response
+                    } else {
+                        r#"{"status": "error", "message": "Missing file"}"#.to_owned()
+                    };
+                    ("application/json", response.into_bytes())
+                } else {
+                    let file = uploaded_1.lock().unwrap().to_owned();
+                    ("text/plain", file.unwrap_or_else(|| FILE_CONTENT.to_vec()))
+                };
+
+            let mut response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                content_type,
+                response_body.len()
+            )
+            .into_bytes();
+            response.extend_from_slice(&response_body);
+            socket.write_all(&response).await.unwrap();
+            socket.shutdown().await.unwrap();
+        }
+    });
+
+    (base_url, auth_events, uploaded)
+}
+
+/// Head and body of the request
+async fn read_request(socket: &mut TcpStream) -> (String, Vec<u8>) {
+    let mut request = vec![];
+    let mut buf = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let n = socket.read(&mut buf).await.unwrap();
+        if n == 0 {
+            break request.len();
+        }
+        request.extend_from_slice(&buf[..n]);
+    };
+    let head = String::from_utf8_lossy(&request[..head_end]).into_owned();
+    let content_length: usize = header(&head, "content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+
+    let mut body = request[head_end..].to_vec();
+    while body.len() < content_length {
+        let n = socket.read(&mut buf).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&buf[..n]);
+    }
+    (head, body)
+}
+
+/// Content of the `file` part of a multipart body, it is the first part
+fn file_part(head: &str, body: &[u8]) -> Vec<u8> {
+    let Some(boundary) = header(head, "content-type")
+        .and_then(|value| value.split_once("boundary="))
+        .map(|(_, boundary)| format!("\r\n--{}", boundary.trim()))
+    else {
+        return vec![];
+    };
+    let Some(start) = body.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return vec![];
+    };
+    let content = &body[start + 4..];
+    let end = content
+        .windows(boundary.len())
+        .position(|w| w == boundary.as_bytes())
+        .unwrap_or(content.len());
+    content[..end].to_vec()
+}
+
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+#[tokio::test]
+async fn upload_is_authenticated() {
+    // PREPARE
+    let (server_url, auth_events, _) = spawn_media_server().await;
+    let keys = Keys::generate();
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("notes.txt");
+    std::fs::write(&path, FILE_CONTENT).unwrap();
+
+    // PERFORM
+    let attachment = upload_encrypted_file(&reqwest::Client::new(), &keys, &server_url, &path)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert_eq!(
+        attachment.url,
+        format!("{}files/notes.txt", server_url).as_str()
+    );
+
+    let auth_events = auth_events.lock().unwrap();
+    assert_eq!(auth_events.len(), 1);
+    let auth_event = &auth_events[0];
+    assert!(auth_event.verify().is_ok());
+    assert_eq!(auth_event.kind, nostr::Kind::Custom(27235));
+    assert_eq!(auth_event.pubkey, keys.public_key());
+    let tags: Vec<Vec<String>> = auth_event.tags.iter().map(|tag| tag.as_vec()).collect();
+    assert!(tags.contains(&vec!["u".to_owned(), format!("{}api/upload", server_url)]));
+    assert!(tags.contains(&vec!["method".to_owned(), "POST".to_owned()]));
+}
+
+#[tokio::test]
+async fn download_checks_hash() {
+    // PREPARE
+    let (server_url, _, _) = spawn_media_server().await;
+    let dir = TempDir::new().unwrap();
+    let mut attachment = Attachment::new(&format!("{}files/notes.txt", server_url));
+    attachment.sha256 = Some(sha256::Hash::hash(FILE_CONTENT).to_string());
+    let path = dir.path().join("saved.txt");
+
+    // PERFORM
+    let result = download_attachment(&reqwest::Client::new(), &attachment, &path).await;
+
+    // ASSERT
+    assert!(result.is_ok(), "Error downloading: {:?}", result.err());
+    assert_eq!(std::fs::read(&path).unwrap(), FILE_CONTENT);
+}
+
+#[tokio::test]
+async fn download_rejects_wrong_hash() {
+    // PREPARE
+    let (server_url, _, _) = spawn_media_server().await;
+    let dir = TempDir::new().unwrap();
+    let mut attachment = Attachment::new(&format!("{}files/notes.txt", server_url));
+    attachment.sha256 = Some(sha256::Hash::hash(b"something else").to_string());
+    let path = dir.path().join("saved.txt");
+
+    // PERFORM
+    let result = download_attachment(&reqwest::Client::new(), &attachment, &path).await;
+
+    // ASSERT
+    assert!(result.is_err());
+    assert!(!path.exists());
+}
+
+/// A server can't send more than the size in the message
+#[tokio::test]
+async fn download_rejects_file_over_its_size() {
+    // PREPARE
+    let (server_url, _, _) = spawn_media_server().await;
+    let dir = TempDir::new().unwrap();
+    let mut attachment = Attachment::new(&format!("{}files/notes.txt", server_url));
+    attachment.size = Some(FILE_CONTENT.len() as u64 - 1);
+    let path = dir.path().join("saved.txt");
+
+    // PERFORM
+    let result = download_attachment(&reqwest::Client::new(), &attachment, &path).await;
+
+    // ASSERT
+    assert!(result.is_err());
+    assert!(!path.exists());
+}
+
+/// The server only gets the encrypted file, which is decrypted when downloaded
+#[tokio::test]
+async fn encrypted_file_round_trip() {
+    // PREPARE
+    let (server_url, _, uploaded) = spawn_media_server().await;
+    let keys = Keys::generate();
+    let client = reqwest::Client::new();
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("notes.txt");
+    std::fs::write(&path, FILE_CONTENT).unwrap();
+    let saved_path = dir.path().join("saved.txt");
+
+    // PERFORM
+    let attachment = upload_encrypted_file(&client, &keys, &server_url, &path)
+        .await
+        .unwrap();
+    let result = download_attachment(&client, &attachment, &saved_path).await;
+
+    // ASSERT
+    let uploaded = uploaded.lock().unwrap().to_owned().unwrap();
+    assert!(!uploaded
+        .windows(FILE_CONTENT.len())
+        .any(|w| w == FILE_CONTENT));
+    assert_eq!(
+        attachment.sha256,
+        Some(sha256::Hash::hash(&uploaded).to_string())
+    );
+    assert_eq!(attachment.mime_type.as_deref(), Some("text/plain"));
+    assert_eq!(attachment.alt.as_deref(), Some("notes.txt"));
+    let encryption = attachment.encryption.unwrap();
+    assert_eq!(
+        encryption.original_sha256,
+        Some(sha256::Hash::hash(FILE_CONTENT).to_string())
+    );
+
+    assert!(result.is_ok(), "Error downloading: {:?}", result.err());
+    assert_eq!(std::fs::read(&saved_path).unwrap(), FILE_CONTENT);
+}
+
+#[tokio::test]
+async fn download_rejects_wrong_key() {
+    // PREPARE
+    let (server_url, _, _) = spawn_media_server().await;
+    let dir = TempDir::new().unwrap();
+    let mut attachment = Attachment::new(&format!("{}files/notes.txt", server_url));
+    attachment.encryption = Some(FileEncryption {
+        key: "11".repeat(32),
+        nonce: "22".repeat(12),
+        original_sha256: None,
+    });
+    let path = dir.path().join("saved.txt");
+
+    // PERFORM
+    let result = download_attachment(&reqwest::Client::new(), &attachment, &path).await;
+
+    // ASSERT
+    assert!(result.is_err());
+    assert!(!path.exists());
+}
@@ -0,0 +1,292 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use image::{DynamicImage, ImageFormat, RgbImage};
+use nostr::{EventId, Keys};
+use nostrtalk::db::ImageDownloaded;
+use nostrtalk::net::{download_image, media_event_hash, ImageKind, MAX_IMAGE_BYTES};
+use sqlx::SqlitePool;
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::common::make_metadata_event;
+use crate::spawn_app;
+
+/// Tests for the content-addressed image cache, against a local HTTP stub
+
+#[derive(Default)]
+struct StubCounters {
+    requests: AtomicUsize,
+    not_modified: AtomicUsize,
+}
+
+/// Serves the same PNG on every path, with `ETag: "v1"`
+async fn spawn_image_server(cache_control: &'static str) -> (String, Arc<StubCounters>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let counters = Arc::new(StubCounters::default());
+    let counters_1 = counters.clone();
+    let body = png_bytes();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buf = [0u8; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            counters_1.requests.fetch_add(1, Ordering::SeqCst);
+            let request = String::from_utf8_lossy(&request).to_lowercase();
+
+            let response = if request.contains("if-none-match: \"v1\"") {
+                counters_1.not_modified.fetch_add(1, Ordering::SeqCst);
+                format!(
+                    "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nCache-Control: {}\r\nConnection: close\r\n\r\n",
+                    cache_control
+                )
+                .into_bytes()
+            } else {
+                let mut response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nETag: \"v1\"\r\nCache-Control: {}\r\nConnection: close\r\n\r\n",
+                    body.len(),
+                    cache_control
+                )
+                .into_bytes();
+                response.extend_from_slice(&body);
+                response
+            };
+            socket.write_all(&response).await.unwrap();
+            socket.shutdown().await.unwrap();
+        }
+    });
+
+    (base_url, counters)
+}
+
+/// Serves an image over `MAX_IMAGE_BYTES`, with its size in `Content-Length`
+/// or streamed in chunks without announcing it
+async fn spawn_oversized_image_server(declare_length: bool) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+
+            let size = MAX_IMAGE_BYTES + 1;
+            if declare_length {
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    size
+                );
+                let _ = socket.write_all(head.as_bytes()).await;
+            } else {
+                let head = "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n";
+                if socket.write_all(head.as_bytes()).await.is_err() {
+                    continue;
+                }
+                let chunk = vec![0u8; 1024 * 1024];
+                let mut sent = 0;
+                // the client hangs up once over the limit
+                while sent < size {
+                    let mut frame = format!("{:x}\r\n", chunk.len()).into_bytes();
+                    frame.extend_from_slice(&chunk);
+                    frame.extend_from_slice(b"\r\n");
+                    if socket.write_all(&frame).await.is_err() {
+                        break;
+                    }
+                    sent += chunk.len() as u64;
+                }
+                let _ = socket.write_all(b"0\r\n\r\n").await;
+            }
+            let _ = socket.shutdown().await;
+        }
+    });
+
+    base_url
+}
+
+fn png_bytes() -> Vec<u8> {
+    let image = DynamicImage::ImageRgb8(RgbImage::new(4, 4));
+    let mut bytes = vec![];
+    image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+    bytes
+}
+
+fn random_event_hash() -> EventId {
+    make_metadata_event(&Keys::generate(), "random").id
+}
+
+async fn download(cache_pool: &SqlitePool, blobs_dir: &TempDir, url: &str) -> ImageDownloaded {
+    download_image(
+        &reqwest::Client::new(),
+        cache_pool,
+        blobs_dir.path(),
+        url,
+        &random_event_hash(),
+        ImageKind::Profile,
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn fresh_image_skips_network() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let blobs_dir = TempDir::new().unwrap();
+    let (base_url, counters) = spawn_image_server("max-age=3600").await;
+    let url = format!("{}/avatar.png", base_url);
+
+    // PERFORM
+    let first = download(test_app.cache_pool(), &blobs_dir, &url).await;
+    let second = download(test_app.cache_pool(), &blobs_dir, &url).await;
+
+    // ASSERT
+    assert_eq!(counters.requests.load(Ordering::SeqCst), 1);
+    assert_eq!(first.path, second.path);
+    assert!(first.path.exists());
+}
+
+#[tokio::test]
+async fn stale_image_is_revalidated() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let blobs_dir = TempDir::new().unwrap();
+    let (base_url, counters) = spawn_image_server("max-age=0").await;
+    let url = format!("{}/avatar.png", base_url);
+
+    // PERFORM
+    let first = download(test_app.cache_pool(), &blobs_dir, &url).await;
+    let second = download(test_app.cache_pool(), &blobs_dir, &url).await;
+
+    // ASSERT
+    assert_eq!(counters.requests.load(Ordering::SeqCst), 2);
+    assert_eq!(counters.not_modified.load(Ordering::SeqCst), 1);
+    assert_eq!(first.path, second.path);
+}
+
+#[tokio::test]
+async fn same_content_is_stored_once() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let blobs_dir = TempDir::new().unwrap();
+    let (base_url, _) = spawn_image_server("max-age=3600").await;
+
+    // PERFORM
+    let first = download(
+        test_app.cache_pool(),
+        &blobs_dir,
+        &format!("{}/a.png", base_url),
+    )
+    .await;
+    let second = download(
+        test_app.cache_pool(),
+        &blobs_dir,
+        &format!("{}/b.png", base_url),
+    )
+    .await;
+
+    // ASSERT
+    assert_eq!(first.path, second.path);
+    assert_eq!(first.content_hash, second.content_hash);
+    let blobs: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM image_blob")
+        .fetch_one(test_app.cache_pool())
+        .await
+        .unwrap();
+    assert_eq!(blobs, 1);
+}
+
+#[tokio::test]
+async fn base64_image_is_stored() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let blobs_dir = TempDir::new().unwrap();
+    let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png_bytes());
+    let url = format!("data:image/png;base64,{}", encoded);
+
+    // PERFORM
+    let image = download(test_app.cache_pool(), &blobs_dir, &url).await;
+
+    // ASSERT
+    assert!(image.path.exists());
+    assert!(image.sized_image(nostrtalk::net::ImageSize::Small).exists());
+}
+
+#[tokio::test]
+async fn media_is_cached_by_url() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let blobs_dir = TempDir::new().unwrap();
+    let (base_url, _) = spawn_image_server("max-age=3600").await;
+    let url = format!("{}/photo.png", base_url);
+    let event_hash = media_event_hash(&url);
+
+    // PERFORM
+    let image = download_image(
+        &reqwest::Client::new(),
+        test_app.cache_pool(),
+        blobs_dir.path(),
+        &url,
+        &event_hash,
+        ImageKind::Media,
+    )
+    .await
+    .unwrap();
+    ImageDownloaded::insert(test_app.cache_pool(), &image)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert_eq!(media_event_hash(&url), event_hash);
+    assert_ne!(
+        media_event_hash(&format!("{}/other.png", base_url)),
+        event_hash
+    );
+    let cached = ImageDownloaded::fetch(test_app.cache_pool(), &event_hash, ImageKind::Media)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(cached.path, image.path);
+    assert!(cached
+        .sized_image(nostrtalk::net::ImageSize::Medium)
+        .exists());
+}
+
+#[tokio::test]
+async fn oversized_image_is_rejected() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let blobs_dir = TempDir::new().unwrap();
+
+    for declare_length in [true, false] {
+        let base_url = spawn_oversized_image_server(declare_length).await;
+
+        // PERFORM
+        let result = download_image(
+            &reqwest::Client::new(),
+            test_app.cache_pool(),
+            blobs_dir.path(),
+            &format!("{}/huge.png", base_url),
+            &random_event_hash(),
+            ImageKind::Profile,
+        )
+        .await;
+
+        // ASSERT
+        assert!(result.is_err(), "declare_length: {}", declare_length);
+    }
+    let blobs: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM image_blob")
+        .fetch_one(test_app.cache_pool())
+        .await
+        .unwrap();
+    assert_eq!(blobs, 0);
+}
@@ -0,0 +1,155 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use nostrtalk::net::{fetch_link_preview, RateLimiter, MAX_PAGE_BYTES};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::time::Instant;
+
+use crate::spawn_app;
+
+/// Tests for the link preview fetcher, against a local HTTP stub
+
+/// Serves `body` with `content_type` on every path, counting the requests
+async fn spawn_page_server(content_type: &'static str, body: String) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let requests_1 = requests.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buf = [0u8; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            requests_1.fetch_add(1, Ordering::SeqCst);
+
+            let mut response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                content_type,
+                body.len()
+            )
+            .into_bytes();
+            response.extend_from_slice(body.as_bytes());
+            // the client may stop reading after the size cap
+            let _ = socket.write_all(&response).await;
+            let _ = socket.shutdown().await;
+        }
+    });
+
+    (base_url, requests)
+}
+
+fn og_page(title: &str) -> String {
+    format!(
+        r#"<html><head>
+        <meta property="og:title" content="{}">
+        <meta property="og:description" content="Some description">
+        <meta property="og:image" content="/cover.png">
+        </head><body></body></html>"#,
+        title
+    )
+}
+
+#[tokio::test]
+async fn preview_is_parsed_and_cached() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let limiter = RateLimiter::new(Duration::ZERO);
+    let (base_url, requests) =
+        spawn_page_server("text/html; charset=utf-8", og_page("Hello")).await;
+    let url = format!("{}/post", base_url);
+    let client = reqwest::Client::new();
+
+    // PERFORM
+    let first = fetch_link_preview(&client, test_app.cache_pool(), &limiter, &url)
+        .await
+        .unwrap();
+    let second = fetch_link_preview(&client, test_app.cache_pool(), &limiter, &url)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    assert_eq!(first.title.as_deref(), Some("Hello"));
+    assert_eq!(first.description.as_deref(), Some("Some description"));
+    assert_eq!(
+        first.image_url.as_deref(),
+        Some(format!("{}/cover.png", base_url).as_str())
+    );
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+async fn non_html_is_cached_empty() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let limiter = RateLimiter::new(Duration::ZERO);
+    let (base_url, requests) = spawn_page_server("application/pdf", og_page("Hidden")).await;
+    let url = format!("{}/file.pdf", base_url);
+    let client = reqwest::Client::new();
+
+    // PERFORM
+    let first = fetch_link_preview(&client, test_app.cache_pool(), &limiter, &url)
+        .await
+        .unwrap();
+    let _ = fetch_link_preview(&client, test_app.cache_pool(), &limiter, &url)
+        .await
+        .unwrap();
+
+    // ASSERT
+    assert!(first.is_empty());
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn page_is_read_up_to_max_size() {
+    // PREPARE
+    let test_app = spawn_app().await;
+    let limiter = RateLimiter::new(Duration::ZERO);
+    let body = format!(
+        "<html><head><title>Big page</title>{}{}",
+        " ".repeat(MAX_PAGE_BYTES),
+        og_page("Too far")
+    );
+    let (base_url, _) = spawn_page_server("text/html", body).await;
+    let url = format!("{}/big", base_url);
+
+    // PERFORM
+    let preview = fetch_link_preview(
+        &reqwest::Client::new(),
+        test_app.cache_pool(),
+        &limiter,
+        &url,
+    )
+    .await
+    .unwrap();
+
+    // ASSERT
+    assert_eq!(preview.title.as_deref(), Some("Big page"));
+    assert_eq!(preview.description, None);
+}
+
+#[tokio::test]
+async fn rate_limiter_spaces_requests() {
+    // PREPARE
+    let interval = Duration::from_millis(100);
+    let limiter = RateLimiter::new(interval);
+    let start = Instant::now();
+
+    // PERFORM
+    for _ in 0..3 {
+        limiter.wait().await;
+    }
+
+    // ASSERT
+    assert!(start.elapsed() >= interval * 2);
+}
@@ -0,0 +1,6 @@
+mod attachment;
+mod image_download;
+mod link_preview;
+mod pow;
+mod wallet_connect;
+mod zap;
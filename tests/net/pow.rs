@@ -0,0 +1,79 @@
+use std::sync::atomic::AtomicBool;
+
+use nostr::{EventBuilder, EventId, Keys, Kind, Tag};
+use nostrtalk::net::{event_difficulty, leading_zero_bits, mine, raised_difficulty};
+
+/// Tests for mining NIP-13 proof of work
+
+fn unsigned_note(keys: &Keys) -> nostr::UnsignedEvent {
+    EventBuilder::new(Kind::TextNote, "hello", &[]).to_unsigned_event(keys.public_key())
+}
+
+#[test]
+fn counts_leading_zero_bits() {
+    assert_eq!(leading_zero_bits(&[0xff, 0x00]), 0);
+    assert_eq!(leading_zero_bits(&[0x00, 0x0f]), 12);
+    assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+}
+
+#[test]
+fn all_zero_id_doesnt_overflow() {
+    // PREPARE
+    let event_hash = EventId::from_hex("0".repeat(64)).unwrap();
+
+    // PERFORM
+    let bits = leading_zero_bits(&[0u8; 32]);
+    let difficulty = event_difficulty(&event_hash);
+
+    // ASSERT
+    assert_eq!(bits, 256);
+    assert_eq!(difficulty, u8::MAX);
+}
+
+#[test]
+fn mined_event_reaches_target() {
+    // PREPARE
+    let keys = Keys::generate();
+    let unsigned = unsigned_note(&keys);
+    let cancel = AtomicBool::new(false);
+
+    // PERFORM
+    let mined = mine(unsigned, 8, &cancel, |_| ()).unwrap();
+
+    // ASSERT
+    assert!(event_difficulty(&mined.id) >= 8);
+    assert!(mined
+        .tags
+        .iter()
+        .any(|tag| matches!(tag, Tag::POW { difficulty: 8, .. })));
+    // the id still matches the signed event
+    let ns_event = mined.sign(&keys).unwrap();
+    assert!(ns_event.verify().is_ok());
+}
+
+#[test]
+fn cancelled_mining_returns_none() {
+    // PREPARE
+    let keys = Keys::generate();
+    let unsigned = unsigned_note(&keys);
+    let cancel = AtomicBool::new(true);
+
+    // PERFORM
+    // far above what could be found before the first cancel check
+    let mined = mine(unsigned, 64, &cancel, |_| ());
+
+    // ASSERT
+    assert!(mined.is_none());
+}
+
+#[test]
+fn raises_difficulty_on_pow_rejections() {
+    assert_eq!(
+        raised_difficulty("pow: difficulty 12 is less than 20", 0),
+        Some(20)
+    );
+    // no number asked, a step above the current one
+    assert_eq!(raised_difficulty("pow: not enough work", 16), Some(20));
+    assert_eq!(raised_difficulty("pow: difficulty 99", 0), Some(40));
+    assert_eq!(raised_difficulty("blocked: spam", 0), None);
+}
@@ -0,0 +1,178 @@
+use nostr::nips::nip04;
+use nostr::{EventBuilder, Keys, Kind, Tag};
+use nostrtalk::net::{
+    WalletConnectUri, WalletRequest, WalletResponse, WalletResult, NWC_REQUEST_KIND,
+    NWC_RESPONSE_KIND,
+};
+
+/// Tests for NIP-47 wallet connect URIs, requests and responses
+
+fn make_uri(wallet_keys: &Keys, client_keys: &Keys) -> String {
+    format!(
+        "nostr+walletconnect://{}?relay=wss%3A%2F%2Frelay.example.com&secret={}&lud16=alice%40example.com",
+        wallet_keys.public_key(),
+        client_keys.secret_key().unwrap().display_secret()
+    )
+}
+
+fn make_response(
+    wallet_keys: &Keys,
+    uri: &WalletConnectUri,
+    request: &nostr::Event,
+    content: &str,
+) -> nostr::Event {
+    let client_pubkey = uri.keys().public_key();
+    let content =
+        nip04::encrypt(&wallet_keys.secret_key().unwrap(), &client_pubkey, content).unwrap();
+    EventBuilder::new(
+        Kind::from(NWC_RESPONSE_KIND),
+        content,
+        &[
+            Tag::PubKey(client_pubkey, None),
+            Tag::Event(request.id, None, None),
+        ],
+    )
+    .to_event(wallet_keys)
+    .unwrap()
+}
+
+#[test]
+fn parse_wallet_connect_uri() {
+    // PREPARE
+    let wallet_keys = Keys::generate();
+    let client_keys = Keys::generate();
+
+    // PERFORM
+    let uri: WalletConnectUri = make_uri(&wallet_keys, &client_keys).parse().unwrap();
+
+    // ASSERT
+    assert_eq!(uri.wallet_pubkey, wallet_keys.public_key());
+    assert_eq!(uri.relay_url.as_str(), "wss://relay.example.com/");
+    assert_eq!(uri.keys().public_key(), client_keys.public_key());
+    assert_eq!(uri.lud16.as_deref(), Some("alice@example.com"));
+    // stored as a string
+    assert_eq!(uri.to_string().parse::<WalletConnectUri>().unwrap(), uri);
+    assert!(!format!("{:?}", uri).contains(
+        &client_keys
+            .secret_key()
+            .unwrap()
+            .display_secret()
+            .to_string()
+    ));
+}
+
+#[test]
+fn parse_wallet_connect_uri_without_slashes() {
+    let wallet_keys = Keys::generate();
+    let uri = make_uri(&wallet_keys, &Keys::generate()).replace("://", ":");
+
+    let uri: WalletConnectUri = uri.parse().unwrap();
+
+    assert_eq!(uri.wallet_pubkey, wallet_keys.public_key());
+}
+
+#[test]
+fn invalid_wallet_connect_uris() {
+    let wallet_keys = Keys::generate();
+    let uri = make_uri(&wallet_keys, &Keys::generate());
+
+    assert!("".parse::<WalletConnectUri>().is_err());
+    assert!(uri
+        .replace("nostr+walletconnect", "https")
+        .parse::<WalletConnectUri>()
+        .is_err());
+    assert!(uri
+        .replace(&wallet_keys.public_key().to_string(), "npub")
+        .parse::<WalletConnectUri>()
+        .is_err());
+    let without_secret = uri.split("&secret").next().unwrap();
+    assert!(without_secret.parse::<WalletConnectUri>().is_err());
+}
+
+#[test]
+fn pay_invoice_request_and_response() {
+    // PREPARE
+    let wallet_keys = Keys::generate();
+    let uri: WalletConnectUri = make_uri(&wallet_keys, &Keys::generate()).parse().unwrap();
+
+    // PERFORM
+    let request = WalletRequest::PayInvoice("lnbc210n1pjzapstub".into())
+        .to_event(&uri)
+        .unwrap();
+
+    // ASSERT
+    assert!(request.verify().is_ok());
+    assert_eq!(request.kind, Kind::from(NWC_REQUEST_KIND));
+    assert_eq!(request.pubkey, uri.keys().public_key());
+    assert!(request
+        .tags
+        .iter()
+        .any(|tag| tag.as_vec() == vec!["p".to_owned(), wallet_keys.public_key().to_string()]));
+    let content = nip04::decrypt(
+        &wallet_keys.secret_key().unwrap(),
+        &request.pubkey,
+        &request.content,
+    )
+    .unwrap();
+    let content: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(content["method"], "pay_invoice");
+    assert_eq!(content["params"]["invoice"], "lnbc210n1pjzapstub");
+
+    let response = make_response(
+        &wallet_keys,
+        &uri,
+        &request,
+        r#"{"result_type": "pay_invoice", "result": {"preimage": "0123456789abcdef"}}"#,
+    );
+    let response = WalletResponse::from_event(&uri, &response).unwrap();
+    assert_eq!(response.request_hash, request.id);
+    assert_eq!(
+        response.result,
+        Ok(WalletResult::PayInvoice {
+            preimage: "0123456789abcdef".into()
+        })
+    );
+}
+
+#[test]
+fn wallet_error_response() {
+    // PREPARE
+    let wallet_keys = Keys::generate();
+    let uri: WalletConnectUri = make_uri(&wallet_keys, &Keys::generate()).parse().unwrap();
+    let request = WalletRequest::GetBalance.to_event(&uri).unwrap();
+    let response = make_response(
+        &wallet_keys,
+        &uri,
+        &request,
+        r#"{"result_type": "get_balance", "error": {"code": "UNAUTHORIZED", "message": "No permission"}}"#,
+    );
+
+    // PERFORM
+    let response = WalletResponse::from_event(&uri, &response).unwrap();
+
+    // ASSERT
+    assert_eq!(
+        response.result,
+        Err("UNAUTHORIZED: No permission".to_owned())
+    );
+}
+
+#[test]
+fn response_from_another_wallet() {
+    // PREPARE
+    let wallet_keys = Keys::generate();
+    let uri: WalletConnectUri = make_uri(&wallet_keys, &Keys::generate()).parse().unwrap();
+    let request = WalletRequest::GetBalance.to_event(&uri).unwrap();
+    let response = make_response(
+        &Keys::generate(),
+        &uri,
+        &request,
+        r#"{"result_type": "get_balance", "result": {"balance": 21000}}"#,
+    );
+
+    // PERFORM
+    let result = WalletResponse::from_event(&uri, &response);
+
+    // ASSERT
+    assert!(result.is_err());
+}
@@ -0,0 +1,208 @@
+use std::sync::{Arc, Mutex};
+
+use nostr::{secp256k1::XOnlyPublicKey, EventId, Keys, Metadata};
+use nostrtalk::net::{fetch_zap_invoice, fetch_zap_signer, ZapRequest, ZAP_REQUEST_KIND};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use url::Url;
+
+/// Tests for requesting zap invoices, against a local LNURL-pay stub
+
+/// Zap requests received by the stub callback
+type ZapRequests = Arc<Mutex<Vec<nostr::Event>>>;
+
+/// Serves the LNURL-pay parameters of `alice` and answers the callback with
+/// an invoice for the requested amount. Returns the server's `nostrPubkey`.
+async fn spawn_lnurl_server(allows_nostr: bool) -> (String, ZapRequests, XOnlyPublicKey) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let host = listener.local_addr().unwrap().to_string();
+    let base_url = format!("http://{}", host);
+    let zap_requests = ZapRequests::default();
+    let zap_requests_1 = zap_requests.clone();
+    let server_pubkey = Keys::generate().public_key();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 16384];
+            let n = socket.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).into_owned();
+            let target = request
+                .lines()
+                .next()
+                .and_then(|line| line.split(' ').nth(1))
+                .unwrap_or_default()
+                .to_owned();
+            let url = Url::parse(&format!("{}{}", base_url, target)).unwrap();
+
+            let body = if url.path() == "/.well-known/lnurlp/alice" {
+                format!(
+                    r#"{{"tag": "payRequest", "callback": "{}/callback",
+                    "minSendable": 1000, "maxSendable": 10000000,
+                    "metadata": "[[\"text/plain\", \"alice\"]]",
+                    "allowsNostr": {}, "nostrPubkey": "{}"}}"#,
+                    base_url, allows_nostr, server_pubkey
+                )
+            } else if url.path() == "/callback" {
+                let param = |name: &str| {
+                    url.query_pairs()
+                        .find(|(key, _)| key == name)
+                        .map(|(_, value)| value.into_owned())
+                };
+                if let Some(zap_request) = param("nostr") {
+                    zap_requests_1
+                        .lock()
+                        .unwrap()
+                        .push(nostr::Event::from_json(zap_request).unwrap());
+                }
+                let amount_msats: u64 = param("amount").unwrap().parse().unwrap();
+                // 1n is 100 msats
+                format!(
+                    r#"{{"pr": "lnbc{}n1pjzapstub", "routes": []}}"#,
+                    amount_msats / 100
+                )
+            } else {
+                r#"{"status": "ERROR", "reason": "Not found"}"#.to_owned()
+            };
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            socket.shutdown().await.unwrap();
+        }
+    });
+
+    (format!("alice@{}", host), zap_requests, server_pubkey)
+}
+
+fn zap_request(recipient: &Keys, event_hash: Option<EventId>, amount_msats: u64) -> ZapRequest {
+    ZapRequest {
+        recipient: recipient.public_key(),
+        event_hash,
+        amount_msats,
+        comment: "great post".into(),
+        relays: vec!["wss://relay.example.com".into()],
+    }
+}
+
+#[tokio::test]
+async fn zap_invoice_from_lightning_address() {
+    // PREPARE
+    let (address, zap_requests, server_pubkey) = spawn_lnurl_server(true).await;
+    let keys = Keys::generate();
+    let recipient = Keys::generate();
+    let metadata = Metadata::new().lud16(&address);
+    let event_hash = EventId::from_hex("a".repeat(64)).unwrap();
+    let zap = zap_request(&recipient, Some(event_hash), 21_000);
+
+    // PERFORM
+    let result = fetch_zap_invoice(&reqwest::Client::new(), &keys, &metadata, &zap).await;
+
+    // ASSERT
+    let invoice = result.unwrap();
+    assert_eq!(invoice.invoice, "lnbc210n1pjzapstub");
+    assert_eq!(invoice.amount_msats, 21_000);
+    assert_eq!(invoice.nostr_pubkey, server_pubkey);
+
+    let zap_requests = zap_requests.lock().unwrap();
+    assert_eq!(zap_requests.len(), 1);
+    let zap_request = &zap_requests[0];
+    assert!(zap_request.verify().is_ok());
+    assert_eq!(zap_request.kind, nostr::Kind::from(ZAP_REQUEST_KIND));
+    assert_eq!(zap_request.pubkey, keys.public_key());
+    assert_eq!(zap_request.content, "great post");
+
+    let tags: Vec<Vec<String>> = zap_request.tags.iter().map(|tag| tag.as_vec()).collect();
+    assert!(tags.contains(&vec!["amount".to_owned(), "21000".to_owned()]));
+    assert!(tags.contains(&vec!["p".to_owned(), recipient.public_key().to_string()]));
+    assert!(tags.contains(&vec!["e".to_owned(), event_hash.to_hex()]));
+    assert!(tags.contains(&vec![
+        "relays".to_owned(),
+        "wss://relay.example.com".to_owned()
+    ]));
+    let lnurl = tags
+        .iter()
+        .find(|tag| tag[0] == "lnurl")
+        .map(|tag| tag[1].to_owned())
+        .unwrap();
+    let (hrp, data, _) = bech32::decode(&lnurl).unwrap();
+    let pay_url = String::from_utf8(bech32::FromBase32::from_base32(&data).unwrap()).unwrap();
+    assert_eq!(hrp, "lnurl");
+    assert!(pay_url.ends_with("/.well-known/lnurlp/alice"));
+}
+
+#[tokio::test]
+async fn zap_amount_out_of_range() {
+    // PREPARE
+    let (address, zap_requests, _) = spawn_lnurl_server(true).await;
+    let metadata = Metadata::new().lud16(&address);
+    let zap = zap_request(&Keys::generate(), None, 500);
+
+    // PERFORM
+    let result =
+        fetch_zap_invoice(&reqwest::Client::new(), &Keys::generate(), &metadata, &zap).await;
+
+    // ASSERT
+    assert!(result.is_err());
+    assert!(zap_requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn zap_without_nostr_support() {
+    // PREPARE
+    let (address, zap_requests, _) = spawn_lnurl_server(false).await;
+    let metadata = Metadata::new().lud16(&address);
+    let zap = zap_request(&Keys::generate(), None, 21_000);
+
+    // PERFORM
+    let result =
+        fetch_zap_invoice(&reqwest::Client::new(), &Keys::generate(), &metadata, &zap).await;
+
+    // ASSERT
+    assert!(result.is_err());
+    assert!(zap_requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn zap_without_lightning_address() {
+    // PERFORM
+    let result = fetch_zap_invoice(
+        &reqwest::Client::new(),
+        &Keys::generate(),
+        &Metadata::new(),
+        &zap_request(&Keys::generate(), None, 21_000),
+    )
+    .await;
+
+    // ASSERT
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn zap_signer_from_lightning_address() {
+    // PREPARE
+    let (address, _, server_pubkey) = spawn_lnurl_server(true).await;
+    let metadata = Metadata::new().lud16(&address);
+
+    // PERFORM
+    let result = fetch_zap_signer(&reqwest::Client::new(), &metadata).await;
+
+    // ASSERT
+    assert_eq!(result.unwrap(), server_pubkey);
+}
+
+#[tokio::test]
+async fn no_zap_signer_without_nostr_support() {
+    // PREPARE
+    let (address, _, _) = spawn_lnurl_server(false).await;
+    let metadata = Metadata::new().lud16(&address);
+
+    // PERFORM
+    let result = fetch_zap_signer(&reqwest::Client::new(), &metadata).await;
+
+    // ASSERT
+    assert!(result.is_err());
+}